@@ -0,0 +1,140 @@
+use crate::report::ReportNode;
+use flate2::read::GzDecoder;
+use std::{ collections::BTreeMap, fs::File, io::{ self, BufReader, Read }, path::Path };
+use zip::ZipArchive;
+
+/// Suffixes of the tar archives whose entries can be counted, and whether they are gzipped
+const TAR_SUFFIXES: &[(&str, bool)] = &[(".tar", false), (".tar.gz", true), (".tgz", true)];
+
+/// Whether `path` is named like an archive whose entries can be listed
+pub fn is_archive(path: &Path) -> bool {
+    let name = path.file_name().map(|n| n.to_string_lossy().to_lowercase()).unwrap_or_default();
+    name.ends_with(".zip") || tar_kind(&name).is_some()
+}
+
+/// Whether a lowercase file name is a plain (false) or gzipped (true) tar archive
+fn tar_kind(name: &str) -> Option<bool> {
+    TAR_SUFFIXES.iter()
+        .find(|(suffix, _)| name.ends_with(suffix))
+        .map(|&(_, gzipped)| gzipped)
+}
+
+/// Directory tree of an archive, counted like a directory on disk; directory
+/// entries are not counted. The root is named after the archive path.
+pub fn read_archive(path: &Path) -> io::Result<ReportNode> {
+    let name = path.file_name().map(|n| n.to_string_lossy().to_lowercase()).unwrap_or_default();
+    let names = match tar_kind(&name) {
+        Some(gzipped) => tar_entry_names(path, gzipped)?,
+        None => zip_entry_names(path)?,
+    };
+
+    let mut root = Tree::default();
+    for name in &names {
+        let components: Vec<&str> = name.split('/').filter(|c| !c.is_empty() && *c != ".").collect();
+        if components.is_empty() || components.contains(&"..") {
+            continue; // Nothing to show, or an entry escaping the archive
+        }
+        let (last, parents) = components.split_last().unwrap_or((&"", &[]));
+        let mut dir = &mut root;
+        for component in parents {
+            dir = dir.dirs.entry(component.to_string()).or_default();
+        }
+        if name.ends_with('/') {
+            dir.dirs.entry(last.to_string()).or_default();
+        } else {
+            dir.files.push(last.to_string());
+        }
+    }
+    Ok(root.into_node(path.display().to_string()))
+}
+
+/// Entries of an archive directory while the tree is built
+#[derive(Default)]
+struct Tree {
+    dirs: BTreeMap<String, Tree>,
+    files: Vec<String>,
+}
+
+impl Tree {
+    fn into_node(mut self, name: String) -> ReportNode {
+        let mut children: Vec<ReportNode> = self.dirs
+            .into_iter()
+            .map(|(name, dir)| dir.into_node(name))
+            .collect();
+        self.files.sort();
+        self.files.dedup(); // Appended tar members repeat names
+        let files = self.files.len() + children.iter().filter_map(|child| child.files).sum::<usize>();
+        children.extend(
+            self.files.into_iter().map(|name| ReportNode { name, is_dir: false, files: None, children: Vec::new(), count_time: None })
+        );
+        ReportNode { name, is_dir: true, files: Some(files), children, count_time: None }
+    }
+}
+
+/// Member names of a tar archive, decompressing .tar.gz on the fly; directories end with `/`
+fn tar_entry_names(path: &Path, gzipped: bool) -> io::Result<Vec<String>> {
+    let file = BufReader::new(File::open(path)?);
+    let reader: Box<dyn Read> = if gzipped { Box::new(GzDecoder::new(file)) } else { Box::new(file) };
+    let mut names = Vec::new();
+    for entry in tar::Archive::new(reader).entries()? {
+        let entry = entry?;
+        let mut name = String::from_utf8_lossy(&entry.path_bytes()).into_owned();
+        if entry.header().entry_type().is_dir() && !name.ends_with('/') {
+            name.push('/');
+        }
+        names.push(name);
+    }
+    Ok(names)
+}
+
+/// Entry names from the central directory of a zip archive, without decompressing anything
+fn zip_entry_names(path: &Path) -> io::Result<Vec<String>> {
+    let archive = ZipArchive::new(BufReader::new(File::open(path)?)).map_err(|e| {
+        io::Error::new(io::ErrorKind::InvalidData, format!("not a zip archive: {}", e))
+    })?;
+    Ok(archive.file_names().map(|name| name.replace('\\', "/")).collect())
+}
+
+#[cfg(all(test, feature = "archive"))]
+mod tests {
+    use super::*;
+    use std::{ fs, io::Write };
+
+    /// Zip, tar and gzipped tar archives list their files, without the directory entries
+    #[test]
+    fn counts_archive_entries() {
+        let dir = std::env::temp_dir().join(format!("file_counter-archives-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let names = ["src/a", "src/b", "src/d/c"];
+
+        let mut zip = zip::ZipWriter::new(fs::File::create(dir.join("t.zip")).unwrap());
+        let stored = zip::write::SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        zip.add_directory("src/", stored).unwrap();
+        for name in names {
+            zip.start_file(name, stored).unwrap();
+            zip.write_all(b"x").unwrap();
+        }
+        zip.finish().unwrap();
+
+        let tar_of = |writer: Box<dyn Write>| {
+            let mut tar = tar::Builder::new(writer);
+            for name in names {
+                let mut header = tar::Header::new_gnu();
+                header.set_size(1);
+                header.set_cksum();
+                tar.append_data(&mut header, name, &b"x"[..]).unwrap();
+            }
+            tar.into_inner().unwrap().flush().unwrap();
+        };
+        tar_of(Box::new(fs::File::create(dir.join("t.tar")).unwrap()));
+        let gz = flate2::write::GzEncoder::new(fs::File::create(dir.join("t.tgz")).unwrap(), flate2::Compression::fast());
+        tar_of(Box::new(gz));
+
+        for name in ["t.zip", "t.tar", "t.tgz"] {
+            let tree = read_archive(&dir.join(name)).unwrap();
+            assert_eq!(tree.files, Some(3), "{}", name);
+        }
+        assert!(read_archive(&dir.join("missing.zip")).is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,136 @@
+use crate::{ events::AppEvent, scan::VisitKey, theme::Theme };
+use ratatui::{ style::{ Modifier, Style }, text::{ Span, Spans } };
+use std::{
+    collections::HashSet,
+    fs,
+    io,
+    path::{ Path, PathBuf },
+    sync::mpsc::Sender,
+    thread,
+};
+
+/// A symlink whose target is missing, or that loops back on itself
+pub struct BrokenLink {
+    pub path: PathBuf,
+    pub target: PathBuf, // As stored in the link, relative targets unresolved
+}
+
+/// Result of looking for broken symlinks below a directory
+pub struct BrokenLinkReport {
+    pub dir: PathBuf,
+    pub links: Vec<BrokenLink>, // Sorted by path
+}
+
+/// Broken symlink view of a directory: searching until the report arrives,
+/// then optionally confirming their removal
+pub struct BrokenLinkView {
+    pub dir: PathBuf,
+    pub report: Option<BrokenLinkReport>,
+    pub removed: usize, // Links removed so far
+    pub failures: Vec<(PathBuf, io::Error)>, // Of the last removal
+    pub scroll: u16,
+}
+
+impl BrokenLinkView {
+    pub fn lines(&self, theme: &Theme, spinner_frame: &str, read_only: bool) -> Vec<Spans<'static>> {
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        let mut lines = Vec::new();
+        let Some(report) = &self.report else {
+            lines.push(Spans::from(format!("Looking for broken symlinks in {}{}", self.dir.display(), spinner_frame)));
+            return lines;
+        };
+
+        if report.links.is_empty() {
+            lines.push(Spans::from(format!("No broken symlinks in {}", report.dir.display())));
+        } else {
+            lines.push(
+                Spans::from(
+                    vec![
+                        Span::styled(format!("{} broken symlinks", report.links.len()), bold),
+                        Span::raw(format!(" in {}", report.dir.display()))
+                    ]
+                )
+            );
+        }
+        if self.removed > 0 {
+            lines.push(Spans::from(Span::styled(format!("Removed {} broken symlinks", self.removed), theme.marked)));
+        }
+        for (path, e) in &self.failures {
+            lines.push(Spans::from(format!("Cannot remove {}: {}", path.display(), e)));
+        }
+        lines.push(Spans::from(""));
+        for link in &report.links {
+            let shown = link.path.strip_prefix(&report.dir).unwrap_or(&link.path);
+            lines.push(
+                Spans::from(
+                    vec![
+                        Span::styled(format!("  {}", shown.display()), theme.file),
+                        Span::raw(format!(" -> {}", link.target.display()))
+                    ]
+                )
+            );
+        }
+        if !report.links.is_empty() {
+            lines.push(Spans::from(""));
+        }
+        let mut keys = vec![Span::styled("↑/↓", theme.key), Span::raw(" - Scroll   ")];
+        if !report.links.is_empty() && !read_only {
+            keys.extend([Span::styled("d", theme.key), Span::raw(" - Remove all   ")]);
+        }
+        keys.extend([Span::styled("Esc", theme.key), Span::raw(" - Close")]);
+        lines.push(Spans::from(keys));
+        lines
+    }
+}
+
+/// Walk the tree below `dir` on a background thread and send its broken
+/// symlinks to the UI
+pub fn spawn_broken_link_scan(dir: PathBuf, sender: Sender<AppEvent>) {
+    thread::spawn(move || {
+        let mut links = find_broken_links(&dir);
+        links.sort_by(|a, b| a.path.cmp(&b.path));
+        sender.send(AppEvent::BrokenLinks(BrokenLinkReport { dir, links })).unwrap_or(());
+    });
+}
+
+/// Symlinks below `dir` that cannot be followed. Directories are walked
+/// without following symlinks, so every link is looked at once.
+fn find_broken_links(dir: &Path) -> Vec<BrokenLink> {
+    let mut links = Vec::new();
+    let mut visited = HashSet::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        if !visited.insert(VisitKey::of(&current)) {
+            continue; // Bind mount of a directory already walked
+        }
+        let Ok(entries) = fs::read_dir(&current) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let Ok(kind) = entry.file_type() else {
+                continue;
+            };
+            let path = entry.path();
+            if kind.is_dir() {
+                pending.push(path);
+            } else if kind.is_symlink() && is_broken(&path) {
+                let target = fs::read_link(&path).unwrap_or_default();
+                links.push(BrokenLink { path, target });
+            }
+        }
+    }
+    links
+}
+
+/// Whether `path` is a symlink that cannot be followed
+fn is_broken(path: &Path) -> bool {
+    path.is_symlink() && fs::metadata(path).is_err()
+}
+
+/// Remove a broken symlink, unless its target has appeared since the search
+pub fn remove_link(path: &Path) -> io::Result<()> {
+    if !is_broken(path) {
+        return Err(io::Error::other("no longer a broken symlink"));
+    }
+    fs::remove_file(path)
+}
@@ -0,0 +1,117 @@
+use crate::{ count_cache::{ CacheStats, CachedCount, CountCache }, format, numbers::NumberFormat, theme::Theme };
+use ratatui::{ style::{ Modifier, Style }, text::{ Span, Spans } };
+use std::{ path::Path, time::SystemTime };
+
+/// Oldest counts listed; the rest are summed up in the statistics
+const OLDEST_SHOWN: usize = 100;
+
+/// What the count cache holds and how well it answers (`C`), with its oldest
+/// counts to pin or clear
+pub struct CacheView {
+    pub oldest: Vec<CachedCount>,
+    pub selected: usize,
+}
+
+impl CacheView {
+    pub fn new(cache: &CountCache) -> CacheView {
+        CacheView { oldest: cache.oldest(OLDEST_SHOWN), selected: 0 }
+    }
+
+    /// Read the oldest counts again after the cache changed, staying on the selected one if it is still there
+    pub fn refresh(&mut self, cache: &CountCache) {
+        let selected = self.target().map(Path::to_path_buf);
+        self.oldest = cache.oldest(OLDEST_SHOWN);
+        self.selected = selected
+            .and_then(|selected| self.oldest.iter().position(|count| count.path == selected))
+            .unwrap_or(self.selected)
+            .min(self.oldest.len().saturating_sub(1));
+    }
+
+    pub fn move_selection(&mut self, down: bool) {
+        self.selected = match down {
+            true => (self.selected + 1).min(self.oldest.len().saturating_sub(1)),
+            false => self.selected.saturating_sub(1),
+        };
+    }
+
+    pub fn target(&self) -> Option<&Path> {
+        self.oldest.get(self.selected).map(|count| count.path.as_path())
+    }
+
+    /// Statistics and the oldest counts, scrolled to keep the selection among the `rows` shown
+    pub fn lines(
+        &self,
+        theme: &Theme,
+        numbers: &NumberFormat,
+        cache: &CountCache,
+        can_clear: bool,
+        rows: usize
+    ) -> Vec<Spans<'static>> {
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        let stats = cache.stats();
+        let CacheStats { entries, bytes, hits, misses, evicted, pinned } = stats;
+        let budget = cache.budget();
+        let limit = match (budget.max_bytes, budget.max_entries) {
+            (Some(max_bytes), Some(max_entries)) =>
+                format!("budget {} or {} entries", format::human_bytes(max_bytes), numbers.count(max_entries as u64)),
+            (Some(max_bytes), None) => format!("budget {}", format::human_bytes(max_bytes)),
+            (None, Some(max_entries)) => format!("budget {} entries", numbers.count(max_entries as u64)),
+            (None, None) => String::from("no budget"),
+        };
+        let hit_rate = match stats.hit_rate() {
+            Some(rate) => format!("{:.0}% answered from the cache", rate * 100.0),
+            None => String::from("no lookups yet"),
+        };
+        let mut lines = vec![
+            Spans::from(
+                vec![
+                    Span::styled(format!("{} counts", numbers.count(entries as u64)), bold),
+                    Span::raw(format!(", about {} ({})", format::human_bytes(bytes), limit))
+                ]
+            ),
+            Spans::from(
+                format!("Lookups: {} hits, {} misses, {}", numbers.count(hits), numbers.count(misses), hit_rate)
+            ),
+            Spans::from(format!("Evicted: {}   Pinned (*): {}", numbers.count(evicted), pinned)),
+            Spans::from("")
+        ];
+
+        let mut keys = vec![Span::styled("↑/↓", theme.key), Span::raw(" - Select   "), Span::styled("p", theme.key), Span::raw(" - Pin   ")];
+        if can_clear {
+            keys.extend([
+                Span::styled("d", theme.key),
+                Span::raw(" - Clear the selected subtree   "),
+                Span::styled("D", theme.key),
+                Span::raw(" - Clear all   "),
+            ]);
+        }
+        keys.extend([Span::styled("Esc", theme.key), Span::raw(" - Close")]);
+
+        if self.oldest.is_empty() {
+            lines.push(Spans::from("Nothing is cached"));
+        } else {
+            lines.push(Spans::from(Span::styled("Oldest counts", bold)));
+            let now = SystemTime::now();
+            let rows = rows.saturating_sub(lines.len() + 2).max(1); // The key hints below
+            let first = self.selected.saturating_sub(rows - 1);
+            for (index, count) in self.oldest.iter().enumerate().skip(first).take(rows) {
+                let text = format!(
+                    "{} {:>10}  {:<16}  {}",
+                    if count.pinned { "*" } else { " " },
+                    numbers.count(count.files as u64),
+                    format::relative_time(count.counted, now),
+                    count.path.display()
+                );
+                lines.push(
+                    match index == self.selected {
+                        true => Spans::from(Span::styled(text, theme.selection)),
+                        false => Spans::from(Span::styled(text, theme.dir)),
+                    }
+                );
+            }
+        }
+        lines.push(Spans::from(""));
+        lines.push(Spans::from(keys));
+        lines
+    }
+}
@@ -0,0 +1,216 @@
+use crate::{
+    events::AppEvent,
+    format,
+    junk::junk_category,
+    numbers::NumberFormat,
+    scan::{ scan_dir, ByteSizes, FileTotals, ScanOptions, VisitKey },
+    theme::Theme,
+    vfs::LocalFs,
+};
+use ratatui::{ style::{ Modifier, Style }, text::{ Span, Spans } };
+use std::{
+    collections::{ HashMap, HashSet },
+    fs,
+    io,
+    path::{ Path, PathBuf },
+    sync::mpsc::Sender,
+    thread,
+};
+
+/// Directories listed below the categories; the rest are summed up
+const LISTED_DIRS: usize = 50;
+
+/// A recognized build or cache directory, counted as a whole
+pub struct CleanableDir {
+    pub path: PathBuf,
+    pub category: &'static str,
+    pub totals: FileTotals,
+}
+
+/// Result of looking for cleanable directories below a directory
+pub struct CleanableReport {
+    pub dir: PathBuf,
+    pub dirs: Vec<CleanableDir>, // Most files first
+}
+
+impl CleanableReport {
+    pub fn total(&self) -> FileTotals {
+        let mut total = FileTotals::default();
+        for dir in &self.dirs {
+            total.files += dir.totals.files;
+            total.bytes.add(dir.totals.bytes);
+        }
+        total
+    }
+
+    /// Totals and directories of each category, most files first
+    pub fn by_category(&self) -> Vec<(&'static str, FileTotals, usize)> {
+        let mut categories: HashMap<&'static str, (FileTotals, usize)> = HashMap::new();
+        for dir in &self.dirs {
+            let (totals, dirs) = categories.entry(dir.category).or_default();
+            totals.files += dir.totals.files;
+            totals.bytes.add(dir.totals.bytes);
+            *dirs += 1;
+        }
+        let mut categories: Vec<(&'static str, FileTotals, usize)> = categories
+            .into_iter()
+            .map(|(category, (totals, dirs))| (category, totals, dirs))
+            .collect();
+        categories.sort_by(|a, b| b.1.files.cmp(&a.1.files).then(a.0.cmp(b.0)));
+        categories
+    }
+}
+
+/// Cleanable space view of a directory: searching until the report arrives,
+/// then optionally confirming that everything found goes to the trash
+pub struct CleanableView {
+    pub dir: PathBuf,
+    pub report: Option<CleanableReport>,
+    pub reclaimed: FileTotals, // Trashed so far
+    pub failures: Vec<(PathBuf, io::Error)>, // Of the last cleanup
+    pub scroll: u16,
+}
+
+impl CleanableView {
+    pub fn lines(
+        &self,
+        theme: &Theme,
+        numbers: &NumberFormat,
+        apparent: bool,
+        spinner_frame: &str,
+        read_only: bool
+    ) -> Vec<Spans<'static>> {
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        let bytes = |sizes: ByteSizes| format::human_bytes(sizes.get(apparent));
+        let mut lines = Vec::new();
+        let Some(report) = &self.report else {
+            lines.push(
+                Spans::from(format!("Looking for build output and caches in {}{}", self.dir.display(), spinner_frame))
+            );
+            return lines;
+        };
+
+        let total = report.total();
+        if report.dirs.is_empty() {
+            lines.push(Spans::from(format!("No build output or caches found in {}", report.dir.display())));
+        } else {
+            lines.push(
+                Spans::from(
+                    vec![
+                        Span::styled(
+                            format!("Reclaimable: {} files, {}", numbers.count(total.files as u64), bytes(total.bytes)),
+                            bold
+                        ),
+                        Span::raw(format!(" in {} directories below {}", report.dirs.len(), report.dir.display()))
+                    ]
+                )
+            );
+        }
+        if self.reclaimed.files > 0 {
+            lines.push(
+                Spans::from(
+                    Span::styled(
+                        format!(
+                            "Trashed {} files, {}",
+                            numbers.count(self.reclaimed.files as u64),
+                            bytes(self.reclaimed.bytes)
+                        ),
+                        theme.marked
+                    )
+                )
+            );
+        }
+        for (path, e) in &self.failures {
+            lines.push(Spans::from(format!("Cannot trash {}: {}", path.display(), e)));
+        }
+
+        if !report.dirs.is_empty() {
+            lines.push(Spans::from(""));
+            for (category, totals, dirs) in report.by_category() {
+                lines.push(
+                    Spans::from(
+                        vec![
+                            Span::styled(format!("{:<28}", category), theme.junk),
+                            Span::raw(
+                                format!(
+                                    "{:>12} files {:>11}  in {} directories",
+                                    numbers.count(totals.files as u64),
+                                    bytes(totals.bytes),
+                                    dirs
+                                )
+                            )
+                        ]
+                    )
+                );
+            }
+            lines.push(Spans::from(""));
+            for dir in report.dirs.iter().take(LISTED_DIRS) {
+                let shown = dir.path.strip_prefix(&report.dir).unwrap_or(&dir.path);
+                lines.push(
+                    Spans::from(
+                        vec![
+                            Span::raw(
+                                format!("{:>12} {:>11}  ", numbers.count(dir.totals.files as u64), bytes(dir.totals.bytes))
+                            ),
+                            Span::styled(format!("{}/", shown.display()), theme.dir)
+                        ]
+                    )
+                );
+            }
+            if report.dirs.len() > LISTED_DIRS {
+                lines.push(Spans::from(format!("  and {} smaller directories", report.dirs.len() - LISTED_DIRS)));
+            }
+        }
+        lines.push(Spans::from(""));
+        let mut keys = vec![Span::styled("↑/↓", theme.key), Span::raw(" - Scroll   ")];
+        if !report.dirs.is_empty() && !read_only {
+            keys.extend([Span::styled("d", theme.key), Span::raw(" - Trash all   ")]);
+        }
+        keys.extend([Span::styled("Esc", theme.key), Span::raw(" - Close")]);
+        lines.push(Spans::from(keys));
+        lines
+    }
+}
+
+/// Find the topmost build and cache directories below `dir` on a background
+/// thread, count each as a whole and send the report to the UI
+pub fn spawn_cleanable_scan(dir: PathBuf, options: ScanOptions, sender: Sender<AppEvent>) {
+    thread::spawn(move || {
+        let mut dirs: Vec<CleanableDir> = find_junk_dirs(&dir)
+            .into_iter()
+            .map(|(path, category)| {
+                let stats = scan_dir(&path, &options).unwrap_or_default();
+                CleanableDir { path, category, totals: FileTotals { files: stats.files, bytes: stats.bytes } }
+            })
+            .collect();
+        dirs.sort_by(|a, b| b.totals.files.cmp(&a.totals.files).then_with(|| a.path.cmp(&b.path)));
+        sender.send(AppEvent::Cleanable(CleanableReport { dir, dirs })).unwrap_or(());
+    });
+}
+
+/// Recognized directories below `dir`, without descending into them or
+/// following symlinks. `dir` itself is not offered for removal.
+fn find_junk_dirs(dir: &Path) -> Vec<(PathBuf, &'static str)> {
+    let mut found = Vec::new();
+    let mut visited = HashSet::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        if !visited.insert(VisitKey::of(&current)) {
+            continue; // Bind mount of a directory already walked
+        }
+        let Ok(entries) = fs::read_dir(&current) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            if !entry.file_type().is_ok_and(|kind| kind.is_dir()) {
+                continue;
+            }
+            let path = entry.path();
+            match junk_category(&LocalFs, &path) {
+                Some(category) => found.push((path, category)),
+                None => pending.push(path),
+            }
+        }
+    }
+    found
+}
@@ -0,0 +1,696 @@
+use crate::mountinfo::FsClasses;
+use std::{ path::PathBuf, time::Duration };
+
+pub const USAGE: &str = "Usage: file_counter [OPTIONS] [DIRECTORY...]
+       file_counter [OPTIONS] s3://BUCKET[/PREFIX] | gs://BUCKET[/PREFIX]
+       file_counter k8s [K8S OPTIONS] [PATH]
+       file_counter self-update [--check] [--cache-dir DIR]
+       file_counter doctor [--cache-dir DIR] [--state-dir DIR] [PATH]
+       file_counter diff [--json] [--compact] [--depth N] LEFT RIGHT
+       file_counter users [--csv FILE] [PATH]
+       file_counter daemon --config FILE [--state-dir DIR] [--control-socket PATH] [--once]
+       file_counter control SOCKET (query PATH | invalidate PATH | export snapshot)
+       file_counter history [--json] [--state-dir DIR] [PATH]
+
+Options:
+    --host-paths            Show host-side paths next to container paths (reads /proc/self/mountinfo)
+    --index                 Show instant approximate counts from Spotlight/plocate while counting
+    --mft                   Count NTFS volumes from their Master File Table (Windows, as administrator)
+    --exclude-fs CLASSES    Don't descend into mounts of these filesystem classes: network, fuse, virtual, local
+                            (comma-separated, e.g. network,fuse)
+    --watch                 Recount directories when their contents change
+    --stdin                 Read the directories to count from stdin, one per line (e.g. from find -type d)
+    --json                  Scan headlessly and print a JSON report instead of starting the TUI
+    --depth N               Levels of entries listed in the JSON report (default 1)
+    --timings               Add how long each directory's count took to the JSON report (count_ms)
+    --fail-if-count-gt N    Count headlessly and exit with 1 if the directory holds more than N files
+    --fail-if-count-lt N    Count headlessly and exit with 1 if the directory holds fewer than N files
+    --export-ncdu FILE      Scan the whole tree headlessly and write an ncdu JSON export (- for stdout)
+    --import-ncdu FILE      Browse an ncdu JSON export instead of the disk
+    --snapshot FILE         Scan headlessly and save every directory's count for a later --diff (- for stdout)
+    --diff FILE             Show each directory's change in count since a --snapshot file
+    --summary               Print a summary of the session (visited, largest, deleted) on quit
+    --no-color              Plain display without colors, bars or icons, for serial consoles and screen readers
+                            (NO_COLOR set in the environment drops only the colors)
+    --simple                Browse with numbered lines and a prompt instead of the TUI, for screen readers
+    --min-count N           Hide directories with fewer than N files (> and < change it in the TUI)
+    --threshold N           Keep rescanning headlessly and alert when a directory exceeds N files
+    --interval SECS         Seconds between rescans with --threshold (default 60; with --watch, changes rescan sooner)
+    --config-file FILE      Config file to use instead of $XDG_CONFIG_HOME/file_counter/config.toml
+    --cache-dir DIR         Cache directory instead of $XDG_CACHE_HOME/file_counter
+    --state-dir DIR         State directory instead of $XDG_STATE_HOME/file_counter
+    --export-state FILE     On quit, save marks, exclusions, the current directory and the --diff baseline
+    --import-state FILE     Continue an investigation saved with --export-state
+    --control-socket PATH   Accept query/invalidate/export commands from other tools on this Unix socket
+    --invalidate DIR        Make the instance listening on --control-socket forget its counts of DIR, of everything
+                            below it and of the directories above it, then exit
+    --alert-command CMD     Shell command run for each alert, with FILE_COUNTER_PATH, FILE_COUNTER_COUNT
+                            and FILE_COUNTER_THRESHOLD set (default: desktop notification)
+    -h, --help              Print this help
+
+K8s options (run the scanner inside pods and browse the aggregated counts):
+    --kubeconfig FILE       kubeconfig to use
+    -n, --namespace NS      Namespace of the pods
+    -l, --selector LABELS   Select pods by label
+    --pod NAME              Select a single pod
+    --pvc NAME              Scan wherever this claim is mounted (PATH is then relative to the mount)
+    -c, --container NAME    Container to exec into (default: first matching)
+    --scanner FILE          Statically linked scanner binary to copy into the pods (default: this binary)
+    --depth N               Levels of entries fetched from each pod (default 1)
+
+Self-update options (download the latest release and verify its SHA-256 checksum):
+    --check                 Only report whether a newer release exists
+    --cache-dir DIR         Where the release lookup is cached for an hour
+
+Diff (count two trees and list the directories whose recursive file count differs; exits with 1 if any):
+    --json                  Print the differences as JSON
+    --compact               Write counts as 1.2M instead of 1,234,567
+    --depth N               Only report directories up to N levels below the roots
+
+Users (count a tree by file owner and list each user's files and bytes; Unix only):
+    --csv FILE              Write the table as CSV instead (- for stdout)
+
+Daemon (rescan the roots listed in a config file on a schedule and record each directory's count):
+    --config FILE           Config with a [daemon] section: roots = [...], interval (seconds), depth
+    --state-dir DIR         Where the history is recorded instead of $XDG_STATE_HOME/file_counter
+    --control-socket PATH   Answer query/invalidate/export commands with the latest counts on this Unix socket
+    --once                  Scan every root once, record it and exit (for cron)
+
+History (show how a directory's count changed across the daemon's scans):
+    --json                  Print the recorded counts as JSON
+    --state-dir DIR         State directory the daemon recorded into
+
+Doctor (count a small subtree with each scanner setting, explain the differences and check the environment):
+    --cache-dir DIR         Cache directory to check
+    --state-dir DIR         State directory to check";
+
+/// Options of the `k8s` subcommand
+#[derive(Default)]
+pub struct K8sOptions {
+    pub kubeconfig: Option<PathBuf>,
+    pub namespace: Option<String>,
+    pub selector: Option<String>,
+    pub pod: Option<String>,
+    pub pvc: Option<String>,
+    pub container: Option<String>,
+    pub scanner: Option<PathBuf>,
+    pub path: Option<String>,
+    pub depth: usize,
+}
+
+/// Options of the `self-update` subcommand
+#[derive(Default)]
+pub struct UpdateOptions {
+    pub check_only: bool, // Report whether an update exists without installing it
+    pub cache_dir: Option<PathBuf>,
+}
+
+/// Options of the `doctor` subcommand
+#[derive(Default)]
+pub struct DoctorOptions {
+    pub path: Option<PathBuf>, // Defaults to the current directory
+    pub cache_dir: Option<PathBuf>,
+    pub state_dir: Option<PathBuf>,
+}
+
+/// Options of the `diff` subcommand
+#[derive(Default)]
+pub struct CompareOptions {
+    pub left: PathBuf,
+    pub right: PathBuf,
+    pub json: bool,
+    pub compact: bool, // Counts as `1.2M` in the text output
+    pub depth: Option<usize>, // All levels by default
+}
+
+/// Options of the `users` subcommand
+#[derive(Default)]
+pub struct UsersOptions {
+    pub path: Option<PathBuf>, // Defaults to the current directory
+    pub csv: Option<PathBuf>, // `-` for stdout
+}
+
+/// Options of the `daemon` subcommand
+#[derive(Default)]
+pub struct DaemonOptions {
+    pub config: PathBuf,
+    pub state_dir: Option<PathBuf>,
+    pub once: bool, // Record one scan of every root and exit
+    pub control_socket: Option<PathBuf>,
+}
+
+/// Options of the `control` subcommand
+#[derive(Default)]
+pub struct ControlOptions {
+    pub socket: PathBuf,
+    pub command: String, // One line of the control protocol
+}
+
+/// Options of the `history` subcommand
+#[derive(Default)]
+pub struct HistoryOptions {
+    pub path: Option<PathBuf>, // Defaults to the current directory
+    pub state_dir: Option<PathBuf>,
+    pub json: bool,
+}
+
+/// Options of the threshold alert mode (`--threshold`)
+pub struct MonitorOptions {
+    pub threshold: usize,
+    pub interval: Duration,
+    pub command: Option<String>, // Run for each alert instead of a desktop notification
+    pub watch: bool, // Also rescan when the tree changes
+}
+
+/// Policy checked by a headless count (`--fail-if-count-gt`, `--fail-if-count-lt`)
+#[derive(Clone, Copy, Default)]
+pub struct CountLimits {
+    pub max: Option<usize>, // At most this many files
+    pub min: Option<usize>, // At least this many files
+}
+
+/// Command-line options
+pub struct Options {
+    pub start_dir: Option<PathBuf>,
+    pub more_dirs: Vec<PathBuf>, // Further directories, listed next to the first as roots in the TUI
+    pub stdin: bool, // Directories come from stdin, one per line
+    pub host_paths: bool,
+    pub index: bool,
+    pub mft: bool, // Read counts from the NTFS Master File Table
+    pub exclude_fs: Option<FsClasses>, // Classes of mounted filesystems left out of counts
+    pub watch: bool,
+    pub json: bool,
+    pub depth: usize,
+    pub timings: bool, // Count times in the JSON report
+    pub export_ncdu: Option<PathBuf>,
+    pub import_ncdu: Option<PathBuf>,
+    pub snapshot: Option<PathBuf>,
+    pub diff: Option<PathBuf>,
+    pub summary: bool,
+    pub no_color: bool, // Plain ASCII display; NO_COLOR alone only drops the colors
+    pub simple: bool, // Numbered lines and a prompt instead of the TUI
+    pub min_count: Option<usize>, // Hide directories with fewer files
+    pub count_limits: Option<CountLimits>, // Exit with 1 when the count is outside them
+    pub monitor: Option<MonitorOptions>,
+    pub export_state: Option<PathBuf>,
+    pub import_state: Option<PathBuf>,
+    pub config_file: Option<PathBuf>,
+    pub cache_dir: Option<PathBuf>,
+    pub state_dir: Option<PathBuf>,
+    pub control_socket: Option<PathBuf>,
+    pub invalidate: Option<PathBuf>, // Sent to the instance on `control_socket` instead of starting one
+    pub k8s: Option<K8sOptions>,
+    pub self_update: Option<UpdateOptions>,
+    pub doctor: Option<DoctorOptions>,
+    pub compare: Option<CompareOptions>,
+    pub users: Option<UsersOptions>,
+    pub daemon: Option<DaemonOptions>,
+    pub history: Option<HistoryOptions>,
+    pub control: Option<ControlOptions>,
+    pub show_usage: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            start_dir: None,
+            more_dirs: Vec::new(),
+            stdin: false,
+            host_paths: false,
+            index: false,
+            mft: false,
+            exclude_fs: None,
+            watch: false,
+            json: false,
+            depth: 1,
+            timings: false,
+            export_ncdu: None,
+            import_ncdu: None,
+            snapshot: None,
+            diff: None,
+            summary: false,
+            no_color: false,
+            simple: false,
+            min_count: None,
+            count_limits: None,
+            monitor: None,
+            export_state: None,
+            import_state: None,
+            config_file: None,
+            cache_dir: None,
+            state_dir: None,
+            control_socket: None,
+            invalidate: None,
+            k8s: None,
+            self_update: None,
+            doctor: None,
+            compare: None,
+            users: None,
+            daemon: None,
+            history: None,
+            control: None,
+            show_usage: false,
+        }
+    }
+}
+
+/// Time between rescans in threshold alert mode
+const DEFAULT_MONITOR_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Take the value following an option
+fn value_of<I: Iterator<Item = String>>(args: &mut I, option: &str) -> Result<String, String> {
+    args.next().ok_or_else(|| format!("Missing value for {}", option))
+}
+
+fn parse_depth(value: &str) -> Result<usize, String> {
+    value.parse().map_err(|_| format!("Invalid depth: {}", value))
+}
+
+/// Parse command-line arguments (without the program name)
+pub fn parse_args<I: Iterator<Item = String>>(args: I) -> Result<Options, String> {
+    let mut args = args.peekable();
+    match args.peek().map(String::as_str) {
+        Some("k8s") => {
+            args.next();
+            return parse_k8s_args(args);
+        }
+        Some("self-update") => {
+            args.next();
+            return parse_update_args(args);
+        }
+        Some("doctor") => {
+            args.next();
+            return parse_doctor_args(args);
+        }
+        Some("diff") => {
+            args.next();
+            return parse_compare_args(args);
+        }
+        Some("users") => {
+            args.next();
+            return parse_users_args(args);
+        }
+        Some("daemon") => {
+            args.next();
+            return parse_daemon_args(args);
+        }
+        Some("history") => {
+            args.next();
+            return parse_history_args(args);
+        }
+        Some("control") => {
+            args.next();
+            return parse_control_args(args);
+        }
+        _ => {}
+    }
+
+    let mut options = Options::default();
+    let mut threshold = None;
+    let mut interval = None;
+    let mut alert_command = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--host-paths" => {
+                options.host_paths = true;
+            }
+            "--index" => {
+                options.index = true;
+            }
+            "--mft" => {
+                options.mft = true;
+            }
+            "--exclude-fs" => {
+                options.exclude_fs = Some(FsClasses::parse(&value_of(&mut args, &arg)?)?);
+            }
+            "--timings" => {
+                options.timings = true;
+            }
+            "--watch" => {
+                options.watch = true;
+            }
+            "--stdin" => {
+                options.stdin = true;
+            }
+            "--json" => {
+                options.json = true;
+            }
+            "--depth" => {
+                options.depth = parse_depth(&value_of(&mut args, &arg)?)?;
+            }
+            "--export-ncdu" => {
+                options.export_ncdu = Some(PathBuf::from(value_of(&mut args, &arg)?));
+            }
+            "--import-ncdu" => {
+                options.import_ncdu = Some(PathBuf::from(value_of(&mut args, &arg)?));
+            }
+            "--snapshot" => {
+                options.snapshot = Some(PathBuf::from(value_of(&mut args, &arg)?));
+            }
+            "--diff" => {
+                options.diff = Some(PathBuf::from(value_of(&mut args, &arg)?));
+            }
+            "--summary" => {
+                options.summary = true;
+            }
+            "--no-color" => {
+                options.no_color = true;
+            }
+            "--simple" => {
+                options.simple = true;
+            }
+            "--min-count" => {
+                let value = value_of(&mut args, &arg)?;
+                options.min_count = Some(value.parse().map_err(|_| format!("Invalid minimum count: {}", value))?);
+            }
+            "--fail-if-count-gt" => {
+                let value = value_of(&mut args, &arg)?;
+                let max = value.parse().map_err(|_| format!("Invalid count limit: {}", value))?;
+                options.count_limits = Some(CountLimits { max: Some(max), ..options.count_limits.unwrap_or_default() });
+            }
+            "--fail-if-count-lt" => {
+                let value = value_of(&mut args, &arg)?;
+                let min = value.parse().map_err(|_| format!("Invalid count limit: {}", value))?;
+                options.count_limits = Some(CountLimits { min: Some(min), ..options.count_limits.unwrap_or_default() });
+            }
+            "--threshold" => {
+                let value = value_of(&mut args, &arg)?;
+                threshold = Some(value.parse().map_err(|_| format!("Invalid threshold: {}", value))?);
+            }
+            "--interval" => {
+                let value = value_of(&mut args, &arg)?;
+                let seconds: u64 = value.parse().map_err(|_| format!("Invalid interval: {}", value))?;
+                interval = Some(Duration::from_secs(seconds.max(1)));
+            }
+            "--alert-command" => {
+                alert_command = Some(value_of(&mut args, &arg)?);
+            }
+            "--export-state" => {
+                options.export_state = Some(PathBuf::from(value_of(&mut args, &arg)?));
+            }
+            "--import-state" => {
+                options.import_state = Some(PathBuf::from(value_of(&mut args, &arg)?));
+            }
+            "--config-file" => {
+                options.config_file = Some(PathBuf::from(value_of(&mut args, &arg)?));
+            }
+            "--cache-dir" => {
+                options.cache_dir = Some(PathBuf::from(value_of(&mut args, &arg)?));
+            }
+            "--state-dir" => {
+                options.state_dir = Some(PathBuf::from(value_of(&mut args, &arg)?));
+            }
+            "--control-socket" => {
+                options.control_socket = Some(PathBuf::from(value_of(&mut args, &arg)?));
+            }
+            "--invalidate" => {
+                options.invalidate = Some(PathBuf::from(value_of(&mut args, &arg)?));
+            }
+            "-h" | "--help" => {
+                options.show_usage = true;
+            }
+            _ if arg.starts_with('-') => {
+                return Err(format!("Unknown option: {}", arg));
+            }
+            _ => {
+                match options.start_dir {
+                    Some(_) => options.more_dirs.push(PathBuf::from(arg)),
+                    None => {
+                        options.start_dir = Some(PathBuf::from(arg));
+                    }
+                }
+            }
+        }
+    }
+    match threshold {
+        Some(threshold) => {
+            options.monitor = Some(MonitorOptions {
+                threshold,
+                interval: interval.unwrap_or(DEFAULT_MONITOR_INTERVAL),
+                command: alert_command,
+                watch: options.watch,
+            });
+        }
+        None if interval.is_some() || alert_command.is_some() => {
+            return Err(String::from("--interval and --alert-command need --threshold"));
+        }
+        None => {}
+    }
+    Ok(options)
+}
+
+fn parse_k8s_args<I: Iterator<Item = String>>(mut args: I) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut k8s = K8sOptions { depth: 1, ..K8sOptions::default() };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--kubeconfig" => {
+                k8s.kubeconfig = Some(PathBuf::from(value_of(&mut args, &arg)?));
+            }
+            "-n" | "--namespace" => {
+                k8s.namespace = Some(value_of(&mut args, &arg)?);
+            }
+            "-l" | "--selector" => {
+                k8s.selector = Some(value_of(&mut args, &arg)?);
+            }
+            "--pod" => {
+                k8s.pod = Some(value_of(&mut args, &arg)?);
+            }
+            "--pvc" => {
+                k8s.pvc = Some(value_of(&mut args, &arg)?);
+            }
+            "-c" | "--container" => {
+                k8s.container = Some(value_of(&mut args, &arg)?);
+            }
+            "--scanner" => {
+                k8s.scanner = Some(PathBuf::from(value_of(&mut args, &arg)?));
+            }
+            "--depth" => {
+                k8s.depth = parse_depth(&value_of(&mut args, &arg)?)?;
+            }
+            "-h" | "--help" => {
+                options.show_usage = true;
+            }
+            _ if arg.starts_with('-') => {
+                return Err(format!("Unknown k8s option: {}", arg));
+            }
+            _ => {
+                if k8s.path.is_some() {
+                    return Err(format!("Unexpected argument: {}", arg));
+                }
+                k8s.path = Some(arg);
+            }
+        }
+    }
+    options.k8s = Some(k8s);
+    Ok(options)
+}
+
+fn parse_update_args<I: Iterator<Item = String>>(mut args: I) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut update = UpdateOptions::default();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--check" => {
+                update.check_only = true;
+            }
+            "--cache-dir" => {
+                update.cache_dir = Some(PathBuf::from(value_of(&mut args, &arg)?));
+            }
+            "-h" | "--help" => {
+                options.show_usage = true;
+            }
+            _ => {
+                return Err(format!("Unknown self-update option: {}", arg));
+            }
+        }
+    }
+    options.self_update = Some(update);
+    Ok(options)
+}
+
+fn parse_doctor_args<I: Iterator<Item = String>>(mut args: I) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut doctor = DoctorOptions::default();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--cache-dir" => {
+                doctor.cache_dir = Some(PathBuf::from(value_of(&mut args, &arg)?));
+            }
+            "--state-dir" => {
+                doctor.state_dir = Some(PathBuf::from(value_of(&mut args, &arg)?));
+            }
+            "-h" | "--help" => {
+                options.show_usage = true;
+            }
+            _ if arg.starts_with('-') => {
+                return Err(format!("Unknown doctor option: {}", arg));
+            }
+            _ => {
+                if doctor.path.is_some() {
+                    return Err(format!("Unexpected argument: {}", arg));
+                }
+                doctor.path = Some(PathBuf::from(arg));
+            }
+        }
+    }
+    options.doctor = Some(doctor);
+    Ok(options)
+}
+
+fn parse_compare_args<I: Iterator<Item = String>>(mut args: I) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut compare = CompareOptions::default();
+    let mut roots = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" => {
+                compare.json = true;
+            }
+            "--compact" => {
+                compare.compact = true;
+            }
+            "--depth" => {
+                compare.depth = Some(parse_depth(&value_of(&mut args, &arg)?)?);
+            }
+            "-h" | "--help" => {
+                options.show_usage = true;
+            }
+            _ if arg.starts_with('-') => {
+                return Err(format!("Unknown diff option: {}", arg));
+            }
+            _ => {
+                if roots.len() == 2 {
+                    return Err(format!("Unexpected argument: {}", arg));
+                }
+                roots.push(PathBuf::from(arg));
+            }
+        }
+    }
+    match <[PathBuf; 2]>::try_from(roots) {
+        Ok([left, right]) => {
+            compare.left = left;
+            compare.right = right;
+        }
+        Err(_) if options.show_usage => {}
+        Err(_) => {
+            return Err(String::from("diff needs two directories"));
+        }
+    }
+    options.compare = Some(compare);
+    Ok(options)
+}
+
+fn parse_users_args<I: Iterator<Item = String>>(mut args: I) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut users = UsersOptions::default();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--csv" => {
+                users.csv = Some(PathBuf::from(value_of(&mut args, &arg)?));
+            }
+            "-h" | "--help" => {
+                options.show_usage = true;
+            }
+            _ if arg.starts_with('-') => {
+                return Err(format!("Unknown users option: {}", arg));
+            }
+            _ => {
+                if users.path.is_some() {
+                    return Err(format!("Unexpected argument: {}", arg));
+                }
+                users.path = Some(PathBuf::from(arg));
+            }
+        }
+    }
+    options.users = Some(users);
+    Ok(options)
+}
+
+fn parse_daemon_args<I: Iterator<Item = String>>(mut args: I) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut daemon = DaemonOptions::default();
+    let mut config = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--config" => {
+                config = Some(PathBuf::from(value_of(&mut args, &arg)?));
+            }
+            "--state-dir" => {
+                daemon.state_dir = Some(PathBuf::from(value_of(&mut args, &arg)?));
+            }
+            "--control-socket" => {
+                daemon.control_socket = Some(PathBuf::from(value_of(&mut args, &arg)?));
+            }
+            "--once" => {
+                daemon.once = true;
+            }
+            "-h" | "--help" => {
+                options.show_usage = true;
+            }
+            _ => {
+                return Err(format!("Unknown daemon option: {}", arg));
+            }
+        }
+    }
+    match config {
+        Some(config) => {
+            daemon.config = config;
+        }
+        None if options.show_usage => {}
+        None => {
+            return Err(String::from("daemon needs --config FILE"));
+        }
+    }
+    options.daemon = Some(daemon);
+    Ok(options)
+}
+
+fn parse_history_args<I: Iterator<Item = String>>(mut args: I) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut history = HistoryOptions::default();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" => {
+                history.json = true;
+            }
+            "--state-dir" => {
+                history.state_dir = Some(PathBuf::from(value_of(&mut args, &arg)?));
+            }
+            "-h" | "--help" => {
+                options.show_usage = true;
+            }
+            _ if arg.starts_with('-') => {
+                return Err(format!("Unknown history option: {}", arg));
+            }
+            _ => {
+                if history.path.is_some() {
+                    return Err(format!("Unexpected argument: {}", arg));
+                }
+                history.path = Some(PathBuf::from(arg));
+            }
+        }
+    }
+    options.history = Some(history);
+    Ok(options)
+}
+
+fn parse_control_args<I: Iterator<Item = String>>(mut args: I) -> Result<Options, String> {
+    let mut options = Options::default();
+    match args.next() {
+        Some(arg) if arg == "-h" || arg == "--help" => {
+            options.show_usage = true;
+        }
+        Some(socket) => {
+            let command: Vec<String> = args.collect();
+            if command.is_empty() {
+                return Err(String::from("control needs a command: query PATH, invalidate PATH or export snapshot"));
+            }
+            options.control = Some(ControlOptions { socket: PathBuf::from(socket), command: command.join(" ") });
+        }
+        None => {
+            return Err(String::from("control needs a socket and a command"));
+        }
+    }
+    Ok(options)
+}
@@ -0,0 +1,54 @@
+use icu_collator::{ Collator, CollatorOptions, Strength };
+use icu_locid::Locale;
+use std::{ cmp::Ordering, env, sync::OnceLock };
+
+/// How names are ordered where the sort leaves a tie
+enum Collation {
+    Simple, // Lowercased code points, as before collation was configurable
+    Locale(Box<Collator>), // The CLDR collation of the locale's language
+}
+
+static COLLATION: OnceLock<Collation> = OnceLock::new();
+
+/// Collate names for `locale`, set once at startup: `simple` for plain
+/// lowercase order, or a locale name like `sv_SE.UTF-8`. Without one, the
+/// collation locale of the environment applies.
+pub fn set_locale(locale: &str) {
+    COLLATION.set(collation_for(locale)).unwrap_or(());
+}
+
+fn collation() -> &'static Collation {
+    COLLATION.get_or_init(|| {
+        let locale = ["LC_ALL", "LC_COLLATE", "LANG"]
+            .iter()
+            .filter_map(|name| env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .unwrap_or_default();
+        collation_for(&locale)
+    })
+}
+
+/// The root collation for `C`, `POSIX` and names ICU cannot parse
+fn collation_for(locale: &str) -> Collation {
+    if locale.eq_ignore_ascii_case("simple") {
+        return Collation::Simple;
+    }
+    // POSIX names (`sv_SE.UTF-8@euro`) to BCP 47 (`sv-SE`)
+    let name = locale.split(['.', '@']).next().unwrap_or_default().replace('_', "-");
+    let locale = name.parse::<Locale>().unwrap_or_default();
+    let mut options = CollatorOptions::new();
+    options.strength = Some(Strength::Tertiary);
+    match Collator::try_new(&(&locale).into(), options) {
+        Ok(collator) => Collation::Locale(Box::new(collator)),
+        Err(_) => Collation::Simple,
+    }
+}
+
+/// Compare two names: by their letters first, ignoring accents and case,
+/// then by accents, then lowercase before uppercase
+pub fn compare(a: &str, b: &str) -> Ordering {
+    match collation() {
+        Collation::Simple => a.to_lowercase().cmp(&b.to_lowercase()),
+        Collation::Locale(collator) => collator.compare(a, b).then_with(|| a.cmp(b)),
+    }
+}
@@ -0,0 +1,236 @@
+use crate::{ config::ConfigFile, theme::Theme };
+use ratatui::text::{ Span, Spans };
+use std::collections::{ HashMap, HashSet };
+
+/// Columns of the listing
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Column {
+    Type,
+    Name,
+    Count,
+    #[cfg(feature = "charts")]
+    Bar, // Proportional to the count
+    Percent, // Of the current directory's total
+    Size, // On disk or apparent, see the `a` key
+    Modified,
+    Newest, // Most recent modification below a directory
+    CountTime, // How long counting a directory took
+    #[cfg(unix)]
+    Owner,
+    #[cfg(unix)]
+    Group,
+    #[cfg(unix)]
+    Mode,
+    #[cfg(unix)]
+    Owners, // Who owns most of the files below a directory
+    Delta, // Only with a --diff baseline
+    Git, // Tracked, untracked or ignored, inside a git repository
+}
+
+impl Column {
+    /// Every column, in the default order
+    pub const ALL: &'static [Column] = &[
+        Column::Type,
+        Column::Name,
+        Column::Count,
+        #[cfg(feature = "charts")]
+        Column::Bar,
+        Column::Percent,
+        Column::Size,
+        Column::Modified,
+        Column::Newest,
+        Column::CountTime,
+        #[cfg(unix)]
+        Column::Owner,
+        #[cfg(unix)]
+        Column::Group,
+        #[cfg(unix)]
+        Column::Mode,
+        #[cfg(unix)]
+        Column::Owners,
+        Column::Delta,
+        Column::Git,
+    ];
+
+    /// Name in the config file
+    pub fn key(self) -> &'static str {
+        match self {
+            Column::Type => "type",
+            Column::Name => "name",
+            Column::Count => "count",
+            #[cfg(feature = "charts")]
+            Column::Bar => "bar",
+            Column::Percent => "percent",
+            Column::Size => "size",
+            Column::Modified => "modified",
+            Column::Newest => "newest",
+            Column::CountTime => "count_time",
+            #[cfg(unix)]
+            Column::Owner => "owner",
+            #[cfg(unix)]
+            Column::Group => "group",
+            #[cfg(unix)]
+            Column::Mode => "mode",
+            #[cfg(unix)]
+            Column::Owners => "owners",
+            Column::Delta => "delta",
+            Column::Git => "git",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Column::Type => "Type",
+            Column::Name => "Name",
+            Column::Count => "Count",
+            #[cfg(feature = "charts")]
+            Column::Bar => "Bar (proportional to the count)",
+            Column::Percent => "Percent of the total",
+            Column::Size => "Size (on disk or apparent)",
+            Column::Modified => "Modified",
+            Column::Newest => "Newest below (sort with m)",
+            Column::CountTime => "Count time (slow subtrees)",
+            #[cfg(unix)]
+            Column::Owner => "Owner",
+            #[cfg(unix)]
+            Column::Group => "Group",
+            #[cfg(unix)]
+            Column::Mode => "Mode",
+            #[cfg(unix)]
+            Column::Owners => "Mostly owned by (files below)",
+            Column::Delta => "Delta (with --diff)",
+            Column::Git => "Git status (in a repository)",
+        }
+    }
+
+    /// Whether the column is shown without a `visible` list in the config
+    fn shown_by_default(self) -> bool {
+        match self {
+            #[cfg(unix)]
+            Column::Owner | Column::Group | Column::Mode | Column::Owners => false,
+            Column::CountTime => false,
+            _ => true,
+        }
+    }
+
+    /// Key toggling the column at `index` in the picker; `c` closes it
+    fn picker_key(index: usize) -> Option<char> {
+        PICKER_KEYS.chars().nth(index)
+    }
+}
+
+/// Keys of the column picker, in the order of the columns
+const PICKER_KEYS: &str = "123456789abdefgh";
+
+/// Which columns are shown, in which order, and the widths fixed in the config
+pub struct ColumnLayout {
+    pub order: Vec<Column>, // Every column, the configured ones first
+    pub hidden: HashSet<Column>,
+    pub widths: HashMap<Column, u16>, // Instead of fitting the content
+}
+
+impl Default for ColumnLayout {
+    fn default() -> Self {
+        ColumnLayout {
+            order: Column::ALL.to_vec(),
+            hidden: Column::ALL
+                .iter()
+                .copied()
+                .filter(|column| !column.shown_by_default())
+                .collect(),
+            widths: HashMap::new(),
+        }
+    }
+}
+
+impl ColumnLayout {
+    /// Build the layout from the `[columns]` section of the config file.
+    ///
+    /// `visible` lists the columns shown, in order (the name is always shown);
+    /// `<column>_width` keys fix the width of a column.
+    pub fn from_config(config: &ConfigFile) -> Result<ColumnLayout, String> {
+        let mut layout = ColumnLayout::default();
+        if let Some(visible) = config.get_str_list("columns.visible") {
+            let mut order = Vec::new();
+            for key in &visible {
+                let column = Column::ALL
+                    .iter()
+                    .copied()
+                    .find(|column| column.key() == key)
+                    .ok_or_else(|| {
+                        let keys: Vec<&str> = Column::ALL.iter().map(|column| column.key()).collect();
+                        format!("Unknown column: {} (available: {})", key, keys.join(", "))
+                    })?;
+                if !order.contains(&column) {
+                    order.push(column);
+                }
+            }
+            if !order.contains(&Column::Name) {
+                order.insert(0, Column::Name);
+            }
+            let hidden: Vec<Column> = Column::ALL
+                .iter()
+                .copied()
+                .filter(|column| !order.contains(column))
+                .collect();
+            order.extend(&hidden);
+            layout.hidden = hidden.into_iter().collect();
+            layout.order = order;
+        }
+        for &column in Column::ALL {
+            let key = format!("columns.{}_width", column.key());
+            if let Some(width) = config.get_int(&key) {
+                let width = u16::try_from(width)
+                    .ok()
+                    .filter(|&width| width > 0)
+                    .ok_or_else(|| format!("{} must be a positive width", key))?;
+                layout.widths.insert(column, width);
+            }
+        }
+        Ok(layout)
+    }
+
+    /// Shown columns, in order
+    pub fn visible(&self) -> impl Iterator<Item = Column> + '_ {
+        self.order.iter().copied().filter(|column| !self.hidden.contains(column))
+    }
+
+    /// Show or hide the column whose picker key is `key`; the name stays
+    pub fn toggle(&mut self, key: char) {
+        let index = (0..self.order.len()).find(|&index| Column::picker_key(index) == Some(key));
+        if let Some(&column) = index.and_then(|index| self.order.get(index)).filter(|&&column| column != Column::Name) {
+            if !self.hidden.remove(&column) {
+                self.hidden.insert(column);
+            }
+        }
+    }
+
+    /// Lines of the column picker popup
+    pub fn picker_lines(&self, theme: &Theme) -> Vec<Spans<'static>> {
+        let mut lines: Vec<Spans> = self.order
+            .iter()
+            .enumerate()
+            .map(|(index, column)| {
+                let mark = if self.hidden.contains(column) { "[ ]" } else { "[x]" };
+                Spans::from(
+                    vec![
+                        Span::styled(Column::picker_key(index).unwrap_or(' ').to_string(), theme.key),
+                        Span::raw(format!(" {} {}", mark, column.label()))
+                    ]
+                )
+            })
+            .collect();
+        lines.push(Spans::from(""));
+        lines.push(
+            Spans::from(
+                vec![
+                    Span::styled("Key", theme.key),
+                    Span::raw(" - Show/hide   "),
+                    Span::styled("Esc", theme.key),
+                    Span::raw(" - Close")
+                ]
+            )
+        );
+        lines
+    }
+}
@@ -0,0 +1,147 @@
+use crate::{ cli::CompareOptions, json::Value, numbers::NumberFormat, report::{ build_snapshot, ReportNode } };
+use std::{ collections::BTreeMap, path::{ Path, PathBuf }, thread };
+
+/// How a directory differs between the two trees
+#[derive(Clone, Copy)]
+enum Status {
+    OnlyLeft,
+    OnlyRight,
+    Changed,
+}
+
+impl Status {
+    fn label(self) -> &'static str {
+        match self {
+            Status::OnlyLeft => "only_left",
+            Status::OnlyRight => "only_right",
+            Status::Changed => "changed",
+        }
+    }
+}
+
+/// A directory whose recursive file count differs, by its path relative to the roots
+struct Difference {
+    path: PathBuf,
+    status: Status,
+    left: Option<usize>,
+    right: Option<usize>,
+}
+
+impl Difference {
+    /// The relative path, `.` for the roots
+    fn shown_path(&self) -> String {
+        match self.path.as_os_str().is_empty() {
+            true => String::from("."),
+            false => self.path.display().to_string(),
+        }
+    }
+
+    fn delta(&self) -> i64 {
+        (self.right.unwrap_or(0) as i64) - (self.left.unwrap_or(0) as i64)
+    }
+
+    fn to_json(&self) -> Value {
+        let count = |count: Option<usize>| count.map(Value::from).unwrap_or(Value::Null);
+        Value::Object(
+            vec![
+                (String::from("path"), Value::from(self.shown_path())),
+                (String::from("status"), Value::from(self.status.label())),
+                (String::from("left"), count(self.left)),
+                (String::from("right"), count(self.right)),
+                (String::from("delta"), Value::Number(self.delta() as f64))
+            ]
+        )
+    }
+}
+
+/// Differences between two snapshots, parents before their subdirectories. A
+/// directory present on one side only is reported once, without its subdirectories.
+fn differences(left: &ReportNode, right: &ReportNode, path: &Path, out: &mut Vec<Difference>) {
+    if left.files != right.files {
+        out.push(Difference { path: path.to_path_buf(), status: Status::Changed, left: left.files, right: right.files });
+    }
+    let mut children: BTreeMap<&str, (Option<&ReportNode>, Option<&ReportNode>)> = BTreeMap::new();
+    for child in &left.children {
+        children.entry(child.name.as_str()).or_default().0 = Some(child);
+    }
+    for child in &right.children {
+        children.entry(child.name.as_str()).or_default().1 = Some(child);
+    }
+    for (name, sides) in children {
+        let child_path = path.join(name);
+        match sides {
+            (Some(left), Some(right)) => differences(left, right, &child_path, out),
+            (Some(left), None) =>
+                out.push(Difference { path: child_path, status: Status::OnlyLeft, left: left.files, right: None }),
+            (None, Some(right)) =>
+                out.push(Difference { path: child_path, status: Status::OnlyRight, left: None, right: right.files }),
+            (None, None) => {}
+        }
+    }
+}
+
+/// Walk both roots, print the directories whose file count differs and
+/// return whether any did, so scripts can fail a verification
+pub fn run(options: &CompareOptions) -> Result<bool, String> {
+    let root = |path: &Path| {
+        path.canonicalize()
+            .ok()
+            .filter(|root| root.is_dir())
+            .ok_or_else(|| format!("Not a readable directory: {}", path.display()))
+    };
+    let (left_root, right_root) = (root(&options.left)?, root(&options.right)?);
+    let (left, right) = thread::scope(|scope| {
+        let left = scope.spawn(|| build_snapshot(&left_root));
+        let right = build_snapshot(&right_root);
+        (left.join(), right)
+    });
+    let left = left.map_err(|_| String::from("scan thread panicked"))?;
+
+    let mut found = Vec::new();
+    differences(&left, &right, Path::new(""), &mut found);
+    found.retain(|difference| options.depth.is_none_or(|depth| difference.path.components().count() <= depth));
+
+    if options.json {
+        let root = |path: &Path, node: &ReportNode| {
+            Value::Object(
+                vec![
+                    (String::from("path"), Value::from(path.display().to_string())),
+                    (String::from("files"), node.files.map(Value::from).unwrap_or(Value::Null))
+                ]
+            )
+        };
+        let document = Value::Object(
+            vec![
+                (String::from("left"), root(&left_root, &left)),
+                (String::from("right"), root(&right_root, &right)),
+                (String::from("differences"), Value::Array(found.iter().map(Difference::to_json).collect()))
+            ]
+        );
+        println!("{}", document.to_json());
+        return Ok(!found.is_empty());
+    }
+
+    let numbers = NumberFormat::from_env(options.compact);
+    let count = |count: Option<usize>| count.map(|count| numbers.count(count as u64)).unwrap_or_default();
+    println!("Left:  {} ({} files)", left_root.display(), count(left.files));
+    println!("Right: {} ({} files)", right_root.display(), count(right.files));
+    if found.is_empty() {
+        println!("No differences in directory counts");
+        return Ok(false);
+    }
+    println!();
+    println!("{:<12}{:>12}{:>12}{:>10}  Path", "Status", "Left", "Right", "Delta");
+    for difference in &found {
+        println!(
+            "{:<12}{:>12}{:>12}{:>10}  {}",
+            difference.status.label().replace('_', " "),
+            count(difference.left),
+            count(difference.right),
+            numbers.signed(difference.delta()),
+            difference.shown_path()
+        );
+    }
+    println!();
+    println!("{} directories differ", found.len());
+    Ok(true)
+}
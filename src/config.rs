@@ -0,0 +1,115 @@
+use crate::dirs;
+use std::{ fs, io, path::Path };
+use toml::{ Table, Value };
+
+/// Parsed config file: TOML, where `section.key` names `key` of the
+/// `[section]` table
+#[derive(Default)]
+pub struct ConfigFile {
+    values: Table,
+}
+
+impl ConfigFile {
+    /// Load the config given with --config-file, which must exist, or from the
+    /// default location, where a missing file yields an empty config
+    pub fn load(config_file: Option<&Path>) -> Result<Self, String> {
+        let (path, required) = match config_file {
+            Some(path) => (path.to_path_buf(), true),
+            None =>
+                match dirs::default_config_file() {
+                    Some(path) => (path, false),
+                    None => {
+                        return Ok(ConfigFile::default());
+                    }
+                }
+        };
+        match fs::read_to_string(&path) {
+            Ok(text) => ConfigFile::parse(&text).map_err(|e| format!("{}: {}", path.display(), e)),
+            Err(e) if !required && e.kind() == io::ErrorKind::NotFound => Ok(ConfigFile::default()),
+            Err(e) => Err(format!("{}: {}", path.display(), e)),
+        }
+    }
+
+    /// Parse config text
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let values = text.parse::<Table>().map_err(|e| e.to_string().trim_end().to_string())?;
+        Ok(ConfigFile { values })
+    }
+
+    /// The value at a dotted `section.key` path
+    fn get(&self, key: &str) -> Option<&Value> {
+        let (tables, key) = match key.rsplit_once('.') {
+            Some((tables, key)) => (Some(tables), key),
+            None => (None, key),
+        };
+        let mut table = &self.values;
+        for name in tables.into_iter().flat_map(|tables| tables.split('.')) {
+            table = table.get(name)?.as_table()?;
+        }
+        table.get(key)
+    }
+
+    #[cfg(any(test, feature = "tui"))]
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        self.get(key)?.as_str()
+    }
+
+    pub fn get_int(&self, key: &str) -> Option<i64> {
+        self.get(key)?.as_integer()
+    }
+
+    #[cfg(any(test, feature = "tui"))]
+    pub fn get_bool(&self, key: &str) -> Option<bool> {
+        self.get(key)?.as_bool()
+    }
+
+    /// An array of strings; None if any item is not one
+    pub fn get_str_list(&self, key: &str) -> Option<Vec<String>> {
+        self.get(key)?
+            .as_array()?
+            .iter()
+            .map(|item| item.as_str().map(str::to_string))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_sections_arrays_and_strings() {
+        let config = ConfigFile::parse(
+            r#"
+            top = 1
+
+            [daemon]
+            roots = [
+                "/data/logs",  # one per line
+                '/srv/C:\literal',
+            ]
+            interval = 1_800
+
+            [display]
+            min_count = 10
+            collation = "sv_SE"
+            compact_counts = true
+            columns = { order = ["name", "count"] }
+            "#
+        ).unwrap();
+        assert_eq!(config.get_int("top"), Some(1));
+        assert_eq!(config.get_str_list("daemon.roots"), Some(vec![String::from("/data/logs"), String::from(r"/srv/C:\literal")]));
+        assert_eq!(config.get_int("daemon.interval"), Some(1800));
+        assert_eq!(config.get_str("display.collation"), Some("sv_SE"));
+        assert_eq!(config.get_bool("display.compact_counts"), Some(true));
+        assert_eq!(config.get_str_list("display.columns.order").map(|order| order.len()), Some(2));
+        assert_eq!(config.get_int("display.collation"), None);
+        assert_eq!(config.get_int("missing.key"), None);
+    }
+
+    #[test]
+    fn reports_the_line_of_an_error() {
+        let error = ConfigFile::parse("[scan]\nmax_depth = = 3\n").err().unwrap();
+        assert!(error.contains("line 2"), "{}", error);
+    }
+}
@@ -0,0 +1,184 @@
+#[cfg(any(feature = "tui", feature = "persist"))]
+use crate::json::Value;
+use std::path::{ Path, PathBuf };
+#[cfg(any(feature = "tui", feature = "persist"))]
+use std::sync::mpsc::Sender;
+#[cfg(unix)]
+use std::{ io::{ BufRead, BufReader, Write }, os::unix::net::UnixStream, time::Duration };
+#[cfg(all(unix, any(feature = "tui", feature = "persist")))]
+use std::{ fs, os::unix::net::UnixListener, sync::mpsc::channel, thread };
+
+/// How long a client waits for the instance to answer a command
+#[cfg(unix)]
+const REPLY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A command sent to a running instance over its control socket, one per line;
+/// a build that only sends commands parses them just to check them
+#[cfg_attr(not(any(feature = "tui", feature = "persist")), allow(dead_code))]
+pub enum Command {
+    Query(PathBuf), // `query PATH`: the cached recursive count of a directory
+    Invalidate(PathBuf), // `invalidate PATH`: forget the counts of a directory, what is below it and its ancestors
+    ExportSnapshot, // `export snapshot`: every count cached so far
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Result<Command, String> {
+        let line = line.trim();
+        let (verb, argument) = line.split_once(' ').unwrap_or((line, ""));
+        let argument = argument.trim();
+        match (verb, argument) {
+            ("query", path) if !path.is_empty() => Ok(Command::Query(PathBuf::from(path))),
+            ("invalidate", path) if !path.is_empty() => Ok(Command::Invalidate(PathBuf::from(path))),
+            ("export", "snapshot") => Ok(Command::ExportSnapshot),
+            _ => Err(format!("unknown command: {} (query PATH, invalidate PATH, export snapshot)", line)),
+        }
+    }
+}
+
+#[cfg(any(feature = "tui", feature = "persist"))]
+/// A command waiting for the instance's answer
+pub struct Request {
+    pub command: Command,
+    reply: Sender<Value>,
+}
+
+#[cfg(any(feature = "tui", feature = "persist"))]
+impl Request {
+    /// Answer with a JSON value, written back as one line
+    pub fn respond(self, value: Value) {
+        self.reply.send(value).unwrap_or(());
+    }
+}
+
+#[cfg(any(feature = "tui", feature = "persist"))]
+/// `{"error": message}`
+pub fn error(message: impl Into<String>) -> Value {
+    Value::Object(vec![(String::from("error"), Value::from(message.into()))])
+}
+
+#[cfg(any(feature = "tui", feature = "persist"))]
+/// Answer to `query`: the count, or null while it is not known
+pub fn count_answer(path: &Path, files: Option<usize>, counting: bool) -> Value {
+    Value::Object(
+        vec![
+            (String::from("path"), Value::from(path.display().to_string())),
+            (String::from("files"), files.map_or(Value::Null, Value::from)),
+            (String::from("counting"), Value::from(counting))
+        ]
+    )
+}
+
+#[cfg(any(feature = "tui", feature = "persist"))]
+/// Answer to `invalidate`: how many cached counts were dropped
+pub fn invalidated_answer(dropped: usize) -> Value {
+    Value::Object(vec![(String::from("invalidated"), Value::from(dropped))])
+}
+
+#[cfg(any(feature = "tui", feature = "persist"))]
+/// Answer to `export snapshot`: the counts by absolute path, sorted
+pub fn snapshot_answer(mut counts: Vec<(PathBuf, usize)>) -> Value {
+    counts.sort();
+    let counts = counts
+        .into_iter()
+        .map(|(path, files)| (path.display().to_string(), Value::from(files)))
+        .collect();
+    Value::Object(vec![(String::from("counts"), Value::Object(counts))])
+}
+
+#[cfg(any(feature = "tui", feature = "persist"))]
+/// The socket a running instance listens on; removed when dropped
+pub struct ControlSocket {
+    path: PathBuf,
+}
+
+#[cfg(any(feature = "tui", feature = "persist"))]
+impl Drop for ControlSocket {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Accept connections on `path` and forward every command read from them to
+/// `sender`, wrapped by `wrap`; whoever receives it answers through the request.
+/// A socket file left behind by an instance that is gone is replaced.
+#[cfg(all(unix, any(feature = "tui", feature = "persist")))]
+pub fn listen<T: Send + 'static>(path: &Path, sender: Sender<T>, wrap: fn(Request) -> T) -> Result<ControlSocket, String> {
+    if path.exists() {
+        if UnixStream::connect(path).is_ok() {
+            return Err(format!("{} is in use by another instance", path.display()));
+        }
+        fs::remove_file(path).map_err(|e| format!("Cannot replace {}: {}", path.display(), e))?;
+    }
+    let listener = UnixListener::bind(path).map_err(|e| format!("Cannot listen on {}: {}", path.display(), e))?;
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let sender = sender.clone();
+            thread::spawn(move || serve(stream, sender, wrap));
+        }
+    });
+    Ok(ControlSocket { path: path.to_path_buf() })
+}
+
+#[cfg(all(not(unix), any(feature = "tui", feature = "persist")))]
+pub fn listen<T: Send + 'static>(path: &Path, _sender: Sender<T>, _wrap: fn(Request) -> T) -> Result<ControlSocket, String> {
+    Err(format!("Cannot listen on {}: control sockets are only available on Unix", path.display()))
+}
+
+/// Answer the commands of one connection until it is closed
+#[cfg(all(unix, any(feature = "tui", feature = "persist")))]
+fn serve<T>(stream: UnixStream, sender: Sender<T>, wrap: fn(Request) -> T) {
+    let mut writer = match stream.try_clone() {
+        Ok(writer) => writer,
+        Err(_) => {
+            return;
+        }
+    };
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else {
+            return;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let answer = match Command::parse(&line) {
+            Ok(command) => {
+                let (reply, answer) = channel();
+                if sender.send(wrap(Request { command, reply })).is_err() {
+                    return; // The instance is shutting down
+                }
+                answer.recv_timeout(REPLY_TIMEOUT).unwrap_or_else(|_| error("no answer from the instance"))
+            }
+            Err(e) => error(e),
+        };
+        if writeln!(writer, "{}", answer.to_json()).is_err() {
+            return;
+        }
+    }
+}
+
+/// Send one command to the instance listening on `socket` and return its
+/// answer; `Err` also when the instance answered with an error
+#[cfg(unix)]
+pub fn send(socket: &Path, command: &str) -> Result<String, String> {
+    Command::parse(command)?;
+    let mut stream = UnixStream::connect(socket).map_err(|e| format!("Cannot connect to {}: {}", socket.display(), e))?;
+    stream.set_read_timeout(Some(REPLY_TIMEOUT)).unwrap_or(());
+    writeln!(stream, "{}", command.trim()).map_err(|e| e.to_string())?;
+    let mut answer = String::new();
+    BufReader::new(stream).read_line(&mut answer).map_err(|e| e.to_string())?;
+    let answer = answer.trim_end().to_string();
+    match crate::json::parse(&answer) {
+        Ok(value) =>
+            match value.get("error") {
+                Some(error) => Err(error.as_str().unwrap_or_default().to_string()),
+                None => Ok(answer),
+            }
+        Err(_) => Err(String::from("no answer from the instance")),
+    }
+}
+
+#[cfg(not(unix))]
+pub fn send(socket: &Path, _command: &str) -> Result<String, String> {
+    Err(format!("Cannot connect to {}: control sockets are only available on Unix", socket.display()))
+}
@@ -0,0 +1,345 @@
+use crate::config::ConfigFile;
+use dashmap::{ DashMap, DashSet };
+use std::{ mem, path::{ Path, PathBuf }, sync::atomic::{ AtomicU64, Ordering }, time::SystemTime };
+
+/// Default `cache.max_memory_mb`, room for about two million directories
+const DEFAULT_MAX_MEMORY_MB: i64 = 256;
+/// Eviction goes this many percent below the budget, so that it does not run
+/// again for each count arriving next
+const EVICTION_HEADROOM_PERCENT: u64 = 10;
+/// Memory of an entry besides the bytes of its path: the key, the value and
+/// the map's own bookkeeping
+const ENTRY_OVERHEAD: usize = mem::size_of::<PathBuf>() + mem::size_of::<Cached>() + 16;
+
+struct Cached {
+    files: usize,
+    counted: SystemTime,
+    last_used: AtomicU64, // Tick of the cache's clock at the last lookup or store
+}
+
+/// A count as the cache view lists it
+pub struct CachedCount {
+    pub path: PathBuf,
+    pub files: usize,
+    pub counted: SystemTime,
+    pub pinned: bool,
+}
+
+/// How much the count cache may hold (`[cache]` in the config file); None is unlimited
+#[derive(Clone, Copy, Debug)]
+pub struct CacheBudget {
+    pub max_entries: Option<usize>,
+    pub max_bytes: Option<u64>,
+}
+
+impl CacheBudget {
+    /// `cache.max_entries` and `cache.max_memory_mb`, where 0 lifts the limit
+    pub fn from_config(config: &ConfigFile) -> Result<Self, String> {
+        let limit = |key: &str, default: Option<i64>| -> Result<Option<u64>, String> {
+            match config.get_int(key).or(default) {
+                Some(n) if n < 0 => Err(format!("{} must not be negative", key)),
+                Some(0) | None => Ok(None),
+                Some(n) => Ok(Some(n as u64)),
+            }
+        };
+        Ok(CacheBudget {
+            max_entries: limit("cache.max_entries", None)?.map(|n| n as usize),
+            max_bytes: limit("cache.max_memory_mb", Some(DEFAULT_MAX_MEMORY_MB))?.map(|mb| mb << 20),
+        })
+    }
+}
+
+/// Use of the count cache since the start of the session
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CacheStats {
+    pub entries: usize,
+    pub bytes: u64, // Approximate
+    pub hits: u64,
+    pub misses: u64,
+    pub evicted: u64,
+    pub pinned: usize,
+}
+
+impl CacheStats {
+    /// Share of the lookups answered from the cache, once there were any
+    pub fn hit_rate(&self) -> Option<f64> {
+        let lookups = self.hits + self.misses;
+        (lookups > 0).then(|| (self.hits as f64) / (lookups as f64))
+    }
+}
+
+/// Recursive file counts by directory, shared with the counting threads.
+/// Past its budget the least recently used counts are evicted; they are
+/// counted again when needed.
+pub struct CountCache {
+    counts: DashMap<PathBuf, Cached>,
+    pinned: DashSet<PathBuf>, // Never evicted, whether counted yet or not
+    budget: CacheBudget,
+    clock: AtomicU64, // Bumped by each lookup and store
+    bytes: AtomicU64, // Approximate memory of the entries
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evicted: AtomicU64,
+}
+
+impl CountCache {
+    pub fn new(budget: CacheBudget) -> Self {
+        CountCache {
+            counts: DashMap::new(),
+            pinned: DashSet::new(),
+            budget,
+            clock: AtomicU64::new(0),
+            bytes: AtomicU64::new(0),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evicted: AtomicU64::new(0),
+        }
+    }
+
+    fn tick(&self) -> u64 {
+        self.clock.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    /// The count of `path` when a view needs it, recorded as a hit or a miss
+    /// and keeping the entry from eviction for longer
+    pub fn get(&self, path: &Path) -> Option<usize> {
+        match self.counts.get(path) {
+            Some(cached) => {
+                cached.last_used.store(self.tick(), Ordering::Relaxed);
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                Some(cached.files)
+            }
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    /// The count of `path` without touching the statistics or the eviction order
+    pub fn peek(&self, path: &Path) -> Option<usize> {
+        self.counts.get(path).map(|cached| cached.files)
+    }
+
+    pub fn contains_key(&self, path: &Path) -> bool {
+        self.counts.contains_key(path)
+    }
+
+    pub fn insert(&self, path: PathBuf, files: usize) {
+        let size = entry_bytes(&path);
+        let cached = Cached { files, counted: SystemTime::now(), last_used: AtomicU64::new(self.tick()) };
+        if self.counts.insert(path, cached).is_none() {
+            self.bytes.fetch_add(size, Ordering::Relaxed);
+        }
+    }
+
+    /// Replace the count of `path` with `update(count)`, if it is cached
+    pub fn update(&self, path: &Path, update: impl FnOnce(usize) -> usize) {
+        if let Some(mut cached) = self.counts.get_mut(path) {
+            cached.files = update(cached.files);
+        }
+    }
+
+    pub fn remove(&self, path: &Path) {
+        if self.counts.remove(path).is_some() {
+            self.bytes.fetch_sub(entry_bytes(path), Ordering::Relaxed);
+        }
+    }
+
+    /// Keep only the counts whose path passes `keep`; returns how many were dropped.
+    /// `keep` runs with a part of the map locked, so it must not use the cache.
+    pub fn retain(&self, mut keep: impl FnMut(&Path) -> bool) -> usize {
+        let before = self.counts.len();
+        self.counts.retain(|path, _| {
+            let kept = keep(path);
+            if !kept {
+                self.bytes.fetch_sub(entry_bytes(path), Ordering::Relaxed);
+            }
+            kept
+        });
+        before.saturating_sub(self.counts.len())
+    }
+
+    pub fn clear(&self) {
+        self.retain(|_| false);
+    }
+
+    pub fn budget(&self) -> CacheBudget {
+        self.budget
+    }
+
+    /// Pin `path` so that its count is never evicted, or unpin it; returns whether it is pinned now
+    pub fn toggle_pin(&self, path: &Path) -> bool {
+        if self.pinned.remove(path).is_some() {
+            return false;
+        }
+        self.pinned.insert(path.to_path_buf());
+        true
+    }
+
+    /// Pin `path`, whether it is counted yet or not
+    #[cfg(feature = "persist")]
+    pub fn pin(&self, path: PathBuf) {
+        self.pinned.insert(path);
+    }
+
+    /// The pinned paths, sorted
+    #[cfg(feature = "persist")]
+    pub fn pinned(&self) -> Vec<PathBuf> {
+        let mut pinned: Vec<PathBuf> = self.pinned.iter().map(|path| path.clone()).collect();
+        pinned.sort();
+        pinned
+    }
+
+    /// The `limit` counts made the longest ago
+    pub fn oldest(&self, limit: usize) -> Vec<CachedCount> {
+        let mut counts: Vec<CachedCount> = self.counts
+            .iter()
+            .map(|entry| CachedCount {
+                path: entry.key().clone(),
+                files: entry.files,
+                counted: entry.counted,
+                pinned: self.pinned.contains(entry.key()),
+            })
+            .collect();
+        counts.sort_by(|a, b| a.counted.cmp(&b.counted).then_with(|| a.path.cmp(&b.path)));
+        counts.truncate(limit);
+        counts
+    }
+
+    /// Every cached count
+    pub fn snapshot(&self) -> Vec<(PathBuf, usize)> {
+        self.counts
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.files))
+            .collect()
+    }
+
+    /// Once the cache is over its budget, evict the least recently used counts
+    /// until it is somewhat below, except pinned ones and those `in_use` says
+    /// are needed. `in_use` is called once the map is no longer iterated, so it
+    /// may look up the cache.
+    /// Returns the directories evicted, so that what else is cached about them
+    /// can go as well.
+    pub fn evict_over_budget(&self, in_use: impl Fn(&Path) -> bool) -> Vec<PathBuf> {
+        let entries = self.counts.len() as u64;
+        let bytes = self.bytes.load(Ordering::Relaxed);
+        let over_entries = self.budget.max_entries.is_some_and(|max| entries > (max as u64));
+        let over_bytes = self.budget.max_bytes.is_some_and(|max| bytes > max);
+        if !over_entries && !over_bytes {
+            return Vec::new();
+        }
+        let below = |max: u64| max - (max * EVICTION_HEADROOM_PERCENT) / 100;
+        let target_entries = self.budget.max_entries.map_or(u64::MAX, |max| below(max as u64));
+        let target_bytes = self.budget.max_bytes.map_or(u64::MAX, below);
+
+        let mut by_age: Vec<(u64, PathBuf)> = self.counts
+            .iter()
+            .filter(|entry| !self.pinned.contains(entry.key()))
+            .map(|entry| (entry.last_used.load(Ordering::Relaxed), entry.key().clone()))
+            .collect();
+        by_age.retain(|(_, path)| !in_use(path));
+        by_age.sort_unstable_by_key(|(last_used, _)| *last_used);
+
+        let mut evicted = Vec::new();
+        for (last_used, path) in by_age {
+            if
+                (self.counts.len() as u64) <= target_entries &&
+                self.bytes.load(Ordering::Relaxed) <= target_bytes
+            {
+                break;
+            }
+            // A count used or stored again meanwhile stays
+            let removed = self.counts.remove_if(&path, |_, cached| cached.last_used.load(Ordering::Relaxed) == last_used);
+            if removed.is_some() {
+                self.bytes.fetch_sub(entry_bytes(&path), Ordering::Relaxed);
+                evicted.push(path);
+            }
+        }
+        self.evicted.fetch_add(evicted.len() as u64, Ordering::Relaxed);
+        evicted
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            entries: self.counts.len(),
+            bytes: self.bytes.load(Ordering::Relaxed),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evicted: self.evicted.load(Ordering::Relaxed),
+            pinned: self.pinned.len(),
+        }
+    }
+}
+
+/// Approximate memory an entry for `path` takes
+fn entry_bytes(path: &Path) -> u64 {
+    (path.as_os_str().len() + ENTRY_OVERHEAD) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(max_entries: usize) -> CountCache {
+        CountCache::new(CacheBudget { max_entries: Some(max_entries), max_bytes: None })
+    }
+
+    fn paths(names: &[&str]) -> Vec<PathBuf> {
+        names.iter().map(PathBuf::from).collect()
+    }
+
+    #[test]
+    fn evicts_the_least_recently_used() {
+        let cache = cache(3);
+        for name in ["/a", "/b", "/c"] {
+            cache.insert(PathBuf::from(name), 1);
+        }
+        assert!(cache.evict_over_budget(|_| false).is_empty());
+        // Looking /a up makes /b the oldest
+        cache.get(Path::new("/a"));
+        cache.insert(PathBuf::from("/d"), 1);
+        // 10% below a budget of 3 is 3, so one goes
+        assert_eq!(cache.evict_over_budget(|_| false), paths(&["/b"]));
+        cache.insert(PathBuf::from("/e"), 1);
+        assert_eq!(cache.evict_over_budget(|_| false), paths(&["/c"]));
+        assert_eq!(cache.stats().evicted, 2);
+    }
+
+    #[test]
+    fn keeps_pinned_counts_and_those_in_use() {
+        let cache = cache(2);
+        for name in ["/a", "/b", "/c", "/d"] {
+            cache.insert(PathBuf::from(name), 1);
+        }
+        assert!(cache.toggle_pin(Path::new("/a")));
+        // The callback may use the cache
+        let evicted = cache.evict_over_budget(|path| path == Path::new("/b") && cache.peek(path).is_some());
+        assert_eq!(evicted, paths(&["/c", "/d"]));
+        assert_eq!(cache.peek(Path::new("/a")), Some(1));
+        assert_eq!(cache.peek(Path::new("/b")), Some(1));
+
+        // Unpinned, it goes first
+        assert!(!cache.toggle_pin(Path::new("/a")));
+        cache.insert(PathBuf::from("/e"), 1);
+        assert_eq!(cache.evict_over_budget(|_| false), paths(&["/a"]));
+    }
+
+    #[test]
+    fn bytes_follow_the_entries() {
+        let cache = cache(10);
+        let bytes = |names: &[&str]| names.iter().map(|name| entry_bytes(Path::new(name))).sum::<u64>();
+        cache.insert(PathBuf::from("/data"), 1);
+        cache.insert(PathBuf::from("/data/logs"), 2);
+        cache.insert(PathBuf::from("/data/logs"), 3); // Replaced, not added
+        assert_eq!(cache.stats().bytes, bytes(&["/data", "/data/logs"]));
+        cache.remove(Path::new("/data"));
+        cache.remove(Path::new("/data")); // Already gone
+        assert_eq!(cache.stats().bytes, bytes(&["/data/logs"]));
+        cache.insert(PathBuf::from("/srv"), 1);
+        assert_eq!(cache.retain(|path| path.starts_with("/srv")), 1);
+        assert_eq!(cache.stats().bytes, bytes(&["/srv"]));
+        cache.clear();
+        assert_eq!((cache.stats().entries, cache.stats().bytes), (0, 0));
+    }
+}
@@ -0,0 +1,45 @@
+use std::{ env, path::{ Path, PathBuf } };
+
+/// Subdirectory of the base directories used by file_counter
+const APP_DIR: &str = "file_counter";
+
+/// A base directory from an environment variable. Relative paths are ignored,
+/// as the XDG base directory specification requires.
+fn env_dir(var: &str) -> Option<PathBuf> {
+    let dir = PathBuf::from(env::var_os(var)?);
+    dir.is_absolute().then_some(dir)
+}
+
+/// `$XDG_<kind>_HOME`, or the given directory below `$HOME`
+#[cfg(unix)]
+fn base_dir(xdg_var: &str, home_relative: &str) -> Option<PathBuf> {
+    env_dir(xdg_var).or_else(|| env_dir("HOME").map(|home| home.join(home_relative)))
+}
+
+/// Config roams with the profile (`%APPDATA%`), cache and state stay on the machine (`%LOCALAPPDATA%`)
+#[cfg(windows)]
+fn base_dir(xdg_var: &str, _home_relative: &str) -> Option<PathBuf> {
+    let windows_var = if xdg_var == "XDG_CONFIG_HOME" { "APPDATA" } else { "LOCALAPPDATA" };
+    env_dir(xdg_var).or_else(|| env_dir(windows_var))
+}
+
+/// Default config file: `$XDG_CONFIG_HOME/file_counter/config.toml`
+pub fn default_config_file() -> Option<PathBuf> {
+    Some(base_dir("XDG_CONFIG_HOME", ".config")?.join(APP_DIR).join("config.toml"))
+}
+
+/// Directory for data that can be recomputed: `--cache-dir`, or `$XDG_CACHE_HOME/file_counter`
+pub fn cache_dir(override_dir: Option<&Path>) -> Option<PathBuf> {
+    match override_dir {
+        Some(dir) => Some(dir.to_path_buf()),
+        None => Some(base_dir("XDG_CACHE_HOME", ".cache")?.join(APP_DIR)),
+    }
+}
+
+/// Directory for state kept between runs: `--state-dir`, or `$XDG_STATE_HOME/file_counter`
+pub fn state_dir(override_dir: Option<&Path>) -> Option<PathBuf> {
+    match override_dir {
+        Some(dir) => Some(dir.to_path_buf()),
+        None => Some(base_dir("XDG_STATE_HOME", ".local/state")?.join(APP_DIR)),
+    }
+}
@@ -0,0 +1,242 @@
+use crate::{ cli::DoctorOptions, dirs, mountinfo::FsClasses, scan::{ hard_link_identity, scan_dir, ByteSizes, ScanOptions } };
+use std::{ collections::HashSet, env, fs, io::IsTerminal, path::{ Path, PathBuf }, time::Instant };
+
+/// Soft open-file limits below this are reported, concurrent scans each keep a few directories open
+const LOW_FD_LIMIT: u64 = 1024;
+
+/// Scanner settings compared side by side
+const PRESETS: &[(&str, ScanOptions)] = &[
+    ("bundles descended (Linux/Windows default)", preset(false, false)),
+    ("bundles as one file (macOS default, like Finder)", preset(true, false)),
+    ("hard links counted once (scan.dedupe_hard_links)", preset(false, true)),
+];
+
+const fn preset(opaque_bundles: bool, dedupe_hard_links: bool) -> ScanOptions {
+    ScanOptions {
+        collect_extensions: false,
+        opaque_bundles,
+        dedupe_hard_links,
+        #[cfg(feature = "archive")]
+        count_archives: false,
+        collect_owners: false,
+        collect_junk: false,
+        sniff_types: false,
+        count_only: false,
+        exclude_fs: FsClasses::NONE,
+    }
+}
+
+/// What a walk that follows nothing finds in a tree, to explain how other
+/// tools arrive at different numbers
+#[derive(Default)]
+struct TreeCensus {
+    regular_files: usize, // Not through symlinks, like `find -type f`
+    directories: usize, // Below the root
+    file_symlinks: usize,
+    dir_symlinks: usize,
+    dangling_symlinks: usize,
+    special_files: usize, // Sockets, FIFOs, devices
+    extra_hard_links: usize, // Regular files seen again through another hard link
+    hidden_files: usize, // Regular files whose name starts with a dot
+    unreadable_dirs: usize,
+    bytes: ByteSizes,
+}
+
+impl TreeCensus {
+    fn all_entries(&self) -> usize {
+        self.regular_files +
+            self.directories +
+            self.file_symlinks +
+            self.dir_symlinks +
+            self.dangling_symlinks +
+            self.special_files
+    }
+}
+
+/// Walk `root` without following symlinks
+fn take_census(root: &Path) -> TreeCensus {
+    let mut census = TreeCensus::default();
+    let mut inodes = HashSet::new();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            census.unreadable_dirs += 1;
+            continue;
+        };
+        for entry in entries.flatten() {
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            let file_type = metadata.file_type();
+            if file_type.is_symlink() {
+                match fs::metadata(entry.path()) {
+                    Ok(target) if target.is_dir() => {
+                        census.dir_symlinks += 1;
+                    }
+                    Ok(_) => {
+                        census.file_symlinks += 1;
+                    }
+                    Err(_) => {
+                        census.dangling_symlinks += 1;
+                    }
+                }
+            } else if file_type.is_dir() {
+                census.directories += 1;
+                pending.push(entry.path());
+            } else if file_type.is_file() {
+                census.regular_files += 1;
+                census.bytes.add(ByteSizes::of(&metadata));
+                if entry.file_name().to_string_lossy().starts_with('.') {
+                    census.hidden_files += 1;
+                }
+                if let Some(inode) = hard_link_identity(&metadata) {
+                    if !inodes.insert(inode) {
+                        census.extra_hard_links += 1;
+                    }
+                }
+            } else {
+                census.special_files += 1;
+            }
+        }
+    }
+    census
+}
+
+/// Soft and hard limits on open files
+#[cfg(target_os = "linux")]
+fn open_file_limits() -> Option<(String, String)> {
+    let limits = fs::read_to_string("/proc/self/limits").ok()?;
+    let line = limits.lines().find(|line| line.starts_with("Max open files"))?;
+    let mut fields = line["Max open files".len()..].split_whitespace();
+    Some((fields.next()?.to_string(), fields.next()?.to_string()))
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn open_file_limits() -> Option<(String, String)> {
+    let output = std::process::Command::new("sh").args(["-c", "ulimit -Sn; ulimit -Hn"]).output().ok()?;
+    let text = String::from_utf8_lossy(&output.stdout).into_owned();
+    let mut lines = text.lines();
+    Some((lines.next()?.trim().to_string(), lines.next()?.trim().to_string()))
+}
+
+#[cfg(not(unix))]
+fn open_file_limits() -> Option<(String, String)> {
+    None
+}
+
+/// Whether files can be created in `dir`, creating it if needed
+fn check_writable(dir: &Path) -> Result<(), String> {
+    fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    let probe = dir.join(format!(".doctor-{}", std::process::id()));
+    fs::write(&probe, b"").map_err(|e| e.to_string())?;
+    fs::remove_file(&probe).map_err(|e| e.to_string())
+}
+
+fn describe_dir(label: &str, dir: Option<PathBuf>) -> String {
+    match dir {
+        Some(dir) =>
+            match check_writable(&dir) {
+                Ok(()) => format!("{:<18}{} (writable)", label, dir.display()),
+                Err(e) => format!("{:<18}{} (NOT writable: {})", label, dir.display(), e),
+            }
+        None => format!("{:<18}unknown (set $HOME or pass an explicit directory)", label),
+    }
+}
+
+/// Scan `dir` with each preset, explain why other tools count differently and
+/// check the environment. Meant for a small subtree: it is read several times.
+pub fn run(dir: &Path, options: &DoctorOptions) -> Result<(), String> {
+    let root = dir.canonicalize().map_err(|e| format!("Cannot open {}: {}", dir.display(), e))?;
+    println!("Counting semantics for {}", root.display());
+    println!();
+    println!("{:<52}{:>10}{:>14}{:>14}{:>9}", "file_counter", "Files", "On disk", "Apparent", "Time");
+    let mut counted = Vec::new();
+    let defaults = ScanOptions::default();
+    for (name, options) in PRESETS {
+        let started = Instant::now();
+        let stats = scan_dir(&root, options).map_err(|e| e.to_string())?;
+        let is_default =
+            options.opaque_bundles == defaults.opaque_bundles && options.dedupe_hard_links == defaults.dedupe_hard_links;
+        let default = if is_default { " *" } else { "" };
+        println!(
+            "{:<52}{:>10}{:>14}{:>14}{:>7}ms",
+            format!("{}{}", name, default),
+            stats.files,
+            stats.bytes.disk,
+            stats.bytes.apparent,
+            started.elapsed().as_millis()
+        );
+        if !options.dedupe_hard_links {
+            counted.push(stats.files);
+        }
+    }
+    println!("(* this platform's default; change with scan.opaque_bundles, the `b` key or scan.dedupe_hard_links)");
+
+    let census = take_census(&root);
+    println!();
+    println!("{:<52}{:>10}{:>14}{:>14}", "Other ways to count the same tree", "Files", "On disk", "Apparent");
+    println!(
+        "{:<52}{:>10}{:>14}{:>14}",
+        "find -type f (symlinks not followed)",
+        census.regular_files,
+        census.bytes.disk,
+        census.bytes.apparent
+    );
+    println!("{:<52}{:>10}", "find -type f, hard links once (like du)", census.regular_files - census.extra_hard_links);
+    println!("{:<52}{:>10}", "every entry, directories included (find | wc -l)", census.all_entries());
+
+    println!();
+    println!("Why the numbers differ");
+    let notes = [
+        (census.directories, "directories are never counted as files"),
+        (census.file_symlinks, "symlinks to files are counted as files (find -type f skips them)"),
+        (
+            census.dir_symlinks,
+            "symlinks to directories are descended, but every directory is counted once even when reached twice (bind mounts, loops)",
+        ),
+        (census.dangling_symlinks, "dangling symlinks are skipped"),
+        (census.special_files, "sockets, FIFOs and devices are skipped"),
+        (census.extra_hard_links, "extra hard links are counted again, once per name, unless scan.dedupe_hard_links is set (du counts them once)"),
+        (census.unreadable_dirs, "directories could not be read and are skipped (try running with more privileges)"),
+        (census.hidden_files, "hidden files (names starting with a dot) are counted like any other file"),
+    ];
+    for (count, note) in notes {
+        if count > 0 {
+            println!("  {:>8}  {}", count, note);
+        }
+    }
+    if counted.windows(2).any(|pair| pair[0] != pair[1]) {
+        println!("            bundles (.app, .photoslibrary, ...) count as one file only when opaque");
+    }
+    println!("  Sizes are of counted files only; on disk is the allocated space, apparent the file lengths.");
+
+    println!();
+    println!("Environment");
+    match open_file_limits() {
+        Some((soft, hard)) => {
+            let low = soft.parse::<u64>().is_ok_and(|soft| soft < LOW_FD_LIMIT);
+            let advice = if low { " (low: raise with `ulimit -n` if scans stall)" } else { "" };
+            println!("{:<18}{} soft, {} hard{}", "Open files limit", soft, hard, advice);
+        }
+        None => println!("{:<18}not checked on this platform", "Open files limit"),
+    }
+    let terminal = if std::io::stdout().is_terminal() { "stdout is a terminal" } else { "stdout is not a terminal" };
+    let variable = |name: &str| env::var(name).unwrap_or_else(|_| String::from("unset"));
+    println!(
+        "{:<18}{}, TERM={}, COLORTERM={}, NO_COLOR={}",
+        "Terminal",
+        terminal,
+        variable("TERM"),
+        variable("COLORTERM"),
+        variable("NO_COLOR")
+    );
+    println!("{:<18}{} threads", "CPUs", num_cpus::get());
+    println!("{}", describe_dir("Cache directory", dirs::cache_dir(options.cache_dir.as_deref())));
+    println!("{}", describe_dir("State directory", dirs::state_dir(options.state_dir.as_deref())));
+    match dirs::default_config_file() {
+        Some(file) if file.exists() => println!("{:<18}{}", "Config file", file.display()),
+        Some(file) => println!("{:<18}{} (not present, defaults apply)", "Config file", file.display()),
+        None => println!("{:<18}unknown", "Config file"),
+    }
+    Ok(())
+}
@@ -0,0 +1,235 @@
+use crate::{ format, numbers::NumberFormat, theme::Theme };
+use ratatui::text::{ Span, Spans };
+use std::path::{ Path, PathBuf };
+
+/// A drive or mounted filesystem that can be scanned
+pub struct Drive {
+    pub root: PathBuf,
+    pub kind: &'static str, // fixed, removable, network, ...
+    pub free: Option<u64>, // Bytes available to this user
+    pub total: Option<u64>,
+}
+
+/// Drive picker (`V`, and at startup on Windows without a directory)
+pub struct DriveView {
+    pub drives: Vec<Drive>,
+    pub selected: usize,
+}
+
+impl DriveView {
+    pub fn new(current_dir: &Path) -> DriveView {
+        let drives = list_drives();
+        // Start on the drive holding the current directory
+        let selected = drives
+            .iter()
+            .enumerate()
+            .filter(|(_, drive)| current_dir.starts_with(&drive.root))
+            .max_by_key(|(_, drive)| drive.root.as_os_str().len())
+            .map_or(0, |(index, _)| index);
+        DriveView { drives, selected }
+    }
+
+    pub fn move_selection(&mut self, down: bool) {
+        self.selected = match down {
+            true => (self.selected + 1).min(self.drives.len().saturating_sub(1)),
+            false => self.selected.saturating_sub(1),
+        };
+    }
+
+    pub fn target(&self) -> Option<&Path> {
+        self.drives.get(self.selected).map(|drive| drive.root.as_path())
+    }
+
+    pub fn lines(&self, theme: &Theme) -> Vec<Spans<'static>> {
+        let mut lines = Vec::new();
+        if self.drives.is_empty() {
+            lines.push(Spans::from("No drives found"));
+        }
+        let width = self.drives
+            .iter()
+            .map(|drive| drive.root.as_os_str().len())
+            .max()
+            .unwrap_or(0);
+        for (index, drive) in self.drives.iter().enumerate() {
+            let space = match (drive.free, drive.total) {
+                (Some(free), Some(total)) if total > 0 => {
+                    let used = (total.saturating_sub(free) as f64) / (total as f64);
+                    let bar = (used * 10.0).round() as usize;
+                    format!(
+                        "{:>10} free of {:>10}  [{}{}]",
+                        format::human_bytes(free),
+                        format::human_bytes(total),
+                        "#".repeat(bar),
+                        " ".repeat(10 - bar)
+                    )
+                }
+                _ => String::from("(no space information)"),
+            };
+            let text = format!("{:<width$}  {:<10}  {}", drive.root.display(), drive.kind, space, width = width);
+            lines.push(
+                match index == self.selected {
+                    true => Spans::from(Span::styled(text, theme.selection)),
+                    false => Spans::from(Span::styled(text, theme.dir)),
+                }
+            );
+        }
+        lines.push(Spans::from(""));
+        lines.push(
+            Spans::from(
+                vec![
+                    Span::styled("↑/↓", theme.key),
+                    Span::raw(" - Select   "),
+                    Span::styled("Enter", theme.key),
+                    Span::raw(" - Scan   "),
+                    Span::styled("Esc", theme.key),
+                    Span::raw(" - Close")
+                ]
+            )
+        );
+        lines
+    }
+}
+
+/// Space and inodes of the filesystem holding a directory
+#[derive(Clone, Copy)]
+pub struct FsUsage {
+    pub free: u64, // Bytes available to this user
+    pub total: u64,
+    pub inodes: Option<(u64, u64)>, // Free and total, where the filesystem has a fixed number
+}
+
+impl FsUsage {
+    /// Summary for the Current Directory block
+    pub fn describe(&self, numbers: &NumberFormat) -> String {
+        let used = |free: u64, total: u64| (total.saturating_sub(free) as f64) / (total.max(1) as f64) * 100.0;
+        let mut text = format!(
+            "Filesystem: {} free of {} ({:.0}% used)",
+            format::human_bytes(self.free),
+            format::human_bytes(self.total),
+            used(self.free, self.total)
+        );
+        if let Some((free, total)) = self.inodes {
+            text.push_str(
+                &format!(
+                    ", inodes: {} free of {} ({:.0}% used)",
+                    numbers.count(free),
+                    numbers.count(total),
+                    used(free, total)
+                )
+            );
+        }
+        text
+    }
+}
+
+/// Free and total space and inodes of the filesystem holding `dir`
+#[cfg(unix)]
+pub fn fs_usage(dir: &Path) -> Option<FsUsage> {
+    use std::{ ffi::CString, os::unix::ffi::OsStrExt };
+
+    let path = CString::new(dir.as_os_str().as_bytes()).ok()?;
+    // SAFETY: `path` is NUL-terminated and `stat` is written by the call before it is read
+    let stat = unsafe {
+        let mut stat: libc::statvfs = std::mem::zeroed();
+        if libc::statvfs(path.as_ptr(), &mut stat) != 0 {
+            return None;
+        }
+        stat
+    };
+    let unit = stat.f_frsize as u64;
+    // Filesystems that allocate inodes on demand (btrfs, ZFS) report none
+    let inodes = (stat.f_files > 0).then_some((stat.f_favail as u64, stat.f_files as u64));
+    Some(FsUsage {
+        free: (stat.f_bavail as u64).saturating_mul(unit),
+        total: (stat.f_blocks as u64).saturating_mul(unit),
+        inodes,
+    })
+}
+
+/// NTFS has no fixed inode budget, so only space is known
+#[cfg(windows)]
+pub fn fs_usage(dir: &Path) -> Option<FsUsage> {
+    use std::os::windows::ffi::OsStrExt;
+
+    let wide: Vec<u16> = dir.as_os_str().encode_wide().chain(Some(0)).collect();
+    let (mut available, mut total, mut free) = (0u64, 0u64, 0u64);
+    // SAFETY: `wide` is a NUL-terminated UTF-16 string; the out pointers are valid for the call
+    let known = unsafe { GetDiskFreeSpaceExW(wide.as_ptr(), &mut available, &mut total, &mut free) } != 0;
+    known.then_some(FsUsage { free: available, total, inodes: None })
+}
+
+#[cfg(windows)]
+#[link(name = "kernel32")]
+extern "system" {
+    fn GetLogicalDrives() -> u32;
+    fn GetDriveTypeW(root: *const u16) -> u32;
+    fn GetDiskFreeSpaceExW(dir: *const u16, available: *mut u64, total: *mut u64, free: *mut u64) -> i32;
+}
+
+/// Drive letters in use, mapped network drives included
+#[cfg(windows)]
+pub fn list_drives() -> Vec<Drive> {
+    use std::{ ffi::OsStr, os::windows::ffi::OsStrExt };
+
+    // SAFETY: no arguments; returns a bit mask of the drive letters in use
+    let mask = unsafe { GetLogicalDrives() };
+    (0..26u8)
+        .filter(|letter| mask & (1 << letter) != 0)
+        .filter_map(|letter| {
+            let root = format!("{}:\\", (b'A' + letter) as char);
+            let wide: Vec<u16> = OsStr::new(&root).encode_wide().chain(Some(0)).collect();
+            // SAFETY: `wide` is a NUL-terminated UTF-16 string that outlives the calls
+            let kind = match unsafe { GetDriveTypeW(wide.as_ptr()) } {
+                2 => "removable",
+                3 => "fixed",
+                4 => "network",
+                5 => "optical",
+                6 => "RAM disk",
+                _ => {
+                    return None; // Unknown, or no root directory
+                }
+            };
+            let (mut available, mut total, mut free) = (0u64, 0u64, 0u64);
+            // SAFETY: as above; the out pointers are valid for the duration of the call.
+            // Fails for empty card readers and disc drives, which then show no space.
+            let known = unsafe { GetDiskFreeSpaceExW(wide.as_ptr(), &mut available, &mut total, &mut free) } != 0;
+            Some(Drive {
+                root: PathBuf::from(root),
+                kind,
+                free: known.then_some(available),
+                total: known.then_some(total),
+            })
+        })
+        .collect()
+}
+
+/// Mounted filesystems backed by a device or a network share, as `df` lists them
+#[cfg(not(windows))]
+pub fn list_drives() -> Vec<Drive> {
+    let Ok(output) = std::process::Command::new("df").arg("-Pk").output() else {
+        return Vec::new();
+    };
+    let mut drives: Vec<Drive> = Vec::new();
+    for line in String::from_utf8_lossy(&output.stdout).lines().skip(1) {
+        // Filesystem, 1024-blocks, Used, Available, Capacity, Mounted on (which may contain spaces)
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 6 {
+            continue;
+        }
+        let source = fields[0];
+        let kind = if source.starts_with("//") || source.contains(':') {
+            "network"
+        } else if source.starts_with('/') {
+            "local"
+        } else {
+            continue; // tmpfs, proc, overlay and other virtual filesystems
+        };
+        let root = PathBuf::from(fields[5..].join(" "));
+        if drives.iter().any(|drive| drive.root == root) {
+            continue;
+        }
+        let kib = |field: &str| field.parse::<u64>().ok().map(|blocks| blocks * 1024);
+        drives.push(Drive { root, kind, free: kib(fields[3]), total: kib(fields[1]) });
+    }
+    drives
+}
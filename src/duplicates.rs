@@ -0,0 +1,221 @@
+use crate::{ events::AppEvent, format, scan::{ hard_link_identity, VisitKey }, theme::Theme };
+use ratatui::{ style::{ Modifier, Style }, text::{ Span, Spans } };
+use std::{
+    collections::{ HashMap, HashSet },
+    fs,
+    io::{ self, Read },
+    path::{ Path, PathBuf },
+    sync::mpsc::{ channel, Sender },
+    thread,
+};
+use threadpool::ThreadPool;
+
+/// Bytes hashed first; only files whose beginnings match are hashed in full
+const HEAD_BYTES: u64 = 64 * 1024;
+/// Read buffer for hashing
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// Files with identical content
+pub struct DuplicateSet {
+    pub size: u64, // Of each copy
+    pub paths: Vec<PathBuf>, // Sorted
+}
+
+impl DuplicateSet {
+    /// Bytes freed by keeping a single copy
+    pub fn reclaimable(&self) -> u64 {
+        self.size.saturating_mul(self.paths.len().saturating_sub(1) as u64)
+    }
+}
+
+/// Result of looking for duplicates below a directory
+pub struct DuplicateReport {
+    pub dir: PathBuf,
+    pub files: usize, // Non-empty regular files compared
+    pub sets: Vec<DuplicateSet>, // Most reclaimable first
+}
+
+impl DuplicateReport {
+    pub fn reclaimable_files(&self) -> usize {
+        self.sets.iter().map(|set| set.paths.len() - 1).sum()
+    }
+
+    pub fn reclaimable_bytes(&self) -> u64 {
+        self.sets.iter().map(DuplicateSet::reclaimable).sum()
+    }
+}
+
+/// Duplicate view of a directory: searching until the report arrives
+pub struct DuplicateView {
+    pub dir: PathBuf,
+    pub report: Option<DuplicateReport>,
+    pub scroll: u16,
+}
+
+impl DuplicateView {
+    pub fn lines(&self, theme: &Theme, spinner_frame: &str) -> Vec<Spans<'static>> {
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        let mut lines = Vec::new();
+        let Some(report) = &self.report else {
+            lines.push(Spans::from(format!("Looking for duplicates in {}{}", self.dir.display(), spinner_frame)));
+            lines.push(Spans::from("Files of equal size are compared by content hash"));
+            return lines;
+        };
+
+        if report.sets.is_empty() {
+            lines.push(Spans::from(format!("No duplicates among {} files in {}", report.files, report.dir.display())));
+        } else {
+            lines.push(
+                Spans::from(
+                    vec![
+                        Span::styled(format!("{} sets of duplicates", report.sets.len()), bold),
+                        Span::raw(format!(" among {} files in {}", report.files, report.dir.display()))
+                    ]
+                )
+            );
+            lines.push(
+                Spans::from(
+                    Span::styled(
+                        format!(
+                            "Reclaimable by keeping one copy of each: {} files, {}",
+                            report.reclaimable_files(),
+                            format::human_bytes(report.reclaimable_bytes())
+                        ),
+                        theme.marked
+                    )
+                )
+            );
+        }
+        for set in &report.sets {
+            lines.push(Spans::from(""));
+            lines.push(
+                Spans::from(
+                    Span::styled(
+                        format!(
+                            "{} copies of {} ({} reclaimable)",
+                            set.paths.len(),
+                            format::human_bytes(set.size),
+                            format::human_bytes(set.reclaimable())
+                        ),
+                        bold
+                    )
+                )
+            );
+            for path in &set.paths {
+                let shown = path.strip_prefix(&report.dir).unwrap_or(path);
+                lines.push(Spans::from(Span::styled(format!("  {}", shown.display()), theme.file)));
+            }
+        }
+        lines.push(Spans::from(""));
+        lines.push(
+            Spans::from(
+                vec![
+                    Span::styled("↑/↓", theme.key),
+                    Span::raw(" - Scroll   "),
+                    Span::styled("Esc", theme.key),
+                    Span::raw(" - Close")
+                ]
+            )
+        );
+        lines
+    }
+}
+
+/// Group the files below `dir` by size on a background thread, hash the
+/// candidates on the worker pool and send the duplicate sets to the UI
+pub fn spawn_duplicate_scan(dir: PathBuf, pool: ThreadPool, sender: Sender<AppEvent>) {
+    thread::spawn(move || {
+        let (files, by_size) = files_by_size(&dir);
+        let (set_tx, set_rx) = channel();
+        for (size, paths) in by_size.into_iter().filter(|(_, paths)| paths.len() > 1) {
+            let set_tx = set_tx.clone();
+            pool.execute(move || {
+                set_tx.send(split_by_content(size, paths)).unwrap_or(());
+            });
+        }
+        drop(set_tx);
+
+        let mut sets: Vec<DuplicateSet> = set_rx.iter().flatten().collect();
+        sets.sort_by(|a, b| b.reclaimable().cmp(&a.reclaimable()).then_with(|| a.paths.cmp(&b.paths)));
+        sender.send(AppEvent::Duplicates(DuplicateReport { dir, files, sets })).unwrap_or(());
+    });
+}
+
+/// Non-empty regular files below `dir` grouped by size. Symlinks are not
+/// copies and are skipped; hard links to one file are kept once.
+fn files_by_size(dir: &Path) -> (usize, HashMap<u64, Vec<PathBuf>>) {
+    let mut by_size: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+    let mut files = 0;
+    let mut visited = HashSet::new();
+    let mut linked = HashSet::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        if !visited.insert(VisitKey::of(&current)) {
+            continue; // Bind mount of a directory already walked
+        }
+        let Ok(entries) = fs::read_dir(&current) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if metadata.is_dir() {
+                pending.push(entry.path());
+            } else if metadata.is_file() && metadata.len() > 0 {
+                if hard_link_identity(&metadata).is_some_and(|identity| !linked.insert(identity)) {
+                    continue;
+                }
+                files += 1;
+                by_size.entry(metadata.len()).or_default().push(entry.path());
+            }
+        }
+    }
+    (files, by_size)
+}
+
+/// Split files of equal size into sets of identical content: by the hash of
+/// their beginning first, then of the whole file for larger ones
+fn split_by_content(size: u64, paths: Vec<PathBuf>) -> Vec<DuplicateSet> {
+    let mut sets = Vec::new();
+    for same_head in group_by_hash(paths, Some(HEAD_BYTES)) {
+        if size <= HEAD_BYTES {
+            sets.push(same_head);
+        } else {
+            sets.extend(group_by_hash(same_head, None));
+        }
+    }
+    sets.into_iter()
+        .map(|mut paths| {
+            paths.sort();
+            DuplicateSet { size, paths }
+        })
+        .collect()
+}
+
+/// Groups of more than one file with the same hash; unreadable files are left out
+fn group_by_hash(paths: Vec<PathBuf>, limit: Option<u64>) -> Vec<Vec<PathBuf>> {
+    let mut groups: HashMap<blake3::Hash, Vec<PathBuf>> = HashMap::new();
+    for path in paths {
+        if let Ok(hash) = hash_file(&path, limit) {
+            groups.entry(hash).or_default().push(path);
+        }
+    }
+    groups.into_values().filter(|group| group.len() > 1).collect()
+}
+
+/// BLAKE3 hash of a file, or of its first `limit` bytes
+fn hash_file(path: &Path, limit: Option<u64>) -> io::Result<blake3::Hash> {
+    let file = fs::File::open(path)?;
+    let mut reader = file.take(limit.unwrap_or(u64::MAX));
+    let mut hasher = blake3::Hasher::new();
+    let mut buffer = vec![0; BUFFER_SIZE];
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher.finalize())
+}
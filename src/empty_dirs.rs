@@ -0,0 +1,168 @@
+use crate::{ events::AppEvent, scan::VisitKey, theme::Theme };
+use ratatui::{ style::{ Modifier, Style }, text::{ Span, Spans } };
+use std::{
+    collections::HashSet,
+    fs,
+    io,
+    path::{ Path, PathBuf },
+    sync::mpsc::Sender,
+    thread,
+};
+
+/// Top of a chain of directories that contain nothing but empty directories
+pub struct EmptyDir {
+    pub path: PathBuf,
+    pub dirs: usize, // Directories removed with it, itself included
+}
+
+/// Result of looking for empty directories below a directory
+pub struct EmptyDirReport {
+    pub dir: PathBuf,
+    pub scanned: usize, // Directories walked
+    pub found: Vec<EmptyDir>, // Sorted by path
+}
+
+impl EmptyDirReport {
+    pub fn total_dirs(&self) -> usize {
+        self.found.iter().map(|empty| empty.dirs).sum()
+    }
+}
+
+/// Empty directory view of a directory: searching until the report arrives,
+/// then optionally confirming their removal
+pub struct EmptyDirView {
+    pub dir: PathBuf,
+    pub report: Option<EmptyDirReport>,
+    pub removed: usize, // Directories removed so far
+    pub failures: Vec<(PathBuf, io::Error)>, // Of the last removal
+    pub scroll: u16,
+}
+
+impl EmptyDirView {
+    pub fn lines(&self, theme: &Theme, spinner_frame: &str, read_only: bool) -> Vec<Spans<'static>> {
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        let mut lines = Vec::new();
+        let Some(report) = &self.report else {
+            lines.push(Spans::from(format!("Looking for empty directories in {}{}", self.dir.display(), spinner_frame)));
+            return lines;
+        };
+
+        if report.found.is_empty() {
+            lines.push(
+                Spans::from(
+                    format!("No empty directories among {} directories in {}", report.scanned, report.dir.display())
+                )
+            );
+        } else {
+            lines.push(
+                Spans::from(
+                    vec![
+                        Span::styled(format!("{} empty directories", report.total_dirs()), bold),
+                        Span::raw(
+                            format!(
+                                " in {} chains among {} directories in {}",
+                                report.found.len(),
+                                report.scanned,
+                                report.dir.display()
+                            )
+                        )
+                    ]
+                )
+            );
+        }
+        if self.removed > 0 {
+            lines.push(Spans::from(Span::styled(format!("Removed {} empty directories", self.removed), theme.marked)));
+        }
+        for (path, e) in &self.failures {
+            lines.push(Spans::from(format!("Cannot remove {}: {}", path.display(), e)));
+        }
+        lines.push(Spans::from(""));
+        for empty in &report.found {
+            let shown = empty.path.strip_prefix(&report.dir).unwrap_or(&empty.path);
+            let chain = match empty.dirs {
+                1 => String::new(),
+                dirs => format!(" ({} directories)", dirs),
+            };
+            lines.push(Spans::from(vec![Span::styled(format!("  {}/", shown.display()), theme.dir), Span::raw(chain)]));
+        }
+        if !report.found.is_empty() {
+            lines.push(Spans::from(""));
+        }
+        let mut keys = vec![Span::styled("↑/↓", theme.key), Span::raw(" - Scroll   ")];
+        if !report.found.is_empty() && !read_only {
+            keys.extend([Span::styled("d", theme.key), Span::raw(" - Remove all   ")]);
+        }
+        keys.extend([Span::styled("Esc", theme.key), Span::raw(" - Close")]);
+        lines.push(Spans::from(keys));
+        lines
+    }
+}
+
+/// Walk the tree below `dir` on a background thread and send the empty
+/// directories to the UI
+pub fn spawn_empty_dir_scan(dir: PathBuf, sender: Sender<AppEvent>) {
+    thread::spawn(move || {
+        let mut search = Search { visited: HashSet::new(), scanned: 0, found: Vec::new() };
+        search.walk(&dir, true);
+        search.found.sort_by(|a, b| a.path.cmp(&b.path));
+        let report = EmptyDirReport { dir, scanned: search.scanned, found: search.found };
+        sender.send(AppEvent::EmptyDirs(report)).unwrap_or(());
+    });
+}
+
+struct Search {
+    visited: HashSet<VisitKey>,
+    scanned: usize,
+    found: Vec<EmptyDir>,
+}
+
+impl Search {
+    /// Directories in the tree of `dir` if it holds nothing else. Empty subtrees
+    /// of a directory that holds something else are reported on their own;
+    /// the root never is, so the current directory is not offered for removal.
+    fn walk(&mut self, dir: &Path, is_root: bool) -> Option<usize> {
+        if !self.visited.insert(VisitKey::of(dir)) {
+            return None; // Bind mount of a directory already walked
+        }
+        self.scanned += 1;
+        let entries = fs::read_dir(dir).ok()?; // Unreadable directories may hold anything
+        let mut holds_other = false;
+        let mut empty_children = Vec::new();
+        for entry in entries {
+            let Ok(entry) = entry else {
+                holds_other = true;
+                continue;
+            };
+            // Symlinks are entries of their own, even to empty directories
+            let is_dir = entry.file_type().is_ok_and(|kind| kind.is_dir());
+            if !is_dir {
+                holds_other = true;
+                continue;
+            }
+            let path = entry.path();
+            match self.walk(&path, false) {
+                Some(dirs) => empty_children.push(EmptyDir { path, dirs }),
+                None => {
+                    holds_other = true;
+                }
+            }
+        }
+        if holds_other || is_root {
+            self.found.extend(empty_children);
+            return None;
+        }
+        Some(1 + empty_children.iter().map(|empty| empty.dirs).sum::<usize>())
+    }
+}
+
+/// Remove a chain of empty directories, deepest first. Only empty directories
+/// are ever removed: anything created in them since the search makes it fail.
+pub fn remove_chain(dir: &Path) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            remove_chain(&entry.path())?;
+        }
+    }
+    fs::remove_dir(dir)
+}
@@ -0,0 +1,148 @@
+use crate::{
+    broken_links::BrokenLinkReport,
+    cleanable::CleanableReport,
+    control::Request,
+    duplicates::DuplicateReport,
+    empty_dirs::EmptyDirReport,
+    git::GitStatus,
+    listing::ListingBatch,
+    mft::MftCounts,
+    preview::Preview,
+    tui::{ CountMessage, FilteredCount },
+    wizard::ArchiveMessage,
+};
+use crossterm::event::{ self, Event };
+use std::{
+    io,
+    sync::{ atomic::{ AtomicBool, Ordering }, mpsc::Sender, Arc, Condvar, Mutex },
+    thread,
+    time::Duration,
+};
+
+/// Default interval between animation frames (`display.tick_interval_ms`)
+pub const TICK_INTERVAL: Duration = Duration::from_millis(100);
+/// How long the input thread sleeps between checks whether it was resumed,
+/// and elsewhere than on Unix how long it waits for a key before checking
+/// whether it was paused
+const INPUT_POLL: Duration = Duration::from_millis(50);
+/// On Unix the input thread waits for keys this long; pausing wakes it earlier
+#[cfg(unix)]
+const INPUT_WAIT: Duration = Duration::from_secs(3600);
+
+/// Everything the main loop reacts to, merged into a single channel
+pub enum AppEvent {
+    Input(io::Result<Event>), // Keyboard, mouse and resize events
+    Tick, // Advances the spinner and flushes pending updates
+    Count(CountMessage), // A finished or estimated count
+    Listing(ListingBatch), // Entries of the directory being listed
+    Archived(ArchiveMessage), // An entry packed by the cleanup wizard or a batch operation
+    Duplicates(DuplicateReport), // Duplicate sets found below a directory
+    EmptyDirs(EmptyDirReport), // Empty directories found below a directory
+    BrokenLinks(BrokenLinkReport), // Broken symlinks found below a directory
+    Cleanable(CleanableReport), // Build output and caches found below a directory
+    Git(GitStatus), // How git sees the entries of a directory
+    FilteredCount(FilteredCount), // Files matching the name filter below a directory
+    Control(Request), // A command from the control socket, answered by the main loop
+    Mft(Result<MftCounts, String>), // The counts of a volume's Master File Table, or why it could not be read
+    Preview(Preview), // The start of a file or the entries of a directory, for the preview pane
+}
+
+/// Drives animation frames from a timer, independently of input. It only
+/// ticks while active, so an idle session does not wake up at all.
+pub struct Ticker {
+    active: Arc<(Mutex<bool>, Condvar)>,
+}
+
+impl Ticker {
+    pub fn spawn(sender: Sender<AppEvent>, interval: Duration) -> Ticker {
+        let active = Arc::new((Mutex::new(true), Condvar::new()));
+        let ticker = Ticker { active: Arc::clone(&active) };
+        thread::spawn(move || {
+            loop {
+                {
+                    let (lock, woken) = &*active;
+                    let mut is_active = lock.lock().unwrap_or_else(|e| e.into_inner());
+                    while !*is_active {
+                        is_active = woken.wait(is_active).unwrap_or_else(|e| e.into_inner());
+                    }
+                }
+                if sender.send(AppEvent::Tick).is_err() {
+                    return;
+                }
+                thread::sleep(interval);
+            }
+        });
+        ticker
+    }
+
+    /// Start or stop ticking
+    pub fn set_active(&self, active: bool) {
+        let (lock, woken) = &*self.active;
+        let mut is_active = lock.lock().unwrap_or_else(|e| e.into_inner());
+        if *is_active != active {
+            *is_active = active;
+            woken.notify_one();
+        }
+    }
+}
+
+/// Reads terminal input on a background thread. Reading can be paused while
+/// another program owns the terminal, so it does not steal that program's keys.
+pub struct InputReader {
+    paused: Arc<AtomicBool>,
+    reading: Arc<Mutex<()>>, // Held while the thread polls the terminal
+}
+
+impl InputReader {
+    pub fn spawn(sender: Sender<AppEvent>) -> InputReader {
+        let paused = Arc::new(AtomicBool::new(false));
+        let reading = Arc::new(Mutex::new(()));
+        let reader = InputReader { paused: Arc::clone(&paused), reading: Arc::clone(&reading) };
+
+        thread::spawn(move || {
+            loop {
+                let event = {
+                    let _reading = reading.lock().unwrap_or_else(|e| e.into_inner());
+                    if paused.load(Ordering::SeqCst) {
+                        None
+                    } else {
+                        #[cfg(unix)]
+                        let wait = INPUT_WAIT;
+                        #[cfg(not(unix))]
+                        let wait = INPUT_POLL;
+                        match event::poll(wait) {
+                            Ok(true) => Some(event::read()),
+                            Ok(false) => None,
+                            Err(e) => Some(Err(e)),
+                        }
+                    }
+                };
+                if let Some(event) = event {
+                    if sender.send(AppEvent::Input(event)).is_err() {
+                        return;
+                    }
+                } else if paused.load(Ordering::SeqCst) {
+                    thread::sleep(INPUT_POLL);
+                }
+            }
+        });
+        reader
+    }
+
+    /// Stop reading input; returns once the thread no longer touches the terminal
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+        // The terminal library also waits for window size changes, so the signal
+        // of one ends the wait; the resize it reports redraws the screen on return
+        // SAFETY: no pointers; SIGWINCH is ignored by default and only has the terminal library's handler
+        #[cfg(unix)]
+        unsafe {
+            libc::raise(libc::SIGWINCH);
+        }
+        drop(self.reading.lock().unwrap_or_else(|e| e.into_inner()));
+    }
+
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+    }
+}
@@ -0,0 +1,99 @@
+use infer::MatcherType;
+use std::{ fs::File, io::Read, path::Path };
+
+/// Broad kinds of files, in the order of the breakdown
+#[cfg(feature = "tui")]
+pub const CATEGORIES: &[&str] = &["Images", "Video", "Audio", "Code", "Documents", "Other"];
+
+/// How much of a file is read to sniff its type; some signatures are not at the very start
+const SNIFF_LENGTH: u64 = 8192;
+
+/// Lowercase extensions of each category; `ts` counts as TypeScript, not as a video stream
+const EXTENSIONS: &[(&str, &[&str])] = &[
+    (
+        "Images",
+        &[
+            "jpg", "jpeg", "png", "gif", "bmp", "tif", "tiff", "webp", "heic", "heif", "avif", "svg", "ico", "psd",
+            "raw", "cr2", "cr3", "nef", "arw", "orf", "rw2", "dng", "xcf",
+        ],
+    ),
+    (
+        "Video",
+        &[
+            "mp4", "m4v", "mkv", "mov", "avi", "wmv", "flv", "webm", "mpg", "mpeg", "m2ts", "mts", "3gp", "vob", "ogv",
+        ],
+    ),
+    ("Audio", &["mp3", "flac", "wav", "aac", "m4a", "ogg", "oga", "opus", "wma", "aif", "aiff", "alac", "ape", "mid", "midi"]),
+    (
+        "Code",
+        &[
+            "rs", "c", "h", "cc", "cpp", "cxx", "hpp", "hh", "java", "kt", "kts", "scala", "go", "py", "pyi", "rb", "php",
+            "js", "mjs", "cjs", "jsx", "ts", "tsx", "vue", "svelte", "swift", "m", "mm", "cs", "fs", "dart", "lua", "pl",
+            "r", "jl", "ex", "exs", "erl", "hs", "ml", "clj", "zig", "sh", "bash", "zsh", "fish", "ps1", "bat", "sql",
+            "html", "htm", "css", "scss", "sass", "less", "json", "yaml", "yml", "toml", "xml", "ini", "cmake", "mk",
+            "gradle", "proto",
+        ],
+    ),
+    (
+        "Documents",
+        &[
+            "pdf", "doc", "docx", "odt", "rtf", "txt", "md", "rst", "tex", "epub", "mobi", "azw3", "xls", "xlsx", "ods",
+            "csv", "tsv", "ppt", "pptx", "odp", "pages", "numbers", "key",
+        ],
+    ),
+];
+
+/// Category of a file: by its extension, or with `sniff` by its first bytes
+/// when the extension is missing or unknown
+pub fn file_category(path: &Path, sniff: bool) -> &'static str {
+    let by_extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .and_then(|ext| {
+            let ext = ext.to_ascii_lowercase();
+            EXTENSIONS.iter().find(|(_, extensions)| extensions.contains(&ext.as_str()))
+        })
+        .map(|&(category, _)| category);
+    by_extension.or_else(|| sniff.then(|| sniff_category(path)).flatten()).unwrap_or("Other")
+}
+
+/// Category from the signature at the start of a file ("magic bytes")
+fn sniff_category(path: &Path) -> Option<&'static str> {
+    let mut head = Vec::with_capacity(SNIFF_LENGTH as usize);
+    File::open(path).ok()?.take(SNIFF_LENGTH).read_to_end(&mut head).ok()?;
+    let kind = infer::get(&head)?;
+    match kind.matcher_type() {
+        MatcherType::Image => Some("Images"),
+        MatcherType::Video => Some("Video"),
+        MatcherType::Audio => Some("Audio"),
+        MatcherType::Doc | MatcherType::Book => Some("Documents"),
+        // PDF and RTF are among the archives
+        MatcherType::Archive if matches!(kind.extension(), "pdf" | "rtf") => Some("Documents"),
+        MatcherType::Text => Some("Code"), // Scripts, HTML and XML
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn sniffs_files_without_a_known_extension() {
+        let dir = std::env::temp_dir().join(format!("file_counter-sniff-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let files: &[(&str, &[u8], &str)] = &[
+            ("image", b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR", "Images"),
+            ("report", b"%PDF-1.7\n", "Documents"),
+            ("deploy", b"#!/bin/sh\necho\n", "Code"),
+            ("notes", b"just text", "Other"),
+        ];
+        for &(name, content, category) in files {
+            fs::write(dir.join(name), content).unwrap();
+            assert_eq!(file_category(&dir.join(name), true), category, "{}", name);
+            assert_eq!(file_category(&dir.join(name), false), "Other", "{}", name);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,155 @@
+use chrono::{ DateTime, Local };
+use std::{ ffi::OsStr, mem, time::{ Duration, SystemTime } };
+use unicode_width::{ UnicodeWidthChar, UnicodeWidthStr };
+
+/// Format a byte count with binary units, e.g. `1.5 GiB`
+pub fn human_bytes(bytes: u64) -> String {
+    const UNITS: &[&str] = &["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// Display form of a file name. Names that are not valid UTF-8 are shown lossily
+/// with a marker, since their replacement characters can make them look alike.
+pub fn display_name(name: &OsStr) -> String {
+    match name.to_str() {
+        Some(name) => name.to_string(),
+        None => format!("{} (non-UTF-8)", name.to_string_lossy()),
+    }
+}
+
+/// `name` cut in the middle to fit `width` columns, e.g. `build-2024-0…-final.tar.gz`.
+/// The extension is kept whole when it leaves room for the start of the name.
+pub fn truncate_middle(name: &str, width: usize) -> String {
+    if UnicodeWidthStr::width(name) <= width {
+        return name.to_string();
+    }
+    if width == 0 {
+        return String::new();
+    }
+    let room = width - 1; // For the ellipsis
+    let extension = name
+        .rfind('.')
+        .filter(|&dot| dot > 0)
+        .map_or(0, |dot| UnicodeWidthStr::width(&name[dot..]));
+    let tail_width = match extension {
+        extension if extension > 0 && extension + 2 <= room => extension.max(room / 2),
+        _ => room / 2,
+    };
+    let take = |chars: &mut dyn Iterator<Item = char>, limit: usize| {
+        let mut taken = Vec::new();
+        let mut used = 0;
+        for c in chars {
+            let char_width = UnicodeWidthChar::width(c).unwrap_or(0);
+            if used + char_width > limit {
+                break;
+            }
+            taken.push(c);
+            used += char_width;
+        }
+        (taken, used)
+    };
+    let (mut tail, used) = take(&mut name.chars().rev(), tail_width);
+    tail.reverse();
+    let (head, _) = take(&mut name.chars(), room - used);
+    let mut cut: String = head.into_iter().collect();
+    cut.push('…');
+    cut.extend(tail);
+    cut
+}
+
+/// `name` without its first `columns` columns, starting with an ellipsis when
+/// anything was skipped, e.g. `…generated_name_2024` for a scrolled column
+pub fn skip_columns(name: &str, columns: usize) -> String {
+    if columns == 0 {
+        return name.to_string();
+    }
+    let mut skipped = 0;
+    let rest: String = name
+        .chars()
+        .skip_while(|&c| {
+            skipped += UnicodeWidthChar::width(c).unwrap_or(0);
+            skipped <= columns + 1 // One more for the ellipsis
+        })
+        .collect();
+    match rest.is_empty() {
+        true => rest,
+        false => format!("…{}", rest),
+    }
+}
+
+/// `text` broken at spaces into lines of at most `width` columns; a word
+/// longer than a line is cut
+pub fn wrap(text: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        if !line.is_empty() && line.width() + 1 + word.width() > width {
+            lines.push(mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        for c in word.chars() {
+            if line.width() + UnicodeWidthChar::width(c).unwrap_or(0) > width {
+                lines.push(mem::take(&mut line));
+            }
+            line.push(c);
+        }
+    }
+    lines.push(line);
+    lines
+}
+
+/// Units for relative times, largest first, with singular and plural labels
+const TIME_UNITS: &[(u64, &str, &str)] = &[
+    (365 * 24 * 3600, "year", "years"),
+    (30 * 24 * 3600, "month", "months"),
+    (7 * 24 * 3600, "week", "weeks"),
+    (24 * 3600, "day", "days"),
+    (3600, "hour", "hours"),
+    (60, "minute", "minutes"),
+];
+
+/// Humanized age of a timestamp relative to `now`, e.g. `3 days ago`.
+/// Times in the future (clock skew) read as `in the future`.
+pub fn relative_time(time: SystemTime, now: SystemTime) -> String {
+    let seconds = match now.duration_since(time) {
+        Ok(age) => age.as_secs(),
+        Err(_) => {
+            return String::from("in the future");
+        }
+    };
+    for (unit_seconds, singular, plural) in TIME_UNITS {
+        let amount = seconds / unit_seconds;
+        if amount > 0 {
+            let unit = if amount == 1 { singular } else { plural };
+            return format!("{} {} ago", amount, unit);
+        }
+    }
+    String::from("just now")
+}
+
+/// Short form of a duration, e.g. `850ms`, `12.3s` or `4m05s`
+pub fn elapsed(duration: Duration) -> String {
+    match duration.as_secs() {
+        0 => format!("{}ms", duration.as_millis()),
+        seconds if seconds < 60 => format!("{:.1}s", duration.as_secs_f64()),
+        seconds => format!("{}m{:02}s", seconds / 60, seconds % 60),
+    }
+}
+
+/// ISO-8601 timestamp in the local timezone, e.g. `2024-05-01T13:45:10+02:00`
+pub fn absolute_time(time: SystemTime) -> String {
+    DateTime::<Local>::from(time).format("%Y-%m-%dT%H:%M:%S%:z").to_string()
+}
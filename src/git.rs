@@ -0,0 +1,130 @@
+use crate::events::AppEvent;
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    path::{ Path, PathBuf },
+    process::{ Command, Stdio },
+    sync::mpsc::Sender,
+    thread,
+};
+
+/// How git sees an entry of the listing
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum GitState {
+    Tracked,
+    Untracked,
+    Ignored,
+    Mixed, // A directory holding tracked files and files git does not track
+}
+
+impl GitState {
+    pub fn label(self) -> &'static str {
+        match self {
+            GitState::Tracked => "tracked",
+            GitState::Untracked => "untracked",
+            GitState::Ignored => "ignored",
+            GitState::Mixed => "mixed",
+        }
+    }
+}
+
+/// Files below an entry of the listing, by how git sees them
+#[derive(Clone, Copy, Default)]
+pub struct GitCounts {
+    pub tracked: usize,
+    pub untracked: usize,
+    pub ignored: usize,
+}
+
+impl GitCounts {
+    /// Files that are untracked or ignored
+    pub fn not_in_git(&self) -> usize {
+        self.untracked + self.ignored
+    }
+
+    pub fn state(&self) -> Option<GitState> {
+        match (self.tracked, self.untracked, self.ignored) {
+            (0, 0, 0) => None,
+            (_, 0, 0) => Some(GitState::Tracked),
+            (0, 0, _) => Some(GitState::Ignored),
+            (0, _, _) => Some(GitState::Untracked),
+            _ => Some(GitState::Mixed),
+        }
+    }
+}
+
+/// Git status of the entries of a directory inside a work tree
+pub struct GitStatus {
+    pub dir: PathBuf,
+    pub children: HashMap<OsString, GitCounts>, // By file name
+}
+
+impl GitStatus {
+    pub fn total(&self) -> GitCounts {
+        let mut total = GitCounts::default();
+        for counts in self.children.values() {
+            total.tracked += counts.tracked;
+            total.untracked += counts.untracked;
+            total.ignored += counts.ignored;
+        }
+        total
+    }
+}
+
+/// Ask git about the files below `dir` on a background thread and send their
+/// status to the UI. Nothing is sent outside a work tree or without git.
+pub fn spawn_git_status(dir: PathBuf, sender: Sender<AppEvent>) {
+    thread::spawn(move || {
+        if let Some(status) = git_status(dir) {
+            sender.send(AppEvent::Git(status)).unwrap_or(());
+        }
+    });
+}
+
+fn git_status(dir: PathBuf) -> Option<GitStatus> {
+    let mut children: HashMap<OsString, GitCounts> = HashMap::new();
+    // Paths are printed relative to `dir`, so the first component is the entry
+    for state in [GitState::Tracked, GitState::Untracked, GitState::Ignored] {
+        let args: &[&str] = match state {
+            GitState::Tracked => &["--cached"],
+            GitState::Untracked => &["--others", "--exclude-standard"],
+            _ => &["--others", "--ignored", "--exclude-standard"],
+        };
+        let output = ls_files(&dir, args)?;
+        for path in output.split(|&byte| byte == 0).filter(|path| !path.is_empty()) {
+            let name = path.split(|&byte| byte == b'/').next().unwrap_or(path);
+            let counts = children.entry(os_string(name)).or_default();
+            match state {
+                GitState::Tracked => counts.tracked += 1,
+                GitState::Untracked => counts.untracked += 1,
+                _ => counts.ignored += 1,
+            }
+        }
+    }
+    Some(GitStatus { dir, children })
+}
+
+/// Output of `git ls-files -z` in `dir`, or None outside a work tree
+fn ls_files(dir: &Path, args: &[&str]) -> Option<Vec<u8>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["ls-files", "-z"])
+        .args(args)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    output.status.success().then_some(output.stdout)
+}
+
+#[cfg(unix)]
+fn os_string(bytes: &[u8]) -> OsString {
+    use std::os::unix::ffi::OsStrExt;
+    std::ffi::OsStr::from_bytes(bytes).to_os_string()
+}
+
+#[cfg(not(unix))]
+fn os_string(bytes: &[u8]) -> OsString {
+    OsString::from(String::from_utf8_lossy(bytes).into_owned())
+}
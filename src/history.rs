@@ -0,0 +1,390 @@
+use crate::{
+    cli::{ DaemonOptions, HistoryOptions },
+    config::ConfigFile,
+    control::{ self, Command, Request },
+    dirs,
+    json::Value,
+    numbers::NumberFormat,
+    report::{ self, ReportNode },
+};
+use chrono::{ DateTime, Local };
+use rusqlite::{ params, Connection };
+use std::{
+    collections::HashMap,
+    fs,
+    path::{ Path, PathBuf },
+    sync::{ mpsc::{ channel, Receiver, Sender }, Arc, Mutex },
+    thread,
+    time::{ Duration, Instant, SystemTime, UNIX_EPOCH },
+};
+
+/// SQLite database of the recorded scans
+const HISTORY_FILE: &str = "history.sqlite";
+/// A scan is a row of `scans`; the count of each directory it recorded, by
+/// absolute path, a row of `counts`. Paths are the bytes of the OS string, so
+/// names that are not UTF-8 are kept apart.
+const SCHEMA: &str =
+    "CREATE TABLE IF NOT EXISTS scans (id INTEGER PRIMARY KEY, time INTEGER NOT NULL, root BLOB NOT NULL);
+     CREATE TABLE IF NOT EXISTS counts (scan INTEGER NOT NULL REFERENCES scans (id), path BLOB NOT NULL, files INTEGER NOT NULL);
+     CREATE INDEX IF NOT EXISTS counts_by_path ON counts (path);";
+/// How long a writer waits for another one, such as a second daemon, to commit
+const BUSY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Defaults of the `[daemon]` config section
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(3600);
+const DEFAULT_DEPTH: usize = 3;
+
+/// Rescan the roots of the config's `[daemon]` section on schedule and append
+/// each directory's count, down to `depth` levels, to the history database
+pub fn run_daemon(options: &DaemonOptions) -> Result<(), String> {
+    let config = ConfigFile::load(Some(&options.config))?;
+    let Schedule { roots, interval, depth } = Schedule::from_config(&config)
+        .ok_or_else(|| format!("{}: no roots = [...] in the [daemon] section", options.config.display()))?;
+    let file = history_file(options.state_dir.as_deref())?;
+    let mut db = open(&file)?;
+
+    // Latest count of every recorded directory, for the control socket
+    let latest: Arc<Mutex<HashMap<PathBuf, usize>>> = Arc::default();
+    let (rescan_tx, rescan_rx) = channel();
+    let _socket = match &options.control_socket {
+        Some(path) if !options.once => {
+            let (request_tx, request_rx) = channel();
+            let socket = control::listen(path, request_tx, |request| request)?;
+            let latest = Arc::clone(&latest);
+            let rescan_tx = rescan_tx.clone();
+            thread::spawn(move || answer_requests(request_rx, latest, rescan_tx));
+            Some(socket)
+        }
+        _ => None,
+    };
+
+    if !options.once {
+        println!("Recording {} roots every {}s into {} (Ctrl-C to stop)", roots.len(), interval.as_secs(), file.display());
+    }
+    let mut invalidated: Option<PathBuf> = None; // Only the roots around it are rescanned early
+    loop {
+        let started = Instant::now();
+        let due = roots.iter().filter(|root| {
+            let root = Path::new(root);
+            invalidated.as_deref().is_none_or(|path| path.starts_with(root) || root.starts_with(path))
+        });
+        for root in due {
+            match record(Path::new(root), depth, &mut db) {
+                Ok(counts) => {
+                    println!("{}: {} files", root, counts.first().map_or(0, |(_, files)| *files));
+                    latest.lock().unwrap_or_else(|e| e.into_inner()).extend(counts);
+                }
+                Err(e) => eprintln!("Cannot record {}: {}", root, e),
+            }
+        }
+        if options.once {
+            return Ok(());
+        }
+        invalidated = rescan_rx.recv_timeout(interval.saturating_sub(started.elapsed())).ok();
+    }
+}
+
+/// What the daemon records and how often, from the `[daemon]` config section
+struct Schedule {
+    roots: Vec<String>,
+    interval: Duration,
+    depth: usize,
+}
+
+impl Schedule {
+    /// None when the section lists no roots
+    fn from_config(config: &ConfigFile) -> Option<Schedule> {
+        let roots = config.get_str_list("daemon.roots").filter(|roots| !roots.is_empty())?;
+        let interval = config
+            .get_int("daemon.interval")
+            .map_or(DEFAULT_INTERVAL, |seconds| Duration::from_secs(seconds.max(1) as u64));
+        let depth = config.get_int("daemon.depth").map_or(DEFAULT_DEPTH, |depth| depth.max(0) as usize);
+        Some(Schedule { roots, interval, depth })
+    }
+}
+
+/// Answer control socket commands from the latest recorded counts; an
+/// invalidated directory is rescanned right away
+fn answer_requests(requests: Receiver<Request>, latest: Arc<Mutex<HashMap<PathBuf, usize>>>, rescan: Sender<PathBuf>) {
+    for request in requests {
+        let mut latest = latest.lock().unwrap_or_else(|e| e.into_inner());
+        let answer = match &request.command {
+            Command::Query(path) => {
+                let path = path.canonicalize().unwrap_or_else(|_| path.clone());
+                control::count_answer(&path, latest.get(&path).copied(), false)
+            }
+            Command::Invalidate(path) => {
+                let path = path.canonicalize().unwrap_or_else(|_| path.clone());
+                let before = latest.len();
+                latest.retain(|recorded, _| !recorded.starts_with(&path) && !path.starts_with(recorded));
+                rescan.send(path).unwrap_or(());
+                control::invalidated_answer(before - latest.len())
+            }
+            Command::ExportSnapshot =>
+                control::snapshot_answer(
+                    latest
+                        .iter()
+                        .map(|(path, files)| (path.clone(), *files))
+                        .collect()
+                ),
+        };
+        request.respond(answer);
+    }
+}
+
+/// Count `root` and record the result as one scan; returns the count of
+/// every recorded directory by its absolute path, the root first
+fn record(root: &Path, depth: usize, db: &mut Connection) -> Result<Vec<(PathBuf, usize)>, String> {
+    let root = root.canonicalize().map_err(|e| e.to_string())?;
+    let snapshot = report::build_snapshot(&root);
+    let mut counts = Vec::new();
+    collect_counts(&snapshot, &root, depth, &mut counts);
+
+    // One transaction per scan, so readers never see half of one
+    let stored = (|| -> rusqlite::Result<()> {
+        let transaction = db.transaction()?;
+        transaction.execute(
+            "INSERT INTO scans (time, root) VALUES (?1, ?2)",
+            params![unix_time() as i64, path_bytes(&root)]
+        )?;
+        let scan = transaction.last_insert_rowid();
+        {
+            let mut insert = transaction.prepare("INSERT INTO counts (scan, path, files) VALUES (?1, ?2, ?3)")?;
+            for (path, files) in &counts {
+                insert.execute(params![scan, path_bytes(path), *files as i64])?;
+            }
+        }
+        transaction.commit()
+    })();
+    stored.map_err(|e| e.to_string())?;
+    Ok(counts)
+}
+
+/// The snapshot names entries lossily, so a directory whose name is not UTF-8
+/// is not recorded on its own, only as part of its parent's count
+fn collect_counts(node: &ReportNode, path: &Path, depth: usize, counts: &mut Vec<(PathBuf, usize)>) {
+    counts.push((path.to_path_buf(), node.files.unwrap_or(0)));
+    if depth == 0 {
+        return;
+    }
+    let children: Vec<&ReportNode> = node.children
+        .iter()
+        .filter(|child| child.is_dir)
+        .collect();
+    for child in &children {
+        let child_path = path.join(&child.name);
+        let lossy = child.name.contains(char::REPLACEMENT_CHARACTER) && !child_path.is_dir();
+        let ambiguous = children.iter().filter(|other| other.name == child.name).count() > 1;
+        if !lossy && !ambiguous {
+            collect_counts(child, &child_path, depth - 1, counts);
+        }
+    }
+}
+
+/// Print how the count of `path` changed across the recorded scans, and since
+/// when it has been growing
+pub fn run(path: &Path, options: &HistoryOptions) -> Result<(), String> {
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let file = history_file(options.state_dir.as_deref())?;
+    if !file.exists() {
+        return Err(format!("Cannot read {}: no scans recorded yet, run the daemon first", file.display()));
+    }
+    let db = open(&file)?;
+    let scans = scans_of(&db, &path).map_err(|e| format!("Cannot read {}: {}", file.display(), e))?;
+    if scans.is_empty() {
+        return Err(
+            format!(
+                "No history for {}: it is not below a daemon root, or deeper than the recorded depth",
+                path.display()
+            )
+        );
+    }
+
+    if options.json {
+        let scans = scans
+            .iter()
+            .map(|&(time, files)| {
+                Value::Object(
+                    vec![(String::from("time"), Value::from(time)), (String::from("files"), Value::from(files))]
+                )
+            })
+            .collect();
+        let value = Value::Object(
+            vec![
+                (String::from("path"), Value::from(path.display().to_string())),
+                (String::from("scans"), Value::Array(scans))
+            ]
+        );
+        println!("{}", value.to_json());
+        return Ok(());
+    }
+
+    let numbers = NumberFormat::from_env(false);
+    let plural = if scans.len() == 1 { "scan" } else { "scans" };
+    println!("History of {} ({} {})", path.display(), scans.len(), plural);
+    println!();
+    println!("{:<24}{:>14}{:>14}", "Time", "Files", "Change");
+    let mut previous: Option<usize> = None;
+    for &(time, files) in &scans {
+        let change = match previous {
+            Some(previous) => signed(&numbers, files as i64 - previous as i64),
+            None => String::new(),
+        };
+        println!("{:<24}{:>14}{:>14}", format_time(time), numbers.count(files as u64), change);
+        previous = Some(files);
+    }
+    println!();
+    let (latest_time, latest) = scans[scans.len() - 1];
+    match growth_start(&scans) {
+        Some(start) => {
+            let (since, before) = scans[start];
+            println!(
+                "Growing since {}: {} → {} files ({})",
+                format_time(since),
+                numbers.count(before as u64),
+                numbers.count(latest as u64),
+                signed(&numbers, latest as i64 - before as i64)
+            );
+        }
+        None => println!("Not growing: {} files at {}", numbers.count(latest as u64), format_time(latest_time)),
+    }
+    Ok(())
+}
+
+/// `(time, files)` of every scan that recorded `path`, oldest first. When
+/// nested roots both recorded it, the scans of the innermost root are used.
+fn scans_of(db: &Connection, path: &Path) -> rusqlite::Result<Vec<(u64, usize)>> {
+    let mut query = db.prepare(
+        "SELECT scans.root, scans.time, counts.files FROM counts JOIN scans ON scans.id = counts.scan
+         WHERE counts.path = ?1 ORDER BY scans.time, scans.id"
+    )?;
+    let rows = query.query_map(params![path_bytes(path)], |row| {
+        Ok((row.get::<_, Vec<u8>>(0)?, row.get::<_, i64>(1)?, row.get::<_, i64>(2)?))
+    })?;
+    let mut by_root: HashMap<Vec<u8>, Vec<(u64, usize)>> = HashMap::new();
+    for row in rows {
+        let (root, time, files) = row?;
+        by_root.entry(root).or_default().push((time.max(0) as u64, files.max(0) as usize));
+    }
+    Ok(
+        by_root
+            .into_iter()
+            .max_by_key(|(root, _)| root.len())
+            .map(|(_, scans)| scans)
+            .unwrap_or_default()
+    )
+}
+
+/// Index of the last scan before the count started rising to the latest one
+/// without dropping in between, or None if the latest scan is no higher
+fn growth_start(scans: &[(u64, usize)]) -> Option<usize> {
+    let mut start = scans.len().checked_sub(1)?;
+    while start > 0 && scans[start - 1].1 <= scans[start].1 {
+        start -= 1;
+    }
+    // Skip the plateau the growth started from
+    while start + 1 < scans.len() && scans[start + 1].1 == scans[start].1 {
+        start += 1;
+    }
+    (start + 1 < scans.len()).then_some(start)
+}
+
+/// How a path is stored: its bytes, which are only compared, never shown
+fn path_bytes(path: &Path) -> &[u8] {
+    path.as_os_str().as_encoded_bytes()
+}
+
+fn history_file(state_dir: Option<&Path>) -> Result<PathBuf, String> {
+    let dir = dirs::state_dir(state_dir).ok_or("No state directory: set $HOME or pass --state-dir")?;
+    Ok(dir.join(HISTORY_FILE))
+}
+
+/// Open the history database, creating it and its tables if needed. In WAL
+/// mode `history` reads while a daemon writes, and concurrent writers wait
+/// for each other instead of failing.
+fn open(file: &Path) -> Result<Connection, String> {
+    if let Some(dir) = file.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    }
+    let opened = (|| -> rusqlite::Result<Connection> {
+        let db = Connection::open(file)?;
+        db.busy_timeout(BUSY_TIMEOUT)?;
+        db.query_row("PRAGMA journal_mode = WAL", [], |_| Ok(()))?;
+        db.execute_batch(SCHEMA)?;
+        Ok(db)
+    })();
+    opened.map_err(|e| format!("{}: {}", file.display(), e))
+}
+
+fn signed(numbers: &NumberFormat, change: i64) -> String {
+    match change {
+        change if change > 0 => format!("+{}", numbers.count(change as u64)),
+        change if change < 0 => format!("-{}", numbers.count(change.unsigned_abs())),
+        _ => String::from("0"),
+    }
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+/// `YYYY-MM-DD HH:MM` in the local time zone
+fn format_time(time: u64) -> String {
+    DateTime::from_timestamp(time as i64, 0)
+        .map(|time| time.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_daemon_section() {
+        let config = ConfigFile::parse(
+            r#"
+            [daemon]
+            roots = [
+                "/data/logs",
+                "/srv/uploads",  # trailing comma
+            ]
+            interval = 600
+            "#
+        ).unwrap();
+        let schedule = Schedule::from_config(&config).unwrap();
+        assert_eq!(schedule.roots, ["/data/logs", "/srv/uploads"]);
+        assert_eq!(schedule.interval, Duration::from_secs(600));
+        assert_eq!(schedule.depth, DEFAULT_DEPTH);
+
+        let empty = ConfigFile::parse("[daemon]\nroots = []").unwrap();
+        assert!(Schedule::from_config(&empty).is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn records_paths_that_are_not_utf8() {
+        use std::{ ffi::OsStr, os::unix::ffi::OsStrExt };
+
+        let scratch = std::env::temp_dir().join(format!("file_counter-history-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&scratch);
+        let root = scratch.join(OsStr::from_bytes(b"root\xff"));
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::create_dir_all(root.join(OsStr::from_bytes(b"bad\xfe"))).unwrap();
+        fs::write(root.join("sub").join("file"), "").unwrap();
+        fs::write(root.join(OsStr::from_bytes(b"bad\xfe")).join("file"), "").unwrap();
+        let mut db = Connection::open_in_memory().unwrap();
+        db.execute_batch(SCHEMA).unwrap();
+        record(&root, 1, &mut db).unwrap();
+
+        let root = root.canonicalize().unwrap();
+        let files = |path: &Path| scans_of(&db, path).unwrap().iter().map(|&(_, files)| files).collect::<Vec<_>>();
+        assert_eq!(files(&root), [2]);
+        assert_eq!(files(&root.join("sub")), [1]);
+        // Its snapshot name is lossy, so it only counts towards the root
+        assert!(files(&root.join(OsStr::from_bytes(b"bad\xfe"))).is_empty());
+        assert!(files(&root.join("bad\u{FFFD}")).is_empty());
+        fs::remove_dir_all(&scratch).unwrap();
+    }
+}
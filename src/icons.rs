@@ -0,0 +1,52 @@
+use crate::file_types::file_category;
+use std::path::Path;
+
+/// Nerd Font glyphs, from the Font Awesome and Octicons sets every patched font carries
+const FOLDER: &str = "\u{f07b}";
+const PARENT: &str = "\u{f07c}"; // Open folder
+const MOUNT: &str = "\u{f0a0}"; // Hard disk
+const LINK: &str = "\u{f0c1}";
+const ARCHIVE: &str = "\u{f410}";
+const FILE: &str = "\u{f15b}";
+
+/// File glyphs by `file_types::CATEGORIES`; "Other" keeps the plain file
+const CATEGORY_ICONS: &[(&str, &str)] = &[
+    ("Images", "\u{f1c5}"),
+    ("Video", "\u{f1c8}"),
+    ("Audio", "\u{f1c7}"),
+    ("Code", "\u{f1c9}"),
+    ("Documents", "\u{f15c}"),
+];
+
+/// What an entry is, as far as its icon goes
+pub struct IconKind {
+    pub is_dir: bool,
+    pub is_parent: bool,
+    pub is_link: bool,
+    pub is_archive: bool,
+    pub is_mount: bool,
+}
+
+/// Glyph shown before the name of an entry with `display.icons`. Files get the
+/// icon of their category by extension only, as drawing must not read them.
+pub fn icon(path: &Path, kind: IconKind) -> &'static str {
+    if kind.is_parent {
+        return PARENT;
+    }
+    if kind.is_link {
+        return LINK;
+    }
+    if kind.is_archive {
+        return ARCHIVE;
+    }
+    if kind.is_mount {
+        return MOUNT;
+    }
+    if kind.is_dir {
+        return FOLDER;
+    }
+    let category = file_category(path, false);
+    CATEGORY_ICONS.iter()
+        .find(|(name, _)| *name == category)
+        .map_or(FILE, |&(_, icon)| icon)
+}
@@ -0,0 +1,103 @@
+use std::{ collections::HashMap, ffi::OsString, path::Path, process::{ Command, Stdio } };
+#[cfg(not(windows))]
+use std::path::Component;
+
+/// Approximate counts answered by the system search index
+pub struct IndexCounts {
+    pub total: usize,
+    pub total_bytes: Option<u64>, // Only known to backends that index sizes
+    pub children: HashMap<OsString, usize>, // Indexed paths below each direct child
+}
+
+/// Query the Everything service through its command-line client (`es.exe`),
+/// which answers per-directory file counts and size sums from the NTFS index.
+/// Returns None when Everything is not installed or not running.
+#[cfg(windows)]
+pub fn index_counts(dir: &Path) -> Option<IndexCounts> {
+    use std::fs;
+
+    // Everything does not understand verbatim (`\\?\`) paths, so avoid canonicalize
+    let dir = std::path::absolute(dir).ok()?;
+    let total = everything_query(&dir, "-get-result-count")?;
+    let total_bytes = everything_query(&dir, "-get-total-size");
+
+    let mut children = HashMap::new();
+    for entry in fs::read_dir(&dir).ok()?.flatten() {
+        if entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
+            if let Some(files) = everything_query(&entry.path(), "-get-result-count") {
+                children.insert(entry.file_name(), files as usize);
+            }
+        }
+    }
+    Some(IndexCounts { total: total as usize, total_bytes, children })
+}
+
+/// Run an `es.exe` query restricted to files below `dir` that prints a single number
+#[cfg(windows)]
+fn everything_query(dir: &Path, query: &str) -> Option<u64> {
+    let output = run(Command::new("es.exe").arg("-path").arg(dir).arg("/a-d").arg(query))?;
+    String::from_utf8_lossy(&output).trim().parse().ok()
+}
+
+/// Query the platform search index (Spotlight on macOS, plocate/locate elsewhere)
+/// for everything below `dir`. Returns None when no index tool is available.
+///
+/// The numbers are approximate: the index may be stale, and locate databases
+/// do not distinguish files from directories.
+#[cfg(not(windows))]
+pub fn index_counts(dir: &Path) -> Option<IndexCounts> {
+    let dir = dir.canonicalize().ok()?;
+    let output = query_index(&dir)?;
+
+    let mut counts = IndexCounts { total: 0, total_bytes: None, children: HashMap::new() };
+    for line in output.split(|&b| b == b'\n' || b == 0) {
+        if line.is_empty() {
+            continue;
+        }
+        let path = match std::str::from_utf8(line) {
+            Ok(path) => Path::new(path),
+            Err(_) => {
+                continue;
+            }
+        };
+        let relative = match path.strip_prefix(&dir) {
+            Ok(relative) if !relative.as_os_str().is_empty() => relative,
+            _ => {
+                continue; // Substring match outside of `dir`, or `dir` itself
+            }
+        };
+        counts.total += 1;
+        if let Some(Component::Normal(child)) = relative.components().next() {
+            // Only entries below the child count towards it
+            if relative.components().nth(1).is_some() {
+                *counts.children.entry(child.to_os_string()).or_insert(0) += 1;
+            }
+        }
+    }
+    Some(counts)
+}
+
+#[cfg(target_os = "macos")]
+fn query_index(dir: &Path) -> Option<Vec<u8>> {
+    run(Command::new("mdfind").arg("-0").arg("-onlyin").arg(dir).arg("kMDItemContentTypeTree != public.folder"))
+}
+
+#[cfg(not(target_os = "macos"))]
+fn query_index(dir: &Path) -> Option<Vec<u8>> {
+    // Substring search; results outside of `dir` are filtered by the caller
+    let mut pattern = dir.as_os_str().to_os_string();
+    pattern.push("/");
+    ["plocate", "locate"]
+        .iter()
+        .find_map(|tool| run(Command::new(tool).arg("-0").arg(&pattern)))
+}
+
+fn run(command: &mut Command) -> Option<Vec<u8>> {
+    let output = command.stdin(Stdio::null()).stderr(Stdio::null()).output().ok()?;
+    // locate exits with 1 when nothing matched
+    if output.status.success() || (cfg!(not(windows)) && output.status.code() == Some(1)) {
+        Some(output.stdout)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,121 @@
+use crate::{ count_cache::CountCache, numbers::NumberFormat, theme::Theme };
+use ratatui::text::{ Span, Spans };
+use std::path::{ Path, PathBuf };
+
+/// Matches kept for the list; a longer query narrows them down
+const MAX_MATCHES: usize = 200;
+
+/// Fuzzy finder over every directory counted or listed so far (`Ctrl-p`)
+pub struct JumpView {
+    pub query: String,
+    candidates: Vec<(PathBuf, String)>, // With their path relative to the root, as matched
+    pub matches: Vec<usize>, // Indices into `candidates`, best first
+    pub selected: usize, // Index into `matches`
+}
+
+impl JumpView {
+    /// Finder over `dirs` below `root`, the root itself left out
+    pub fn new(root: &Path, dirs: impl IntoIterator<Item = PathBuf>) -> JumpView {
+        let mut candidates: Vec<(PathBuf, String)> = dirs
+            .into_iter()
+            .filter_map(|dir| {
+                let relative = dir.strip_prefix(root).ok()?.to_string_lossy().into_owned();
+                (!relative.is_empty()).then_some((dir, relative))
+            })
+            .collect();
+        candidates.sort_by(|a, b| a.1.cmp(&b.1));
+        candidates.dedup_by(|a, b| a.0 == b.0);
+        let mut view = JumpView { query: String::new(), candidates, matches: Vec::new(), selected: 0 };
+        view.update();
+        view
+    }
+
+    /// Match the candidates against the query again, best scores first
+    pub fn update(&mut self) {
+        let mut scored: Vec<(i64, usize)> = self.candidates
+            .iter()
+            .enumerate()
+            .filter_map(|(index, (_, relative))| Some((fuzzy_score(&self.query, relative)?, index)))
+            .collect();
+        scored.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+        self.matches = scored
+            .into_iter()
+            .take(MAX_MATCHES)
+            .map(|(_, index)| index)
+            .collect();
+        self.selected = 0;
+    }
+
+    pub fn move_selection(&mut self, down: bool) {
+        self.selected = match down {
+            true => (self.selected + 1).min(self.matches.len().saturating_sub(1)),
+            false => self.selected.saturating_sub(1),
+        };
+    }
+
+    /// Directory of the selected match
+    pub fn target(&self) -> Option<&Path> {
+        let &index = self.matches.get(self.selected)?;
+        Some(&self.candidates[index].0)
+    }
+
+    /// The query line and the matches, scrolled to keep the selection among the `rows` shown
+    pub fn lines(
+        &self,
+        theme: &Theme,
+        numbers: &NumberFormat,
+        counts: &CountCache,
+        rows: usize
+    ) -> Vec<Spans<'static>> {
+        let mut lines = vec![
+            Spans::from(vec![Span::styled("Jump to: ", theme.key), Span::raw(format!("{}{}", self.query, theme.cursor()))]),
+            Spans::from(format!("{} of {} directories", self.matches.len(), self.candidates.len()))
+        ];
+        let rows = rows.saturating_sub(lines.len()).max(1);
+        let first = self.selected.saturating_sub(rows - 1);
+        for (position, &index) in self.matches.iter().enumerate().skip(first).take(rows) {
+            let (dir, relative) = &self.candidates[index];
+            let count = match counts.peek(dir) {
+                Some(count) => numbers.count(count as u64),
+                None => String::from("-"),
+            };
+            let count = format!("{:>10}  ", count);
+            let name = format!("{}/", relative);
+            lines.push(
+                match position == self.selected {
+                    true => Spans::from(Span::styled(format!("{}{}", count, name), theme.selection)),
+                    false => Spans::from(vec![Span::raw(count), Span::styled(name, theme.dir)]),
+                }
+            );
+        }
+        lines
+    }
+}
+
+/// How well `query` matches `candidate` as a case-insensitive subsequence, None
+/// if it does not. Runs of adjacent characters, matches at the start of a path
+/// component and matches in the last component score higher; long paths lower.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+    let text: Vec<char> = candidate.chars().flat_map(char::to_lowercase).collect();
+    let last_component = text.iter().rposition(|&c| c == '/').map_or(0, |slash| slash + 1);
+    let mut score = 0i64;
+    let mut next = 0;
+    let mut previous: Option<usize> = None;
+    for wanted in query.chars().flat_map(char::to_lowercase).filter(|c| !c.is_whitespace()) {
+        let found = next + text[next..].iter().position(|&c| c == wanted)?;
+        let at_boundary = found == 0 || matches!(text[found - 1], '/' | '_' | '-' | '.' | ' ');
+        if at_boundary {
+            score += 8;
+        }
+        if previous.is_some_and(|previous| previous + 1 == found) {
+            score += 5;
+        }
+        if found >= last_component {
+            score += 2;
+        }
+        score -= ((found - next) as i64).min(3);
+        previous = Some(found);
+        next = found + 1;
+    }
+    Some(score - (text.len() as i64) / 8)
+}
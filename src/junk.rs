@@ -0,0 +1,66 @@
+use crate::vfs::FileSystem;
+use std::path::Path;
+
+/// How a directory name is confirmed to be build output or a cache rather
+/// than a folder that just happens to share the name
+enum Marker {
+    Always, // The name alone is specific enough
+    Sibling(&'static [&'static str]), // One of these files next to the directory
+    Inside(&'static str), // This file inside the directory
+}
+
+/// A well-known directory that can be regenerated, e.g. by reinstalling or rebuilding
+pub struct JunkRule {
+    pub name: &'static str,
+    pub category: &'static str,
+    marker: Marker,
+}
+
+/// Recognized directories, by exact name
+pub const JUNK_RULES: &[JunkRule] = &[
+    JunkRule { name: "node_modules", category: "Node.js packages", marker: Marker::Always },
+    JunkRule { name: "bower_components", category: "Node.js packages", marker: Marker::Always },
+    JunkRule { name: ".next", category: "JavaScript build caches", marker: Marker::Sibling(&["package.json"]) },
+    JunkRule { name: ".nuxt", category: "JavaScript build caches", marker: Marker::Sibling(&["package.json"]) },
+    JunkRule { name: ".parcel-cache", category: "JavaScript build caches", marker: Marker::Always },
+    JunkRule { name: "target", category: "Rust and Maven builds", marker: Marker::Sibling(&["Cargo.toml", "pom.xml"]) },
+    JunkRule { name: "__pycache__", category: "Python caches", marker: Marker::Always },
+    JunkRule { name: ".pytest_cache", category: "Python caches", marker: Marker::Always },
+    JunkRule { name: ".mypy_cache", category: "Python caches", marker: Marker::Always },
+    JunkRule { name: ".ruff_cache", category: "Python caches", marker: Marker::Always },
+    JunkRule { name: ".tox", category: "Python environments", marker: Marker::Always },
+    JunkRule { name: ".venv", category: "Python environments", marker: Marker::Inside("pyvenv.cfg") },
+    JunkRule { name: "venv", category: "Python environments", marker: Marker::Inside("pyvenv.cfg") },
+    JunkRule { name: "Pods", category: "CocoaPods", marker: Marker::Sibling(&["Podfile"]) },
+    JunkRule { name: "DerivedData", category: "Xcode builds", marker: Marker::Always },
+    JunkRule { name: ".gradle", category: "Gradle builds", marker: Marker::Always },
+    JunkRule {
+        name: "build",
+        category: "Gradle builds",
+        marker: Marker::Sibling(&["build.gradle", "build.gradle.kts"]),
+    },
+    JunkRule { name: ".terraform", category: "Terraform providers", marker: Marker::Always },
+    JunkRule { name: ".dart_tool", category: "Dart tool caches", marker: Marker::Always },
+];
+
+/// The rule `dir` matches, checking markers only for matching names
+pub fn junk_rule(filesystem: &dyn FileSystem, dir: &Path) -> Option<&'static JunkRule> {
+    let name = dir.file_name()?.to_str()?;
+    JUNK_RULES.iter()
+        .filter(|rule| rule.name == name)
+        .find(|rule| {
+            match rule.marker {
+                Marker::Always => true,
+                Marker::Sibling(files) =>
+                    dir.parent().is_some_and(|parent| {
+                        files.iter().any(|file| filesystem.metadata(&parent.join(file)).is_ok())
+                    }),
+                Marker::Inside(file) => filesystem.metadata(&dir.join(file)).is_ok(),
+            }
+        })
+}
+
+/// Category of a recognized directory
+pub fn junk_category(filesystem: &dyn FileSystem, dir: &Path) -> Option<&'static str> {
+    junk_rule(filesystem, dir).map(|rule| rule.category)
+}
@@ -0,0 +1,204 @@
+use crate::{ cli::K8sOptions, json::{ self, Value }, report::ReportNode };
+use std::{ fs, io::Write, path::Path, process::{ Command, Stdio }, thread };
+
+/// Where the scanner binary is copied to inside the pods
+const REMOTE_SCANNER: &str = "/tmp/file_counter-scan";
+
+/// A directory to scan inside a pod
+struct Target {
+    pod: String,
+    container: String,
+    path: String,
+}
+
+/// Run the scanner in every selected pod and aggregate the reports under a single root.
+/// Returns the aggregated tree and one warning per pod/path that could not be scanned.
+pub fn scan_cluster(options: &K8sOptions) -> Result<(ReportNode, Vec<String>), String> {
+    let scanner_path = match &options.scanner {
+        Some(path) => path.clone(),
+        None => std::env::current_exe().map_err(|e| format!("Cannot locate scanner binary: {}", e))?,
+    };
+    let scanner = fs::read(&scanner_path).map_err(|e|
+        format!("Cannot read scanner binary {}: {}", scanner_path.display(), e)
+    )?;
+
+    let targets = find_targets(options)?;
+    if targets.is_empty() {
+        return Err(String::from("No running pods matched the selection"));
+    }
+
+    let results: Vec<Result<ReportNode, String>> = thread::scope(|scope| {
+        let handles: Vec<_> = targets
+            .iter()
+            .map(|target| scope.spawn(|| scan_target(options, target, &scanner)))
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap_or_else(|_| Err(String::from("scan thread panicked"))))
+            .collect()
+    });
+
+    let mut root = ReportNode {
+        name: String::from("k8s"),
+        is_dir: true,
+        files: Some(0),
+        children: Vec::new(),
+        count_time: None,
+    };
+    let mut warnings = Vec::new();
+    for (target, result) in targets.iter().zip(results) {
+        match result {
+            Ok(mut node) => {
+                let base = Path::new(&target.path)
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| String::from("root"));
+                node.name = format!("{}:{}", target.pod, base);
+                root.files = Some(root.files.unwrap_or(0) + node.files.unwrap_or(0));
+                root.children.push(node);
+            }
+            Err(e) => warnings.push(format!("{} ({}): {}", target.pod, target.path, e)),
+        }
+    }
+    Ok((root, warnings))
+}
+
+/// Run `kubectl` with the common connection arguments
+fn kubectl(options: &K8sOptions, args: &[&str], stdin: Option<&[u8]>) -> Result<Vec<u8>, String> {
+    let mut command = Command::new("kubectl");
+    if let Some(kubeconfig) = &options.kubeconfig {
+        command.arg("--kubeconfig").arg(kubeconfig);
+    }
+    if let Some(namespace) = &options.namespace {
+        command.arg("--namespace").arg(namespace);
+    }
+    command.args(args);
+    command.stdin(if stdin.is_some() { Stdio::piped() } else { Stdio::null() });
+    command.stdout(Stdio::piped());
+    command.stderr(Stdio::piped());
+
+    let mut child = command.spawn().map_err(|e| format!("Cannot run kubectl: {}", e))?;
+    if let (Some(data), Some(mut pipe)) = (stdin, child.stdin.take()) {
+        pipe.write_all(data).map_err(|e| format!("Cannot write to kubectl: {}", e))?;
+    }
+    let output = child.wait_with_output().map_err(|e| format!("kubectl failed: {}", e))?;
+    if output.status.success() {
+        Ok(output.stdout)
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
+
+/// List the selected pods and the directories to scan in each of them
+fn find_targets(options: &K8sOptions) -> Result<Vec<Target>, String> {
+    let mut args = vec!["get", "pods", "-o", "json"];
+    if let Some(pod) = &options.pod {
+        args.insert(2, pod.as_str());
+    } else if let Some(selector) = &options.selector {
+        args.extend(["-l", selector.as_str()]);
+    }
+    let output = kubectl(options, &args, None)?;
+    let document = json::parse(&String::from_utf8_lossy(&output))?;
+
+    // `get pods` returns a list, `get pods NAME` a single object
+    let pods = match document.get("items").and_then(Value::as_array) {
+        Some(items) => items.to_vec(),
+        None => vec![document],
+    };
+
+    let mut targets = Vec::new();
+    for pod in &pods {
+        let running = pod
+            .get("status")
+            .and_then(|s| s.get("phase"))
+            .and_then(Value::as_str) == Some("Running");
+        let name = pod
+            .get("metadata")
+            .and_then(|m| m.get("name"))
+            .and_then(Value::as_str);
+        let (Some(name), true) = (name, running) else {
+            continue;
+        };
+        let containers = pod
+            .get("spec")
+            .and_then(|s| s.get("containers"))
+            .and_then(Value::as_array)
+            .unwrap_or(&[]);
+        let container_matches = |c: &Value| {
+            match &options.container {
+                Some(wanted) => c.get("name").and_then(Value::as_str) == Some(wanted),
+                None => true,
+            }
+        };
+
+        if let Some(pvc) = &options.pvc {
+            // Scan wherever the claim is mounted
+            let volumes: Vec<&str> = pod
+                .get("spec")
+                .and_then(|s| s.get("volumes"))
+                .and_then(Value::as_array)
+                .unwrap_or(&[])
+                .iter()
+                .filter(|v| {
+                    v
+                        .get("persistentVolumeClaim")
+                        .and_then(|c| c.get("claimName"))
+                        .and_then(Value::as_str) == Some(pvc)
+                })
+                .filter_map(|v| v.get("name").and_then(Value::as_str))
+                .collect();
+            let mount = containers
+                .iter()
+                .filter(|c| container_matches(c))
+                .find_map(|c| {
+                    let mounts = c.get("volumeMounts").and_then(Value::as_array)?;
+                    let mount = mounts.iter().find(|m| {
+                        m.get("name")
+                            .and_then(Value::as_str)
+                            .is_some_and(|n| volumes.contains(&n))
+                    })?;
+                    Some((c.get("name")?.as_str()?, mount.get("mountPath")?.as_str()?))
+                });
+            if let Some((container, mount_path)) = mount {
+                let path = match &options.path {
+                    Some(sub) => format!("{}/{}", mount_path.trim_end_matches('/'), sub.trim_start_matches('/')),
+                    None => mount_path.to_string(),
+                };
+                targets.push(Target { pod: name.to_string(), container: container.to_string(), path });
+            }
+        } else {
+            let path = options.path
+                .clone()
+                .ok_or("A PATH to scan is required unless --pvc is given")?;
+            let container = containers
+                .iter()
+                .find(|c| container_matches(c))
+                .and_then(|c| c.get("name"))
+                .and_then(Value::as_str);
+            if let Some(container) = container {
+                targets.push(Target { pod: name.to_string(), container: container.to_string(), path });
+            }
+        }
+    }
+    Ok(targets)
+}
+
+/// Copy the scanner into the pod, run it headlessly and parse its JSON report
+fn scan_target(options: &K8sOptions, target: &Target, scanner: &[u8]) -> Result<ReportNode, String> {
+    let pod = target.pod.as_str();
+    let container = target.container.as_str();
+    let upload = format!("cat > {0} && chmod +x {0}", REMOTE_SCANNER);
+    kubectl(options, &["exec", "-i", pod, "-c", container, "--", "sh", "-c", &upload], Some(scanner))?;
+
+    let depth = options.depth.to_string();
+    let result = kubectl(
+        options,
+        &["exec", pod, "-c", container, "--", REMOTE_SCANNER, "--json", "--depth", &depth, &target.path],
+        None
+    );
+
+    // Best-effort cleanup, the report is what matters
+    let _ = kubectl(options, &["exec", pod, "-c", container, "--", "rm", "-f", REMOTE_SCANNER], None);
+
+    ReportNode::parse(&String::from_utf8_lossy(&result?))
+}
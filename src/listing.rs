@@ -0,0 +1,101 @@
+use crate::{
+    drives::{ fs_usage, FsUsage },
+    events::AppEvent,
+    junk::junk_category,
+    mountinfo,
+    owners::Ownership,
+    scan::{ dir_identity, ByteSizes },
+    vfs::{ long_path, LocalFs },
+};
+use std::{
+    ffi::OsString,
+    fs,
+    path::PathBuf,
+    sync::{ atomic::{ AtomicU64, Ordering }, mpsc::Sender, Arc },
+    thread,
+    time::{ Duration, Instant, SystemTime },
+};
+
+/// Entries per batch sent to the UI
+const BATCH_SIZE: usize = 512;
+/// A partial batch is sent anyway after this long, so slow filesystems show progress
+const BATCH_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A directory entry read on the listing thread, with the metadata the UI needs
+pub struct ListedEntry {
+    pub path: PathBuf,
+    pub file_name: OsString,
+    pub is_dir: bool,
+    pub is_link: bool, // Symbolic link; `is_dir` tells what it points to
+    pub identity: Option<(u64, u64)>, // Device and inode of a directory, see `dir_identity`
+    pub modified: Option<SystemTime>,
+    pub sizes: Option<ByteSizes>, // Of a file; directories get theirs from the count
+    pub ownership: Option<Ownership>, // Unix only
+    pub junk: Option<&'static str>, // Category of a recognized build or cache directory
+    pub mount: Option<String>, // Filesystem type of a directory that is a mount point
+}
+
+/// Part of a directory listing, streamed to the UI as it is read
+pub struct ListingBatch {
+    pub generation: u64, // Listing the batch belongs to
+    pub entries: Vec<ListedEntry>,
+    pub done: bool, // Last batch of the listing
+    pub usage: Option<FsUsage>, // Of the directory's filesystem, with the last batch
+}
+
+/// Read `dir` on a background thread and send its entries in batches. Stops early
+/// once `current` moves past `generation`, i.e. the UI started another listing.
+pub fn spawn_listing(dir: PathBuf, generation: u64, current: Arc<AtomicU64>, sender: Sender<AppEvent>) {
+    thread::spawn(move || {
+        let entries = match fs::read_dir(long_path(&dir)) {
+            Ok(entries) => entries,
+            Err(_) => {
+                // Unable to read directory, the list stays empty
+                let batch = ListingBatch { generation, entries: Vec::new(), done: true, usage: fs_usage(&dir) };
+                sender.send(AppEvent::Listing(batch)).unwrap_or(());
+                return;
+            }
+        };
+
+        let device = dir_identity(&dir).map(|(device, _)| device);
+        let mut batch = Vec::new();
+        let mut last_sent = Instant::now();
+        for entry in entries.flatten() {
+            if current.load(Ordering::Relaxed) != generation {
+                return;
+            }
+            let path = dir.join(entry.file_name());
+            let metadata = fs::metadata(long_path(&path)).ok(); // Follows symlinks
+            let is_dir = metadata.as_ref().is_some_and(|m| m.is_dir());
+            let identity = if is_dir { dir_identity(&path) } else { None };
+            // On another device: a mount point, or a symlink to another filesystem
+            let mount = match (identity, device) {
+                (Some((entry_device, _)), Some(device)) if entry_device != device =>
+                    fs::canonicalize(&path).ok().and_then(|real| mountinfo::mounted_fs_type(&real)),
+                _ => None,
+            };
+            batch.push(ListedEntry {
+                ownership: metadata.as_ref().and_then(Ownership::of),
+                sizes: metadata.filter(|m| m.is_file()).map(|m| ByteSizes::of(&m)),
+                identity,
+                mount,
+                junk: if is_dir { junk_category(&LocalFs, &path) } else { None },
+                is_link: entry.file_type().is_ok_and(|kind| kind.is_symlink()),
+                file_name: entry.file_name(),
+                modified: entry.metadata().and_then(|m| m.modified()).ok(),
+                path,
+                is_dir,
+            });
+            if batch.len() >= BATCH_SIZE || last_sent.elapsed() >= BATCH_INTERVAL {
+                let entries = std::mem::take(&mut batch);
+                if sender.send(AppEvent::Listing(ListingBatch { generation, entries, done: false, usage: None })).is_err() {
+                    return;
+                }
+                last_sent = Instant::now();
+            }
+        }
+        // Asked here rather than on the UI thread, as a stalled network filesystem blocks the call
+        let usage = fs_usage(&dir);
+        sender.send(AppEvent::Listing(ListingBatch { generation, entries: batch, done: true, usage })).unwrap_or(());
+    });
+}
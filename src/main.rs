@@ -1,649 +1,436 @@
-use crossterm::{
-    event::{
-        self,
-        DisableMouseCapture,
-        EnableMouseCapture,
-        Event,
-        KeyCode,
-        MouseButton,
-        MouseEventKind,
-    },
-    execute,
-    terminal::{ disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen },
-};
-use dashmap::DashMap;
-use num_cpus;
-use ratatui::{
-    backend::CrosstermBackend,
-    layout::{ Constraint, Direction, Layout, Rect },
-    style::{ Modifier, Style, Color },
-    text::{ Span, Spans },
-    widgets::{ Block, Borders, Table, Cell, Row, TableState, Paragraph, Wrap },
-    Terminal,
-};
-use std::{
-    collections::HashSet,
-    fs,
-    io,
-    path::{ Path, PathBuf },
-    sync::{ mpsc::{ channel, Receiver, Sender }, Arc },
-};
-use threadpool::ThreadPool;
-use unicode_width::UnicodeWidthStr;
-
-struct App {
-    current_dir: PathBuf,
-    home_dir: PathBuf,
-    current_dir_count: Option<usize>, // Store the file count of the current directory
-    items: Vec<DirEntry>,
-    table_state: TableState,
-    action_pending: Option<Action>,
-    file_count_tx: Sender<(PathBuf, usize)>,
-    file_count_rx: Receiver<(PathBuf, usize)>,
-    thread_pool: ThreadPool,
-    spinner_index: usize,
-    spinner_frames: Vec<&'static str>,
-    file_count_cache: Arc<DashMap<PathBuf, usize>>, // Cache using DashMap
-}
-
-enum Action {
-    EnterDirectory(usize),
-}
 
-struct DirEntry {
-    name: String,
-    path: PathBuf,
-    is_dir: bool,
-    file_count: Option<usize>,
-}
+#[cfg(feature = "archive")]
+mod archive;
+#[cfg(feature = "tui")]
+mod broken_links;
+#[cfg(feature = "tui")]
+mod cache_view;
+#[cfg(feature = "tui")]
+mod cleanable;
+mod cli;
+#[cfg(feature = "tui")]
+mod collate;
+#[cfg(feature = "tui")]
+mod columns;
+mod compare;
+#[cfg(feature = "tui")]
+mod count_cache;
+#[cfg(any(feature = "tui", feature = "persist"))]
+mod config;
+mod control;
+mod dirs;
+mod doctor;
+#[cfg(feature = "tui")]
+mod drives;
+mod file_types;
+#[cfg(feature = "tui")]
+mod duplicates;
+#[cfg(feature = "tui")]
+mod empty_dirs;
+#[cfg(feature = "tui")]
+mod events;
+#[cfg(feature = "tui")]
+mod format;
+#[cfg(feature = "tui")]
+mod git;
+#[cfg(feature = "persist")]
+mod history;
+#[cfg(feature = "tui")]
+mod icons;
+#[cfg(feature = "tui")]
+mod index;
+#[cfg(feature = "tui")]
+mod jump;
+mod json;
+mod junk;
+#[cfg(feature = "tui")]
+mod listing;
+#[cfg(feature = "tui")]
+mod mft;
+#[cfg(feature = "tui")]
+mod modal;
+#[cfg(feature = "remote")]
+mod k8s;
+mod monitor;
+mod mountinfo;
+#[cfg(feature = "remote")]
+mod objects;
+mod ncdu;
+mod numbers;
+mod owners;
+#[cfg(feature = "tui")]
+mod pattern;
+#[cfg(feature = "tui")]
+mod preview;
+mod report;
+mod scan;
+#[cfg(feature = "tui")]
+mod screen;
+mod simple;
+#[cfg(all(feature = "tui", feature = "persist"))]
+mod state;
+#[cfg(feature = "tui")]
+mod skipped;
+#[cfg(feature = "tui")]
+mod summary;
+#[cfg(feature = "tui")]
+mod theme;
+#[cfg(feature = "tui")]
+mod toast;
+#[cfg(feature = "tui")]
+mod trash;
+#[cfg(feature = "tui")]
+mod tui;
+#[cfg(feature = "remote")]
+mod update;
+#[cfg(all(feature = "io_uring", target_os = "linux", target_env = "gnu"))]
+mod uring;
+mod users;
+mod vfs;
+#[cfg(feature = "watch")]
+mod watch;
+#[cfg(feature = "tui")]
+mod wizard;
+
+use cli::{ CountLimits, DaemonOptions, HistoryOptions, K8sOptions, Options, UpdateOptions };
+use mountinfo::HostPathTranslator;
+use report::ReportNode;
+use std::{ io::{ self, IsTerminal }, path::{ Path, PathBuf } };
 
-impl App {
-    fn new(start_dir: PathBuf) -> io::Result<Self> {
-        let (file_count_tx, file_count_rx) = channel();
-        let thread_pool = ThreadPool::new(num_cpus::get());
-
-        // Define spinner frames
-        let spinner_frames = vec!["   ", ".  ", ".. ", "..."];
-
-        // Initialize cache
-        let file_count_cache = Arc::new(DashMap::new());
-
-        let mut app = App {
-            current_dir: start_dir.clone(),
-            home_dir: start_dir,
-            current_dir_count: None, // Initialize as None
-            items: Vec::new(),
-            table_state: TableState::default(),
-            action_pending: None,
-            file_count_tx,
-            file_count_rx,
-            thread_pool,
-            spinner_index: 0,
-            spinner_frames,
-            file_count_cache,
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Parse command-line options
+    let mut options = cli::parse_args(std::env::args().skip(1))?;
+    if options.show_usage {
+        println!("{}", cli::USAGE);
+        return Ok(());
+    }
+    if let Some(update_options) = &options.self_update {
+        self_update(update_options)?;
+        return Ok(());
+    }
+    if let Some(doctor_options) = &options.doctor {
+        let dir = match &doctor_options.path {
+            Some(path) => path.clone(),
+            None => std::env::current_dir()?,
         };
-        app.refresh_items()?;
-        Ok(app)
+        doctor::run(&dir, doctor_options)?;
+        return Ok(());
+    }
+    if let Some(users_options) = &options.users {
+        let dir = match &users_options.path {
+            Some(path) => path.clone(),
+            None => std::env::current_dir()?,
+        };
+        users::run(&dir, users_options)?;
+        return Ok(());
+    }
+    if let Some(control_options) = &options.control {
+        println!("{}", control::send(&control_options.socket, &control_options.command)?);
+        return Ok(());
+    }
+    if let Some(dir) = &options.invalidate {
+        let socket = options.control_socket
+            .as_deref()
+            .ok_or("--invalidate needs the --control-socket of a running instance")?;
+        // Resolved here, the instance may run in another directory; a removed one is stale too
+        let dir = dir
+            .canonicalize()
+            .or_else(|_| std::path::absolute(dir))
+            .map_err(|e| format!("Cannot invalidate {}: {}", dir.display(), e))?;
+        println!("{}", control::send(socket, &format!("invalidate {}", dir.display()))?);
+        return Ok(());
+    }
+    if let Some(daemon_options) = &options.daemon {
+        record_history(daemon_options)?;
+        return Ok(());
+    }
+    if let Some(history_options) = &options.history {
+        let path = match &history_options.path {
+            Some(path) => path.clone(),
+            None => std::env::current_dir()?,
+        };
+        show_history(&path, history_options)?;
+        return Ok(());
+    }
+    if let Some(compare_options) = &options.compare {
+        // Like diff(1), exit with 1 when the trees differ
+        if compare::run(compare_options)? {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    if options.stdin {
+        read_stdin_dirs(&mut options)?;
+    }
+    let host_paths = if options.host_paths { HostPathTranslator::detect() } else { None };
+
+    // Run the scanner inside Kubernetes pods and browse the aggregated result
+    let mut snapshot = None;
+    if let Some(k8s_options) = &options.k8s {
+        let (root, warnings) = scan_cluster(k8s_options)?;
+        for warning in warnings {
+            eprintln!("Warning: {}", warning);
+        }
+        snapshot = Some(root);
     }
 
-    /// Refresh the item list in the current directory
-    fn refresh_items(&mut self) -> io::Result<()> {
-        self.items.clear();
-
-        let previous_selection = self.table_state.selected().unwrap_or(0);
-
-        let include_back = self.current_dir != self.home_dir;
+    // Count the objects below a bucket prefix and browse them like a snapshot
+    if let Some(url) = options.start_dir.as_deref().filter(|dir| is_object_url(dir)) {
+        snapshot = Some(scan_object_store(url)?);
+    }
 
-        self.table_state.select(Some(previous_selection));
+    // Browse an ncdu export offline
+    if let Some(path) = &options.import_ncdu {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+        let root = ncdu::import(&text).map_err(|e| format!("Cannot import {}: {}", path.display(), e))?;
+        snapshot = Some(root);
+    }
 
-        // Check if the file count of the current directory is in the cache
-        self.current_dir_count = self.file_count_cache.get(&self.current_dir).map(|v| *v);
+    let start_dir = match (&snapshot, options.start_dir.clone()) {
+        (Some(root), _) => PathBuf::from(&root.name),
+        (None, Some(dir)) => dir,
+        (None, None) => std::env::current_dir()?,
+    };
+
+    // Several roots are listed side by side in the TUI, or reported together
+    if !options.more_dirs.is_empty() {
+        let headless = options.export_ncdu.is_some() ||
+            options.snapshot.is_some() ||
+            options.monitor.is_some() ||
+            options.count_limits.is_some() ||
+            options.simple;
+        if headless || snapshot.is_some() {
+            return Err("Several directories can only be browsed or printed with --json; give one for this mode".into());
+        }
+        let roots: Vec<PathBuf> = std::iter::once(start_dir.clone()).chain(options.more_dirs.iter().cloned()).collect();
+        if options.json || !cfg!(feature = "tui") {
+            println!("{}", report::build_roots_report(&roots, options.timings).to_json().to_json());
+            return Ok(());
+        }
+        if !io::stdout().is_terminal() {
+            // Each root, then their total, like `du -c`
+            let report = report::build_roots_report(&roots, options.timings);
+            for root in &report.children {
+                print!("{}", root.to_plain(Path::new(&root.name)));
+            }
+            println!("{}\ttotal", report.files.unwrap_or(0));
+            return Ok(());
+        }
+    }
 
-        // If not cached, start a thread to compute the file count
-        if self.current_dir_count.is_none() {
-            let path = self.current_dir.clone();
-            let sender = self.file_count_tx.clone();
-            let cache: Arc<DashMap<PathBuf, usize>> = Arc::clone(&self.file_count_cache);
+    // Headless mode: write an ncdu export of the whole tree and exit
+    if let Some(path) = &options.export_ncdu {
+        let host_path = host_paths.as_ref().and_then(|t| t.translate(&start_dir));
+        let export = ncdu::export(&start_dir, host_path.as_deref());
+        write_output(path, &export.to_json())?;
+        return Ok(());
+    }
 
-            self.thread_pool.execute(move || {
-                let count = count_files(&path).unwrap_or(0);
+    // Headless mode: save the count of every directory as a --diff baseline and exit
+    if let Some(path) = &options.snapshot {
+        let root = start_dir.canonicalize().unwrap_or_else(|_| start_dir.clone());
+        let text = report_json(report::build_snapshot(&root), &start_dir, host_paths.as_ref()).to_json();
+        write_output(path, &text)?;
+        return Ok(());
+    }
 
-                // Update cache
-                cache.insert(path.clone(), count);
+    // Headless mode: keep rescanning and alert on directories above the threshold
+    if let Some(monitor_options) = &options.monitor {
+        monitor::run(&start_dir, monitor_options, dirs::state_dir(options.state_dir.as_deref()))?;
+        return Ok(());
+    }
 
-                // Send result
-                sender.send((path, count)).unwrap_or(());
-            });
+    // Headless mode: print a JSON report and exit
+    if options.json {
+        let report = match snapshot {
+            Some(root) => root,
+            None => report::build_report(&start_dir, options.depth, options.timings),
+        };
+        let files = report.files.unwrap_or(0);
+        println!("{}", report_json(report, &start_dir, host_paths.as_ref()).to_json());
+        if let Some(limits) = &options.count_limits {
+            check_count(&start_dir, files, limits);
         }
+        return Ok(());
+    }
 
-        // Add option to go back to parent directory (if not at home_dir)
-        if include_back {
-            if let Some(parent) = self.current_dir.parent() {
-                // Check if the file count of the parent directory is in the cache
-                let parent_count = self.file_count_cache.get(&parent.to_path_buf()).map(|v| *v);
-
-                // If not cached, start a thread to compute the file count
-                if parent_count.is_none() {
-                    let path = parent.to_path_buf();
-                    let sender = self.file_count_tx.clone();
-                    let cache: Arc<DashMap<PathBuf, usize>> = Arc::clone(&self.file_count_cache);
-
-                    self.thread_pool.execute(move || {
-                        let count = count_files(&path).unwrap_or(0);
-
-                        // Update cache
-                        cache.insert(path.clone(), count);
-
-                        // Send result
-                        sender.send((path, count)).unwrap_or(());
-                    });
-                }
-
-                self.items.push(DirEntry {
-                    name: String::from(".. (Back to parent directory)"),
-                    path: parent.to_path_buf(),
-                    is_dir: true,
-                    file_count: parent_count, // Use cached file count
-                });
+    // Headless mode: count the tree and exit with 1 if it breaks the limits
+    if let Some(limits) = &options.count_limits {
+        let files = match snapshot {
+            Some(root) => root.files.unwrap_or(0),
+            // A missing directory is an error, not zero files
+            None if !start_dir.is_dir() => {
+                return Err(format!("Cannot count {}: not a directory", start_dir.display()).into());
             }
-        }
-
-        let entries: Vec<_> = match fs::read_dir(&self.current_dir) {
-            Ok(entries) => entries.collect::<Result<Vec<_>, _>>()?,
-            Err(_) => Vec::new(), // Unable to read directory, use empty list
+            None => scan::count_files(&start_dir).map_err(|e| format!("Cannot count {}: {}", start_dir.display(), e))?,
         };
+        println!("{}: {} files", start_dir.display(), files);
+        check_count(&start_dir, files, limits);
+        return Ok(());
+    }
 
-        for entry in entries {
-            let path = entry.path();
-            let is_dir = path.is_dir();
-            let name = entry
-                .file_name()
-                .into_string()
-                .unwrap_or_else(|_| String::from("Unknown"));
-
-            // Check cache
-            let cached_count = if is_dir {
-                self.file_count_cache.get(&path).map(|v| *v)
-            } else {
-                None
-            };
-
-            self.items.push(DirEntry {
-                name,
-                path,
-                is_dir,
-                file_count: cached_count, // Use cached file count if available
-            });
-        }
+    // Plain numbered listing for screen readers instead of the TUI
+    if options.simple {
+        simple::run(&start_dir, snapshot.as_ref())?;
+        return Ok(());
+    }
 
-        // Submit tasks to compute file counts for each directory (if not cached)
-        for item in self.items.iter() {
-            if item.is_dir && item.file_count.is_none() {
-                // Clone necessary data
-                let path = item.path.clone();
-                let sender = self.file_count_tx.clone();
-                let cache: Arc<DashMap<PathBuf, usize>> = Arc::clone(&self.file_count_cache);
+    // Redirected or piped: print the report instead of drawing the TUI into it
+    if cfg!(feature = "tui") && !io::stdout().is_terminal() {
+        let report = match snapshot {
+            Some(root) => root,
+            None => report::build_report(&start_dir, options.depth, options.timings),
+        };
+        print!("{}", report.to_plain(&start_dir));
+        return Ok(());
+    }
 
-                self.thread_pool.execute(move || {
-                    let count = count_files(&path).unwrap_or(0);
+    run_tui(start_dir, host_paths, snapshot, &options)
+}
 
-                    // Update cache
-                    cache.insert(path.clone(), count);
+/// Take the directories to count from stdin, one per line, after any given as arguments
+fn read_stdin_dirs(options: &mut Options) -> Result<(), String> {
+    use std::io::BufRead;
 
-                    // Send result
-                    sender.send((path, count)).unwrap_or(());
-                });
-            }
+    for line in std::io::stdin().lock().lines() {
+        let line = line.map_err(|e| format!("Cannot read directories from stdin: {}", e))?;
+        // Only the line ending is stripped: names may start or end with spaces
+        let line = line.strip_suffix('\r').unwrap_or(&line);
+        if line.is_empty() {
+            continue;
         }
-
-        // Sort items based on file count
-        if include_back && self.items.len() > 1 {
-            let (_first, rest) = self.items.split_at_mut(1);
-            rest.sort_by(|a, b| {
-                match (a.is_dir, b.is_dir) {
-                    (true, true) =>
-                        match (a.file_count, b.file_count) {
-                            (Some(a_count), Some(b_count)) =>
-                                b_count
-                                    .cmp(&a_count)
-                                    .then(a.name.to_lowercase().cmp(&b.name.to_lowercase())),
-                            (Some(_), None) => std::cmp::Ordering::Less,
-                            (None, Some(_)) => std::cmp::Ordering::Greater,
-                            (None, None) => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
-                        }
-                    (false, false) => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
-                    (true, false) => std::cmp::Ordering::Less,
-                    (false, true) => std::cmp::Ordering::Greater,
-                }
-            });
-        } else {
-            self.items.sort_by(|a, b| {
-                match (a.is_dir, b.is_dir) {
-                    (true, true) =>
-                        match (a.file_count, b.file_count) {
-                            (Some(a_count), Some(b_count)) =>
-                                b_count
-                                    .cmp(&a_count)
-                                    .then(a.name.to_lowercase().cmp(&b.name.to_lowercase())),
-                            (Some(_), None) => std::cmp::Ordering::Less,
-                            (None, Some(_)) => std::cmp::Ordering::Greater,
-                            (None, None) => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
-                        }
-                    (false, false) => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
-                    (true, false) => std::cmp::Ordering::Less,
-                    (false, true) => std::cmp::Ordering::Greater,
-                }
-            });
+        match options.start_dir {
+            Some(_) => options.more_dirs.push(PathBuf::from(line)),
+            None => {
+                options.start_dir = Some(PathBuf::from(line));
+            }
         }
-
-        Ok(())
     }
+    match options.start_dir {
+        Some(_) => Ok(()),
+        None => Err(String::from("No directories on stdin")),
+    }
+}
 
-    /// Move selection to the next item
-    fn next(&mut self) {
-        let i = match self.table_state.selected() {
-            Some(i) => {
-                if i >= self.items.len() - 1 { 0 } else { i + 1 }
-            }
-            None => 0,
-        };
-        self.table_state.select(Some(i));
+/// Exit with 1, after saying why, when `files` is outside `limits`
+fn check_count(dir: &std::path::Path, files: usize, limits: &CountLimits) {
+    let violation = match (limits.max, limits.min) {
+        (Some(max), _) if files > max => Some(format!("more than {}", max)),
+        (_, Some(min)) if files < min => Some(format!("fewer than {}", min)),
+        _ => None,
+    };
+    if let Some(violation) = violation {
+        eprintln!("{}: {} files, {}", dir.display(), files, violation);
+        std::process::exit(1);
     }
+}
 
-    /// Move selection to the previous item
-    fn previous(&mut self) {
-        let i = match self.table_state.selected() {
-            Some(i) => {
-                if i == 0 { self.items.len() - 1 } else { i - 1 }
-            }
-            None => self.items.len() - 1,
-        };
-        self.table_state.select(Some(i));
+/// JSON form of a report, with the host-side path when --host-paths is given
+fn report_json(report: ReportNode, start_dir: &std::path::Path, host_paths: Option<&HostPathTranslator>) -> json::Value {
+    let mut value = report.to_json();
+    let host_path = host_paths.and_then(|t| t.translate(start_dir));
+    if let (json::Value::Object(fields), Some(host_path)) = (&mut value, host_path) {
+        fields.push((String::from("host_path"), json::Value::from(host_path.display().to_string())));
     }
+    value
 }
 
-/// Count the number of files in a directory using an iterative approach to avoid stack overflow
-fn count_files(dir: &Path) -> io::Result<usize> {
-    let mut count = 0usize;
-    let mut dirs_to_visit = Vec::new();
-    let mut visited = HashSet::new();
+/// Write a headless result to a file, or to stdout for `-`
+fn write_output(path: &std::path::Path, text: &str) -> Result<(), String> {
+    if path.as_os_str() == "-" {
+        println!("{}", text);
+        Ok(())
+    } else {
+        std::fs::write(path, format!("{}\n", text)).map_err(|e| format!("Cannot write {}: {}", path.display(), e))
+    }
+}
 
-    dirs_to_visit.push(dir.to_path_buf());
+/// Error for options that need a feature this binary was built without
+#[cfg(any(not(feature = "remote"), not(feature = "watch"), not(feature = "persist")))]
+fn missing_feature(what: &str, feature: &str) -> String {
+    format!("{} is not available in this build (cargo feature `{}`)", what, feature)
+}
 
-    while let Some(current_dir) = dirs_to_visit.pop() {
-        let real_dir = match current_dir.canonicalize() {
-            Ok(path) => path,
-            Err(_) => {
-                continue;
-            } // Unable to get real path, skip
-        };
+#[cfg(feature = "persist")]
+fn record_history(options: &DaemonOptions) -> Result<(), String> {
+    history::run_daemon(options)
+}
 
-        if !visited.insert(real_dir.clone()) {
-            continue; // Already visited, skip
-        }
+#[cfg(not(feature = "persist"))]
+fn record_history(_options: &DaemonOptions) -> Result<(), String> {
+    Err(missing_feature("The daemon subcommand", "persist"))
+}
 
-        let entries = match fs::read_dir(&real_dir) {
-            Ok(entries) => entries,
-            Err(_) => {
-                continue;
-            } // Unable to read directory, skip
-        };
+#[cfg(feature = "persist")]
+fn show_history(path: &Path, options: &HistoryOptions) -> Result<(), String> {
+    history::run(path, options)
+}
 
-        for entry_result in entries {
-            match entry_result {
-                Ok(entry) => {
-                    let path = entry.path();
-                    if path.is_file() {
-                        count += 1;
-                    } else if path.is_dir() {
-                        dirs_to_visit.push(path);
-                    }
-                }
-                Err(_) => {
-                    continue;
-                } // Unable to read entry, skip
-            }
-        }
-    }
+#[cfg(not(feature = "persist"))]
+fn show_history(_path: &Path, _options: &HistoryOptions) -> Result<(), String> {
+    Err(missing_feature("The history subcommand", "persist"))
+}
 
-    Ok(count)
+#[cfg(feature = "remote")]
+fn self_update(options: &UpdateOptions) -> Result<(), String> {
+    update::self_update(options)
 }
 
-/// Calculate the wrapped height of text given a maximum width
-fn calculate_wrapped_height(text: &str, max_width: u16) -> u16 {
-    let mut height = 0u16;
-    for line in text.lines() {
-        let line_width = UnicodeWidthStr::width(line) as u16;
-        let line_height = if line_width == 0 { 1 } else { (line_width - 1) / max_width + 1 };
-        height += line_height;
-    }
-    height
+#[cfg(not(feature = "remote"))]
+fn self_update(_options: &UpdateOptions) -> Result<(), String> {
+    Err(missing_feature("self-update", "remote"))
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Get the starting directory
-    let args: Vec<String> = std::env::args().collect();
-    let start_dir = if args.len() > 1 { PathBuf::from(&args[1]) } else { std::env::current_dir()? };
-
-    // Initialize the App
-    let mut app = App::new(start_dir)?;
-
-    // Set up the terminal
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
-
-    // Initialize table_area
-    let mut table_area = Rect::default();
-
-    // Main loop
-    let mut redraw_ui = true;
-    loop {
-        // Update spinner frame index
-        app.spinner_index = (app.spinner_index + 1) % app.spinner_frames.len();
-
-        // Handle messages from file_count_rx
-        let mut counts_updated = false;
-        while let Ok((path, count)) = app.file_count_rx.try_recv() {
-            if path == app.current_dir {
-                app.current_dir_count = Some(count);
-                counts_updated = true;
-            }
+#[cfg(feature = "remote")]
+fn scan_cluster(options: &K8sOptions) -> Result<(ReportNode, Vec<String>), String> {
+    k8s::scan_cluster(options)
+}
 
-            // Update file count for "back to parent directory"
-            if let Some(item) = app.items.iter_mut().find(|i| i.path == path) {
-                item.file_count = Some(count);
-                counts_updated = true;
-            }
-        }
+#[cfg(not(feature = "remote"))]
+fn scan_cluster(_options: &K8sOptions) -> Result<(ReportNode, Vec<String>), String> {
+    Err(missing_feature("The k8s subcommand", "remote"))
+}
 
-        if counts_updated {
-            // Re-sort items
-            let include_back = app.current_dir != app.home_dir;
-            if include_back && app.items.len() > 1 {
-                let (_first, rest) = app.items.split_at_mut(1);
-                rest.sort_by(|a, b| {
-                    match (a.is_dir, b.is_dir) {
-                        (true, true) =>
-                            match (a.file_count, b.file_count) {
-                                (Some(a_count), Some(b_count)) =>
-                                    b_count
-                                        .cmp(&a_count)
-                                        .then(a.name.to_lowercase().cmp(&b.name.to_lowercase())),
-                                (Some(_), None) => std::cmp::Ordering::Less,
-                                (None, Some(_)) => std::cmp::Ordering::Greater,
-                                (None, None) => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
-                            }
-                        (false, false) => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
-                        (true, false) => std::cmp::Ordering::Less,
-                        (false, true) => std::cmp::Ordering::Greater,
-                    }
-                });
-            } else {
-                app.items.sort_by(|a, b| {
-                    match (a.is_dir, b.is_dir) {
-                        (true, true) =>
-                            match (a.file_count, b.file_count) {
-                                (Some(a_count), Some(b_count)) =>
-                                    b_count
-                                        .cmp(&a_count)
-                                        .then(a.name.to_lowercase().cmp(&b.name.to_lowercase())),
-                                (Some(_), None) => std::cmp::Ordering::Less,
-                                (None, Some(_)) => std::cmp::Ordering::Greater,
-                                (None, None) => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
-                            }
-                        (false, false) => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
-                        (true, false) => std::cmp::Ordering::Less,
-                        (false, true) => std::cmp::Ordering::Greater,
-                    }
-                });
-            }
+/// Object store URLs are recognized even without the backend, to explain why they cannot be read
+fn is_object_url(path: &std::path::Path) -> bool {
+    ["s3://", "gs://"].iter().any(|scheme| path.to_string_lossy().starts_with(scheme))
+}
 
-            redraw_ui = true;
-        }
+#[cfg(feature = "remote")]
+fn scan_object_store(url: &std::path::Path) -> Result<ReportNode, String> {
+    objects::scan_object_store(url)
+}
 
-        if redraw_ui {
-            // Draw the UI
-            terminal.draw(|f| {
-                let size = f.size();
-
-                // Calculate block width (subtract borders)
-                let block_width = size.width - 2;
-
-                // Get current directory path string
-                let current_dir_text = if let Some(count) = app.current_dir_count {
-                    format!("{} (Total files: {})", app.current_dir.display(), count)
-                } else {
-                    let spinner_frame = app.spinner_frames[app.spinner_index];
-                    format!("{} (Counting files{})", app.current_dir.display(), spinner_frame)
-                };
-
-                // Calculate the height after wrapping
-                let num_lines = calculate_wrapped_height(&current_dir_text, block_width);
-
-                // Set block height including borders
-                let current_dir_height = num_lines + 2; // +2 for borders
-
-                // Set up the layout
-                let chunks = Layout::default()
-                    .direction(Direction::Vertical)
-                    .constraints(
-                        [
-                            Constraint::Length(current_dir_height), // Current directory
-                            Constraint::Min(1), // File list
-                            Constraint::Length(3), // Footer
-                        ].as_ref()
-                    )
-                    .split(size);
-
-                // Display the "Current Directory" block
-                let title_block = Block::default()
-                    .borders(Borders::ALL)
-                    .title(
-                        Span::styled(
-                            "Current Directory",
-                            Style::default().add_modifier(Modifier::BOLD)
-                        )
-                    );
-
-                // Paragraph containing the current directory, with wrapping enabled
-                let current_dir_paragraph = Paragraph::new(current_dir_text)
-                    .block(title_block)
-                    .wrap(Wrap { trim: false });
-
-                f.render_widget(current_dir_paragraph, chunks[0]);
-
-                // Prepare table data
-                let header_cells = ["Type", "Name", "Count"]
-                    .iter()
-                    .map(|h| Cell::from(*h).style(Style::default().fg(Color::Yellow)));
-                let header = Row::new(header_cells)
-                    .style(Style::default().bg(Color::DarkGray))
-                    .height(1);
-
-                let spinner_frame = app.spinner_frames[app.spinner_index];
-
-                let rows = app.items.iter().map(|entry| {
-                    let type_cell = if entry.is_dir {
-                        Cell::from("Dir").style(Style::default().fg(Color::Blue))
-                    } else {
-                        Cell::from("File").style(Style::default().fg(Color::Gray))
-                    };
-                    let name_cell = if
-                        entry.is_dir &&
-                        entry.name == ".. (Back to parent directory)"
-                    {
-                        Cell::from(entry.name.clone()).style(Style::default().fg(Color::Green))
-                    } else {
-                        Cell::from(entry.name.clone())
-                    };
-                    let file_count_cell = if entry.is_dir {
-                        match entry.file_count {
-                            Some(count) => Cell::from(count.to_string()),
-                            None => Cell::from(spinner_frame),
-                        }
-                    } else {
-                        Cell::from("-")
-                    };
-                    Row::new(vec![type_cell, name_cell, file_count_cell]).height(1)
-                });
-
-                let t = Table::new(rows)
-                    .header(header)
-                    .block(Block::default().borders(Borders::ALL).title("File Counter"))
-                    .highlight_style(
-                        Style::default()
-                            .bg(Color::LightGreen)
-                            .fg(Color::Black)
-                            .add_modifier(Modifier::BOLD)
-                    )
-                    .highlight_symbol(">> ")
-                    .widths(
-                        &[Constraint::Length(6), Constraint::Percentage(70), Constraint::Length(6)]
-                    );
-
-                let mut state = app.table_state.clone();
-
-                f.render_stateful_widget(t, chunks[1], &mut state);
-
-                // Save the table area for mouse event handling
-                table_area = chunks[1];
-
-                // Footer: display key bindings
-                let footer_text = vec![
-                    Spans::from(
-                        vec![
-                            Span::styled(
-                                "q - Quit",
-                                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
-                            ),
-                            Span::raw(" | "),
-                            Span::styled(
-                                "↑/↓/k/j - Move",
-                                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
-                            ),
-                            Span::raw(" | "),
-                            Span::styled(
-                                "Enter - Open",
-                                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
-                            ),
-                            Span::raw(" | "),
-                            Span::styled(
-                                "h - Home",
-                                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
-                            )
-                        ]
-                    )
-                ];
-                let footer_paragraph = Paragraph::new(footer_text)
-                    .block(Block::default().borders(Borders::ALL))
-                    .wrap(Wrap { trim: true });
-
-                f.render_widget(footer_paragraph, chunks[2]);
-            })?;
-            redraw_ui = false;
-        }
+#[cfg(not(feature = "remote"))]
+fn scan_object_store(_url: &std::path::Path) -> Result<ReportNode, String> {
+    Err(missing_feature("Counting object store prefixes", "remote"))
+}
 
-        // After drawing, handle any pending actions
-        if let Some(action) = app.action_pending.take() {
-            match action {
-                Action::EnterDirectory(index) => {
-                    if index < app.items.len() {
-                        let selected_entry = &app.items[index];
-                        if selected_entry.is_dir {
-                            app.current_dir = selected_entry.path.clone();
-                            app.refresh_items()?;
-                            redraw_ui = true;
-                        }
-                    }
-                }
-            }
-        }
+#[cfg(feature = "tui")]
+fn run_tui(
+    start_dir: PathBuf,
+    host_paths: Option<HostPathTranslator>,
+    snapshot: Option<ReportNode>,
+    options: &Options
+) -> Result<(), Box<dyn std::error::Error>> {
+    tui::run(start_dir, host_paths, snapshot, options)
+}
 
-        // Handle input events
-        if event::poll(std::time::Duration::from_millis(100))? {
-            match event::read() {
-                Ok(evt) =>
-                    match evt {
-                        // Handle keyboard events
-                        Event::Key(key) =>
-                            match key.code {
-                                // Quit the program
-                                KeyCode::Char('q') => {
-                                    break;
-                                }
-                                // Move up
-                                KeyCode::Up | KeyCode::Char('k') => {
-                                    app.previous();
-                                    redraw_ui = true;
-                                }
-                                // Move down
-                                KeyCode::Down | KeyCode::Char('j') => {
-                                    app.next();
-                                    redraw_ui = true;
-                                }
-                                // Enter directory
-                                KeyCode::Enter => {
-                                    if let Some(selected) = app.table_state.selected() {
-                                        app.action_pending = Some(Action::EnterDirectory(selected));
-                                    }
-                                }
-                                // Go to home directory
-                                KeyCode::Char('h') => {
-                                    app.current_dir = app.home_dir.clone();
-                                    app.refresh_items()?;
-                                    redraw_ui = true;
-                                }
-                                _ => {}
-                            }
-                        // Handle mouse events
-                        Event::Mouse(mouse_event) =>
-                            match mouse_event.kind {
-                                MouseEventKind::Down(MouseButton::Left) => {
-                                    let mouse_row = mouse_event.row;
-                                    let mouse_col = mouse_event.column;
-                                    // Check if the click is within the table area
-                                    if
-                                        mouse_row >= table_area.top() + 2 &&
-                                        // +1 for top border, +1 for header
-                                        mouse_row < table_area.bottom() - 1 &&
-                                        // -1 for bottom border
-                                        mouse_col >= table_area.left() + 1 &&
-                                        // +1 for left border
-                                        mouse_col < table_area.right() - 1
-                                        // -1 for right border
-                                    {
-                                        // Calculate the index of the clicked item
-                                        let relative_row = mouse_row - table_area.top() - 2;
-                                        // -2 for top border and header
-                                        if relative_row < (app.items.len() as u16) {
-                                            app.table_state.select(Some(relative_row as usize));
-                                            // Set pending action
-                                            app.action_pending = Some(
-                                                Action::EnterDirectory(relative_row as usize)
-                                            );
-                                            redraw_ui = true;
-                                        }
-                                    }
-                                }
-                                _ => {}
-                            }
-                        _ => {}
-                    }
-                Err(e) => {
-                    // Handle errors, such as logging or displaying error messages
-                    eprintln!("Error reading event: {}", e);
-                }
-            }
-        }
+/// Headless builds print the report instead of browsing it
+#[cfg(not(feature = "tui"))]
+fn run_tui(
+    start_dir: PathBuf,
+    host_paths: Option<HostPathTranslator>,
+    snapshot: Option<ReportNode>,
+    options: &Options
+) -> Result<(), Box<dyn std::error::Error>> {
+    if options.diff.is_some() {
+        return Err(missing_feature("--diff", "tui").into());
     }
-
-    // Restore terminal
-    disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen, DisableMouseCapture)?;
-    terminal.show_cursor()?;
-
+    let report = match snapshot {
+        Some(root) => root,
+        None => report::build_report(&start_dir, options.depth, options.timings),
+    };
+    println!("{}", report_json(report, &start_dir, host_paths.as_ref()).to_json());
     Ok(())
 }
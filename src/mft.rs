@@ -0,0 +1,273 @@
+use std::{ collections::HashMap, path::{ Component, Path, Prefix } };
+
+/// Recursive file counts of every directory of an NTFS volume, read from its
+/// Master File Table at one point in time
+pub struct MftCounts {
+    pub volume: char, // Drive letter, uppercase
+    counts: HashMap<String, usize>, // By `key`
+}
+
+impl MftCounts {
+    /// Count of `path` as the table recorded it, if it is a directory of this volume
+    pub fn files(&self, path: &Path) -> Option<usize> {
+        (drive_letter(path)? == self.volume).then_some(())?;
+        self.counts.get(&key(path)).copied()
+    }
+}
+
+/// Drive letter of a `C:\...` or `\\?\C:\...` path
+pub fn drive_letter(path: &Path) -> Option<char> {
+    match path.components().next()? {
+        Component::Prefix(prefix) =>
+            match prefix.kind() {
+                Prefix::Disk(letter) | Prefix::VerbatimDisk(letter) => Some((letter as char).to_ascii_uppercase()),
+                _ => None,
+            }
+        _ => None,
+    }
+}
+
+/// NTFS compares names without case, and the TUI may hold either path form
+fn key(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    let path = path.strip_prefix(r"\\?\").unwrap_or(&path);
+    path.trim_end_matches('\\').to_lowercase()
+}
+
+/// Read the Master File Table of the volume holding `path`. Needs
+/// administrator rights and an NTFS volume; the error says which is missing.
+#[cfg(windows)]
+pub fn read_volume(path: &Path) -> Result<MftCounts, String> {
+    use std::{ ffi::{ c_void, OsStr }, io, os::windows::ffi::OsStrExt, ptr };
+
+    type Handle = *mut c_void;
+    const GENERIC_READ: u32 = 0x8000_0000;
+    const FILE_SHARE_READ_WRITE: u32 = 0x1 | 0x2;
+    const OPEN_EXISTING: u32 = 3;
+    const FSCTL_ENUM_USN_DATA: u32 = 0x0009_00b3;
+    const ERROR_ACCESS_DENIED: i32 = 5;
+    const ERROR_INVALID_FUNCTION: i32 = 1;
+    const ERROR_HANDLE_EOF: i32 = 38;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn CreateFileW(
+            name: *const u16,
+            access: u32,
+            share: u32,
+            security: *mut c_void,
+            disposition: u32,
+            flags: u32,
+            template: Handle
+        ) -> Handle;
+        fn DeviceIoControl(
+            device: Handle,
+            code: u32,
+            input: *const c_void,
+            input_size: u32,
+            output: *mut c_void,
+            output_size: u32,
+            returned: *mut u32,
+            overlapped: *mut c_void
+        ) -> i32;
+        fn CloseHandle(handle: Handle) -> i32;
+    }
+
+    /// MFT_ENUM_DATA_V0: enumerate every record from `start`, whatever its USN
+    #[repr(C)]
+    struct EnumData {
+        start: u64,
+        low_usn: i64,
+        high_usn: i64,
+    }
+
+    struct Volume(Handle);
+
+    impl Drop for Volume {
+        fn drop(&mut self) {
+            // SAFETY: the handle was opened by CreateFileW and is closed once
+            unsafe {
+                CloseHandle(self.0);
+            }
+        }
+    }
+
+    let letter = drive_letter(path).ok_or_else(|| format!("{} is not on a drive letter", path.display()))?;
+    let device: Vec<u16> = OsStr::new(&format!(r"\\.\{}:", letter)).encode_wide().chain(Some(0)).collect();
+    // SAFETY: `device` is a NUL-terminated UTF-16 string; the other pointers may be null
+    let handle = unsafe {
+        CreateFileW(device.as_ptr(), GENERIC_READ, FILE_SHARE_READ_WRITE, ptr::null_mut(), OPEN_EXISTING, 0, ptr::null_mut())
+    };
+    if handle as isize == -1 {
+        let e = io::Error::last_os_error();
+        return Err(match e.raw_os_error() {
+            Some(ERROR_ACCESS_DENIED) => format!("reading the MFT of {}: needs administrator rights", letter),
+            _ => format!("cannot open volume {}: {}", letter, e),
+        });
+    }
+    let volume = Volume(handle);
+
+    let mut table = Table::default();
+    let mut input = EnumData { start: 0, low_usn: 0, high_usn: i64::MAX };
+    let mut buffer = vec![0u8; 1 << 20];
+    loop {
+        let mut returned = 0u32;
+        // SAFETY: input and output point to live buffers of the given sizes
+        let ok = unsafe {
+            DeviceIoControl(
+                volume.0,
+                FSCTL_ENUM_USN_DATA,
+                &input as *const EnumData as *const c_void,
+                std::mem::size_of::<EnumData>() as u32,
+                buffer.as_mut_ptr() as *mut c_void,
+                buffer.len() as u32,
+                &mut returned,
+                ptr::null_mut()
+            )
+        };
+        if ok == 0 {
+            let e = io::Error::last_os_error();
+            match e.raw_os_error() {
+                Some(ERROR_HANDLE_EOF) => {
+                    break;
+                }
+                Some(ERROR_INVALID_FUNCTION) => {
+                    return Err(format!("{}: is not an NTFS volume", letter));
+                }
+                _ => {
+                    return Err(format!("reading the MFT of {}: {}", letter, e));
+                }
+            }
+        }
+        let returned = &buffer[..returned as usize];
+        match returned.get(..8) {
+            Some(next) => {
+                input.start = u64::from_le_bytes(next.try_into().unwrap_or_default());
+            }
+            None => {
+                break;
+            }
+        }
+        table.add_records(&returned[8..]);
+    }
+    Ok(table.counts(letter))
+}
+
+#[cfg(not(windows))]
+pub fn read_volume(_path: &Path) -> Result<MftCounts, String> {
+    Err(String::from("the MFT backend is only available on Windows"))
+}
+
+/// File reference of the root directory of every NTFS volume
+#[cfg(windows)]
+const ROOT: u64 = 5;
+
+/// File references carry a sequence number in their top 16 bits
+#[cfg(windows)]
+const REFERENCE_MASK: u64 = 0x0000_ffff_ffff_ffff;
+
+/// Directory tree and direct file counts gathered from USN_RECORD_V2 records
+#[cfg(windows)]
+#[derive(Default)]
+struct Table {
+    directories: HashMap<u64, (u64, String)>, // Parent and name by reference
+    files: HashMap<u64, usize>, // Files directly in a directory
+}
+
+#[cfg(windows)]
+impl Table {
+    fn add_records(&mut self, mut records: &[u8]) {
+        const FILE_ATTRIBUTE_DIRECTORY: u32 = 0x10;
+        let u16_at = |record: &[u8], offset: usize| u16::from_le_bytes([record[offset], record[offset + 1]]);
+        let u32_at = |record: &[u8], offset: usize| {
+            u32::from_le_bytes(record[offset..offset + 4].try_into().unwrap_or_default())
+        };
+        let u64_at = |record: &[u8], offset: usize| {
+            u64::from_le_bytes(record[offset..offset + 8].try_into().unwrap_or_default())
+        };
+        while records.len() >= 60 {
+            let length = u32_at(records, 0) as usize;
+            if length < 60 || length > records.len() {
+                return;
+            }
+            let record = &records[..length];
+            records = &records[length..];
+            if u16_at(record, 4) != 2 {
+                continue; // Only version 2 records are asked for
+            }
+            let reference = u64_at(record, 8) & REFERENCE_MASK;
+            let parent = u64_at(record, 16) & REFERENCE_MASK;
+            let attributes = u32_at(record, 52);
+            let (name_length, name_offset) = (u16_at(record, 56) as usize, u16_at(record, 58) as usize);
+            if attributes & FILE_ATTRIBUTE_DIRECTORY == 0 {
+                *self.files.entry(parent).or_default() += 1;
+                continue;
+            }
+            let Some(name) = record.get(name_offset..name_offset + name_length) else {
+                continue;
+            };
+            let name: Vec<u16> = name
+                .chunks_exact(2)
+                .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+                .collect();
+            self.directories.insert(reference, (parent, String::from_utf16_lossy(&name)));
+        }
+    }
+
+    /// Add every directory's files to all of its ancestors, and key the
+    /// totals by path
+    fn counts(self, letter: char) -> MftCounts {
+        let mut totals: HashMap<u64, usize> = self.directories
+            .keys()
+            .map(|&reference| (reference, 0))
+            .collect();
+        totals.insert(ROOT, 0);
+        for (&directory, &files) in &self.files {
+            let mut current = directory;
+            // Bounded, in case the table is inconsistent and names a cycle
+            for _ in 0..4096 {
+                let Some(total) = totals.get_mut(&current) else {
+                    break; // Orphaned, or below a directory that was not enumerated
+                };
+                *total += files;
+                match self.directories.get(&current) {
+                    Some(&(parent, _)) if current != ROOT => {
+                        current = parent;
+                    }
+                    _ => {
+                        break;
+                    }
+                }
+            }
+        }
+
+        let mut paths: HashMap<u64, String> = HashMap::from([(ROOT, format!("{}:", letter).to_lowercase())]);
+        let mut counts = HashMap::with_capacity(totals.len());
+        for (&reference, &files) in &totals {
+            if let Some(path) = self.path_of(reference, &mut paths) {
+                counts.insert(path, files);
+            }
+        }
+        MftCounts { volume: letter, counts }
+    }
+
+    /// Lowercase path of a directory, remembering those of its ancestors
+    fn path_of(&self, reference: u64, paths: &mut HashMap<u64, String>) -> Option<String> {
+        let mut chain = Vec::new();
+        let mut current = reference;
+        while !paths.contains_key(&current) {
+            let &(parent, ref name) = self.directories.get(&current)?;
+            chain.push((current, name.to_lowercase()));
+            if chain.len() > 4096 {
+                return None;
+            }
+            current = parent;
+        }
+        let mut path = paths[&current].clone();
+        for (reference, name) in chain.into_iter().rev() {
+            path = format!("{}\\{}", path, name);
+            paths.insert(reference, path.clone());
+        }
+        Some(path)
+    }
+}
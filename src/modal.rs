@@ -0,0 +1,173 @@
+use crate::theme::Theme;
+use crossterm::event::{ KeyCode, KeyEvent, KeyModifiers };
+use ratatui::text::{ Span, Spans };
+
+/// What a modal asks for
+enum Kind {
+    Input {
+        text: String,
+        hint: &'static str, // Below the input, until an error replaces it
+    },
+    Confirm,
+    Choice {
+        options: Vec<String>,
+        selected: usize,
+    },
+}
+
+/// What the user answered
+pub enum Answer {
+    Text(String),
+    Yes,
+    Choice(usize), // Index into the options
+}
+
+/// Result of a key press in a modal
+pub enum Outcome {
+    Open, // Still asking
+    Cancelled,
+    Answered(Answer),
+}
+
+/// Prompt drawn over everything else that takes every key until it is
+/// answered or cancelled. `purpose` tells the caller what the answer is for.
+pub struct Modal<P> {
+    pub purpose: P,
+    pub title: String,
+    question: Option<String>, // Above the input or the options
+    kind: Kind,
+    pub error: Option<String>, // Of the last answer, which left the modal open
+}
+
+impl<P> Modal<P> {
+    /// Line of text, starting from `text`
+    pub fn input(purpose: P, title: impl Into<String>, text: impl Into<String>, hint: &'static str) -> Modal<P> {
+        Modal {
+            purpose,
+            title: title.into(),
+            question: None,
+            kind: Kind::Input { text: text.into(), hint },
+            error: None,
+        }
+    }
+
+    /// Yes or no
+    pub fn confirm(purpose: P, title: impl Into<String>, question: impl Into<String>) -> Modal<P> {
+        Modal { purpose, title: title.into(), question: Some(question.into()), kind: Kind::Confirm, error: None }
+    }
+
+    /// One of `options`, starting on `selected`
+    pub fn choice(purpose: P, title: impl Into<String>, options: Vec<String>, selected: usize) -> Modal<P> {
+        Modal {
+            purpose,
+            title: title.into(),
+            question: None,
+            kind: Kind::Choice { selected: selected.min(options.len().saturating_sub(1)), options },
+            error: None,
+        }
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> Outcome {
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        if key.code == KeyCode::Esc {
+            return Outcome::Cancelled;
+        }
+        match &mut self.kind {
+            Kind::Input { text, .. } =>
+                match key.code {
+                    KeyCode::Enter => {
+                        return Outcome::Answered(Answer::Text(text.clone()));
+                    }
+                    KeyCode::Backspace => {
+                        text.pop();
+                        self.error = None;
+                    }
+                    // Clear the line, as shells do
+                    KeyCode::Char('u') if control => {
+                        text.clear();
+                        self.error = None;
+                    }
+                    KeyCode::Char(c) if !control => {
+                        text.push(c);
+                        self.error = None;
+                    }
+                    _ => {}
+                }
+            Kind::Confirm =>
+                match key.code {
+                    KeyCode::Char('y') | KeyCode::Char('Y') => {
+                        return Outcome::Answered(Answer::Yes);
+                    }
+                    KeyCode::Char('n') | KeyCode::Char('N') => {
+                        return Outcome::Cancelled;
+                    }
+                    _ => {}
+                }
+            Kind::Choice { options, selected } =>
+                match key.code {
+                    KeyCode::Enter if !options.is_empty() => {
+                        return Outcome::Answered(Answer::Choice(*selected));
+                    }
+                    KeyCode::Down | KeyCode::Char('j') => {
+                        *selected = (*selected + 1).min(options.len().saturating_sub(1));
+                    }
+                    KeyCode::Up | KeyCode::Char('k') => {
+                        *selected = selected.saturating_sub(1);
+                    }
+                    KeyCode::Char('q') => {
+                        return Outcome::Cancelled;
+                    }
+                    _ => {}
+                }
+        }
+        Outcome::Open
+    }
+
+    pub fn lines(&self, theme: &Theme) -> Vec<Spans<'static>> {
+        let mut lines = Vec::new();
+        if let Some(question) = &self.question {
+            lines.push(Spans::from(question.clone()));
+        }
+        let keys = |pairs: &[(&'static str, &'static str)]| {
+            let mut spans = Vec::new();
+            for (index, (key, action)) in pairs.iter().enumerate() {
+                let separator = if index + 1 < pairs.len() { "   " } else { "" };
+                spans.push(Span::styled(*key, theme.key));
+                spans.push(Span::raw(format!(" - {}{}", action, separator)));
+            }
+            Spans::from(spans)
+        };
+        match &self.kind {
+            Kind::Input { text, hint } => {
+                lines.push(Spans::from(format!("{}{}", text, theme.cursor())));
+                match &self.error {
+                    Some(e) => lines.push(Spans::from(Span::styled(e.clone(), theme.differs))),
+                    None if !hint.is_empty() => lines.push(Spans::from(Span::styled(*hint, theme.approximate))),
+                    None => {}
+                }
+                lines.push(keys(&[("Enter", "Apply"), ("Esc", "Cancel")]));
+            }
+            Kind::Confirm => {
+                if let Some(e) = &self.error {
+                    lines.push(Spans::from(Span::styled(e.clone(), theme.differs)));
+                }
+                lines.push(keys(&[("y", "Yes"), ("n/Esc", "No")]));
+            }
+            Kind::Choice { options, selected } => {
+                for (index, option) in options.iter().enumerate() {
+                    lines.push(
+                        match index == *selected {
+                            true => Spans::from(Span::styled(format!("> {}", option), theme.selection)),
+                            false => Spans::from(format!("  {}", option)),
+                        }
+                    );
+                }
+                if let Some(e) = &self.error {
+                    lines.push(Spans::from(Span::styled(e.clone(), theme.differs)));
+                }
+                lines.push(keys(&[("↑/↓", "Select"), ("Enter", "Choose"), ("Esc", "Cancel")]));
+            }
+        }
+        lines
+    }
+}
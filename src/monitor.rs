@@ -0,0 +1,197 @@
+use crate::{ cli::MonitorOptions, json::{ self, Value }, report::{ self, ReportNode } };
+use std::{ collections::HashSet, fs, path::{ Path, PathBuf }, process::Command, thread, time::Instant };
+#[cfg(feature = "watch")]
+use crate::watch::{ Debouncer, Watcher };
+#[cfg(feature = "watch")]
+use std::time::Duration;
+
+/// How often the change watcher is checked while waiting for the next rescan
+#[cfg(feature = "watch")]
+const WATCH_POLL: Duration = Duration::from_millis(250);
+
+/// Rescan `root` forever, on a schedule or when it changes, and alert once
+/// for every directory that grows above the threshold. A directory is alerted
+/// again only after it dropped below the threshold in between. Alerted
+/// directories are remembered in the state directory across restarts.
+pub fn run(root: &Path, options: &MonitorOptions, state_dir: Option<PathBuf>) -> Result<(), String> {
+    #[cfg(feature = "watch")]
+    let watcher = options.watch.then(|| {
+        let watcher = Watcher::spawn();
+        watcher.watch(root);
+        watcher
+    });
+    #[cfg(not(feature = "watch"))]
+    if options.watch {
+        return Err(crate::missing_feature("Watch mode", "watch"));
+    }
+
+    println!(
+        "Watching {} for directories above {} files (Ctrl-C to stop)",
+        root.display(),
+        options.threshold
+    );
+    let state_file = state_dir.map(|dir| dir.join("alerts.json"));
+    let mut alerted = state_file
+        .as_deref()
+        .map(|file| load_alerted(file, root, options.threshold))
+        .unwrap_or_default();
+    loop {
+        let snapshot = report::build_snapshot(root);
+        let mut over = Vec::new();
+        collect_over(&snapshot, root, options.threshold, &mut over);
+
+        let before = alerted.clone();
+        alerted.retain(|path| over.iter().any(|(p, _)| p == path));
+        for (path, files) in over {
+            if alerted.insert(path.clone()) {
+                alert(&path, files, options);
+            }
+        }
+        if let (Some(file), true) = (&state_file, alerted != before) {
+            save_alerted(file, root, options.threshold, &alerted);
+        }
+
+        let started = Instant::now();
+        #[cfg(feature = "watch")]
+        if let Some(watcher) = &watcher {
+            // Rescan once changes settle, or on schedule if nothing happens
+            let mut debouncer = Debouncer::default();
+            while started.elapsed() < options.interval {
+                let now = Instant::now();
+                for _ in watcher.changes() {
+                    debouncer.record(root.to_path_buf(), now);
+                }
+                if !debouncer.due(now).is_empty() {
+                    break;
+                }
+                thread::sleep(WATCH_POLL);
+            }
+            continue;
+        }
+        thread::sleep(options.interval.saturating_sub(started.elapsed()));
+    }
+}
+
+/// Directories already alerted by an earlier run on the same root and threshold
+fn load_alerted(file: &Path, root: &Path, threshold: usize) -> HashSet<PathBuf> {
+    let value = match fs::read_to_string(file).ok().and_then(|text| json::parse(&text).ok()) {
+        Some(value) => value,
+        None => {
+            return HashSet::new();
+        }
+    };
+    let same_watch =
+        value.get("root").and_then(Value::as_str) == Some(root.display().to_string().as_str()) &&
+        value.get("threshold").and_then(Value::as_u64) == Some(threshold as u64);
+    if !same_watch {
+        return HashSet::new();
+    }
+    value
+        .get("alerted")
+        .and_then(Value::as_array)
+        .unwrap_or(&[])
+        .iter()
+        .filter_map(Value::as_str)
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// Remember the alerted directories; failures only cost a repeated alert
+fn save_alerted(file: &Path, root: &Path, threshold: usize, alerted: &HashSet<PathBuf>) {
+    let mut paths: Vec<String> = alerted
+        .iter()
+        .map(|p| p.display().to_string())
+        .collect();
+    paths.sort();
+    let value = Value::Object(
+        vec![
+            (String::from("root"), Value::from(root.display().to_string())),
+            (String::from("threshold"), Value::from(threshold)),
+            (String::from("alerted"), Value::Array(paths.into_iter().map(Value::from).collect()))
+        ]
+    );
+    if let Some(dir) = file.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    let _ = fs::write(file, value.to_json());
+}
+
+/// Directories above the threshold none of whose subdirectories are, so only
+/// the culprits are reported and not every ancestor along with them
+fn collect_over(node: &ReportNode, path: &Path, threshold: usize, over: &mut Vec<(PathBuf, usize)>) {
+    let files = node.files.unwrap_or(0);
+    if files <= threshold {
+        return;
+    }
+    let before = over.len();
+    for child in node.children.iter().filter(|child| child.is_dir) {
+        collect_over(child, &path.join(&child.name), threshold, over);
+    }
+    if over.len() == before {
+        over.push((path.to_path_buf(), files));
+    }
+}
+
+/// Report a directory above the threshold on stdout, then run the alert
+/// command or show a desktop notification
+fn alert(path: &Path, files: usize, options: &MonitorOptions) {
+    let message = format!("{} has {} files (threshold {})", path.display(), files, options.threshold);
+    println!("{}", message);
+
+    let result = match &options.command {
+        Some(command) =>
+            shell(command)
+                .env("FILE_COUNTER_PATH", path)
+                .env("FILE_COUNTER_COUNT", files.to_string())
+                .env("FILE_COUNTER_THRESHOLD", options.threshold.to_string())
+                .status(),
+        None => {
+            match notification(&message) {
+                Some(mut command) => command.status(),
+                None => {
+                    return;
+                }
+            }
+        }
+    };
+    match result {
+        Ok(status) if !status.success() => eprintln!("Alert command failed: {}", status),
+        Err(e) => eprintln!("Cannot run the alert command: {}", e),
+        Ok(_) => {}
+    }
+}
+
+#[cfg(unix)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+/// Desktop notification command for this platform, if there is a stock one
+#[cfg(target_os = "macos")]
+fn notification(message: &str) -> Option<Command> {
+    let escaped = message.replace('\\', "\\\\").replace('"', "\\\"");
+    let mut command = Command::new("osascript");
+    command.arg("-e").arg(format!("display notification \"{}\" with title \"file_counter\"", escaped));
+    Some(command)
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn notification(message: &str) -> Option<Command> {
+    let mut command = Command::new("notify-send");
+    command.arg("file_counter").arg(message);
+    Some(command)
+}
+
+#[cfg(windows)]
+fn notification(_message: &str) -> Option<Command> {
+    None // The message on stdout is the notification
+}
@@ -0,0 +1,218 @@
+use std::{ fs, io, path::{ Path, PathBuf } };
+
+/// A single line of `/proc/self/mountinfo`
+#[derive(Clone, Debug)]
+pub struct MountEntry {
+    pub root: PathBuf,
+    pub mount_point: PathBuf,
+    pub fs_type: String,
+}
+
+/// Filesystem types that never correspond to a directory on the host
+const PSEUDO_FS_TYPES: &[&str] = &[
+    "overlay",
+    "proc",
+    "sysfs",
+    "tmpfs",
+    "devpts",
+    "mqueue",
+    "cgroup",
+    "cgroup2",
+    "devtmpfs",
+    "shm",
+];
+
+/// Filesystem types served over the network
+const NETWORK_FS_TYPES: &[&str] = &[
+    "nfs",
+    "nfs4",
+    "cifs",
+    "smb3",
+    "smbfs",
+    "afs",
+    "ceph",
+    "glusterfs",
+    "lustre",
+    "9p",
+    "davfs",
+];
+
+/// Broad kind of a filesystem, to badge mount points and leave some out of scans
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FsClass {
+    Local,
+    Network, // NFS, SMB and other network filesystems
+    Fuse, // Userspace filesystems (sshfs, rclone, ...), which may stall on any call
+    Virtual, // Kernel pseudo-filesystems and tmpfs
+}
+
+impl FsClass {
+    pub const ALL: [FsClass; 4] = [FsClass::Local, FsClass::Network, FsClass::Fuse, FsClass::Virtual];
+
+    pub fn of(fs_type: &str) -> FsClass {
+        if fs_type.starts_with("fuse") {
+            FsClass::Fuse
+        } else if NETWORK_FS_TYPES.contains(&fs_type) {
+            FsClass::Network
+        } else if PSEUDO_FS_TYPES.contains(&fs_type) {
+            FsClass::Virtual
+        } else {
+            FsClass::Local
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            FsClass::Local => "local",
+            FsClass::Network => "network",
+            FsClass::Fuse => "fuse",
+            FsClass::Virtual => "virtual",
+        }
+    }
+}
+
+/// Set of filesystem classes, e.g. those a scan does not descend into
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FsClasses(u8);
+
+impl FsClasses {
+    pub const NONE: FsClasses = FsClasses(0);
+
+    /// Parse a comma-separated list of class names, like `network,fuse`
+    pub fn parse(list: &str) -> Result<FsClasses, String> {
+        let mut classes = FsClasses::NONE;
+        for name in list.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+            let class = FsClass::ALL
+                .into_iter()
+                .find(|class| class.name() == name)
+                .ok_or_else(|| format!("unknown filesystem class '{}' (expected local, network, fuse or virtual)", name))?;
+            classes.0 |= 1 << (class as u8);
+        }
+        Ok(classes)
+    }
+
+    pub fn contains(self, class: FsClass) -> bool {
+        self.0 & (1 << (class as u8)) != 0
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Names of the classes in the set, like `network, fuse`
+    #[cfg(feature = "tui")]
+    pub fn describe(self) -> String {
+        let names: Vec<&str> = FsClass::ALL
+            .into_iter()
+            .filter(|&class| self.contains(class))
+            .map(FsClass::name)
+            .collect();
+        names.join(", ")
+    }
+}
+
+/// Filesystem type of the mount whose mount point is exactly `path`, which
+/// must be canonical. Reads the mount table, so only call it for directories
+/// known to be on another device than their parent.
+pub fn mounted_fs_type(path: &Path) -> Option<String> {
+    let mounts = read_mountinfo().ok()?;
+    // The last of mounts stacked on the same point is the visible one
+    mounts
+        .into_iter()
+        .rev()
+        .find(|m| m.mount_point == path)
+        .map(|m| m.fs_type)
+}
+
+/// Read and parse the mount table of the current process
+pub fn read_mountinfo() -> io::Result<Vec<MountEntry>> {
+    let text = fs::read_to_string("/proc/self/mountinfo")?;
+    Ok(parse_mountinfo(&text))
+}
+
+/// Parse the contents of a mountinfo file, skipping malformed lines
+pub fn parse_mountinfo(text: &str) -> Vec<MountEntry> {
+    text.lines().filter_map(parse_line).collect()
+}
+
+fn parse_line(line: &str) -> Option<MountEntry> {
+    // Format: id parent major:minor root mount_point options [optional...] - fs_type source super_options
+    let (left, right) = line.split_once(" - ")?;
+    let fields: Vec<&str> = left.split(' ').collect();
+    if fields.len() < 6 {
+        return None;
+    }
+    let fs_type = right.split(' ').next()?.to_string();
+
+    Some(MountEntry {
+        root: PathBuf::from(unescape(fields[3])),
+        mount_point: PathBuf::from(unescape(fields[4])),
+        fs_type,
+    })
+}
+
+/// Decode the octal escapes (`\040` for space, etc.) used in mountinfo
+fn unescape(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = match bytes.get(i + 1..i + 4) {
+            Some(digits) if bytes[i] == b'\\' && digits.iter().all(|b| (b'0'..=b'7').contains(b)) => {
+                // Out-of-range escapes like `\777` are kept literally
+                let value = digits.iter().fold(0u32, |value, digit| value * 8 + u32::from(digit - b'0'));
+                u8::try_from(value).ok()
+            }
+            _ => None,
+        };
+        if let Some(value) = escaped {
+            out.push(value);
+            i += 4;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Find the mount entry that contains `path` (longest matching mount point)
+pub fn mount_for<'a>(mounts: &'a [MountEntry], path: &Path) -> Option<&'a MountEntry> {
+    mounts
+        .iter()
+        .filter(|m| path.starts_with(&m.mount_point))
+        .max_by_key(|m| m.mount_point.components().count())
+}
+
+/// Translates container paths to the host-side paths they are bind-mounted from
+pub struct HostPathTranslator {
+    mounts: Vec<MountEntry>,
+}
+
+impl HostPathTranslator {
+    /// Load the mount table; returns None when it cannot be read (e.g. not on Linux)
+    pub fn detect() -> Option<Self> {
+        read_mountinfo()
+            .ok()
+            .map(|mounts| HostPathTranslator { mounts })
+    }
+
+    /// Translate a container path to its host path.
+    ///
+    /// Only bind mounts of a host subdirectory can be translated: the mount root
+    /// is relative to the source filesystem, which matches the host path when that
+    /// filesystem is the host's `/`. Returns None for pseudo/overlay filesystems.
+    pub fn translate(&self, path: &Path) -> Option<PathBuf> {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        let mount = mount_for(&self.mounts, &path)?;
+        if PSEUDO_FS_TYPES.contains(&mount.fs_type.as_str()) || mount.root == Path::new("/") {
+            return None;
+        }
+        let relative = path.strip_prefix(&mount.mount_point).ok()?;
+        if relative.as_os_str().is_empty() {
+            Some(mount.root.clone())
+        } else {
+            Some(mount.root.join(relative))
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use crate::{ json::{ self, Value }, report::ReportNode };
+use std::{ fs, path::Path, time::{ SystemTime, UNIX_EPOCH } };
+
+/// Major and minor version of the ncdu JSON export format
+const FORMAT_VERSION: [usize; 2] = [1, 2];
+
+/// Walk `root` completely and build an ncdu-compatible export:
+/// `[1, 2, {metadata}, [{root info}, {file}, [{dir info}, ...], ...]]`.
+/// Like ncdu, symlinks are listed but not followed. The host-side path of the
+/// root, when known, is an extra `host_path` field that ncdu ignores.
+pub fn export(root: &Path, host_path: Option<&Path>) -> Value {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let metadata = Value::Object(
+        vec![
+            (String::from("progname"), Value::from(env!("CARGO_PKG_NAME"))),
+            (String::from("progver"), Value::from(env!("CARGO_PKG_VERSION"))),
+            (String::from("timestamp"), Value::from(timestamp))
+        ]
+    );
+    Value::Array(
+        vec![
+            Value::from(FORMAT_VERSION[0]),
+            Value::from(FORMAT_VERSION[1]),
+            metadata,
+            export_root(root, host_path)
+        ]
+    )
+}
+
+fn export_root(root: &Path, host_path: Option<&Path>) -> Value {
+    let mut tree = export_dir(root, root.display().to_string());
+    if let (Value::Array(items), Some(host_path)) = (&mut tree, host_path) {
+        if let Some(Value::Object(info)) = items.first_mut() {
+            info.push((String::from("host_path"), Value::from(host_path.display().to_string())));
+        }
+    }
+    tree
+}
+
+/// Info object of one entry: name, apparent and disk size
+fn entry_info(name: String, metadata: Option<&fs::Metadata>) -> Vec<(String, Value)> {
+    let mut info = vec![(String::from("name"), Value::from(name))];
+    if let Some(metadata) = metadata {
+        info.push((String::from("asize"), Value::from(metadata.len())));
+        info.push((String::from("dsize"), Value::from(disk_size(metadata))));
+    }
+    info
+}
+
+#[cfg(unix)]
+fn disk_size(metadata: &fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.blocks().saturating_mul(512)
+}
+
+#[cfg(not(unix))]
+fn disk_size(metadata: &fs::Metadata) -> u64 {
+    metadata.len()
+}
+
+fn export_dir(path: &Path, name: String) -> Value {
+    let metadata = fs::symlink_metadata(path).ok();
+    let mut info = entry_info(name, metadata.as_ref());
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(_) => {
+            info.push((String::from("read_error"), Value::from(true)));
+            return Value::Array(vec![Value::Object(info)]);
+        }
+    };
+
+    let mut items = vec![Value::Object(info)];
+    for entry in entries.flatten() {
+        let child_path = entry.path();
+        let child_name = entry.file_name().to_string_lossy().into_owned();
+        let child_metadata = entry.metadata().ok();
+        match &child_metadata {
+            Some(metadata) if metadata.is_dir() => {
+                items.push(export_dir(&child_path, child_name));
+            }
+            _ => {
+                let mut info = entry_info(child_name, child_metadata.as_ref());
+                if !child_metadata.as_ref().is_some_and(|m| m.is_file()) {
+                    info.push((String::from("notreg"), Value::from(true)));
+                }
+                items.push(Value::Object(info));
+            }
+        }
+    }
+    Value::Array(items)
+}
+
+/// Load an ncdu JSON export as a browsable report. Every directory counts
+/// the non-directory entries below it; excluded entries are left out.
+pub fn import(text: &str) -> Result<ReportNode, String> {
+    let value = json::parse(text)?;
+    let items = value.as_array().ok_or("not an ncdu export: expected a JSON array")?;
+    let major = items.first().and_then(Value::as_u64);
+    if major != Some(FORMAT_VERSION[0] as u64) {
+        return Err(String::from("not an ncdu export: unsupported format version"));
+    }
+    let root = items.get(3).ok_or("ncdu export without a directory tree")?;
+    import_dir(root)
+}
+
+fn import_dir(value: &Value) -> Result<ReportNode, String> {
+    let items = value.as_array().ok_or("ncdu directory is not an array")?;
+    let (info, entries) = items.split_first().ok_or("ncdu directory without an info object")?;
+    let name = info
+        .get("name")
+        .and_then(Value::as_str)
+        .ok_or("ncdu entry without a name")?
+        .to_string();
+
+    let mut files = 0usize;
+    let mut children = Vec::new();
+    for entry in entries {
+        if entry.as_array().is_some() {
+            let child = import_dir(entry)?;
+            files = files.saturating_add(child.files.unwrap_or(0));
+            children.push(child);
+        } else if entry.get("excluded").is_none() {
+            let name = entry
+                .get("name")
+                .and_then(Value::as_str)
+                .ok_or("ncdu entry without a name")?
+                .to_string();
+            files = files.saturating_add(1);
+            children.push(ReportNode { name, is_dir: false, files: None, children: Vec::new(), count_time: None });
+        }
+    }
+    Ok(ReportNode { name, is_dir: true, files: Some(files), children, count_time: None })
+}
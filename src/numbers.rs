@@ -0,0 +1,120 @@
+use std::env;
+
+/// Suffixes of compact counts, each a thousand times the previous
+const SUFFIXES: &[&str] = &["K", "M", "B", "T"];
+
+/// Thousands separator and decimal mark of locales that differ from `1,234.5`,
+/// by language or `language_TERRITORY`
+const LOCALE_MARKS: &[(&str, char, char)] = &[
+    ("de_CH", '\'', '.'),
+    ("de", '.', ','),
+    ("es", '.', ','),
+    ("it", '.', ','),
+    ("nl", '.', ','),
+    ("pt", '.', ','),
+    ("da", '.', ','),
+    ("id", '.', ','),
+    ("tr", '.', ','),
+    ("el", '.', ','),
+    ("fr", '\u{a0}', ','),
+    ("ru", '\u{a0}', ','),
+    ("uk", '\u{a0}', ','),
+    ("pl", '\u{a0}', ','),
+    ("cs", '\u{a0}', ','),
+    ("sk", '\u{a0}', ','),
+    ("sv", '\u{a0}', ','),
+    ("fi", '\u{a0}', ','),
+    ("nb", '\u{a0}', ','),
+    ("hu", '\u{a0}', ','),
+];
+
+/// How file counts are written for people; JSON output keeps plain numbers
+#[derive(Clone, Copy, Debug)]
+pub struct NumberFormat {
+    pub compact: bool, // `1.2M` instead of `1,234,567`
+    separator: char, // Between groups of thousands
+    decimal: char, // Of compact counts
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        NumberFormat { compact: false, separator: ',', decimal: '.' }
+    }
+}
+
+impl NumberFormat {
+    /// Marks of the numeric locale, from `LC_ALL`, `LC_NUMERIC` or `LANG`
+    pub fn from_env(compact: bool) -> NumberFormat {
+        let locale = ["LC_ALL", "LC_NUMERIC", "LANG"]
+            .iter()
+            .filter_map(|name| env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .unwrap_or_default();
+        NumberFormat { compact, ..NumberFormat::for_locale(&locale) }
+    }
+
+    /// Marks of a locale name like `de_DE.UTF-8`; `C`, English and unknown
+    /// locales write `1,234.5`
+    pub fn for_locale(locale: &str) -> NumberFormat {
+        let name = locale.split(['.', '@']).next().unwrap_or_default();
+        let language = name.split('_').next().unwrap_or_default();
+        LOCALE_MARKS.iter()
+            .find(|(prefix, _, _)| *prefix == name || *prefix == language)
+            .map(|&(_, separator, decimal)| NumberFormat { compact: false, separator, decimal })
+            .unwrap_or_default()
+    }
+
+    /// A count, e.g. `1,234,567` or `1.2M`
+    pub fn count(&self, count: u64) -> String {
+        if self.compact { self.compact_count(count) } else { self.grouped(count) }
+    }
+
+    /// A signed count, e.g. `+1,204`, `-35` or `+1.2K`
+    pub fn signed(&self, count: i64) -> String {
+        let text = self.count(count.unsigned_abs());
+        match count {
+            0 => text,
+            _ if count > 0 => format!("+{}", text),
+            _ => format!("-{}", text),
+        }
+    }
+
+    /// Width of `count` once formatted, without formatting the grouped form
+    #[cfg(feature = "tui")]
+    pub fn width(&self, count: u64) -> usize {
+        if self.compact {
+            self.compact_count(count).chars().count()
+        } else {
+            let digits = count.checked_ilog10().unwrap_or(0) as usize + 1;
+            digits + (digits - 1) / 3
+        }
+    }
+
+    fn grouped(&self, count: u64) -> String {
+        let digits = count.to_string();
+        let mut grouped = String::new();
+        for (index, digit) in digits.chars().enumerate() {
+            if index > 0 && (digits.len() - index).is_multiple_of(3) {
+                grouped.push(self.separator);
+            }
+            grouped.push(digit);
+        }
+        grouped
+    }
+
+    /// Three significant digits at most, e.g. `999`, `1.2K`, `12.3K`, `123K`
+    fn compact_count(&self, count: u64) -> String {
+        let mut value = count as f64;
+        let mut unit = 0;
+        // Scale up early enough that rounding never shows `1000K`
+        while value >= 999.5 && unit < SUFFIXES.len() {
+            value /= 1000.0;
+            unit += 1;
+        }
+        if unit == 0 {
+            return count.to_string();
+        }
+        let number = if value < 99.95 { format!("{:.1}", value) } else { format!("{:.0}", value) };
+        format!("{}{}", number.replace('.', &self.decimal.to_string()), SUFFIXES[unit - 1])
+    }
+}
@@ -0,0 +1,163 @@
+use crate::{ json::{ self, Value }, report::{ self, ReportNode }, scan::ByteSizes, vfs::{ EntryMetadata, FileSystem } };
+use std::{ collections::{ HashMap, HashSet }, io, path::{ Path, PathBuf }, process::Command };
+
+/// Object stores whose prefixes can be counted, and the CLI each is listed with
+const SCHEMES: &[(&str, &str)] = &[("s3://", "aws"), ("gs://", "gcloud")];
+
+/// Objects below a prefix, with `/` in their keys read as directories
+pub struct ObjectStoreFs {
+    dirs: HashMap<PathBuf, Vec<PathBuf>>, // Children of each folder, rooted at `scheme://bucket`
+    objects: HashMap<PathBuf, u64>, // Size of each object
+}
+
+impl ObjectStoreFs {
+    /// Build the tree from `(key, size)` pairs of `bucket`; keys ending in `/`
+    /// are folder markers and are not counted
+    fn new(bucket_url: &str, keys: Vec<(String, u64)>) -> ObjectStoreFs {
+        let root = PathBuf::from(bucket_url);
+        let mut filesystem = ObjectStoreFs { dirs: HashMap::new(), objects: HashMap::new() };
+        filesystem.dirs.insert(root.clone(), Vec::new());
+        let mut known = HashSet::new();
+        for (key, size) in keys {
+            let components: Vec<&str> = key.split('/').filter(|c| !c.is_empty() && *c != ".").collect();
+            if components.contains(&"..") {
+                continue; // Would escape the bucket as a path
+            }
+            let Some((last, parents)) = components.split_last() else {
+                continue;
+            };
+            let mut dir = root.clone();
+            for component in parents {
+                let child = dir.join(component);
+                if known.insert(child.clone()) {
+                    filesystem.dirs.entry(dir).or_default().push(child.clone());
+                    filesystem.dirs.entry(child.clone()).or_default();
+                }
+                dir = child;
+            }
+            let path = dir.join(last);
+            if known.insert(path.clone()) {
+                filesystem.dirs.entry(dir).or_default().push(path.clone());
+            }
+            if key.ends_with('/') {
+                filesystem.dirs.entry(path).or_default();
+            } else {
+                filesystem.objects.insert(path, size);
+            }
+        }
+        filesystem
+    }
+}
+
+impl FileSystem for ObjectStoreFs {
+    fn read_dir(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        self.dirs.get(dir).cloned().ok_or_else(|| io::ErrorKind::NotFound.into())
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<EntryMetadata> {
+        // A key can be both an object and a prefix of others; the folder wins
+        if self.dirs.contains_key(path) {
+            return Ok(EntryMetadata { is_dir: true, links: 1, ..Default::default() });
+        }
+        let size = *self.objects.get(path).ok_or(io::ErrorKind::NotFound)?;
+        Ok(EntryMetadata {
+            is_file: true,
+            sizes: ByteSizes { apparent: size, disk: size },
+            links: 1,
+            ..Default::default()
+        })
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        if self.dirs.contains_key(path) || self.objects.contains_key(path) {
+            Ok(path.to_path_buf())
+        } else {
+            Err(io::ErrorKind::NotFound.into())
+        }
+    }
+}
+
+/// List every object below `url` (`s3://bucket/prefix` or `gs://bucket/prefix`)
+/// and count them per folder, for browsing like a snapshot
+pub fn scan_object_store(url: &Path) -> Result<ReportNode, String> {
+    let url = url.to_string_lossy().trim_end_matches('/').to_string();
+    let (scheme, tool) = SCHEMES
+        .iter()
+        .find(|(scheme, _)| url.starts_with(scheme))
+        .ok_or_else(|| format!("Not an object store URL: {}", url))?;
+    let (bucket, prefix) = match url[scheme.len()..].split_once('/') {
+        Some((bucket, prefix)) => (bucket, format!("{}/", prefix)),
+        None => (&url[scheme.len()..], String::new()),
+    };
+    if bucket.is_empty() {
+        return Err(format!("No bucket in {}", url));
+    }
+    let keys = match *tool {
+        "aws" => list_s3(bucket, &prefix)?,
+        _ => list_gcs(bucket, &prefix)?,
+    };
+    let bucket_url = format!("{}{}", scheme, bucket);
+    let filesystem = ObjectStoreFs::new(&bucket_url, keys);
+    let root = Path::new(&bucket_url).join(prefix.trim_end_matches('/'));
+    if filesystem.canonicalize(&root).is_err() {
+        return Err(format!("No objects below {}", url));
+    }
+    Ok(report::build_snapshot_in(&filesystem, &root))
+}
+
+/// Run an object store CLI and return its output
+fn run_tool(tool: &str, args: &[&str]) -> Result<String, String> {
+    let output = Command::new(tool)
+        .args(args)
+        .output()
+        .map_err(|e| format!("Cannot run {}: {}", tool, e))?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
+
+/// Keys and sizes from `aws s3api list-objects-v2`, which follows the pagination
+/// itself; credentials, region and endpoint come from the usual AWS settings
+fn list_s3(bucket: &str, prefix: &str) -> Result<Vec<(String, u64)>, String> {
+    let mut args = vec!["s3api", "list-objects-v2", "--bucket", bucket, "--output", "json"];
+    args.extend(["--query", "Contents[].[Key, Size]"]);
+    if !prefix.is_empty() {
+        args.extend(["--prefix", prefix]);
+    }
+    let document = json::parse(&run_tool("aws", &args)?)?;
+    // `null` when nothing matches the prefix
+    let objects = document.as_array().unwrap_or_default();
+    Ok(
+        objects
+            .iter()
+            .filter_map(|object| {
+                let fields = object.as_array()?;
+                let key = fields.first().and_then(Value::as_str)?;
+                let size = fields.get(1).and_then(Value::as_u64).unwrap_or(0);
+                Some((key.to_string(), size))
+            })
+            .collect()
+    )
+}
+
+/// Keys and sizes from `gcloud storage ls --long`, whose lines are
+/// `SIZE  CREATED  gs://bucket/key` followed by a total
+fn list_gcs(bucket: &str, prefix: &str) -> Result<Vec<(String, u64)>, String> {
+    let pattern = format!("gs://{}/{}**", bucket, prefix);
+    let output = run_tool("gcloud", &["storage", "ls", "--long", &pattern])?;
+    let bucket_url = format!("gs://{}/", bucket);
+    Ok(
+        output
+            .lines()
+            .filter_map(|line| {
+                let (size, rest) = line.trim_start().split_once(char::is_whitespace)?;
+                let (_created, url) = rest.trim_start().split_once(char::is_whitespace)?;
+                let size = size.parse().ok()?;
+                let url = url.trim_start();
+                Some((url.strip_prefix(&bucket_url)?.to_string(), size))
+            })
+            .collect()
+    )
+}
@@ -0,0 +1,104 @@
+#[cfg(feature = "tui")]
+use crate::scan::FileTotals;
+use std::{ collections::HashMap, fs };
+
+/// Owner, group and permission bits of an entry
+#[cfg(feature = "tui")]
+#[derive(Clone, Copy, Debug)]
+pub struct Ownership {
+    pub uid: u32,
+    pub gid: u32,
+    pub mode: u32,
+}
+
+#[cfg(feature = "tui")]
+impl Ownership {
+    #[cfg(unix)]
+    pub fn of(metadata: &fs::Metadata) -> Option<Ownership> {
+        use std::os::unix::fs::MetadataExt;
+        Some(Ownership { uid: metadata.uid(), gid: metadata.gid(), mode: metadata.mode() })
+    }
+
+    #[cfg(not(unix))]
+    pub fn of(_metadata: &fs::Metadata) -> Option<Ownership> {
+        None
+    }
+
+    /// Permissions like `ls -l`, e.g. `drwxr-xr-x` or `-rwsr-xr-x`
+    pub fn mode_text(&self) -> String {
+        let kind = match self.mode & 0o170000 {
+            0o040000 => 'd',
+            0o120000 => 'l',
+            0o020000 => 'c',
+            0o060000 => 'b',
+            0o010000 => 'p',
+            0o140000 => 's',
+            _ => '-',
+        };
+        let mut text = String::from(kind);
+        // Read, write, execute for user, group and others, with setuid, setgid and sticky
+        for (shift, special, set, unset) in [(6, 0o4000, 's', 'S'), (3, 0o2000, 's', 'S'), (0, 0o1000, 't', 'T')] {
+            let bits = (self.mode >> shift) & 0o7;
+            text.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+            text.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+            text.push(match (bits & 0o1 != 0, self.mode & special != 0) {
+                (true, true) => set,
+                (false, true) => unset,
+                (true, false) => 'x',
+                (false, false) => '-',
+            });
+        }
+        text
+    }
+}
+
+/// User and group names by id, read once from `/etc/passwd` and `/etc/group`.
+/// Ids without an entry there (LDAP, containers) are shown as numbers.
+#[derive(Default)]
+pub struct Names {
+    users: HashMap<u32, String>,
+    #[cfg(feature = "tui")]
+    groups: HashMap<u32, String>,
+}
+
+impl Names {
+    pub fn load() -> Names {
+        Names {
+            users: read_id_file("/etc/passwd"),
+            #[cfg(feature = "tui")]
+            groups: read_id_file("/etc/group"),
+        }
+    }
+
+    pub fn user(&self, uid: u32) -> String {
+        self.users.get(&uid).cloned().unwrap_or_else(|| uid.to_string())
+    }
+
+    #[cfg(feature = "tui")]
+    pub fn group(&self, gid: u32) -> String {
+        self.groups.get(&gid).cloned().unwrap_or_else(|| gid.to_string())
+    }
+}
+
+/// Names by id of a `name:password:id:...` file; missing files give no names
+fn read_id_file(path: &str) -> HashMap<u32, String> {
+    let text = fs::read_to_string(path).unwrap_or_default();
+    text.lines()
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            let id = fields.nth(1)?.parse().ok()?;
+            Some((id, name.to_string()))
+        })
+        .collect()
+}
+
+/// The user owning most of the files of a histogram, with their share
+#[cfg(feature = "tui")]
+pub fn main_owner(owners: &HashMap<u32, FileTotals>) -> Option<(u32, f64)> {
+    let total: usize = owners.values().map(|totals| totals.files).sum();
+    let (&uid, &FileTotals { files, .. }) = owners
+        .iter()
+        .max_by(|a, b| a.1.files.cmp(&b.1.files).then(b.0.cmp(a.0)))?;
+    (total > 0).then(|| (uid, (files as f64) / (total as f64)))
+}
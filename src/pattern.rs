@@ -0,0 +1,93 @@
+use globset::{ GlobBuilder, GlobMatcher };
+use regex::{ Regex, RegexBuilder };
+
+/// Compiled size a filter may take; `(a?){200000}` and the like are refused
+/// instead of building a huge automaton
+const SIZE_LIMIT: usize = 1 << 20;
+
+/// A file name pattern typed in the filter bar: a glob (`*.log`, `IMG_????.jpg`,
+/// `[!abc]*`), a regular expression after `re:` (`re:^core\.\d+$`, in the syntax
+/// of the `regex` crate, with `(?i)` to ignore case), or plain text matching
+/// names that contain it, ignoring case.
+///
+/// Globs match the whole name, regular expressions anywhere in it unless
+/// anchored. Both run in time linear in the name, whatever the pattern, since
+/// they are matched on every scan worker and on the UI thread.
+#[derive(Clone)]
+pub struct NamePattern {
+    text: String, // As typed
+    matcher: Matcher,
+}
+
+#[derive(Clone)]
+enum Matcher {
+    Glob(GlobMatcher),
+    Regex(Regex),
+}
+
+impl NamePattern {
+    pub fn parse(text: &str) -> Result<NamePattern, String> {
+        let matcher = match text.strip_prefix("re:") {
+            Some(source) => Matcher::Regex(compile(source, false)?),
+            None if text.contains(['*', '?', '[']) => {
+                let glob = GlobBuilder::new(text)
+                    .literal_separator(true)
+                    .backslash_escape(true)
+                    .build()
+                    .map_err(|e| e.kind().to_string())?;
+                Matcher::Glob(glob.compile_matcher())
+            }
+            None => Matcher::Regex(compile(&regex::escape(text), true)?),
+        };
+        Ok(NamePattern { text: text.to_string(), matcher })
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        match &self.matcher {
+            Matcher::Glob(glob) => glob.is_match(name),
+            Matcher::Regex(regex) => regex.is_match(name),
+        }
+    }
+}
+
+fn compile(source: &str, ignore_case: bool) -> Result<Regex, String> {
+    RegexBuilder::new(source)
+        .case_insensitive(ignore_case)
+        .size_limit(SIZE_LIMIT)
+        .dfa_size_limit(SIZE_LIMIT)
+        .build()
+        .map_err(|e| match e {
+            regex::Error::CompiledTooBig(_) => String::from("pattern too large"),
+            e => e.to_string(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Globs, regular expressions and plain text, matched against file names
+    #[test]
+    fn matches_names() {
+        let names = ["app.log", "app.log.1", "core.1234", "Error.LOG", "readme"];
+        let matching = |text: &str| {
+            let pattern = NamePattern::parse(text).unwrap();
+            names.iter().filter(|name| pattern.matches(name)).count()
+        };
+        assert_eq!(matching("*.log"), 1);
+        assert_eq!(matching("*.log*"), 2);
+        assert_eq!(matching("re:(?i)\\.log$"), 2);
+        assert_eq!(matching("re:^core\\.\\d{2,}$|readme"), 2);
+        assert_eq!(matching("[!a]*"), 3);
+        assert_eq!(matching("error"), 1);
+        assert!(NamePattern::parse("re:(a|b").is_err());
+        // No backtracking: these finish at once, and huge repetitions are refused
+        assert!(!NamePattern::parse("re:(a|a)*b").unwrap().matches(&"a".repeat(24)));
+        assert!(!NamePattern::parse("*a*a*a*a*a*a*a*b").unwrap().matches(&"a".repeat(44)));
+        assert!(NamePattern::parse("re:(a?){200000}b").is_err());
+    }
+}
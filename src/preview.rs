@@ -0,0 +1,123 @@
+use crate::{ events::AppEvent, file_types::file_category };
+use std::{ fs, io::Read, path::{ Path, PathBuf }, sync::mpsc::Sender, thread, time::SystemTime };
+
+/// Lines of a text file the preview pane shows
+const PREVIEW_LINES: usize = 100;
+/// Bytes read from a file to preview it, enough for the lines of most text
+const PREVIEW_BYTES: u64 = 16 * 1024;
+
+/// What the preview pane shows for the selected entry (`p`)
+pub struct Preview {
+    pub path: PathBuf,
+    pub content: PreviewContent,
+}
+
+pub enum PreviewContent {
+    Text(Vec<String>), // The first lines, tabs expanded
+    Binary(FileInfo),
+    Dir(DirChildren),
+    Error(String),
+}
+
+/// Details shown for a file that is not text
+pub struct FileInfo {
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+    pub category: &'static str, // From `file_types::CATEGORIES`
+    pub mode: Option<u32>, // Permission bits, on Unix
+}
+
+/// Entries directly in a directory
+pub struct DirChildren {
+    pub files: usize,
+    pub subdirs: Vec<PathBuf>,
+    pub others: usize, // Symlinks, sockets, devices, ...
+}
+
+/// Read the preview of `path` on a background thread
+pub fn spawn_preview(path: PathBuf, is_dir: bool, sender: Sender<AppEvent>) {
+    thread::spawn(move || {
+        let content = match is_dir {
+            true => read_children(&path),
+            false => read_file(&path),
+        };
+        sender.send(AppEvent::Preview(Preview { path, content })).unwrap_or(());
+    });
+}
+
+fn read_children(dir: &Path) -> PreviewContent {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            return PreviewContent::Error(e.to_string());
+        }
+    };
+    let mut children = DirChildren { files: 0, subdirs: Vec::new(), others: 0 };
+    for entry in entries.flatten() {
+        match entry.file_type() {
+            Ok(kind) if kind.is_dir() => children.subdirs.push(entry.path()),
+            Ok(kind) if kind.is_file() => {
+                children.files += 1;
+            }
+            _ => {
+                children.others += 1;
+            }
+        }
+    }
+    PreviewContent::Dir(children)
+}
+
+fn read_file(path: &Path) -> PreviewContent {
+    let read = || -> std::io::Result<(fs::Metadata, Vec<u8>)> {
+        let file = fs::File::open(path)?;
+        let metadata = file.metadata()?;
+        let mut head = Vec::new();
+        file.take(PREVIEW_BYTES).read_to_end(&mut head)?;
+        Ok((metadata, head))
+    };
+    let (metadata, head) = match read() {
+        Ok(read) => read,
+        Err(e) => {
+            return PreviewContent::Error(e.to_string());
+        }
+    };
+    if let Some(lines) = text_lines(&head) {
+        return PreviewContent::Text(lines);
+    }
+    #[cfg(unix)]
+    let mode = Some(std::os::unix::fs::PermissionsExt::mode(&metadata.permissions()) & 0o7777);
+    #[cfg(not(unix))]
+    let mode = None;
+    PreviewContent::Binary(FileInfo {
+        size: metadata.len(),
+        modified: metadata.modified().ok(),
+        category: file_category(path, true),
+        mode,
+    })
+}
+
+/// First lines of `head` when it looks like text: UTF-8 without NUL bytes,
+/// allowing a character cut in two at the end of what was read
+fn text_lines(head: &[u8]) -> Option<Vec<String>> {
+    if head.contains(&0) {
+        return None;
+    }
+    let text = match std::str::from_utf8(head) {
+        Ok(text) => text,
+        Err(e) if e.error_len().is_none() => std::str::from_utf8(&head[..e.valid_up_to()]).ok()?,
+        Err(_) => {
+            return None;
+        }
+    };
+    let lines = text
+        .lines()
+        .take(PREVIEW_LINES)
+        .map(|line| {
+            line.replace('\t', "    ")
+                .chars()
+                .filter(|c| !c.is_control())
+                .collect()
+        })
+        .collect();
+    Some(lines)
+}
@@ -0,0 +1,269 @@
+use crate::{
+    json::Value,
+    scan::{ count_files, is_bundle, is_system_dir, ScanOptions, VisitKey },
+    vfs::{ long_path, EntryKind, EntryMetadata, FileSystem, LocalFs },
+};
+use std::{
+    collections::{ HashMap, HashSet },
+    fs,
+    path::{ Component, Path, PathBuf },
+    sync::mpsc::channel,
+    time::{ Duration, Instant },
+};
+use threadpool::ThreadPool;
+
+/// A node of a pre-computed count tree, produced by headless scans
+/// and browsable in the TUI without touching the disk
+#[derive(Clone, Debug)]
+pub struct ReportNode {
+    pub name: String,
+    pub is_dir: bool,
+    pub files: Option<usize>,
+    pub children: Vec<ReportNode>,
+    pub count_time: Option<Duration>, // How long counting the directory took, where measured
+}
+
+impl ReportNode {
+    /// Find a descendant by its path relative to this node
+    pub fn find(&self, relative: &Path) -> Option<&ReportNode> {
+        let mut node = self;
+        for component in relative.components() {
+            match component {
+                Component::Normal(name) => {
+                    node = node.children.iter().find(|child| name == child.name.as_str())?;
+                }
+                Component::CurDir => {}
+                _ => {
+                    return None;
+                }
+            }
+        }
+        Some(node)
+    }
+
+    pub fn to_json(&self) -> Value {
+        let mut fields = vec![
+            (String::from("name"), Value::from(self.name.as_str())),
+            (String::from("dir"), Value::from(self.is_dir)),
+            (String::from("files"), self.files.map(Value::from).unwrap_or(Value::Null))
+        ];
+        if let Some(count_time) = self.count_time {
+            fields.push((String::from("count_ms"), Value::from(count_time.as_millis() as u64)));
+        }
+        if self.is_dir && !self.children.is_empty() {
+            fields.push((
+                String::from("children"),
+                Value::Array(self.children.iter().map(ReportNode::to_json).collect()),
+            ));
+        }
+        Value::Object(fields)
+    }
+
+    #[cfg(any(feature = "tui", feature = "remote"))]
+    pub fn from_json(value: &Value) -> Result<ReportNode, String> {
+        let name = value
+            .get("name")
+            .and_then(Value::as_str)
+            .ok_or("report node without a name")?
+            .to_string();
+        let is_dir = value.get("dir").and_then(Value::as_bool).unwrap_or(false);
+        let files = value
+            .get("files")
+            .and_then(Value::as_u64)
+            .map(|n| n as usize);
+        let children = match value.get("children").and_then(Value::as_array) {
+            Some(items) => items.iter().map(ReportNode::from_json).collect::<Result<Vec<_>, _>>()?,
+            None => Vec::new(),
+        };
+        let count_time = value.get("count_ms").and_then(Value::as_u64).map(Duration::from_millis);
+        Ok(ReportNode { name, is_dir, files, children, count_time })
+    }
+
+    /// Plain text report, like `du`: one `files<TAB>path` line per directory,
+    /// `path` first and the largest subdirectories before the others
+    pub fn to_plain(&self, path: &Path) -> String {
+        let mut text = String::new();
+        self.write_plain(path, &mut text);
+        text
+    }
+
+    fn write_plain(&self, path: &Path, text: &mut String) {
+        let files = self.files.map_or_else(|| String::from("-"), |files| files.to_string());
+        text.push_str(&format!("{}\t{}\n", files, path.display()));
+        let mut dirs: Vec<&ReportNode> = self.children.iter().filter(|child| child.is_dir).collect();
+        dirs.sort_by(|a, b| b.files.cmp(&a.files).then_with(|| a.name.cmp(&b.name)));
+        for dir in dirs {
+            dir.write_plain(&path.join(&dir.name), text);
+        }
+    }
+
+    /// Parse a report from JSON text
+    #[cfg(any(feature = "tui", feature = "remote"))]
+    pub fn parse(text: &str) -> Result<ReportNode, String> {
+        ReportNode::from_json(&crate::json::parse(text)?)
+    }
+}
+
+/// Scan `root` and build a report listing entries down to `depth` levels,
+/// counting every listed directory in parallel; with `timed`, each one
+/// records how long its count took
+pub fn build_report(root: &Path, depth: usize, timed: bool) -> ReportNode {
+    let mut dirs = Vec::new();
+    let mut node = list_tree(root, root.display().to_string(), depth, &mut dirs);
+
+    let counts = count_in_parallel(dirs);
+    fill_counts(&mut node, root, &counts, timed);
+    node
+}
+
+/// Count unrelated directories, like those given on the command line, in
+/// parallel and report them as the children of one node holding their total
+pub fn build_roots_report(roots: &[PathBuf], timed: bool) -> ReportNode {
+    let counts = count_in_parallel(roots.to_vec());
+    let children: Vec<ReportNode> = roots
+        .iter()
+        .map(|root| {
+            let counted = counts.get(root).copied();
+            ReportNode {
+                name: root.display().to_string(),
+                is_dir: true,
+                files: counted.map(|(files, _)| files),
+                children: Vec::new(),
+                count_time: counted.filter(|_| timed).map(|(_, count_time)| count_time),
+            }
+        })
+        .collect();
+    let files = children.iter().map(|child| child.files).sum();
+    ReportNode { name: String::from("roots"), is_dir: true, files, children, count_time: None }
+}
+
+/// File count of each directory and how long it took, on a pool of one thread per core
+fn count_in_parallel(dirs: Vec<PathBuf>) -> HashMap<PathBuf, (usize, Duration)> {
+    let pool = ThreadPool::new(num_cpus::get());
+    let (tx, rx) = channel();
+    for dir in dirs {
+        let tx = tx.clone();
+        pool.execute(move || {
+            let started = Instant::now();
+            let count = count_files(&dir).unwrap_or(0);
+            tx.send((dir, (count, started.elapsed()))).unwrap_or(());
+        });
+    }
+    drop(tx);
+    rx.iter().collect()
+}
+
+fn list_tree(path: &Path, name: String, depth: usize, dirs: &mut Vec<PathBuf>) -> ReportNode {
+    dirs.push(path.to_path_buf());
+    let mut children = Vec::new();
+    if depth > 0 {
+        if let Ok(entries) = fs::read_dir(long_path(path)) {
+            for entry in entries.flatten() {
+                let child_path = path.join(entry.file_name());
+                let child_name = entry.file_name().to_string_lossy().into_owned();
+                if is_system_dir(&child_path) {
+                    // Listed, but neither counted nor descended into
+                    children.push(ReportNode {
+                        name: child_name,
+                        is_dir: true,
+                        files: None,
+                        children: Vec::new(),
+                        count_time: None,
+                    });
+                } else if long_path(&child_path).is_dir() {
+                    children.push(list_tree(&child_path, child_name, depth - 1, dirs));
+                } else {
+                    children.push(ReportNode {
+                        name: child_name,
+                        is_dir: false,
+                        files: None,
+                        children: Vec::new(),
+                        count_time: None,
+                    });
+                }
+            }
+        }
+    }
+    ReportNode { name, is_dir: true, files: None, children, count_time: None }
+}
+
+fn fill_counts(node: &mut ReportNode, path: &Path, counts: &HashMap<PathBuf, (usize, Duration)>, timed: bool) {
+    let counted = counts.get(path).copied();
+    node.files = counted.map(|(files, _)| files);
+    node.count_time = counted.filter(|_| timed).map(|(_, count_time)| count_time);
+    for child in node.children.iter_mut().filter(|child| child.is_dir) {
+        let child_path = path.join(&child.name);
+        fill_counts(child, &child_path, counts, timed);
+    }
+}
+
+/// Walk `root` once and build a report of every directory below it with its
+/// recursive file count, following the same rules as a scan. Used as the
+/// baseline of `--diff`.
+pub fn build_snapshot(root: &Path) -> ReportNode {
+    build_snapshot_in(&LocalFs, root)
+}
+
+/// Snapshot of a tree read through another filesystem backend
+pub fn build_snapshot_in(filesystem: &dyn FileSystem, root: &Path) -> ReportNode {
+    let options = ScanOptions::default();
+    let mut visited = HashSet::new();
+    snapshot_dir(filesystem, root, root.display().to_string(), &options, &mut visited)
+}
+
+fn snapshot_dir(
+    filesystem: &dyn FileSystem,
+    path: &Path,
+    name: String,
+    options: &ScanOptions,
+    visited: &mut HashSet<VisitKey>
+) -> ReportNode {
+    let mut node = ReportNode { name, is_dir: true, files: Some(0), children: Vec::new(), count_time: None };
+    let real_dir = match filesystem.canonicalize(path) {
+        Ok(real_dir) => real_dir,
+        Err(_) => {
+            return node;
+        }
+    };
+    let key = match filesystem.metadata(&real_dir).ok().and_then(|metadata| metadata.identity) {
+        Some(identity) => VisitKey::Identity(identity),
+        None => VisitKey::Path(real_dir.clone()),
+    };
+    if !visited.insert(key) {
+        return node; // Already counted through another path
+    }
+    let entries = match filesystem.read_dir_typed(&real_dir) {
+        Ok(entries) => entries,
+        Err(_) => {
+            return node;
+        }
+    };
+
+    let mut files = 0usize;
+    for (child_path, kind) in entries {
+        // Only counts are kept, so entries the listing types need no metadata
+        let metadata = match kind {
+            Some(EntryKind::File) => EntryMetadata { is_file: true, ..EntryMetadata::default() },
+            Some(EntryKind::Dir) => EntryMetadata { is_dir: true, ..EntryMetadata::default() },
+            Some(EntryKind::Other) => {
+                continue;
+            }
+            Some(EntryKind::Symlink) | None => {
+                let Ok(metadata) = filesystem.metadata(&child_path) else {
+                    continue;
+                };
+                metadata
+            }
+        };
+        if metadata.is_file || (options.opaque_bundles && is_bundle(&child_path) && metadata.is_dir) {
+            files = files.saturating_add(1);
+        } else if metadata.is_dir && !is_system_dir(&child_path) {
+            let child_name = child_path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+            let child = snapshot_dir(filesystem, &child_path, child_name, options, visited);
+            files = files.saturating_add(child.files.unwrap_or(0));
+            node.children.push(child);
+        }
+    }
+    node.files = Some(files);
+    node
+}
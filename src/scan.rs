@@ -0,0 +1,1077 @@
+use crate::{
+    file_types::file_category,
+    junk::junk_category,
+    mountinfo::{ self, FsClass, FsClasses },
+    report::ReportNode,
+    vfs::{ EntryKind, EntryMetadata, FileSystem, LocalFs },
+};
+use std::{
+    collections::{ HashMap, HashSet },
+    fs,
+    io,
+    path::{ Path, PathBuf },
+    sync::{ atomic::{ AtomicUsize, Ordering }, Condvar, Mutex, OnceLock },
+    thread,
+    time::SystemTime,
+};
+
+/// Result of scanning a directory tree
+#[derive(Clone, Default)]
+pub struct DirStats {
+    pub files: usize,
+    pub bytes: ByteSizes, // Of the counted files
+    pub hard_links: usize, // Extra links to already counted files, skipped with `dedupe_hard_links`
+    pub broken_links: usize, // Symlinks to missing targets (or loops), not counted as files
+    pub extensions: Option<HashMap<String, usize>>, // Only collected on request
+    pub file_types: Option<HashMap<&'static str, FileTotals>>, // By category, collected with the extensions
+    pub newest: Option<SystemTime>, // Most recent modification of any entry below the root
+    pub owners: Option<HashMap<u32, FileTotals>>, // Per user id, only collected on request
+    pub junk: Option<HashMap<&'static str, FileTotals>>, // Inside recognized junk directories, by category
+    pub skipped: Skipped, // Entries that could not be read, so the count may be short
+    pub excluded: Vec<PathBuf>, // Directories deliberately not descended into: system directories and excluded mounts
+}
+
+/// Most skipped entries a scan keeps the details of; the rest are only counted
+const MAX_SKIPPED: usize = 1000;
+
+/// Entries a scan could not read
+#[derive(Clone, Debug, Default)]
+pub struct Skipped {
+    pub count: usize,
+    pub entries: Vec<SkippedEntry>, // The first `MAX_SKIPPED`
+}
+
+impl Skipped {
+    fn add(&mut self, path: &Path, error: &io::Error) {
+        self.count += 1;
+        if self.entries.len() < MAX_SKIPPED {
+            let reason = SkipReason::of(error);
+            self.entries.push(SkippedEntry { path: path.to_path_buf(), reason, message: error.to_string() });
+        }
+    }
+
+    fn merge(&mut self, other: Skipped) {
+        self.count += other.count;
+        let room = MAX_SKIPPED.saturating_sub(self.entries.len());
+        self.entries.extend(other.entries.into_iter().take(room));
+    }
+}
+
+/// An entry left out of a count, and why. Only the TUI lists them; headless
+/// reports count them.
+#[cfg_attr(not(feature = "tui"), allow(dead_code))]
+#[derive(Clone, Debug)]
+pub struct SkippedEntry {
+    pub path: PathBuf,
+    pub reason: SkipReason,
+    pub message: String, // The error as reported by the system
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SkipReason {
+    PermissionDenied,
+    SymlinkLoop, // Too many levels of symbolic links
+    Io, // Any other error: vanished entries, I/O and network errors
+}
+
+/// `ELOOP`, or what Windows reports for a symlink it cannot resolve
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const SYMLINK_LOOP: i32 = 40;
+#[cfg(all(unix, not(any(target_os = "linux", target_os = "android"))))]
+const SYMLINK_LOOP: i32 = 62; // macOS and the BSDs
+#[cfg(windows)]
+const SYMLINK_LOOP: i32 = 1921; // ERROR_CANT_RESOLVE_FILENAME
+
+impl SkipReason {
+    fn of(error: &io::Error) -> SkipReason {
+        match error.kind() {
+            io::ErrorKind::PermissionDenied => SkipReason::PermissionDenied,
+            _ if error.raw_os_error() == Some(SYMLINK_LOOP) => SkipReason::SymlinkLoop,
+            _ => SkipReason::Io,
+        }
+    }
+
+    #[cfg(feature = "tui")]
+    pub fn label(self) -> &'static str {
+        match self {
+            SkipReason::PermissionDenied => "permission denied",
+            SkipReason::SymlinkLoop => "too many symlinks",
+            SkipReason::Io => "I/O error",
+        }
+    }
+}
+
+/// Files and bytes of one owner, junk category or file type
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FileTotals {
+    pub files: usize,
+    pub bytes: ByteSizes,
+}
+
+impl FileTotals {
+    fn add(&mut self, files: usize, bytes: ByteSizes) {
+        self.files += files;
+        self.bytes.add(bytes);
+    }
+}
+
+impl DirStats {
+    /// Empty totals collecting what `options` asks for
+    fn collecting(options: &ScanOptions) -> DirStats {
+        DirStats {
+            extensions: options.collect_extensions.then(HashMap::new),
+            file_types: options.collect_extensions.then(HashMap::new),
+            owners: options.collect_owners.then(HashMap::new),
+            junk: options.collect_junk.then(HashMap::new),
+            ..DirStats::default()
+        }
+    }
+
+    /// Add `files` files found at `path` with `metadata`, inside a junk directory of category `junk`,
+    /// classifying them by their extension or, with `sniff_types`, by their first bytes
+    fn add_files(
+        &mut self,
+        files: usize,
+        path: &Path,
+        metadata: &EntryMetadata,
+        junk: Option<&'static str>,
+        sniff_types: bool
+    ) {
+        self.files += files;
+        self.bytes.add(metadata.sizes);
+        if let Some(extensions) = &mut self.extensions {
+            *extensions.entry(extension_key(path)).or_insert(0) += files;
+        }
+        if let Some(file_types) = &mut self.file_types {
+            file_types.entry(file_category(path, sniff_types)).or_default().add(files, metadata.sizes);
+        }
+        if let (Some(owners), Some(owner)) = (&mut self.owners, metadata.owner) {
+            owners.entry(owner).or_default().add(files, metadata.sizes);
+        }
+        if let (Some(categories), Some(category)) = (&mut self.junk, junk) {
+            categories.entry(category).or_default().add(files, metadata.sizes);
+        }
+    }
+
+    /// Add the totals of another worker
+    fn merge(&mut self, other: DirStats) {
+        self.files += other.files;
+        self.bytes.add(other.bytes);
+        self.hard_links += other.hard_links;
+        self.broken_links += other.broken_links;
+        self.skipped.merge(other.skipped);
+        self.excluded.extend(other.excluded);
+        self.newest = self.newest.max(other.newest);
+        if let (Some(total), Some(part)) = (&mut self.extensions, other.extensions) {
+            for (extension, count) in part {
+                *total.entry(extension).or_insert(0) += count;
+            }
+        }
+        if let (Some(total), Some(part)) = (&mut self.file_types, other.file_types) {
+            for (category, totals) in part {
+                total.entry(category).or_default().add(totals.files, totals.bytes);
+            }
+        }
+        if let (Some(total), Some(part)) = (&mut self.owners, other.owners) {
+            for (owner, totals) in part {
+                total.entry(owner).or_default().add(totals.files, totals.bytes);
+            }
+        }
+        if let (Some(total), Some(part)) = (&mut self.junk, other.junk) {
+            for (category, totals) in part {
+                total.entry(category).or_default().add(totals.files, totals.bytes);
+            }
+        }
+    }
+}
+
+/// Size of files, both as their length and as the space allocated for them
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ByteSizes {
+    pub apparent: u64, // Sum of the lengths, like `du --apparent-size`
+    pub disk: u64, // Allocated space: less for sparse files, rounded up to whole blocks
+}
+
+impl ByteSizes {
+    pub fn of(metadata: &fs::Metadata) -> ByteSizes {
+        ByteSizes { apparent: metadata.len(), disk: allocated_bytes(metadata) }
+    }
+
+    pub fn add(&mut self, other: ByteSizes) {
+        self.apparent = self.apparent.saturating_add(other.apparent);
+        self.disk = self.disk.saturating_add(other.disk);
+    }
+
+    #[cfg(feature = "tui")]
+    pub fn subtract(&mut self, other: ByteSizes) {
+        self.apparent = self.apparent.saturating_sub(other.apparent);
+        self.disk = self.disk.saturating_sub(other.disk);
+    }
+
+    /// The apparent or the allocated size
+    #[cfg(feature = "tui")]
+    pub fn get(&self, apparent: bool) -> u64 {
+        if apparent { self.apparent } else { self.disk }
+    }
+}
+
+#[cfg(unix)]
+fn allocated_bytes(metadata: &fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.blocks().saturating_mul(512) // st_blocks is always in 512-byte units
+}
+
+/// The standard library doesn't expose the allocation size on Windows, so assume
+/// the default 4 KiB NTFS cluster. Sparse and compressed files are overestimated.
+#[cfg(not(unix))]
+fn allocated_bytes(metadata: &fs::Metadata) -> u64 {
+    const CLUSTER: u64 = 4096;
+    metadata.len().div_ceil(CLUSTER).saturating_mul(CLUSTER)
+}
+
+/// Directory extensions treated as bundles (opaque items in Finder)
+const BUNDLE_EXTENSIONS: &[&str] = &[
+    "app",
+    "appex",
+    "bundle",
+    "framework",
+    "kext",
+    "photoslibrary",
+    "musiclibrary",
+    "tvlibrary",
+    "fcpbundle",
+    "logicx",
+    "pkg",
+    "plugin",
+    "rtfd",
+    "xcodeproj",
+    "xcworkspace",
+    "playground",
+];
+
+/// Bundles are opaque by default only on macOS, matching Finder
+const DEFAULT_OPAQUE_BUNDLES: bool = cfg!(target_os = "macos");
+
+/// Options controlling what a scan counts
+#[derive(Clone, Copy)]
+pub struct ScanOptions {
+    pub collect_extensions: bool,
+    pub opaque_bundles: bool, // Count bundles as one file instead of descending
+    pub dedupe_hard_links: bool, // Count a file with several hard links once, like `du`
+    #[cfg(feature = "archive")]
+    pub count_archives: bool, // Count the entries of .zip/.tar archives instead of the archive
+    pub collect_owners: bool, // Files and bytes per user id, for the ownership summary and `users`
+    pub collect_junk: bool, // Files and bytes inside recognized build and cache directories
+    pub sniff_types: bool, // Read the first bytes of files without a known extension to classify them
+    pub count_only: bool, // Count entries the listing types without reading their metadata: no sizes, times or owners
+    pub exclude_fs: FsClasses, // Mounted filesystems of these classes are not descended into, below the root
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        ScanOptions {
+            collect_extensions: false,
+            opaque_bundles: DEFAULT_OPAQUE_BUNDLES,
+            dedupe_hard_links: false,
+            #[cfg(feature = "archive")]
+            count_archives: false,
+            collect_owners: false,
+            collect_junk: false,
+            sniff_types: false,
+            count_only: false,
+            exclude_fs: FsClasses::NONE,
+        }
+    }
+}
+
+/// Whether a directory is a bundle (`.app`, `.photoslibrary`, ...)
+pub fn is_bundle(path: &Path) -> bool {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) => BUNDLE_EXTENSIONS.iter().any(|b| b.eq_ignore_ascii_case(ext)),
+        None => false,
+    }
+}
+
+/// Whether `path` is an archive counted by its entries with these options
+#[cfg(feature = "archive")]
+pub fn is_counted_archive(path: &Path, options: &ScanOptions) -> bool {
+    options.count_archives && crate::archive::is_archive(path)
+}
+
+#[cfg(not(feature = "archive"))]
+pub fn is_counted_archive(_path: &Path, _options: &ScanOptions) -> bool {
+    false
+}
+
+/// Entry tree of an archive, browsable like a report
+#[cfg(feature = "archive")]
+pub fn archive_tree(path: &Path) -> io::Result<ReportNode> {
+    crate::archive::read_archive(path)
+}
+
+#[cfg(not(feature = "archive"))]
+pub fn archive_tree(_path: &Path) -> io::Result<ReportNode> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "built without the `archive` feature"))
+}
+
+/// Kernel pseudo-filesystems and runtime state: their entries are not files on
+/// any disk, and reading some of them blocks or never ends
+#[cfg(unix)]
+const DEFAULT_SYSTEM_DIRS: &[&str] = &["/proc", "/sys", "/dev", "/run"];
+#[cfg(not(unix))]
+const DEFAULT_SYSTEM_DIRS: &[&str] = &[];
+
+static SYSTEM_DIRS: OnceLock<Vec<PathBuf>> = OnceLock::new();
+
+/// Replace the default system directories (`scan.system_dirs`) before the first scan
+#[cfg(feature = "tui")]
+pub fn set_system_dirs(dirs: Vec<PathBuf>) {
+    let _ = SYSTEM_DIRS.set(dirs);
+}
+
+/// Whether scans reaching `path` from above leave it out. A scan started in
+/// a system directory still counts it.
+pub fn is_system_dir(path: &Path) -> bool {
+    SYSTEM_DIRS.get_or_init(|| DEFAULT_SYSTEM_DIRS.iter().map(PathBuf::from).collect())
+        .iter()
+        .any(|dir| dir == path)
+}
+
+/// Device and inode of a directory, after following symlinks. Bind mounts of
+/// the same directory share them, so they identify a directory across mounts.
+#[cfg(all(feature = "tui", unix))]
+pub fn dir_identity(path: &Path) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    fs::metadata(path).ok().map(|m| (m.dev(), m.ino()))
+}
+
+#[cfg(all(feature = "tui", not(unix)))]
+pub fn dir_identity(_path: &Path) -> Option<(u64, u64)> {
+    None
+}
+
+/// Device and inode of a file with more than one hard link
+#[cfg(unix)]
+pub fn hard_link_identity(metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    (metadata.nlink() > 1).then(|| (metadata.dev(), metadata.ino()))
+}
+
+/// The standard library doesn't expose link counts on Windows
+#[cfg(not(unix))]
+pub fn hard_link_identity(_metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// Count the number of files in a directory
+pub fn count_files(dir: &Path) -> io::Result<usize> {
+    scan_dir(dir, &ScanOptions { count_only: true, ..ScanOptions::default() }).map(|stats| stats.files)
+}
+
+/// Extension key used in histograms: lowercase with a leading dot, or "(none)"
+pub fn extension_key(path: &Path) -> String {
+    match path.extension() {
+        Some(ext) => format!(".{}", ext.to_string_lossy().to_lowercase()),
+        None => String::from("(none)"),
+    }
+}
+
+/// What makes a directory "already visited" during a scan
+#[derive(PartialEq, Eq, Hash)]
+pub enum VisitKey {
+    Identity((u64, u64)),
+    Path(PathBuf),
+}
+
+#[cfg(feature = "tui")]
+impl VisitKey {
+    /// Identify a resolved directory by device and inode where possible, so
+    /// bind mounts of the same directory are only counted once
+    pub fn of(real_dir: &Path) -> VisitKey {
+        match dir_identity(real_dir) {
+            Some(identity) => VisitKey::Identity(identity),
+            None => VisitKey::Path(real_dir.to_path_buf()),
+        }
+    }
+}
+
+/// Helper threads currently assisting scans, across all scans. A single huge
+/// tree can use every idle core, while many concurrent scans stay one thread each.
+static HELPERS: AtomicUsize = AtomicUsize::new(0);
+
+/// Helper threads currently assisting scans
+#[cfg(feature = "tui")]
+pub fn helper_threads() -> usize {
+    HELPERS.load(Ordering::Relaxed)
+}
+
+/// Directories waiting to be read, shared by the threads of one scan
+struct WorkQueue {
+    dirs: Vec<QueuedDir>,
+    busy: usize, // Threads currently reading a directory, which may queue more
+}
+
+struct QueuedDir {
+    path: PathBuf,
+    junk: Option<&'static str>, // Junk category of the directory or an ancestor
+    resolved: bool, // Listed as a directory inside a resolved one, so its path has no symlink to resolve
+    parent_device: Option<u64>, // Device of the directory that listed it, None for the root
+}
+
+/// Whether a file with this name counts
+type NameFilter<'a> = dyn Fn(&str) -> bool + Sync + 'a;
+
+/// State shared by the threads of one scan
+struct SharedScan<'a> {
+    filesystem: &'a dyn FileSystem,
+    options: &'a ScanOptions,
+    progress: Option<&'a AtomicUsize>, // Files found so far, for progress display
+    matching: Option<&'a NameFilter<'a>>, // Only files with a matching name count
+    queue: Mutex<WorkQueue>,
+    ready: Condvar,
+    visited: Mutex<HashSet<VisitKey>>,
+    linked_files: Mutex<HashSet<(u64, u64)>>, // Files with several hard links already counted
+    totals: Mutex<DirStats>,
+}
+
+/// Scan a directory tree without recursion, optionally building a histogram of file extensions.
+/// Subdirectories go to a shared queue that idle cores help drain.
+/// The root is always descended, even when it is a bundle.
+pub fn scan_dir(dir: &Path, options: &ScanOptions) -> io::Result<DirStats> {
+    scan_dir_with_progress(dir, options, None)
+}
+
+/// Like `scan_dir`, adding the files of each directory to `progress` as soon as it is read
+pub fn scan_dir_with_progress(dir: &Path, options: &ScanOptions, progress: Option<&AtomicUsize>) -> io::Result<DirStats> {
+    scan_dir_in(&LocalFs, dir, options, progress)
+}
+
+/// Scan a directory tree of any `FileSystem` backend
+pub fn scan_dir_in(
+    filesystem: &dyn FileSystem,
+    dir: &Path,
+    options: &ScanOptions,
+    progress: Option<&AtomicUsize>
+) -> io::Result<DirStats> {
+    scan(filesystem, dir, options, progress, None)
+}
+
+/// Like `scan_dir`, counting only the files whose name matches `pattern`
+#[cfg(feature = "tui")]
+pub fn scan_dir_matching(
+    dir: &Path,
+    options: &ScanOptions,
+    pattern: &crate::pattern::NamePattern
+) -> io::Result<DirStats> {
+    scan_matching_in(&LocalFs, dir, options, pattern)
+}
+
+/// Like `scan_dir_in`, counting only the files whose name matches `pattern`
+#[cfg(feature = "tui")]
+pub fn scan_matching_in(
+    filesystem: &dyn FileSystem,
+    dir: &Path,
+    options: &ScanOptions,
+    pattern: &crate::pattern::NamePattern
+) -> io::Result<DirStats> {
+    scan(filesystem, dir, options, None, Some(&|name: &str| pattern.matches(name)))
+}
+
+fn scan(
+    filesystem: &dyn FileSystem,
+    dir: &Path,
+    options: &ScanOptions,
+    progress: Option<&AtomicUsize>,
+    matching: Option<&NameFilter<'_>>
+) -> io::Result<DirStats> {
+    let root_junk = if options.collect_junk { junk_category(filesystem, dir) } else { None };
+    let shared = SharedScan {
+        filesystem,
+        options,
+        progress,
+        matching,
+        queue: Mutex::new(
+            WorkQueue { dirs: vec![QueuedDir { path: dir.to_path_buf(), junk: root_junk, resolved: false, parent_device: None }], busy: 0 }
+        ),
+        ready: Condvar::new(),
+        visited: Mutex::new(HashSet::new()),
+        linked_files: Mutex::new(HashSet::new()),
+        totals: Mutex::new(DirStats::collecting(options)),
+    };
+
+    thread::scope(|scope| scan_worker(&shared, scope));
+
+    Ok(shared.totals.into_inner().unwrap_or_else(|e| e.into_inner()))
+}
+
+/// Take directories from the queue until the whole tree is read, possibly
+/// recruiting helper threads when work piles up
+fn scan_worker<'scope>(shared: &'scope SharedScan<'scope>, scope: &'scope thread::Scope<'scope, '_>) {
+    let lock = |m: &'scope Mutex<WorkQueue>| m.lock().unwrap_or_else(|e| e.into_inner());
+    let mut stats = DirStats::collecting(shared.options);
+
+    loop {
+        // Wait for a directory, or stop once the queue is empty and nobody can add to it
+        let current_dir = {
+            let mut queue = lock(&shared.queue);
+            loop {
+                if let Some(dir) = queue.dirs.pop() {
+                    queue.busy += 1;
+                    break Some(dir);
+                }
+                if queue.busy == 0 {
+                    break None;
+                }
+                queue = shared.ready.wait(queue).unwrap_or_else(|e| e.into_inner());
+            }
+        };
+        let Some(current_dir) = current_dir else {
+            shared.ready.notify_all();
+            break;
+        };
+
+        let files_before = stats.files;
+        let subdirs = read_one_dir(shared, &current_dir, &mut stats);
+        if let Some(progress) = shared.progress {
+            progress.fetch_add(stats.files - files_before, Ordering::Relaxed);
+        }
+
+        let pending = {
+            let mut queue = lock(&shared.queue);
+            queue.dirs.extend(subdirs);
+            queue.busy -= 1;
+            queue.dirs.len()
+        };
+        shared.ready.notify_all();
+
+        // More than one directory waiting: ask an idle core to help
+        let max_helpers = num_cpus::get().saturating_sub(1);
+        let recruited =
+            pending > 1 &&
+            HELPERS.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| (n < max_helpers).then_some(n + 1)).is_ok();
+        if recruited {
+            scope.spawn(move || {
+                scan_worker(shared, scope);
+                HELPERS.fetch_sub(1, Ordering::SeqCst);
+            });
+        }
+    }
+
+    shared.totals.lock().unwrap_or_else(|e| e.into_inner()).merge(stats);
+}
+
+/// Add the files of one directory to the worker's `stats` and return its subdirectories,
+/// with their junk category: the one of `queued`, or their own
+fn read_one_dir(shared: &SharedScan, queued: &QueuedDir, stats: &mut DirStats) -> Vec<QueuedDir> {
+    let options = shared.options;
+    let mut subdirs = Vec::new();
+    let filesystem = shared.filesystem;
+    let (current_dir, junk) = (queued.path.as_path(), queued.junk);
+    let resolved = match queued.resolved {
+        true => Ok(queued.path.clone()),
+        false => filesystem.canonicalize(current_dir),
+    };
+    let real_dir = match resolved {
+        Ok(path) => path,
+        Err(e) => {
+            stats.skipped.add(current_dir, &e);
+            return subdirs;
+        } // Unable to get real path, skip
+    };
+
+    // Identify the directory by device and inode where possible, so bind
+    // mounts of the same directory are only counted once
+    let identity = filesystem.metadata(&real_dir).ok().and_then(|metadata| metadata.identity);
+    if let (Some((device, _)), Some(parent_device)) = (identity, queued.parent_device) {
+        // On another device than its parent: a mount point, left out if its class is excluded
+        if device != parent_device && !options.exclude_fs.is_empty() {
+            let class = mountinfo::mounted_fs_type(&real_dir).map_or(FsClass::Local, |fs_type| FsClass::of(&fs_type));
+            if options.exclude_fs.contains(class) {
+                stats.excluded.push(current_dir.to_path_buf());
+                return subdirs;
+            }
+        }
+    }
+    let key = match identity {
+        Some(identity) => VisitKey::Identity(identity),
+        None => VisitKey::Path(real_dir.clone()),
+    };
+    if !shared.visited.lock().unwrap_or_else(|e| e.into_inner()).insert(key) {
+        return subdirs; // Already visited, skip
+    }
+
+    let entries = match filesystem.read_dir_typed(&real_dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            stats.skipped.add(current_dir, &e);
+            return subdirs;
+        } // Unable to read directory, skip
+    };
+
+    // Files with several links can only be told apart by their metadata
+    let typed_is_enough = options.count_only && !options.dedupe_hard_links;
+    let is_typed = |kind: Option<EntryKind>| {
+        typed_is_enough && matches!(kind, Some(EntryKind::File | EntryKind::Dir | EntryKind::Other))
+    };
+    // The others are looked up together, so a backend can overlap the calls
+    let lookups: Vec<&Path> = entries
+        .iter()
+        .filter(|(_, kind)| !is_typed(*kind))
+        .map(|(path, _)| path.as_path())
+        .collect();
+    let mut looked_up = filesystem.metadata_batch(&lookups).into_iter();
+    for (path, kind) in &entries {
+        let (path, kind) = (path.clone(), *kind);
+        // Follows symlinks, like `Path::is_file`
+        let looked_up = match kind {
+            Some(EntryKind::File) if typed_is_enough => Ok(EntryMetadata { is_file: true, ..EntryMetadata::default() }),
+            Some(EntryKind::Dir) if typed_is_enough => Ok(EntryMetadata { is_dir: true, ..EntryMetadata::default() }),
+            Some(EntryKind::Other) if typed_is_enough => {
+                continue;
+            }
+            _ => looked_up.next().unwrap_or_else(|| filesystem.metadata(&path)),
+        };
+        let metadata = match looked_up {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                // A dangling symlink is listed by the broken link view; a looping one is an error too
+                let is_symlink = filesystem.is_symlink(&path);
+                if is_symlink {
+                    stats.broken_links += 1;
+                }
+                if !is_symlink || SkipReason::of(&e) == SkipReason::SymlinkLoop {
+                    stats.skipped.add(&path, &e);
+                }
+                continue;
+            } // Dangling symlink or unreadable entry, skip
+        };
+        let is_file = metadata.is_file;
+        let is_dir = metadata.is_dir;
+        if let Some(matches) = shared.matching {
+            let counted_as_file = is_file || (is_dir && options.opaque_bundles && is_bundle(&path));
+            let name = path.file_name().map(|name| name.to_string_lossy());
+            if counted_as_file && !name.is_some_and(|name| matches(&name)) {
+                continue;
+            }
+        }
+        // A directory's own time changes when entries are added or removed
+        stats.newest = stats.newest.max(metadata.modified);
+        if is_file && options.dedupe_hard_links {
+            // Only files with several links pay for the shared set
+            let seen = metadata.hard_link_identity().is_some_and(|identity| {
+                !shared.linked_files.lock().unwrap_or_else(|e| e.into_inner()).insert(identity)
+            });
+            if seen {
+                stats.hard_links += 1;
+                continue;
+            }
+        }
+        // An unreadable archive still counts as one file. Archives are opened on the local disk.
+        let archived = if is_file && is_counted_archive(&path, options) {
+            archive_tree(&path).ok().and_then(|tree| tree.files)
+        } else {
+            None
+        };
+        if let Some(archived) = archived {
+            stats.add_files(archived, &path, &metadata, junk, options.sniff_types);
+        } else if is_file || (is_dir && options.opaque_bundles && is_bundle(&path)) {
+            stats.add_files(1, &path, &metadata, junk, options.sniff_types); // A bundle only adds its own inode
+        } else if is_dir && is_system_dir(&path) {
+            stats.excluded.push(path);
+        } else if is_dir {
+            let junk = match junk {
+                None if options.collect_junk => junk_category(filesystem, &path),
+                junk => junk,
+            };
+            let parent_device = identity.map(|(device, _)| device);
+            subdirs.push(QueuedDir { path, junk, resolved: kind == Some(EntryKind::Dir), parent_device });
+        }
+    }
+    subdirs
+}
+
+/// Stress tests: the scanner must never panic, whatever the filesystem throws at it
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report;
+    use std::path::PathBuf;
+
+    /// Fresh scratch directory for one test
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("file_counter-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// In-memory tree, so counts can be checked exactly
+    #[derive(Default)]
+    struct MemoryFs {
+        entries: HashMap<PathBuf, MemoryEntry>,
+    }
+
+    enum MemoryEntry {
+        Dir,
+        File { len: u64, inode: u64, links: u64 },
+        Symlink(PathBuf),
+    }
+
+    impl MemoryFs {
+        /// Add an entry below `/`, with its missing parent directories
+        fn add(&mut self, path: &str, entry: MemoryEntry) -> &mut MemoryFs {
+            let path = PathBuf::from(path);
+            for ancestor in path.ancestors().skip(1) {
+                self.entries.entry(ancestor.to_path_buf()).or_insert(MemoryEntry::Dir);
+            }
+            self.entries.insert(path, entry);
+            self
+        }
+
+        fn file(&mut self, path: &str, len: u64) -> &mut MemoryFs {
+            let inode = self.entries.len() as u64;
+            self.add(path, MemoryEntry::File { len, inode, links: 1 })
+        }
+
+        /// Follow symlinks component by component
+        fn resolve(&self, path: &Path) -> io::Result<PathBuf> {
+            let mut resolved = PathBuf::from("/");
+            for component in path.components().skip(1) {
+                resolved.push(component);
+                for _ in 0..40 {
+                    match self.entries.get(&resolved) {
+                        Some(MemoryEntry::Symlink(target)) => {
+                            resolved = target.clone();
+                        }
+                        Some(_) => {
+                            break;
+                        }
+                        None => {
+                            return Err(io::ErrorKind::NotFound.into());
+                        }
+                    }
+                }
+            }
+            Ok(resolved)
+        }
+    }
+
+    impl FileSystem for MemoryFs {
+        fn read_dir(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+            let dir = self.resolve(dir)?;
+            Ok(self.entries.keys().filter(|path| path.parent() == Some(&dir)).cloned().collect())
+        }
+
+        fn metadata(&self, path: &Path) -> io::Result<crate::vfs::EntryMetadata> {
+            let metadata = match self.entries.get(&self.resolve(path)?) {
+                Some(MemoryEntry::File { len, inode, links }) =>
+                    crate::vfs::EntryMetadata {
+                        is_file: true,
+                        sizes: ByteSizes { apparent: *len, disk: len.div_ceil(4096) * 4096 },
+                        identity: Some((1, *inode)),
+                        links: *links,
+                        ..Default::default()
+                    },
+                _ => crate::vfs::EntryMetadata { is_dir: true, links: 1, ..Default::default() },
+            };
+            Ok(metadata)
+        }
+
+        fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+            self.resolve(path)
+        }
+
+        fn is_symlink(&self, path: &Path) -> bool {
+            matches!(self.entries.get(path), Some(MemoryEntry::Symlink(_)))
+        }
+    }
+
+    /// Exact counts of a tree with symlink loops, aliases, dangling links, hard links and a bundle
+    #[test]
+    fn counts_in_memory_tree() {
+        let mut tree = MemoryFs::default();
+        tree.file("/data/a.txt", 10)
+            .file("/data/sub/b.txt", 5000)
+            .file("/data/Photos.app/x", 1)
+            .file("/data/Photos.app/y", 1)
+            .add("/data/sub/c.txt", MemoryEntry::File { len: 7, inode: 99, links: 2 })
+            .add("/data/sub/c-link.txt", MemoryEntry::File { len: 7, inode: 99, links: 2 })
+            .add("/data/loop", MemoryEntry::Symlink(PathBuf::from("/data")))
+            .add("/data/alias", MemoryEntry::Symlink(PathBuf::from("/data/sub")))
+            .add("/data/dangling", MemoryEntry::Symlink(PathBuf::from("/nowhere")));
+        let scan = |options: ScanOptions| scan_dir_in(&tree, Path::new("/data"), &options, None).unwrap();
+        let options = ScanOptions { opaque_bundles: false, ..ScanOptions::default() };
+
+        let stats = scan(options);
+        assert_eq!((stats.files, stats.bytes.apparent, stats.bytes.disk), (6, 5026, 6 * 4096 + 4096));
+        assert_eq!(stats.broken_links, 1);
+        assert_eq!(scan(ScanOptions { opaque_bundles: true, ..options }).files, 5);
+        let stats = scan(ScanOptions { dedupe_hard_links: true, ..options });
+        assert_eq!((stats.files, stats.hard_links), (5, 1));
+    }
+
+    /// System directories are left out of scans reaching them from above, and reported
+    #[cfg(unix)]
+    #[test]
+    fn system_dirs_left_out() {
+        let mut tree = MemoryFs::default();
+        tree.file("/etc/hosts", 1).file("/proc/1/status", 1).file("/proc/self/status", 1).file("/run/lock/x", 1);
+        let stats = scan_dir_in(&tree, Path::new("/"), &ScanOptions::default(), None).unwrap();
+        assert_eq!(stats.files, 1);
+        let mut excluded = stats.excluded;
+        excluded.sort();
+        assert_eq!(excluded, [PathBuf::from("/proc"), PathBuf::from("/run")]);
+        assert_eq!(scan_dir_in(&tree, Path::new("/proc"), &ScanOptions::default(), None).unwrap().files, 2);
+    }
+
+    /// Files below recognized build and cache directories, which need a marker file when the name is common
+    #[test]
+    fn junk_by_category() {
+        let mut tree = MemoryFs::default();
+        tree.file("/app/package.json", 1)
+            .file("/app/node_modules/left-pad/index.js", 1)
+            .file("/app/node_modules/left-pad/node_modules/x/y.js", 1)
+            .file("/app/target/notes.txt", 1)
+            .file("/app/lib/Cargo.toml", 1)
+            .file("/app/lib/target/debug/lib", 1);
+        let options = ScanOptions { collect_junk: true, ..ScanOptions::default() };
+        let stats = scan_dir_in(&tree, Path::new("/app"), &options, None).unwrap();
+        let junk = stats.junk.unwrap();
+        assert_eq!(junk.get("Node.js packages").map(|totals| totals.files), Some(2));
+        assert_eq!(junk.get("Rust and Maven builds").map(|totals| totals.files), Some(1));
+        assert_eq!(junk.len(), 2);
+    }
+
+    /// Scan with and without extension histograms, and build a report
+    fn scan_everything(dir: &Path) {
+        for collect_extensions in [false, true] {
+            let options = ScanOptions { collect_extensions, ..ScanOptions::default() };
+            let _ = scan_dir(dir, &options);
+        }
+        let _ = report::build_report(dir, 2, true).to_json().to_json();
+    }
+
+    #[test]
+    fn empty_directory() {
+        let dir = scratch_dir("empty");
+        let stats = scan_dir(&dir, &ScanOptions { collect_extensions: true, ..ScanOptions::default() }).unwrap();
+        assert_eq!(stats.files, 0);
+        assert_eq!(stats.extensions.map(|e| e.len()), Some(0));
+        assert!(report::build_report(&dir, 3, false).children.is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn missing_directory() {
+        let dir = std::env::temp_dir().join("file_counter-does-not-exist");
+        assert_eq!(count_files(&dir).unwrap(), 0);
+        scan_everything(&dir);
+    }
+
+    #[test]
+    fn wide_tree_across_threads() {
+        let dir = scratch_dir("wide");
+        for i in 0..40 {
+            let sub = dir.join(format!("d{}", i)).join("nested");
+            fs::create_dir_all(&sub).unwrap();
+            fs::write(sub.join("a.txt"), b"").unwrap();
+            fs::write(sub.join("b.rs"), b"").unwrap();
+        }
+        let options = ScanOptions { collect_extensions: true, ..ScanOptions::default() };
+        let stats = scan_dir(&dir, &options).unwrap();
+        assert_eq!(stats.files, 80);
+        let extensions = stats.extensions.unwrap();
+        assert_eq!(extensions.get(".txt"), Some(&40));
+        assert_eq!(extensions.get(".rs"), Some(&40));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// File types by extension, and by their first bytes when sniffing
+    #[test]
+    fn file_types_by_extension_and_signature() {
+        let dir = scratch_dir("types");
+        fs::write(dir.join("a.JPG"), b"").unwrap();
+        fs::write(dir.join("b.rs"), b"").unwrap();
+        fs::write(dir.join("c.mkv"), b"").unwrap();
+        fs::write(dir.join("IMG0001"), b"\x89PNG\r\n\x1a\n").unwrap();
+        fs::write(dir.join("run"), b"#!/bin/sh\n").unwrap();
+        let types = |sniff_types: bool| {
+            let options = ScanOptions { collect_extensions: true, sniff_types, ..ScanOptions::default() };
+            let file_types = scan_dir(&dir, &options).unwrap().file_types.unwrap();
+            ["Images", "Video", "Code", "Other"].map(|category| file_types.get(category).map_or(0, |totals| totals.files))
+        };
+        assert_eq!(types(false), [1, 1, 1, 2]);
+        assert_eq!(types(true), [2, 1, 2, 0]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_loops_and_dangling_links() {
+        let dir = scratch_dir("links");
+        fs::create_dir(dir.join("sub")).unwrap();
+        fs::write(dir.join("sub").join("file"), b"").unwrap();
+        std::os::unix::fs::symlink(&dir, dir.join("sub").join("loop")).unwrap();
+        std::os::unix::fs::symlink(dir.join("missing"), dir.join("dangling")).unwrap();
+        assert_eq!(count_files(&dir).unwrap(), 1);
+        scan_everything(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Entries that cannot be read are listed with the reason, dangling symlinks are not
+    #[cfg(unix)]
+    #[test]
+    fn skipped_entries() {
+        let dir = scratch_dir("skipped");
+        std::os::unix::fs::symlink(dir.join("b"), dir.join("a")).unwrap();
+        std::os::unix::fs::symlink(dir.join("a"), dir.join("b")).unwrap();
+        std::os::unix::fs::symlink(dir.join("missing"), dir.join("dangling")).unwrap();
+        let stats = scan_dir(&dir, &ScanOptions::default()).unwrap();
+        assert_eq!(stats.broken_links, 3);
+        assert_eq!(stats.skipped.count, 2);
+        assert!(stats.skipped.entries.iter().all(|entry| entry.reason == SkipReason::SymlinkLoop));
+
+        let missing = scan_dir(&dir.join("missing"), &ScanOptions::default()).unwrap();
+        assert_eq!(missing.skipped.entries[0].reason, SkipReason::Io);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// A sparse file is as long as it claims, but takes (almost) no space on disk
+    #[cfg(unix)]
+    #[test]
+    fn sparse_file_sizes() {
+        let dir = scratch_dir("sparse");
+        fs::File::create(dir.join("image")).unwrap().set_len(64 << 20).unwrap();
+        let stats = scan_dir(&dir, &ScanOptions::default()).unwrap();
+        assert_eq!(stats.bytes.apparent, 64 << 20);
+        assert!(stats.bytes.disk < stats.bytes.apparent);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Hard links across subdirectories are counted once only on request
+    #[cfg(unix)]
+    #[test]
+    fn hard_links_counted_once() {
+        let dir = scratch_dir("hardlinks");
+        fs::create_dir(dir.join("sub")).unwrap();
+        fs::write(dir.join("original"), b"data").unwrap();
+        fs::hard_link(dir.join("original"), dir.join("link")).unwrap();
+        fs::hard_link(dir.join("original"), dir.join("sub").join("link")).unwrap();
+        let stats = scan_dir(&dir, &ScanOptions::default()).unwrap();
+        assert_eq!((stats.files, stats.hard_links), (3, 0));
+        let stats = scan_dir(&dir, &ScanOptions { dedupe_hard_links: true, ..ScanOptions::default() }).unwrap();
+        assert_eq!((stats.files, stats.hard_links, stats.bytes.apparent), (1, 2, 4));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Counting from the entry types of the listing finds what reading every entry's metadata does
+    #[cfg(unix)]
+    #[test]
+    fn typed_listing_counts() {
+        let dir = scratch_dir("typed");
+        fs::create_dir_all(dir.join("a").join("b")).unwrap();
+        fs::write(dir.join("a").join("b").join("file"), b"").unwrap();
+        fs::write(dir.join("top"), b"").unwrap();
+        std::os::unix::fs::symlink(dir.join("top"), dir.join("file_link")).unwrap();
+        std::os::unix::fs::symlink(dir.join("a"), dir.join("dir_link")).unwrap();
+        let _socket = std::os::unix::net::UnixListener::bind(dir.join("socket")).unwrap();
+        let full = scan_dir(&dir, &ScanOptions::default()).unwrap().files;
+        assert_eq!(full, 3);
+        assert_eq!(count_files(&dir).unwrap(), full);
+        assert_eq!(report::build_snapshot(&dir).files, Some(full));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn odd_file_names() {
+        let dir = scratch_dir("names");
+        for name in ["no_extension", ".hidden", "trailing.", "multi.part.tar.gz", "ünïcödé.TXT", " space "] {
+            fs::write(dir.join(name), b"").unwrap();
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+            fs::write(dir.join(std::ffi::OsStr::from_bytes(b"invalid\xff\xfe.bin")), b"").unwrap();
+        }
+        let stats = scan_dir(&dir, &ScanOptions { collect_extensions: true, ..ScanOptions::default() }).unwrap();
+        let extensions = stats.extensions.unwrap();
+        assert_eq!(stats.files, extensions.values().sum::<usize>());
+        scan_everything(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// procfs has zero-size files, entries that vanish mid-scan and unreadable entries.
+    /// (`/proc/self` itself is avoided: its `root` and `cwd` links lead to the whole disk.)
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn procfs() {
+        for dir in ["/proc/sys", "/proc/self/fdinfo", "/proc/self/attr", "/proc/self/ns", "/proc/bus"] {
+            scan_everything(Path::new(dir));
+        }
+    }
+
+    /// FUSE filesystems can fail any call. With a fake mount table holding a
+    /// live FUSE mount and one whose daemon is gone, the scan counts around
+    /// the dead one and reports it as skipped.
+    #[test]
+    fn fuse_mounts() {
+        use crate::{ mountinfo::{ self, MountEntry }, vfs::EntryMetadata };
+
+        /// Answers like the kernel for a FUSE mount without its daemon: every
+        /// call below `dead` fails with "Transport endpoint is not connected"
+        struct FuseFs {
+            tree: MemoryFs,
+            mounts: Vec<MountEntry>,
+            dead: PathBuf,
+        }
+
+        impl FuseFs {
+            fn check(&self, path: &Path) -> io::Result<()> {
+                match mountinfo::mount_for(&self.mounts, path) {
+                    Some(mount) if mount.fs_type.starts_with("fuse") && mount.mount_point == self.dead =>
+                        Err(io::Error::new(io::ErrorKind::NotConnected, "Transport endpoint is not connected")),
+                    _ => Ok(()),
+                }
+            }
+        }
+
+        impl FileSystem for FuseFs {
+            fn read_dir(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+                self.check(dir)?;
+                self.tree.read_dir(dir)
+            }
+
+            fn metadata(&self, path: &Path) -> io::Result<EntryMetadata> {
+                self.check(path)?;
+                self.tree.metadata(path)
+            }
+
+            fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+                self.check(path)?;
+                self.tree.canonicalize(path)
+            }
+        }
+
+        let mounts = mountinfo::parse_mountinfo(
+            "22 1 8:1 / / rw,relatime - ext4 /dev/sda1 rw\n\
+             40 22 0:45 / /data/cloud rw,nosuid,nodev - fuse.rclone remote: rw,user_id=1000\n\
+             41 22 0:46 / /data/ssh\\040box rw,nosuid,nodev - fuse.sshfs box:/ rw,user_id=1000\n"
+        );
+        assert_eq!(mounts.iter().filter(|mount| mount.fs_type.starts_with("fuse")).count(), 2);
+        let mut tree = MemoryFs::default();
+        tree.file("/data/local.txt", 1)
+            .file("/data/cloud/a", 1)
+            .file("/data/cloud/sub/b", 1)
+            .file("/data/ssh box/c", 1);
+        let filesystem = FuseFs { tree, mounts, dead: PathBuf::from("/data/ssh box") };
+
+        let stats = scan_dir_in(&filesystem, Path::new("/data"), &ScanOptions::default(), None).unwrap();
+        assert_eq!(stats.files, 3);
+        assert_eq!(stats.skipped.count, 1);
+        assert_eq!(stats.skipped.entries[0].path, Path::new("/data/ssh box"));
+        assert_eq!(stats.skipped.entries[0].reason, SkipReason::Io);
+
+        let dead = scan_dir_in(&filesystem, Path::new("/data/ssh box"), &ScanOptions::default(), None).unwrap();
+        assert_eq!((dead.files, dead.skipped.count), (0, 1));
+    }
+}
@@ -0,0 +1,70 @@
+use crossterm::{
+    cursor,
+    event::{ DisableMouseCapture, EnableMouseCapture },
+    execute,
+    terminal::{ disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen },
+};
+use std::{ io, panic, process, sync::atomic::{ AtomicBool, Ordering } };
+
+/// Whether the terminal is in TUI mode, so restoring it twice is harmless
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// Switch to raw mode, the alternate screen and mouse capture
+pub fn enter() -> io::Result<()> {
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture)?;
+    ACTIVE.store(true, Ordering::SeqCst);
+    Ok(())
+}
+
+/// Give the terminal back to the shell. Safe to call more than once and from any thread.
+pub fn restore() -> io::Result<()> {
+    if !ACTIVE.swap(false, Ordering::SeqCst) {
+        return Ok(());
+    }
+    disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture, cursor::Show)
+}
+
+/// Restores the terminal when dropped, so early returns on errors don't leave it in raw mode
+pub struct Guard;
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        let _ = restore();
+    }
+}
+
+/// Restore the terminal before the panic message is printed, then exit:
+/// a panic on a worker thread would otherwise leave a half-working UI behind
+pub fn install_panic_hook() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(
+        Box::new(move |info| {
+            let _ = restore();
+            default_hook(info);
+            process::exit(101);
+        })
+    );
+}
+
+/// Restore the terminal and exit on SIGINT, SIGTERM and SIGHUP.
+/// Worker threads only read the disk, so they are simply abandoned.
+#[cfg(unix)]
+pub fn install_signal_handlers() -> io::Result<()> {
+    use signal_hook::{ consts::{ SIGHUP, SIGINT, SIGTERM }, iterator::Signals };
+
+    let mut signals = Signals::new([SIGINT, SIGTERM, SIGHUP])?;
+    std::thread::spawn(move || {
+        if let Some(signal) = signals.forever().next() {
+            let _ = restore();
+            process::exit(128 + signal);
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn install_signal_handlers() -> io::Result<()> {
+    Ok(())
+}
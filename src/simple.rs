@@ -0,0 +1,127 @@
+use crate::{ numbers::NumberFormat, report::{ self, ReportNode } };
+use std::{ io::{ self, BufRead, Write }, path::Path };
+
+const HELP: &str = "Type the number of a directory to open it, 0 for the parent directory, \
+r to count again, l to list again, q to quit.";
+
+/// Browse `start_dir` (or the snapshot) as plain numbered lines read top to bottom,
+/// for braille displays and screen readers: no alternate screen, no cursor
+/// movement, no colors, and a prompt taking the number of the entry to open.
+pub fn run(start_dir: &Path, snapshot: Option<&ReportNode>) -> Result<(), String> {
+    let numbers = NumberFormat::from_env(false);
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    let mut current = start_dir.to_path_buf();
+    let mut listing = list(&current, start_dir, snapshot)?;
+    print_listing(&current, &listing, current != start_dir, &numbers);
+    println!("{}", HELP);
+    loop {
+        print!("> ");
+        io::stdout().flush().unwrap_or(());
+        let line = match lines.next() {
+            Some(line) => line.map_err(|e| format!("Cannot read the answer: {}", e))?,
+            None => {
+                return Ok(()); // End of input
+            }
+        };
+        let answer = line.trim();
+        let next = match answer {
+            "" => {
+                continue;
+            }
+            "q" | "quit" => {
+                return Ok(());
+            }
+            "?" | "h" | "help" => {
+                println!("{}", HELP);
+                continue;
+            }
+            "l" => {
+                print_listing(&current, &listing, current != start_dir, &numbers);
+                continue;
+            }
+            "r" => current.clone(),
+            "0" | ".." if current == start_dir => {
+                println!("Already at {}, the top.", start_dir.display());
+                continue;
+            }
+            "0" | ".." => current.parent().map_or_else(|| current.clone(), Path::to_path_buf),
+            _ =>
+                match answer.parse::<usize>().ok().and_then(|number| listing.children.get(number.wrapping_sub(1))) {
+                    Some(entry) if entry.is_dir => current.join(&entry.name),
+                    Some(entry) => {
+                        println!("{} is a file.", entry.name);
+                        continue;
+                    }
+                    None => {
+                        println!("No entry {}. {}", answer, HELP);
+                        continue;
+                    }
+                }
+        };
+        match list(&next, start_dir, snapshot) {
+            Ok(entries) => {
+                current = next;
+                listing = entries;
+                print_listing(&current, &listing, current != start_dir, &numbers);
+            }
+            Err(e) => println!("{}", e),
+        }
+    }
+}
+
+/// `dir` with its entries: directories by count, largest first, then files by name
+fn list(dir: &Path, start_dir: &Path, snapshot: Option<&ReportNode>) -> Result<ReportNode, String> {
+    let mut node = match snapshot {
+        Some(root) => {
+            let relative = dir.strip_prefix(start_dir).unwrap_or(Path::new(""));
+            root.find(relative)
+                .cloned()
+                .ok_or_else(|| format!("{} is not in the snapshot.", dir.display()))?
+        }
+        None => {
+            if !dir.is_dir() {
+                return Err(format!("Cannot open {}: not a directory.", dir.display()));
+            }
+            println!("Counting {} ...", dir.display());
+            report::build_report(dir, 1, false)
+        }
+    };
+    node.children.sort_by(|a, b| {
+        b.is_dir
+            .cmp(&a.is_dir)
+            .then_with(|| b.files.cmp(&a.files))
+            .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+    });
+    Ok(node)
+}
+
+/// One line for the directory, then one numbered line per entry, each saying
+/// what it is in words rather than with symbols
+fn print_listing(dir: &Path, listing: &ReportNode, has_parent: bool, numbers: &NumberFormat) {
+    let amount = |count: usize, singular: &str, plural: &str| {
+        format!("{} {}", numbers.count(count as u64), if count == 1 { singular } else { plural })
+    };
+    let files_text = |files: Option<usize>| {
+        files.map_or_else(|| String::from("not counted"), |files| amount(files, "file", "files"))
+    };
+    let entries = &listing.children;
+    let dirs = entries.iter().filter(|entry| entry.is_dir).count();
+    println!();
+    println!(
+        "{}: {}, {} and {} directly inside.",
+        dir.display(),
+        files_text(listing.files),
+        amount(dirs, "directory", "directories"),
+        amount(entries.len() - dirs, "file", "files")
+    );
+    if has_parent {
+        println!("0. Parent directory");
+    }
+    for (index, entry) in entries.iter().enumerate() {
+        match entry.is_dir {
+            true => println!("{}. {}, directory, {}", index + 1, entry.name, files_text(entry.files)),
+            false => println!("{}. {}, file", index + 1, entry.name),
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use crate::{ scan::{ SkipReason, Skipped }, theme::Theme };
+use ratatui::{ style::{ Modifier, Style }, text::{ Span, Spans } };
+use std::{ fs, path::PathBuf, sync::Arc };
+#[cfg(unix)]
+use crate::report::ReportNode;
+#[cfg(unix)]
+use std::{ path::Path, process::{ Command, Stdio } };
+
+/// Entries a directory's count left out, with the reason for each (`E`)
+pub struct SkippedView {
+    pub dir: PathBuf,
+    pub skipped: Option<Arc<Skipped>>, // None until the directory is counted
+    pub scroll: u16,
+    pub elevated: Vec<String>, // Outcome of the last retry with sudo
+}
+
+impl SkippedView {
+    /// Directories whose count was refused, which a count as root could add
+    pub fn denied_dirs(&self) -> Vec<PathBuf> {
+        let Some(skipped) = &self.skipped else {
+            return Vec::new();
+        };
+        skipped.entries
+            .iter()
+            .filter(|entry| entry.reason == SkipReason::PermissionDenied)
+            .filter(|entry| fs::symlink_metadata(&entry.path).is_ok_and(|metadata| metadata.is_dir()))
+            .map(|entry| entry.path.clone())
+            .collect()
+    }
+
+    pub fn lines(&self, theme: &Theme, can_elevate: bool) -> Vec<Spans<'static>> {
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        let mut lines = Vec::new();
+        match &self.skipped {
+            None => {
+                lines.push(Spans::from(format!("{} has not been counted yet", self.dir.display())));
+            }
+            Some(skipped) if skipped.count == 0 => {
+                lines.push(Spans::from(format!("Nothing was skipped while counting {}", self.dir.display())));
+            }
+            Some(skipped) => {
+                lines.push(
+                    Spans::from(
+                        vec![
+                            Span::styled(format!("{} entries could not be read", skipped.count), bold),
+                            Span::raw(format!(" while counting {}; the count may be short", self.dir.display()))
+                        ]
+                    )
+                );
+                let reasons = [SkipReason::PermissionDenied, SkipReason::SymlinkLoop, SkipReason::Io]
+                    .into_iter()
+                    .filter_map(|reason| {
+                        let shown = skipped.entries
+                            .iter()
+                            .filter(|entry| entry.reason == reason)
+                            .count();
+                        (shown > 0).then(|| format!("{}: {}", reason.label(), shown))
+                    })
+                    .collect::<Vec<_>>();
+                lines.push(Spans::from(reasons.join(", ")));
+                lines.push(Spans::from(""));
+                for entry in &skipped.entries {
+                    let shown = entry.path.strip_prefix(&self.dir).unwrap_or(&entry.path);
+                    let shown = match shown.as_os_str().is_empty() {
+                        true => String::from("."),
+                        false => shown.display().to_string(),
+                    };
+                    lines.push(
+                        Spans::from(
+                            vec![
+                                Span::styled(format!("  {:<18}", entry.reason.label()), theme.marked),
+                                Span::styled(shown, theme.file),
+                                Span::raw(format!("  ({})", entry.message))
+                            ]
+                        )
+                    );
+                }
+                if skipped.count > skipped.entries.len() {
+                    lines.push(Spans::from(format!("  ... and {} more", skipped.count - skipped.entries.len())));
+                }
+            }
+        }
+        if !self.elevated.is_empty() {
+            lines.push(Spans::from(""));
+            for outcome in &self.elevated {
+                lines.push(Spans::from(Span::styled(outcome.clone(), theme.marked)));
+            }
+        }
+        let denied = can_elevate && !self.denied_dirs().is_empty();
+        if denied {
+            lines.push(Spans::from(""));
+            lines.push(Spans::from("s counts the refused directories as root with sudo and adds them to the counts,"));
+            lines.push(Spans::from(format!("or quit and count everything as root: sudo file_counter {}", self.dir.display())));
+        }
+        lines.push(Spans::from(""));
+        let mut keys = vec![Span::styled("↑/↓", theme.key), Span::raw(" - Scroll   ")];
+        if denied {
+            keys.extend([Span::styled("s", theme.key), Span::raw(" - Count with sudo   ")]);
+        }
+        keys.extend([Span::styled("Esc", theme.key), Span::raw(" - Close")]);
+        lines.push(Spans::from(keys));
+        lines
+    }
+}
+
+/// Count each of `dirs` with this program run as root through `sudo`, which
+/// asks for a password on the terminal if it needs one
+#[cfg(unix)]
+pub fn count_elevated(dirs: &[PathBuf]) -> Vec<(PathBuf, Result<usize, String>)> {
+    let exe = match std::env::current_exe() {
+        Ok(exe) => exe,
+        Err(e) => {
+            let error = format!("Cannot locate the running executable: {}", e);
+            return dirs
+                .iter()
+                .map(|dir| (dir.clone(), Err(error.clone())))
+                .collect();
+        }
+    };
+    dirs.iter()
+        .map(|dir| (dir.clone(), count_as_root(&exe, dir)))
+        .collect()
+}
+
+#[cfg(not(unix))]
+pub fn count_elevated(dirs: &[PathBuf]) -> Vec<(PathBuf, Result<usize, String>)> {
+    dirs.iter()
+        .map(|dir| (dir.clone(), Err(String::from("Run file_counter as administrator to count this directory"))))
+        .collect()
+}
+
+/// Recursive file count of `dir` from a headless run of `exe` under `sudo`
+#[cfg(unix)]
+fn count_as_root(exe: &Path, dir: &Path) -> Result<usize, String> {
+    let output = Command::new("sudo")
+        .arg("--")
+        .arg(exe)
+        .args(["--json", "--depth", "0"])
+        .arg(dir)
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|e| format!("Cannot run sudo: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("sudo failed: {}", output.status));
+    }
+    let report = ReportNode::parse(&String::from_utf8_lossy(&output.stdout))?;
+    report.files.ok_or_else(|| String::from("no count in the report"))
+}
@@ -0,0 +1,143 @@
+use crate::{ json::{ self, Value }, report::ReportNode };
+use std::{ fs, path::{ Path, PathBuf } };
+
+/// Version of the state file format, bumped on incompatible changes
+const STATE_VERSION: usize = 1;
+
+/// Investigation state handed over to another machine or user
+/// (`--export-state` / `--import-state`), or kept for the next launch
+pub struct AppState {
+    pub home_dir: Option<PathBuf>, // Top of the browsed tree, if not the current directory
+    pub current_dir: PathBuf,
+    pub selected: Option<PathBuf>,
+    pub sort_by_growth: bool,
+    pub sort_by_newest: bool,
+    pub dim_filtered: bool,
+    pub marked: Vec<PathBuf>,
+    pub excluded: Vec<PathBuf>, // Hidden by the cleanup wizard
+    pub pinned: Vec<PathBuf>, // Counts kept in the cache however full it gets
+    pub baseline: Option<ReportNode>, // The --diff snapshot, embedded
+}
+
+impl AppState {
+    /// Fails on a path that is not UTF-8, which JSON strings cannot hold as is
+    pub fn to_json(&self) -> Result<Value, String> {
+        let text = |path: &Path| {
+            path.to_str()
+                .map(Value::from)
+                .ok_or_else(|| format!("{} is not valid UTF-8", path.display()))
+        };
+        let paths = |paths: &[PathBuf]| -> Result<Value, String> {
+            Ok(Value::Array(paths.iter().map(|p| text(p)).collect::<Result<_, _>>()?))
+        };
+        let path = |path: &Option<PathBuf>| path.as_deref().map_or(Ok(Value::Null), text);
+        Ok(Value::Object(
+            vec![
+                (String::from("version"), Value::from(STATE_VERSION)),
+                (String::from("home_dir"), path(&self.home_dir)?),
+                (String::from("current_dir"), text(&self.current_dir)?),
+                (String::from("selected"), path(&self.selected)?),
+                (String::from("sort_by_growth"), Value::from(self.sort_by_growth)),
+                (String::from("sort_by_newest"), Value::from(self.sort_by_newest)),
+                (String::from("dim_filtered"), Value::from(self.dim_filtered)),
+                (String::from("marked"), paths(&self.marked)?),
+                (String::from("excluded"), paths(&self.excluded)?),
+                (String::from("pinned"), paths(&self.pinned)?),
+                (String::from("baseline"), self.baseline.as_ref().map(ReportNode::to_json).unwrap_or(Value::Null))
+            ]
+        ))
+    }
+
+    pub fn from_json(value: &Value) -> Result<AppState, String> {
+        let version = value.get("version").and_then(Value::as_u64).ok_or("not a file_counter state file")?;
+        if version != (STATE_VERSION as u64) {
+            return Err(format!("unsupported state file version {}", version));
+        }
+        let current_dir = value
+            .get("current_dir")
+            .and_then(Value::as_str)
+            .map(PathBuf::from)
+            .ok_or("state file without current_dir")?;
+        // Keys added after the first version are optional
+        let path = |key: &str| value.get(key).and_then(Value::as_str).map(PathBuf::from);
+        let flag = |key: &str| value.get(key).and_then(Value::as_bool).unwrap_or(false);
+        let paths = |key: &str| -> Vec<PathBuf> {
+            value
+                .get(key)
+                .and_then(Value::as_array)
+                .unwrap_or(&[])
+                .iter()
+                .filter_map(Value::as_str)
+                .map(PathBuf::from)
+                .collect()
+        };
+        let baseline = match value.get("baseline") {
+            Some(Value::Null) | None => None,
+            Some(baseline) => Some(ReportNode::from_json(baseline)?),
+        };
+        Ok(AppState {
+            home_dir: path("home_dir"),
+            current_dir,
+            selected: path("selected"),
+            sort_by_growth: flag("sort_by_growth"),
+            sort_by_newest: flag("sort_by_newest"),
+            dim_filtered: flag("dim_filtered"),
+            marked: paths("marked"),
+            excluded: paths("excluded"),
+            pinned: paths("pinned"),
+            baseline,
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<AppState, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+        let value = json::parse(&text).map_err(|e| format!("Cannot load {}: {}", path.display(), e))?;
+        AppState::from_json(&value).map_err(|e| format!("Cannot load {}: {}", path.display(), e))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let value = self.to_json().map_err(|e| format!("Cannot save {}: {}", path.display(), e))?;
+        fs::write(path, format!("{}\n", value.to_json())).map_err(|e|
+            format!("Cannot write {}: {}", path.display(), e)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state() -> AppState {
+        AppState {
+            home_dir: None,
+            current_dir: PathBuf::from("/data"),
+            selected: Some(PathBuf::from("/data/logs")),
+            sort_by_growth: false,
+            sort_by_newest: true,
+            dim_filtered: false,
+            marked: vec![PathBuf::from("/data/logs/old.log")],
+            excluded: Vec::new(),
+            pinned: vec![PathBuf::from("/data/cache"), PathBuf::from("/data/logs")],
+            baseline: None,
+        }
+    }
+
+    #[test]
+    fn round_trips_pinned_counts() {
+        let text = state().to_json().unwrap().to_json();
+        let loaded = AppState::from_json(&json::parse(&text).unwrap()).unwrap();
+        assert_eq!(loaded.pinned, state().pinned);
+        assert_eq!(loaded.marked, state().marked);
+        assert!(loaded.sort_by_newest);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn refuses_paths_that_are_not_utf8() {
+        use std::{ ffi::OsStr, os::unix::ffi::OsStrExt };
+
+        let mut state = state();
+        state.pinned.push(PathBuf::from(OsStr::from_bytes(b"/data/bad\xff")));
+        assert!(state.to_json().unwrap_err().contains("not valid UTF-8"));
+    }
+}
@@ -0,0 +1,85 @@
+use crate::{ count_cache::CacheStats, format, numbers::NumberFormat };
+use std::{ collections::BTreeSet, path::{ Path, PathBuf } };
+
+/// Key findings of a TUI session, printed to stdout on quit with `--summary`
+/// so they survive the alternate screen
+#[derive(Default)]
+pub struct SessionSummary {
+    visited: BTreeSet<PathBuf>,
+    largest: Option<(PathBuf, usize)>, // Directory with the highest exact count seen
+    actions: Vec<String>,
+    deleted_entries: usize,
+    deleted_files: usize,
+    deleted_bytes: u64, // Only plain files have a known size
+    cache: Option<CacheStats>, // Use of the count cache, taken on quit
+}
+
+impl SessionSummary {
+    pub fn visit(&mut self, dir: &Path) {
+        self.visited.insert(dir.to_path_buf());
+    }
+
+    pub fn counted(&mut self, dir: &Path, files: usize) {
+        if self.largest.as_ref().is_none_or(|(_, largest)| files > *largest) {
+            self.largest = Some((dir.to_path_buf(), files));
+        }
+    }
+
+    pub fn action(&mut self, description: String) {
+        self.actions.push(description);
+    }
+
+    pub fn deleted(&mut self, files: usize, bytes: Option<u64>) {
+        self.deleted_entries += 1;
+        self.deleted_files = self.deleted_files.saturating_add(files);
+        self.deleted_bytes = self.deleted_bytes.saturating_add(bytes.unwrap_or(0));
+    }
+
+    pub fn cache_used(&mut self, stats: CacheStats) {
+        self.cache = Some(stats);
+    }
+
+    /// Plain-text report, one finding per line
+    pub fn render(&self, numbers: &NumberFormat) -> String {
+        let mut lines = vec![String::from("file_counter session summary")];
+        lines.push(format!("Directories visited: {}", self.visited.len()));
+        for dir in &self.visited {
+            lines.push(format!("  {}", dir.display()));
+        }
+        if let Some((dir, files)) = &self.largest {
+            lines.push(format!("Largest directory: {} ({} files)", dir.display(), numbers.count(*files as u64)));
+        }
+        if self.deleted_entries > 0 {
+            let mut line = format!(
+                "Moved to trash: {} entries, {} files",
+                self.deleted_entries,
+                numbers.count(self.deleted_files as u64)
+            );
+            if self.deleted_bytes > 0 {
+                line.push_str(&format!(", {} in plain files", format::human_bytes(self.deleted_bytes)));
+            }
+            lines.push(line);
+        }
+        if let Some(cache) = &self.cache {
+            let mut line = format!(
+                "Count cache: {} entries, about {}",
+                numbers.count(cache.entries as u64),
+                format::human_bytes(cache.bytes)
+            );
+            if let Some(rate) = cache.hit_rate() {
+                line.push_str(&format!(", {:.0}% of lookups answered", rate * 100.0));
+            }
+            if cache.evicted > 0 {
+                line.push_str(&format!(", {} evicted", numbers.count(cache.evicted)));
+            }
+            lines.push(line);
+        }
+        if !self.actions.is_empty() {
+            lines.push(String::from("Actions:"));
+            for action in &self.actions {
+                lines.push(format!("  {}", action));
+            }
+        }
+        lines.join("\n")
+    }
+}
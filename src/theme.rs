@@ -0,0 +1,295 @@
+use crate::config::ConfigFile;
+use ratatui::style::{ Color, Modifier, Style };
+
+/// Names of the built-in color schemes
+pub const SCHEME_NAMES: &[&str] = &["default", "light", "solarized", "monochrome"];
+
+/// Styles used throughout the UI
+#[derive(Clone)]
+pub struct Theme {
+    pub selection: Style,
+    pub header: Style,
+    pub header_row: Style,
+    pub dir: Style,
+    pub file: Style,
+    pub parent: Style,
+    pub key: Style,
+    pub approximate: Style, // Index-based estimates
+    pub marked: Style, // Entries marked for batch operations
+    pub stale: Style, // Rows whose count is being recomputed
+    pub zebra: Style, // Every other row, when `zebra_rows` is on
+    pub filtered: Style, // Rows the active filter excludes, when they are dimmed rather than hidden
+    pub focused: Style, // Border of the pane receiving keys
+    pub unfocused: Style, // Borders of the other panes
+    pub differs: Style, // Entries whose count differs from the other pane, when comparing
+    pub junk: Style, // Recognized build output and caches (node_modules, target, ...)
+    pub mount: Style, // Filesystem type badge of mount points
+    pub zebra_rows: bool,
+    pub heat_rows: bool, // Tint rows from green to red by their share of the total
+    pub spinner_frames: Vec<String>,
+    pub ascii: bool, // Plain characters only, for serial consoles and screen readers: `#` bars, no icons
+}
+
+fn default_spinner() -> Vec<String> {
+    ["   ", ".  ", ".. ", "..."].iter().map(|s| s.to_string()).collect()
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            selection: Style::default()
+                .bg(Color::LightGreen)
+                .fg(Color::Black)
+                .add_modifier(Modifier::BOLD),
+            header: Style::default().fg(Color::Yellow),
+            header_row: Style::default().bg(Color::DarkGray),
+            dir: Style::default().fg(Color::Blue),
+            file: Style::default().fg(Color::Gray),
+            parent: Style::default().fg(Color::Green),
+            key: Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+            approximate: Style::default().fg(Color::DarkGray).add_modifier(Modifier::ITALIC),
+            marked: Style::default().fg(Color::LightYellow).add_modifier(Modifier::BOLD),
+            stale: Style::default().add_modifier(Modifier::DIM),
+            zebra: Style::default().bg(Color::Indexed(235)),
+            filtered: Style::default().fg(Color::DarkGray).add_modifier(Modifier::CROSSED_OUT),
+            focused: Style::default(),
+            unfocused: Style::default().add_modifier(Modifier::DIM),
+            differs: Style::default().fg(Color::LightRed).add_modifier(Modifier::BOLD),
+            junk: Style::default().fg(Color::Magenta),
+            mount: Style::default().fg(Color::Cyan),
+            zebra_rows: false,
+            heat_rows: true,
+            spinner_frames: default_spinner(),
+            ascii: false,
+        }
+    }
+}
+
+impl Theme {
+    /// Look up a built-in scheme by name
+    pub fn builtin(name: &str) -> Option<Theme> {
+        match name {
+            "default" => Some(Theme::default()),
+            "light" =>
+                Some(Theme {
+                    selection: Style::default()
+                        .bg(Color::Blue)
+                        .fg(Color::White)
+                        .add_modifier(Modifier::BOLD),
+                    header: Style::default().fg(Color::Black).add_modifier(Modifier::BOLD),
+                    header_row: Style::default().bg(Color::Gray),
+                    dir: Style::default().fg(Color::Blue),
+                    file: Style::default().fg(Color::DarkGray),
+                    parent: Style::default().fg(Color::Green),
+                    key: Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD),
+                    approximate: Style::default().fg(Color::Gray).add_modifier(Modifier::ITALIC),
+                    marked: Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                    stale: Style::default().add_modifier(Modifier::DIM),
+                    zebra: Style::default().bg(Color::Indexed(254)),
+                    filtered: Style::default().fg(Color::Gray).add_modifier(Modifier::CROSSED_OUT),
+                    focused: Style::default(),
+                    unfocused: Style::default().add_modifier(Modifier::DIM),
+                    differs: Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                    junk: Style::default().fg(Color::Magenta),
+                    mount: Style::default().fg(Color::Cyan),
+                    zebra_rows: false,
+                    heat_rows: true,
+                    spinner_frames: default_spinner(),
+                    ascii: false,
+                }),
+            "solarized" =>
+                Some(Theme {
+                    selection: Style::default()
+                        .bg(Color::Rgb(0x26, 0x8b, 0xd2))
+                        .fg(Color::Rgb(0xfd, 0xf6, 0xe3))
+                        .add_modifier(Modifier::BOLD),
+                    header: Style::default().fg(Color::Rgb(0xb5, 0x89, 0x00)),
+                    header_row: Style::default().bg(Color::Rgb(0x07, 0x36, 0x42)),
+                    dir: Style::default().fg(Color::Rgb(0x26, 0x8b, 0xd2)),
+                    file: Style::default().fg(Color::Rgb(0x83, 0x94, 0x96)),
+                    parent: Style::default().fg(Color::Rgb(0x85, 0x99, 0x00)),
+                    key: Style::default()
+                        .fg(Color::Rgb(0x2a, 0xa1, 0x98))
+                        .add_modifier(Modifier::BOLD),
+                    approximate: Style::default()
+                        .fg(Color::Rgb(0x58, 0x6e, 0x75))
+                        .add_modifier(Modifier::ITALIC),
+                    marked: Style::default()
+                        .fg(Color::Rgb(0xd3, 0x36, 0x82))
+                        .add_modifier(Modifier::BOLD),
+                    stale: Style::default().add_modifier(Modifier::DIM),
+                    zebra: Style::default().bg(Color::Rgb(0x07, 0x36, 0x42)),
+                    filtered: Style::default()
+                        .fg(Color::Rgb(0x58, 0x6e, 0x75))
+                        .add_modifier(Modifier::CROSSED_OUT),
+                    focused: Style::default().fg(Color::Rgb(0x26, 0x8b, 0xd2)),
+                    unfocused: Style::default().fg(Color::Rgb(0x58, 0x6e, 0x75)),
+                    differs: Style::default()
+                        .fg(Color::Rgb(0xdc, 0x32, 0x2f))
+                        .add_modifier(Modifier::BOLD),
+                    junk: Style::default().fg(Color::Rgb(0x6c, 0x71, 0xc4)),
+                    mount: Style::default().fg(Color::Rgb(0x2a, 0xa1, 0x98)),
+                    zebra_rows: false,
+                    heat_rows: true,
+                    spinner_frames: default_spinner(),
+                    ascii: false,
+                }),
+            "monochrome" =>
+                Some(Theme {
+                    selection: Style::default().add_modifier(Modifier::REVERSED | Modifier::BOLD),
+                    header: Style::default().add_modifier(Modifier::BOLD),
+                    header_row: Style::default().add_modifier(Modifier::UNDERLINED),
+                    dir: Style::default(),
+                    file: Style::default(),
+                    parent: Style::default().add_modifier(Modifier::BOLD),
+                    key: Style::default().add_modifier(Modifier::BOLD),
+                    approximate: Style::default().add_modifier(Modifier::DIM),
+                    marked: Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+                    stale: Style::default().add_modifier(Modifier::DIM),
+                    zebra: Style::default().add_modifier(Modifier::DIM),
+                    filtered: Style::default().add_modifier(Modifier::DIM | Modifier::CROSSED_OUT),
+                    focused: Style::default().add_modifier(Modifier::BOLD),
+                    unfocused: Style::default().add_modifier(Modifier::DIM),
+                    differs: Style::default().add_modifier(Modifier::BOLD | Modifier::ITALIC),
+                    junk: Style::default().add_modifier(Modifier::ITALIC),
+                    mount: Style::default().add_modifier(Modifier::UNDERLINED),
+                    zebra_rows: false,
+                    heat_rows: false,
+                    spinner_frames: default_spinner(),
+                    ascii: false,
+                }),
+            _ => None,
+        }
+    }
+
+    /// The monochrome scheme in place of this one, for `NO_COLOR`, which keeps the
+    /// configured spinner, or with `ascii` for `--no-color`, which keeps nothing fancy
+    pub fn without_colors(&self, ascii: bool) -> Theme {
+        let mut plain = Theme::builtin("monochrome").unwrap_or_default();
+        plain.zebra_rows = self.zebra_rows;
+        plain.ascii = ascii;
+        if !ascii {
+            plain.spinner_frames = self.spinner_frames.clone();
+        }
+        plain
+    }
+
+    /// Cursor at the end of text being typed
+    pub fn cursor(&self) -> &'static str {
+        if self.ascii { "_" } else { "▏" }
+    }
+
+    /// Spinner frame for an ever-increasing animation counter
+    pub fn spinner(&self, index: usize) -> &str {
+        match self.spinner_frames.len() {
+            0 => "",
+            len => &self.spinner_frames[index % len],
+        }
+    }
+
+    /// Row tint for a `share` of the current directory's total between 0 and 1,
+    /// from green through yellow to red
+    pub fn heat(&self, share: f64) -> Style {
+        if !self.heat_rows {
+            return Style::default();
+        }
+        const STOPS: [(f64, f64, f64); 3] = [(58.0, 168.0, 58.0), (208.0, 160.0, 0.0), (215.0, 48.0, 48.0)];
+        let position = share.clamp(0.0, 1.0) * 2.0;
+        let (from, to) = if position < 1.0 { (STOPS[0], STOPS[1]) } else { (STOPS[1], STOPS[2]) };
+        let t = if position < 1.0 { position } else { position - 1.0 };
+        let mix = |a: f64, b: f64| (a + (b - a) * t).round() as u8;
+        Style::default().fg(Color::Rgb(mix(from.0, to.0), mix(from.1, to.1), mix(from.2, to.2)))
+    }
+
+    /// Build the theme from the `[theme]` section of the config file.
+    ///
+    /// `scheme` selects a built-in scheme; `<element>_fg` / `<element>_bg` keys
+    /// override individual colors, `zebra` shades every other row, `heat` turns
+    /// the share tint on or off and `spinner` replaces the spinner frames.
+    pub fn from_config(config: &ConfigFile) -> Result<Theme, String> {
+        let scheme = config.get_str("theme.scheme").unwrap_or("default");
+        let mut theme = Theme::builtin(scheme).ok_or_else(||
+            format!("Unknown theme scheme: {} (available: {})", scheme, SCHEME_NAMES.join(", "))
+        )?;
+
+        let elements: [(&str, &mut Style); 17] = [
+            ("selection", &mut theme.selection),
+            ("header", &mut theme.header),
+            ("header_row", &mut theme.header_row),
+            ("dir", &mut theme.dir),
+            ("file", &mut theme.file),
+            ("parent", &mut theme.parent),
+            ("key", &mut theme.key),
+            ("approximate", &mut theme.approximate),
+            ("marked", &mut theme.marked),
+            ("stale", &mut theme.stale),
+            ("zebra", &mut theme.zebra),
+            ("filtered", &mut theme.filtered),
+            ("focused", &mut theme.focused),
+            ("unfocused", &mut theme.unfocused),
+            ("differs", &mut theme.differs),
+            ("junk", &mut theme.junk),
+            ("mount", &mut theme.mount),
+        ];
+        for (name, style) in elements {
+            if let Some(value) = config.get_str(&format!("theme.{}_fg", name)) {
+                *style = style.fg(parse_color(value)?);
+            }
+            if let Some(value) = config.get_str(&format!("theme.{}_bg", name)) {
+                *style = style.bg(parse_color(value)?);
+            }
+        }
+
+        if let Some(zebra_rows) = config.get_bool("theme.zebra") {
+            theme.zebra_rows = zebra_rows;
+        }
+
+        if let Some(heat_rows) = config.get_bool("theme.heat") {
+            theme.heat_rows = heat_rows;
+        }
+
+        if let Some(frames) = config.get_str_list("theme.spinner") {
+            if frames.is_empty() {
+                return Err(String::from("theme.spinner must not be empty"));
+            }
+            theme.spinner_frames = frames;
+        }
+
+        Ok(theme)
+    }
+}
+
+/// Parse a color name (`red`, `lightblue`, ...) or a `#rrggbb` hex value
+pub fn parse_color(value: &str) -> Result<Color, String> {
+    let lower = value.to_lowercase().replace(['_', '-', ' '], "");
+    let color = match lower.as_str() {
+        "reset" | "none" => Color::Reset,
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "gray" | "grey" => Color::Gray,
+        "darkgray" | "darkgrey" => Color::DarkGray,
+        "lightred" => Color::LightRed,
+        "lightgreen" => Color::LightGreen,
+        "lightyellow" => Color::LightYellow,
+        "lightblue" => Color::LightBlue,
+        "lightmagenta" => Color::LightMagenta,
+        "lightcyan" => Color::LightCyan,
+        "white" => Color::White,
+        _ => {
+            let hex = lower
+                .strip_prefix('#')
+                .filter(|hex| hex.len() == 6)
+                .ok_or_else(|| format!("Invalid color: {}", value))?;
+            let channel = |i: usize| {
+                u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| format!("Invalid color: {}", value))
+            };
+            Color::Rgb(channel(0)?, channel(2)?, channel(4)?)
+        }
+    };
+    Ok(color)
+}
@@ -0,0 +1,106 @@
+use crate::theme::Theme;
+use ratatui::{ layout::Rect, text::{ Span, Spans } };
+use std::{ collections::VecDeque, time::{ Duration, Instant } };
+use unicode_width::UnicodeWidthStr;
+
+/// How long a confirmation stays on screen
+const INFO_DURATION: Duration = Duration::from_secs(3);
+/// Errors stay longer, they usually need reading twice
+const ERROR_DURATION: Duration = Duration::from_secs(6);
+/// Messages shown at once; a new one pushes out the oldest
+const MAX_SHOWN: usize = 3;
+/// Widest a message gets before it is cut, borders excluded
+const MAX_WIDTH: usize = 60;
+
+struct Toast {
+    text: String,
+    error: bool,
+    until: Instant,
+}
+
+/// Short-lived confirmations and errors shown in the bottom right corner,
+/// since printing to the terminal would garble the screen the TUI owns
+#[derive(Default)]
+pub struct Toasts {
+    shown: VecDeque<Toast>,
+}
+
+impl Toasts {
+    /// Confirm that something happened ("Exported to ...")
+    pub fn info(&mut self, text: impl Into<String>) {
+        self.push(text.into(), false, INFO_DURATION);
+    }
+
+    /// Tell that something failed ("Cannot open ...: Permission denied")
+    pub fn error(&mut self, text: impl Into<String>) {
+        self.push(text.into(), true, ERROR_DURATION);
+    }
+
+    fn push(&mut self, text: String, error: bool, duration: Duration) {
+        if self.shown.len() == MAX_SHOWN {
+            self.shown.pop_front();
+        }
+        self.shown.push_back(Toast { text, error, until: Instant::now() + duration });
+    }
+
+    /// Drop the messages whose time is up; true when the screen changed
+    pub fn expire(&mut self) -> bool {
+        let before = self.shown.len();
+        let now = Instant::now();
+        self.shown.retain(|toast| toast.until > now);
+        self.shown.len() != before
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shown.is_empty()
+    }
+
+    pub fn lines(&self, theme: &Theme) -> Vec<Spans<'static>> {
+        self.shown
+            .iter()
+            .map(|toast| {
+                let text = truncate(&toast.text, MAX_WIDTH);
+                match toast.error {
+                    true => Spans::from(Span::styled(text, theme.differs)),
+                    false => Spans::from(Span::raw(text)),
+                }
+            })
+            .collect()
+    }
+
+    /// Place for the messages in the bottom right corner of `area`, borders included
+    pub fn area(&self, area: Rect) -> Rect {
+        let widest = self.shown
+            .iter()
+            .map(|toast| UnicodeWidthStr::width(toast.text.as_str()).min(MAX_WIDTH))
+            .max()
+            .unwrap_or(0);
+        let width = ((widest as u16) + 2).min(area.width);
+        let height = ((self.shown.len() as u16) + 2).min(area.height);
+        Rect {
+            x: area.x + area.width - width,
+            y: area.y + area.height - height,
+            width,
+            height,
+        }
+    }
+}
+
+/// `text` cut to `width` columns, ending with an ellipsis when cut
+fn truncate(text: &str, width: usize) -> String {
+    if UnicodeWidthStr::width(text) <= width {
+        return text.to_string();
+    }
+    let mut cut = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        let char_width = unicode_width::UnicodeWidthChar::width(c).unwrap_or(0);
+        if used + char_width + 1 > width {
+            break;
+        }
+        cut.push(c);
+        used += char_width;
+    }
+    cut.push('…');
+    cut
+}
@@ -0,0 +1,9 @@
+use std::{ io, path::Path };
+
+/// Move a file or directory to the platform's trash / recycle bin: the
+/// freedesktop.org trash (per volume where needed), Finder's Trash with
+/// "Put Back", or the Windows Recycle Bin
+pub fn move_to_trash(path: &Path) -> io::Result<()> {
+    let path = path.canonicalize()?;
+    trash::delete(&path).map_err(io::Error::other)
+}
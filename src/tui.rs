@@ -0,0 +1,5633 @@
+use crate::{
+    cli::Options,
+    collate,
+    columns::{ Column, ColumnLayout },
+    count_cache::{ CacheBudget, CountCache },
+    config,
+    control::{ self, Request },
+    broken_links::{ remove_link, spawn_broken_link_scan, BrokenLinkReport, BrokenLinkView },
+    cache_view::CacheView,
+    cleanable::{ spawn_cleanable_scan, CleanableReport, CleanableView },
+    drives::{ DriveView, FsUsage },
+    duplicates::{ spawn_duplicate_scan, DuplicateReport, DuplicateView },
+    empty_dirs::{ remove_chain, spawn_empty_dir_scan, EmptyDirReport, EmptyDirView },
+    events::{ AppEvent, InputReader, Ticker, TICK_INTERVAL },
+    file_types::CATEGORIES,
+    git::{ spawn_git_status, GitCounts, GitState, GitStatus },
+    icons::{ self, IconKind },
+    jump::JumpView,
+    format,
+    index,
+    listing::{ spawn_listing, ListedEntry, ListingBatch },
+    mft::{ self, MftCounts },
+    modal::{ Answer, Modal, Outcome },
+    mountinfo::{ FsClass, FsClasses, HostPathTranslator },
+    numbers::NumberFormat,
+    owners::{ main_owner, Ownership },
+    pattern::NamePattern,
+    preview::{ spawn_preview, Preview, PreviewContent },
+    report::ReportNode,
+    scan::{
+        scan_dir_matching,
+        self,
+        archive_tree,
+        is_bundle,
+        is_counted_archive,
+        is_system_dir,
+        scan_dir_with_progress,
+        ByteSizes,
+        FileTotals,
+        ScanOptions,
+        Skipped,
+    },
+    screen,
+    skipped::{ count_elevated, SkippedView },
+    summary::SessionSummary,
+    theme::Theme,
+    toast::Toasts,
+    trash,
+    wizard::{ spawn_archive, ArchiveMessage, CleanupWizard, StopAction, WizardStop },
+};
+#[cfg(feature = "watch")]
+use crate::watch::{ Debouncer, Watcher };
+#[cfg(unix)]
+use crate::owners::Names;
+use crossterm::event::{ Event, KeyCode, KeyModifiers, MouseButton, MouseEventKind };
+use dashmap::DashMap;
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{ Constraint, Direction, Layout, Rect },
+    style::{ Modifier, Style },
+    text::{ Span, Spans },
+    widgets::{ Block, Borders, Table, Cell, Row, TableState, Paragraph, Wrap, Clear },
+    Terminal,
+};
+use std::{
+    collections::{ BTreeMap, HashMap, HashSet },
+    fs,
+    io,
+    env,
+    path::{ Path, PathBuf },
+    process::Command,
+    sync::{ atomic::{ AtomicU64, AtomicUsize, Ordering }, mpsc::{ channel, Receiver, Sender }, Arc },
+    time::{ Duration, Instant, SystemTime },
+};
+#[cfg(feature = "persist")]
+use crate::state::AppState;
+use threadpool::ThreadPool;
+use unicode_width::UnicodeWidthStr;
+
+/// Last directory, selection and view settings, in the state directory
+#[cfg(feature = "persist")]
+const SESSION_FILE: &str = "session.json";
+
+/// Name of the first row when not at the top, which opens the parent directory
+const PARENT_ENTRY_NAME: &str = ".. (Back to parent directory)";
+
+/// Time between samples of the status line's files per second
+const THROUGHPUT_INTERVAL: Duration = Duration::from_secs(1);
+/// A count finding no new files for this long is flagged in the status line (e.g. a dead NFS mount)
+const STALL_WARNING: Duration = Duration::from_secs(5);
+
+/// Shown below the name filter while it is typed
+const FILTER_HINT: &str = "*.log, re:^core\\.\\d+$ or text the name contains; empty shows all";
+
+/// Least time between two re-sorts while counts stream in
+const RESORT_INTERVAL: Duration = Duration::from_millis(200);
+/// Counts arriving while keys are pressed wait this long after the last one,
+/// so that rows do not move under the cursor
+const RESORT_IDLE: Duration = Duration::from_millis(300);
+
+/// Terminals narrower than this get the compact layout: no Type column, the
+/// path on its own line above the totals and only the essential key hints
+const NARROW_WIDTH: u16 = 60;
+
+/// Columns the name keeps at least; the columns at the end are dropped for it
+const MIN_NAME_WIDTH: u16 = 12;
+
+/// Columns the names move by for each Left or Right
+const NAME_SCROLL_STEP: usize = 8;
+
+/// Bounds of `display.tick_interval_ms`, from about 60 frames a second to one
+const MIN_TICK_MS: i64 = 16;
+const MAX_TICK_MS: i64 = 1000;
+
+/// Factor by which `>` and `<` raise and lower the minimum count
+const MIN_COUNT_STEP: usize = 10;
+
+/// Columns of the help popup, where the terminal is wide enough
+const HELP_WIDTH: u16 = 80;
+
+/// Key bindings listed in the help popup
+const KEY_BINDINGS: &[(&str, &str)] = &[
+    ("q, Ctrl-C", "Quit"),
+    ("↑/k", "Move up"),
+    ("↓/j", "Move down"),
+    ("Enter", "Open directory"),
+    ("Mouse click", "Select and open"),
+    ("h", "Back to home directory"),
+    ("o", "Open with default application"),
+    ("←/→", "Scroll the names sideways to read long ones"),
+    ("i", "Show the full path of the selected entry (also on hovering a cut name)"),
+    ("Space", "Mark/unmark entry"),
+    ("v", "Visual range selection (v again marks the range)"),
+    ("Esc", "Close the popup, or cancel the visual selection / clear marks"),
+    ("d", "Move marked or selected entries to trash"),
+    ("M", "Move marked entries into the current directory"),
+    ("A", "Archive marked or selected entries (.tar.gz) and trash them"),
+    ("W", "Cleanup wizard: review the largest subdirectories one by one"),
+    ("D", "Find duplicate files in the selected directory"),
+    ("e", "Find empty directories in the selected directory (d removes them)"),
+    ("L", "Find broken symlinks in the selected directory (d removes them)"),
+    ("E", "List the entries the count of the selected directory could not read, and why"),
+    ("J", "Estimate the space build output and caches take below the current directory (d trashes them)"),
+    ("x", "Toggle file type and extension breakdown"),
+    ("p", "Toggle the preview pane: the start of a text file, details of others, a summary of a directory"),
+    ("t", "Toggle relative/absolute modification times"),
+    ("a", "Toggle size on disk/apparent size"),
+    (",", "Toggle grouped/compact counts (1,234,567 or 1.2M)"),
+    (">", "Hide directories with fewer files (10, 100, 1,000, ...)"),
+    ("<", "Lower the minimum count, down to showing all directories"),
+    ("f", "Show only directories, only files, or both"),
+    ("/", "Filter files by name: a glob (*.log), re:REGEX, or text the name contains"),
+    ("F", "Count only the files matching the filter in the selected directory"),
+    ("c", "Show or hide columns"),
+    ("g", "Sort by growth since the --diff snapshot"),
+    ("m", "Sort by the newest modification below each directory"),
+    ("G", "Count only untracked and ignored files, inside a git repository"),
+    ("S", "Pick what directories are sorted by"),
+    ("b", "Expand/collapse bundles (.app, ...)"),
+    ("r", "Re-read the directory listing"),
+    ("Ctrl-l", "Sort the counts that arrived right away and redraw the screen"),
+    ("z", "Freeze the order while counts arrive (z again re-sorts)"),
+    ("R", "Recount the selected directory, ignoring the cache"),
+    ("I", "Invalidate the selected directory: forget the counts in and above it, and count them again"),
+    ("C", "Cache statistics and the oldest counts, to pin them or clear the cache"),
+    ("V", "Pick a drive or mounted filesystem to scan, with its free space"),
+    ("Ctrl-p", "Jump to any directory counted or listed so far by typing part of its path"),
+    (":", "Go to a directory by typing its path (relative, absolute or ~/...)"),
+    ("Ctrl-t", "Open a tab on the current directory"),
+    ("Tab/Shift-Tab", "Switch to the next/previous tab"),
+    ("Ctrl-w", "Close the tab"),
+    ("|", "Dual-pane view: show a second tab side by side (Tab switches panes)"),
+    ("=", "Compare the panes: highlight entries whose count differs"),
+    ("?", "Show this help"),
+];
+
+struct App {
+    current_dir: PathBuf,
+    home_dir: PathBuf,
+    roots: Vec<PathBuf>, // Directories given on the command line, when several: listed at home, an empty path
+    current_dir_count: Option<usize>, // Store the file count of the current directory
+    current_dir_approx: Option<usize>, // Index-based estimate until the count finishes
+    current_dir_approx_bytes: Option<u64>, // Index-based size sum, if the index knows sizes
+    items: Vec<DirEntry>, // Kept sorted, see `resort`
+    rows_by_path: HashMap<PathBuf, Vec<usize>>, // Rows showing each counted path, including aliases
+    unsorted: HashSet<usize>, // Rows whose count changed since the last sort
+    frozen: bool, // Counts arriving leave the order alone until `z` or a refresh
+    name_scroll: usize, // Columns of every name scrolled out of view on the left, 0 to cut long names instead
+    listing: bool, // Entries of the current directory are still streaming in
+    listing_generation: Arc<AtomicU64>, // Bumped for each listing, so stale ones stop
+    primaries: HashMap<(u64, u64), PathBuf>, // First listed path of each directory identity
+    table_state: TableState,
+    scroll_offset: usize, // First row in the viewport
+    action_pending: Option<Action>,
+    event_tx: Sender<AppEvent>, // Count results, input and ticks
+    event_rx: Receiver<AppEvent>,
+    thread_pool: ThreadPool,
+    spinner_index: usize,
+    theme: Theme,
+    file_count_cache: Arc<CountCache>, // Recursive counts, within the budget of `[cache]`
+    extension_cache: Arc<DashMap<PathBuf, Breakdown>>, // Extension histograms and file types
+    size_cache: Arc<DashMap<PathBuf, ByteSizes>>, // Sizes of the files counted in each directory
+    newest_cache: Arc<DashMap<PathBuf, SystemTime>>, // Most recent modification below each directory
+    owner_cache: Arc<DashMap<PathBuf, (u32, f64)>>, // User owning most files below each directory, and their share
+    junk_cache: Arc<DashMap<PathBuf, Vec<(&'static str, FileTotals)>>>, // Files in junk directories below each, by category
+    #[cfg(unix)]
+    names: Names, // Of users and groups
+    hard_link_cache: Arc<DashMap<PathBuf, usize>>, // Extra hard links skipped by each count
+    broken_link_cache: Arc<DashMap<PathBuf, usize>>, // Broken symlinks found by each count
+    skipped_cache: Arc<DashMap<PathBuf, Arc<Skipped>>>, // Entries each count could not read, if any
+    excluded_cache: Arc<DashMap<PathBuf, Arc<Vec<PathBuf>>>>, // Directories each count left out
+    count_time_cache: Arc<DashMap<PathBuf, Duration>>, // How long the last walk of a directory took
+    count_progress: Arc<DashMap<PathBuf, Arc<CountProgress>>>, // Counts running on the pool
+    throughput: Throughput, // Of the current directory's count, for the status line
+    scan_options: ScanOptions,
+    extension_jobs: HashSet<PathBuf>, // Directories with a histogram job submitted
+    recounting: HashSet<PathBuf>, // Shown dimmed until their new count arrives
+    use_index: bool, // Query the search index for instant approximate counts
+    index_cache: Arc<DashMap<PathBuf, usize>>, // Approximate counts from the index
+    use_mft: bool, // Count from the NTFS Master File Table where it can be read
+    mft: Option<Result<Arc<MftCounts>, String>>, // None while it is being read
+    show_extensions: bool,
+    show_preview: bool, // Right-hand pane about the selected entry, over the breakdown panel
+    preview: Option<Preview>, // Of the selected entry, or the one selected before until it arrives
+    preview_pending: Option<PathBuf>, // Being read on a background thread
+    absolute_times: bool, // ISO-8601 timestamps instead of "3 days ago"
+    apparent_size: bool, // File lengths instead of the space allocated on disk
+    numbers: NumberFormat, // Grouped or compact counts, in the locale's marks
+    show_help: bool,
+    help_scroll: u16, // First row of the help popup shown
+    help_page: u16, // Rows the help popup showed at the last draw, what PgUp/PgDn move by
+    full_path: Option<(PathBuf, bool)>, // Popup with the untruncated path of an entry, and whether hovering opened it
+    modal: Option<Modal<Prompt>>, // Takes every key until it is answered or cancelled
+    toasts: Toasts, // Confirmations and errors, in place of printing over the screen
+    columns: ColumnLayout,
+    column_picker: bool, // Popup showing and hiding columns
+    batch_preview: Option<BatchPreview>,
+    marked: BTreeMap<PathBuf, MarkedEntry>, // Marked entries, kept across navigation
+    visual_anchor: Option<usize>, // Start of the visual range selection
+    #[cfg(feature = "watch")]
+    watcher: Option<Watcher>, // Set in watch mode
+    #[cfg(feature = "watch")]
+    debouncer: Debouncer, // Coalesces change notifications into recounts
+    host_paths: Option<HostPathTranslator>, // Set when --host-paths is given
+    snapshot: Option<Arc<ReportNode>>, // Browse a pre-computed report instead of the disk
+    archive: Option<(PathBuf, Arc<ReportNode>)>, // Archive being browsed and its entries
+    archive_trees: Arc<DashMap<PathBuf, Arc<ReportNode>>>, // Entries of the counted archives
+    baseline: Option<ReportNode>, // Earlier snapshot to compare counts with (--diff)
+    git: Option<GitStatus>, // How git sees the entries of the current directory, inside a work tree
+    sort: SortKey, // Of the directories, which come first
+    summary: SessionSummary,
+    wizard: Option<CleanupWizard>,
+    duplicates: Option<DuplicateView>,
+    empty_dirs: Option<EmptyDirView>,
+    broken_links: Option<BrokenLinkView>,
+    skipped: Option<SkippedView>,
+    drives: Option<DriveView>,
+    fs_usage: Option<FsUsage>, // Of the current directory's filesystem, read with its listing
+    cleanable: Option<CleanableView>,
+    jump: Option<JumpView>,
+    cache_view: Option<CacheView>,
+    excluded: HashSet<PathBuf>, // Hidden from the listing for this session, or dimmed
+    dim_filtered: bool, // Show excluded entries dimmed instead of hiding them
+    icons: bool, // Nerd Font glyphs before the names
+    min_count: Option<usize>, // Hide directories counted below this many files
+    shown_kinds: ShownKinds,
+    name_filter: Option<NamePattern>, // Only files with a matching name are listed
+    filtered_count: Option<FilteredCount>, // Recount of the files matching the name filter
+    filtered_out: Vec<DirEntry>, // Rows hidden by `min_count`, `shown_kinds` or `name_filter`, back once they pass
+    restore_selection: Option<PathBuf>, // Selected once the listing shows it
+    shown_dir: PathBuf, // Directory `items` were listed for
+    positions: HashMap<PathBuf, (PathBuf, usize)>, // Selected entry and scroll offset of the directories left
+    tabs: Vec<Tab>, // The active one is only up to date while switching
+    active_tab: usize,
+    split: Option<SplitView>, // Another tab shown side by side
+}
+
+enum Action {
+    EnterDirectory(usize),
+}
+
+/// What directories are sorted by
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SortKey {
+    Count, // Descending
+    Growth, // Since the --diff baseline, descending
+    Newest, // Most recent modification below them first
+    NotInGit, // Untracked and ignored files, descending
+}
+
+impl SortKey {
+    /// Name in the sort picker
+    fn label(self) -> &'static str {
+        match self {
+            SortKey::Count => "File count",
+            SortKey::Growth => "Growth since the --diff snapshot",
+            SortKey::Newest => "Most recent modification below",
+            SortKey::NotInGit => "Untracked and ignored files",
+        }
+    }
+}
+
+/// Kinds of entries the listing shows, cycled with `f`
+#[derive(Clone, Copy)]
+enum ShownKinds {
+    All,
+    Directories,
+    Files,
+}
+
+/// What kinds of files a directory holds, for the breakdown panel
+struct Breakdown {
+    extensions: Vec<(String, usize)>, // Most files first
+    file_types: Vec<(&'static str, FileTotals)>, // In the order of `CATEGORIES`, empty ones left out
+}
+
+/// What the open modal asks for
+enum Prompt {
+    NameFilter,
+    GoToPath,
+    Sort(Vec<SortKey>), // The keys offered, in the order shown
+    RemoveEmptyDirs,
+    RemoveBrokenLinks,
+    TrashCleanable,
+    ClearCache,
+}
+
+/// Files below a directory whose name matches a pattern, counted on request
+pub struct FilteredCount {
+    path: PathBuf,
+    pattern: String, // As typed
+    totals: Option<FileTotals>, // None while counting
+}
+
+/// Where a tab is browsing; the caches are shared by all tabs
+#[derive(Clone)]
+struct Tab {
+    current_dir: PathBuf,
+    archive: Option<(PathBuf, Arc<ReportNode>)>,
+    selected: Option<PathBuf>,
+    sort: SortKey,
+}
+
+/// The tab shown next to the active one in the dual-pane view, with the
+/// entries it had when it lost the focus
+struct SplitView {
+    partner: usize, // Index in `tabs`
+    items: Vec<DirEntry>,
+    scroll_offset: usize,
+    compare: bool, // Highlight entries whose count differs between the panes
+}
+
+/// A count running on the thread pool
+struct CountProgress {
+    files: AtomicUsize, // Found so far
+    started: Instant,
+}
+
+/// Files per second of the current directory's count, sampled about once a second
+struct Throughput {
+    sampled_at: Instant,
+    files: usize,
+    per_second: Option<f64>,
+    last_progress: Instant, // Last time the count found more files
+}
+
+impl Default for Throughput {
+    fn default() -> Self {
+        let now = Instant::now();
+        Throughput { sampled_at: now, files: 0, per_second: None, last_progress: now }
+    }
+}
+
+/// Operation applied to every entry of a batch
+enum BatchOperation {
+    Trash,
+    Move(PathBuf), // Into this directory
+    Archive, // Pack into a .tar.gz next to the entry, then trash it
+}
+
+/// Entries awaiting a single confirmation before a batch operation runs on all of them
+struct BatchPreview {
+    operation: BatchOperation,
+    targets: Vec<BatchTarget>,
+    conflicts: Vec<String>, // Found up front; the batch cannot be confirmed while any remain
+    scroll: u16,
+}
+
+struct BatchTarget {
+    path: PathBuf,
+    name: String,
+    is_dir: bool,
+    files: Option<usize>, // Files inside a directory, if counted
+    size: Option<ByteSizes>, // Size of a plain file
+}
+
+/// An entry marked for batch operations
+struct MarkedEntry {
+    name: String,
+    is_dir: bool,
+    is_bundle: bool,
+    size: Option<ByteSizes>, // Size of a plain file
+}
+
+/// Role of a row in the listing, whatever its label says
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum EntryKind {
+    Parent, // Back to the parent directory, or to the list of roots; always the first row
+    Child, // Entry of the current directory
+}
+
+#[derive(Clone)]
+struct DirEntry {
+    kind: EntryKind,
+    name: String, // Label of the row; the path is the source of truth
+    path: PathBuf,
+    is_dir: bool,
+    is_bundle: bool, // Opaque bundle: counted as one file, its own count is a sub-detail
+    is_archive: bool, // Archive file listed as a read-only directory of its entries
+    is_link: bool, // Symbolic link, to a directory or a file
+    file_count: Option<usize>,
+    approx_count: Option<usize>, // Index-based estimate until the count finishes
+    alias_of: Option<PathBuf>, // Listed entry that is the same directory (bind mount or symlink)
+    modified: Option<SystemTime>, // Unknown for snapshots
+    newest: Option<SystemTime>, // Most recent modification anywhere below a counted directory
+    ownership: Option<Ownership>, // Unknown for snapshots and outside Unix
+    junk: Option<&'static str>, // Category of a recognized build or cache directory
+    mount: Option<String>, // Filesystem type, when the directory is a mount point
+    left_out: bool, // System directory or mount of an excluded filesystem class, not counted
+    sizes: Option<ByteSizes>, // Of a file, or of everything counted in a directory
+    baseline_count: Option<usize>, // Count in the --diff baseline, 0 for new directories
+    git: Option<GitCounts>, // Files below it by git status, inside a work tree
+}
+
+impl DirEntry {
+    fn is_parent(&self) -> bool {
+        self.kind == EntryKind::Parent
+    }
+
+    /// Change of the file count since the --diff baseline
+    fn growth(&self) -> Option<i64> {
+        let count = self.file_count? as i64;
+        Some(count - self.baseline_count? as i64)
+    }
+}
+
+/// Count result sent from worker threads to the UI
+pub struct CountMessage {
+    path: PathBuf,
+    files: usize,
+    bytes: Option<u64>, // Size sum, when the source knows it
+    approximate: bool, // From the search index rather than a walk
+}
+
+impl App {
+    fn new(
+        start_dir: PathBuf,
+        host_paths: Option<HostPathTranslator>,
+        theme: Theme,
+        snapshot: Option<ReportNode>,
+        scan_options: ScanOptions,
+        use_index: bool,
+        cache_budget: CacheBudget
+    ) -> io::Result<Self> {
+        let (event_tx, event_rx) = channel();
+        let thread_pool = ThreadPool::new(num_cpus::get());
+
+        // Initialize cache
+        let file_count_cache = Arc::new(CountCache::new(cache_budget));
+
+        let mut app = App {
+            current_dir: start_dir.clone(),
+            home_dir: start_dir,
+            roots: Vec::new(),
+            current_dir_count: None, // Initialize as None
+            current_dir_approx: None,
+            current_dir_approx_bytes: None,
+            items: Vec::new(),
+            rows_by_path: HashMap::new(),
+            unsorted: HashSet::new(),
+            frozen: false,
+            name_scroll: 0,
+            listing: false,
+            listing_generation: Arc::new(AtomicU64::new(0)),
+            primaries: HashMap::new(),
+            table_state: TableState::default(),
+            scroll_offset: 0,
+            action_pending: None,
+            event_tx,
+            event_rx,
+            thread_pool,
+            spinner_index: 0,
+            theme,
+            file_count_cache,
+            extension_cache: Arc::new(DashMap::new()),
+            size_cache: Arc::new(DashMap::new()),
+            newest_cache: Arc::new(DashMap::new()),
+            owner_cache: Arc::new(DashMap::new()),
+            junk_cache: Arc::new(DashMap::new()),
+            #[cfg(unix)]
+            names: Names::default(),
+            hard_link_cache: Arc::new(DashMap::new()),
+            broken_link_cache: Arc::new(DashMap::new()),
+            skipped_cache: Arc::new(DashMap::new()),
+            excluded_cache: Arc::new(DashMap::new()),
+            count_time_cache: Arc::new(DashMap::new()),
+            count_progress: Arc::new(DashMap::new()),
+            throughput: Throughput::default(),
+            scan_options,
+            extension_jobs: HashSet::new(),
+            recounting: HashSet::new(),
+            use_index,
+            index_cache: Arc::new(DashMap::new()),
+            use_mft: false,
+            mft: None,
+            show_extensions: false,
+            show_preview: false,
+            preview: None,
+            preview_pending: None,
+            absolute_times: false,
+            apparent_size: false,
+            numbers: NumberFormat::default(),
+            show_help: false,
+            help_scroll: 0,
+            help_page: 0,
+            full_path: None,
+            modal: None,
+            toasts: Toasts::default(),
+            columns: ColumnLayout::default(),
+            column_picker: false,
+            batch_preview: None,
+            marked: BTreeMap::new(),
+            visual_anchor: None,
+            #[cfg(feature = "watch")]
+            watcher: None,
+            #[cfg(feature = "watch")]
+            debouncer: Debouncer::default(),
+            host_paths,
+            snapshot: snapshot.map(Arc::new),
+            archive: None,
+            archive_trees: Arc::new(DashMap::new()),
+            baseline: None,
+            git: None,
+            sort: SortKey::Count,
+            summary: SessionSummary::default(),
+            wizard: None,
+            duplicates: None,
+            empty_dirs: None,
+            broken_links: None,
+            skipped: None,
+            drives: None,
+            fs_usage: None,
+            cleanable: None,
+            jump: None,
+            cache_view: None,
+            excluded: HashSet::new(),
+            dim_filtered: false,
+            icons: false,
+            min_count: None,
+            shown_kinds: ShownKinds::All,
+            name_filter: None,
+            filtered_count: None,
+            filtered_out: Vec::new(),
+            restore_selection: None,
+            shown_dir: PathBuf::new(),
+            positions: HashMap::new(),
+            tabs: Vec::new(),
+            active_tab: 0,
+            split: None,
+        };
+        app.tabs.push(app.tab());
+        app.refresh_items()?;
+        Ok(app)
+    }
+
+    /// Refresh the item list in the current directory
+    fn refresh_items(&mut self) -> io::Result<()> {
+        // Row indices change, so a pending range no longer makes sense
+        self.visual_anchor = None;
+        self.frozen = false;
+        self.name_scroll = 0;
+        self.restore_position();
+        self.summary.visit(&self.current_dir);
+        self.git = self.git.take().filter(|git| git.dir == self.current_dir);
+
+        if let Some(snapshot) = self.snapshot.clone() {
+            let root = self.home_dir.clone();
+            self.refresh_snapshot_items(&snapshot, &root);
+            return Ok(());
+        }
+        if self.at_roots() {
+            self.refresh_root_items();
+            return Ok(());
+        }
+
+        // Inside an archive, entries come from its listing
+        self.archive = self.archive.take().filter(|(archive, _)| self.current_dir.starts_with(archive));
+        if self.archive.is_none() && is_counted_archive(&self.current_dir, &self.scan_options) {
+            let tree = match self.archive_trees.get(&self.current_dir) {
+                Some(tree) => Arc::clone(&tree),
+                None => Arc::new(read_archive_or_self(&self.current_dir)),
+            };
+            self.archive = Some((self.current_dir.clone(), tree));
+        }
+        if let Some((archive, tree)) = self.archive.clone() {
+            self.listing_generation.fetch_add(1, Ordering::Relaxed); // Drop batches of the directory left
+            self.listing = false;
+            self.refresh_snapshot_items(&tree, &archive);
+            return Ok(());
+        }
+
+        self.items.clear();
+        self.filtered_out.clear();
+        self.throughput = Throughput::default();
+        self.fs_usage = None;
+
+        // Git status of the entries, when inside a work tree
+        spawn_git_status(self.current_dir.clone(), self.event_tx.clone());
+
+        #[cfg(feature = "watch")]
+        if let Some(watcher) = &self.watcher {
+            watcher.watch(&self.current_dir);
+        }
+
+        let previous_selection = self.table_state.selected().unwrap_or(0);
+
+        let include_back = self.current_dir != self.home_dir;
+
+        self.table_state.select(Some(previous_selection));
+
+        // Check if the file count of the current directory is in the cache
+        self.current_dir_count = self.file_count_cache.get(&self.current_dir);
+        self.current_dir_approx = self.index_cache.get(&self.current_dir).map(|v| *v);
+        self.current_dir_approx_bytes = None;
+
+        // If not cached, start a thread to compute the file count
+        if self.current_dir_count.is_none() {
+            self.spawn_count(self.current_dir.clone(), self.scan_options.collect_extensions);
+        }
+
+        // Add option to go back to parent directory (if not at home_dir)
+        if include_back && self.roots.contains(&self.current_dir) {
+            // Back to the list of roots, which together count their total
+            let home = self.home_dir.clone();
+            self.items.push(DirEntry {
+                kind: EntryKind::Parent,
+                name: String::from(PARENT_ENTRY_NAME),
+                path: home,
+                is_dir: true,
+                is_bundle: false,
+                is_archive: false,
+                is_link: false,
+                file_count: self.roots_total(),
+                approx_count: None,
+                alias_of: None,
+                modified: None,
+                newest: None,
+                ownership: None,
+                junk: None,
+                mount: None,
+                left_out: self.roots_total().is_none(),
+                sizes: None,
+                baseline_count: None,
+                git: None,
+            });
+        } else if include_back {
+            if let Some(parent) = self.current_dir.parent() {
+                // Check if the file count of the parent directory is in the cache
+                let parent_count = self.file_count_cache.get(parent);
+
+                // If not cached, start a thread to compute the file count
+                if parent_count.is_none() {
+                    self.spawn_count(parent.to_path_buf(), self.scan_options.collect_extensions);
+                }
+
+                self.items.push(DirEntry {
+                    kind: EntryKind::Parent,
+                    name: String::from(PARENT_ENTRY_NAME),
+                    path: parent.to_path_buf(),
+                    is_dir: true,
+                    is_bundle: false,
+                    is_archive: false,
+                    is_link: false,
+                    file_count: parent_count, // Use cached file count
+                    approx_count: self.index_cache.get(parent).map(|v| *v),
+                    alias_of: None,
+                    modified: fs::metadata(parent).and_then(|m| m.modified()).ok(),
+                    newest: self.newest_cache.get(parent).map(|v| *v),
+                    ownership: fs::metadata(parent).ok().and_then(|m| Ownership::of(&m)),
+                    junk: None,
+                    mount: None,
+                    left_out: false,
+                    sizes: self.size_cache.get(parent).map(|v| *v),
+                    baseline_count: None,
+                    git: None,
+                });
+            }
+        }
+
+        // Entries stream in from a background thread, see `apply_listing`
+        let generation = self.listing_generation.fetch_add(1, Ordering::Relaxed) + 1;
+        self.listing = true;
+        self.primaries.clear();
+        spawn_listing(
+            self.current_dir.clone(),
+            generation,
+            Arc::clone(&self.listing_generation),
+            self.event_tx.clone()
+        );
+
+        self.fill_baselines(0);
+        self.fill_git(0);
+        self.resort(true);
+
+        Ok(())
+    }
+
+    /// Add a batch of streamed entries to the list, each in its sorted position
+    fn apply_listing(&mut self, batch: ListingBatch) {
+        if batch.generation != self.listing_generation.load(Ordering::Relaxed) {
+            return; // Left that directory already
+        }
+        let mut entries = batch.entries;
+        // Stable order, so the same entry of a group of aliases is usually the primary
+        entries.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+
+        let first_new = self.items.len();
+        for entry in entries {
+            let ListedEntry { path, file_name, is_dir, is_link, identity, modified, sizes, ownership, junk, mount } = entry;
+            let is_archive = !is_dir && is_counted_archive(&path, &self.scan_options);
+            let is_dir = is_dir || is_archive;
+            if !self.dim_filtered && self.excluded.contains(&path) {
+                continue;
+            }
+            // The path is the source of truth, the name is only for display
+            let name = format::display_name(&file_name);
+
+            // Bind mounts of the same directory share the primary's count
+            let alias_of = identity.and_then(|identity| {
+                match self.primaries.get(&identity) {
+                    Some(primary) => Some(primary.clone()),
+                    None => {
+                        self.primaries.insert(identity, path.clone());
+                        None
+                    }
+                }
+            });
+
+            // Check cache
+            let (cached_count, sizes, newest) = if is_dir {
+                let counted = alias_of.as_ref().unwrap_or(&path);
+                let cached_sizes = self.size_cache.get(counted).map(|v| *v);
+                let newest = self.newest_cache.get(counted).map(|v| *v);
+                (self.file_count_cache.get(counted), cached_sizes.or(sizes), newest)
+            } else {
+                (None, sizes, None)
+            };
+
+            // Submit a task to compute the file count (if not cached), unless scans leave the directory out
+            let left_out = is_dir && (is_system_dir(&path) || self.is_excluded_fs(mount.as_deref()));
+            if is_dir && cached_count.is_none() && alias_of.is_none() && !left_out {
+                self.spawn_count(path.clone(), self.scan_options.collect_extensions);
+            }
+
+            let is_bundle = is_dir && self.scan_options.opaque_bundles && is_bundle(&path);
+            let approx_count = if is_dir { self.index_cache.get(&path).map(|v| *v) } else { None };
+
+            self.unsorted.insert(self.items.len());
+            self.items.push(DirEntry {
+                kind: EntryKind::Child,
+                name,
+                path,
+                is_dir,
+                is_bundle,
+                is_archive,
+                is_link,
+                file_count: cached_count, // Use cached file count if available
+                approx_count,
+                alias_of,
+                modified,
+                newest,
+                ownership,
+                junk,
+                mount,
+                left_out,
+                sizes,
+                baseline_count: None,
+                git: None,
+            });
+        }
+
+        if batch.done {
+            self.listing = false;
+            self.fs_usage = batch.usage;
+
+            // Ask the search index for instant estimates while the walkers run
+            let needs_counts =
+                self.current_dir_count.is_none() ||
+                self.items.iter().any(|i| i.is_dir && i.file_count.is_none());
+            if self.use_index && needs_counts {
+                self.spawn_index_counts();
+            }
+        }
+
+        self.fill_baselines(first_new);
+        self.fill_git(first_new);
+        self.resort(false);
+    }
+
+    /// Count a directory on the thread pool, updating the caches and notifying the UI
+    fn spawn_count(&self, path: PathBuf, collect_extensions: bool) {
+        let options = ScanOptions { collect_extensions, ..self.scan_options };
+        let sender = self.event_tx.clone();
+        let cache: Arc<CountCache> = Arc::clone(&self.file_count_cache);
+        let extension_cache = Arc::clone(&self.extension_cache);
+        let size_cache = Arc::clone(&self.size_cache);
+        let newest_cache = Arc::clone(&self.newest_cache);
+        let owner_cache = Arc::clone(&self.owner_cache);
+        let junk_cache = Arc::clone(&self.junk_cache);
+        let hard_link_cache = Arc::clone(&self.hard_link_cache);
+        let broken_link_cache = Arc::clone(&self.broken_link_cache);
+        let skipped_cache = Arc::clone(&self.skipped_cache);
+        let excluded_cache = Arc::clone(&self.excluded_cache);
+        let count_time_cache = Arc::clone(&self.count_time_cache);
+        let count_progress = Arc::clone(&self.count_progress);
+
+        // The Master File Table answers first counts without a walk; recounts
+        // and extension histograms still walk, since the table is not kept up to date
+        if !collect_extensions && !self.recounting.contains(&path) {
+            if let Some(files) = self.mft_files(&path) {
+                cache.insert(path.clone(), files);
+                sender.send(AppEvent::Count(CountMessage { path, files, bytes: None, approximate: false })).unwrap_or(());
+                return;
+            }
+        }
+
+        if is_counted_archive(&path, &options) {
+            let archive_trees = Arc::clone(&self.archive_trees);
+            self.thread_pool.execute(move || {
+                let tree = read_archive_or_self(&path);
+                let files = tree.files.unwrap_or(0);
+                archive_trees.insert(path.clone(), Arc::new(tree));
+                cache.insert(path.clone(), files);
+                if let Ok(metadata) = fs::metadata(&path) {
+                    size_cache.insert(path.clone(), ByteSizes::of(&metadata));
+                }
+                sender.send(AppEvent::Count(CountMessage { path, files, bytes: None, approximate: false })).unwrap_or(());
+            });
+            return;
+        }
+
+        self.thread_pool.execute(move || {
+            // Registered once a worker picks the job, so queued counts keep their spinner
+            let progress = Arc::new(CountProgress { files: AtomicUsize::new(0), started: Instant::now() });
+            count_progress.insert(path.clone(), Arc::clone(&progress));
+            let stats = scan_dir_with_progress(&path, &options, Some(&progress.files)).unwrap_or_default();
+            count_progress.remove_if(&path, |_, running| Arc::ptr_eq(running, &progress));
+
+            // Update caches
+            count_time_cache.insert(path.clone(), progress.started.elapsed());
+            cache.insert(path.clone(), stats.files);
+            size_cache.insert(path.clone(), stats.bytes);
+            hard_link_cache.insert(path.clone(), stats.hard_links);
+            broken_link_cache.insert(path.clone(), stats.broken_links);
+            match stats.skipped.count {
+                0 => {
+                    skipped_cache.remove(&path);
+                }
+                _ => {
+                    skipped_cache.insert(path.clone(), Arc::new(stats.skipped.clone()));
+                }
+            }
+            match stats.excluded.is_empty() {
+                true => {
+                    excluded_cache.remove(&path);
+                }
+                false => {
+                    let mut excluded = stats.excluded.clone();
+                    excluded.sort();
+                    excluded_cache.insert(path.clone(), Arc::new(excluded));
+                }
+            }
+            match stats.newest {
+                Some(newest) => {
+                    newest_cache.insert(path.clone(), newest);
+                }
+                None => {
+                    newest_cache.remove(&path);
+                }
+            }
+            match stats.owners.as_ref().and_then(main_owner) {
+                Some(owner) => {
+                    owner_cache.insert(path.clone(), owner);
+                }
+                None => {
+                    owner_cache.remove(&path);
+                }
+            }
+            if let Some(junk) = stats.junk {
+                let mut categories: Vec<(&'static str, FileTotals)> = junk.into_iter().collect();
+                categories.sort_by(|a, b| b.1.files.cmp(&a.1.files).then(a.0.cmp(b.0)));
+                junk_cache.insert(path.clone(), categories);
+            }
+            if let Some(extensions) = stats.extensions {
+                let mut histogram: Vec<(String, usize)> = extensions.into_iter().collect();
+                histogram.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+                let file_types = stats.file_types.unwrap_or_default();
+                let file_types = CATEGORIES.iter()
+                    .filter_map(|&category| Some((category, *file_types.get(category)?)))
+                    .collect();
+                extension_cache.insert(path.clone(), Breakdown { extensions: histogram, file_types });
+            }
+
+            // Send result
+            sender
+                .send(AppEvent::Count(CountMessage { path, files: stats.files, bytes: None, approximate: false }))
+                .unwrap_or(());
+        });
+    }
+
+    /// Record a count result on the matching rows; returns whether anything changed
+    fn apply_count(&mut self, message: CountMessage) -> bool {
+        let CountMessage { path, files: count, bytes, approximate } = message;
+        let mut updated = false;
+        if !approximate {
+            updated |= self.recounting.remove(&path);
+            if path != self.home_dir && path.starts_with(&self.home_dir) {
+                self.summary.counted(&path, count);
+            }
+        }
+        if !approximate && self.at_roots() && self.roots.contains(&path) {
+            self.current_dir_count = self.roots_total();
+        }
+        if path == self.current_dir {
+            if approximate {
+                self.current_dir_approx = Some(count);
+                self.current_dir_approx_bytes = bytes;
+            } else {
+                self.current_dir_count = Some(count);
+            }
+            updated = true;
+        }
+
+        // Also covers "back to parent directory" and aliases sharing the count
+        let sizes = if approximate { None } else { self.size_cache.get(&path).map(|v| *v) };
+        let newest = if approximate { None } else { self.newest_cache.get(&path).map(|v| *v) };
+        for &index in self.rows_by_path.get(&path).into_iter().flatten() {
+            let item = &mut self.items[index];
+            if approximate {
+                item.approx_count = Some(count);
+            } else {
+                item.file_count = Some(count);
+                item.sizes = sizes;
+                item.newest = newest;
+            }
+            self.unsorted.insert(index);
+            updated = true;
+        }
+        // Rows hidden by the minimum count come back once it is reached
+        let mut index = 0;
+        while index < self.filtered_out.len() {
+            let entry = &mut self.filtered_out[index];
+            if approximate || entry.alias_of.as_ref().unwrap_or(&entry.path) != &path {
+                index += 1;
+                continue;
+            }
+            entry.file_count = Some(count);
+            entry.sizes = sizes;
+            entry.newest = newest;
+            if self.is_filtered_out(&self.filtered_out[index]) {
+                index += 1;
+            } else {
+                self.unsorted.insert(self.items.len());
+                self.items.push(self.filtered_out.remove(index));
+                updated = true;
+            }
+        }
+        updated
+    }
+
+    /// Restore the sort order after rows were added or their counts changed. Only
+    /// those rows are sorted, then merged back, so large listings stay cheap to update.
+    fn resort(&mut self, full: bool) {
+        let start = self.items.iter().take_while(|entry| entry.is_parent()).count();
+        let sort = self.sort;
+        // The highlight stays on the selected entry wherever the sort moves it;
+        // the parent row never moves, so a highlight on it stays there
+        let selected = self.table_state
+            .selected()
+            .filter(|&index| index >= start)
+            .and_then(|index| self.items.get(index))
+            .map(|entry| entry.path.clone());
+        if full {
+            sort_items(&mut self.items, sort);
+        } else if !self.unsorted.is_empty() && !self.frozen {
+            let mut kept = Vec::with_capacity(self.items.len() - start);
+            let mut moved = Vec::with_capacity(self.unsorted.len());
+            for (index, item) in self.items.drain(start..).enumerate() {
+                if self.unsorted.contains(&(start + index)) {
+                    moved.push(item);
+                } else {
+                    kept.push(item);
+                }
+            }
+            moved.sort_by(|a, b| compare_entries(a, b, sort));
+            let mut moved = moved.into_iter().peekable();
+            for item in kept {
+                while let Some(next) = moved.next_if(|next| compare_entries(next, &item, sort).is_lt()) {
+                    self.items.push(next);
+                }
+                self.items.push(item);
+            }
+            self.items.extend(moved);
+        }
+        self.unsorted.clear();
+        self.hide_filtered_out();
+
+        if let Some(path) = self.restore_selection.take() {
+            match self.items.iter().position(|item| item.path == path) {
+                Some(index) => self.table_state.select(Some(index)),
+                None if self.listing => {
+                    self.restore_selection = Some(path);
+                }
+                None => {}
+            }
+        } else if let Some(index) = selected.and_then(|path| self.items.iter().position(|item| item.path == path)) {
+            self.table_state.select(Some(index));
+        }
+
+        self.rows_by_path.clear();
+        for (index, item) in self.items.iter().enumerate() {
+            if item.is_dir {
+                let path = item.alias_of.as_ref().unwrap_or(&item.path);
+                self.rows_by_path.entry(path.clone()).or_default().push(index);
+            }
+        }
+    }
+
+    /// Whether `entry` is of a hidden kind, a directory counted below the minimum
+    /// count or a file whose name does not match the name filter
+    fn is_filtered_out(&self, entry: &DirEntry) -> bool {
+        if entry.is_parent() {
+            return false;
+        }
+        let kind_hidden = match self.shown_kinds {
+            ShownKinds::All => false,
+            ShownKinds::Directories => !entry.is_dir,
+            ShownKinds::Files => entry.is_dir,
+        };
+        let name_hidden = self.name_filter.as_ref().is_some_and(|pattern| {
+            let name = entry.path.file_name().map(|name| name.to_string_lossy());
+            !entry.is_dir && !name.is_some_and(|name| pattern.matches(&name))
+        });
+        kind_hidden ||
+            name_hidden ||
+            self.min_count.is_some_and(|min| entry.is_dir && entry.file_count.is_some_and(|count| count < min))
+    }
+
+    /// Move the rows the minimum count or the shown kinds exclude out of the
+    /// listing, keeping the selection on the same entry. With `dim_filtered` they stay, dimmed.
+    fn hide_filtered_out(&mut self) {
+        if self.dim_filtered || !self.items.iter().any(|entry| self.is_filtered_out(entry)) {
+            return;
+        }
+        let selected = self.table_state.selected();
+        let selected_path = selected.and_then(|index| self.items.get(index)).map(|entry| entry.path.clone());
+        let (hidden, kept): (Vec<DirEntry>, Vec<DirEntry>) = std::mem::take(&mut self.items)
+            .into_iter()
+            .partition(|entry| self.is_filtered_out(entry));
+        self.items = kept;
+        self.filtered_out.extend(hidden);
+        let index = selected_path
+            .and_then(|path| self.items.iter().position(|entry| entry.path == path))
+            .or_else(|| selected.map(|index| index.min(self.items.len().saturating_sub(1))));
+        self.table_state.select(index);
+    }
+
+    /// Change the minimum count, bringing back the rows it no longer hides
+    fn set_min_count(&mut self, min_count: Option<usize>) {
+        self.min_count = min_count;
+        self.refilter();
+    }
+
+    /// Show all entries, only directories or only files, in turn
+    fn cycle_shown_kinds(&mut self) {
+        self.shown_kinds = match self.shown_kinds {
+            ShownKinds::All => ShownKinds::Directories,
+            ShownKinds::Directories => ShownKinds::Files,
+            ShownKinds::Files => ShownKinds::All,
+        };
+        self.refilter();
+    }
+
+    /// Filter the files by the pattern typed, an empty one clearing the name filter
+    fn apply_name_filter(&mut self, text: &str) -> Result<(), String> {
+        self.name_filter = match text {
+            "" => None,
+            text => Some(NamePattern::parse(text)?),
+        };
+        self.filtered_count = None;
+        self.refilter();
+        Ok(())
+    }
+
+    /// Act on the answer to the open modal. It opens again with the error
+    /// when the answer cannot be used, so that it can be corrected.
+    fn answer_modal(&mut self, mut modal: Modal<Prompt>, answer: Answer) -> io::Result<()> {
+        let result = match (&modal.purpose, answer) {
+            (Prompt::NameFilter, Answer::Text(text)) => self.apply_name_filter(&text),
+            (Prompt::GoToPath, Answer::Text(text)) => self.go_to_path(&text),
+            (Prompt::Sort(keys), Answer::Choice(index)) => {
+                if let Some(&key) = keys.get(index) {
+                    self.set_sort(key);
+                }
+                Ok(())
+            }
+            (Prompt::RemoveEmptyDirs, Answer::Yes) => {
+                self.remove_empty_dirs()?;
+                Ok(())
+            }
+            (Prompt::RemoveBrokenLinks, Answer::Yes) => {
+                self.remove_broken_links()?;
+                Ok(())
+            }
+            (Prompt::TrashCleanable, Answer::Yes) => {
+                self.trash_cleanable()?;
+                Ok(())
+            }
+            (Prompt::ClearCache, Answer::Yes) => {
+                let dropped = self.forget_counts(|_| true);
+                self.summary.action(String::from("Cleared the count cache"));
+                self.toasts.info(format!("Dropped {} cached counts", self.numbers.count(dropped as u64)));
+                Ok(())
+            }
+            _ => Ok(()),
+        };
+        if let Err(e) = result {
+            modal.error = Some(e);
+            self.modal = Some(modal);
+        }
+        Ok(())
+    }
+
+    /// Go to a directory typed in the go-to-path prompt: absolute, relative to
+    /// the current directory, or below the home directory with `~`
+    fn go_to_path(&mut self, text: &str) -> Result<(), String> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Err(String::from("Type the path of a directory"));
+        }
+        let home = env::var_os("HOME").or_else(|| env::var_os("USERPROFILE")).map(PathBuf::from);
+        let path = match (text.strip_prefix('~'), home) {
+            (Some(rest), Some(home)) => home.join(rest.trim_start_matches(['/', '\\'])),
+            _ => self.current_dir.join(text),
+        };
+        let path = fs::canonicalize(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        if !path.is_dir() {
+            return Err(format!("{} is not a directory", path.display()));
+        }
+        if !path.starts_with(&self.home_dir) {
+            return Err(format!("{} is outside {}", path.display(), self.home_dir.display()));
+        }
+        self.current_dir = path;
+        self.refresh_items().map_err(|e| e.to_string())
+    }
+
+    /// Offer every sort key that applies here, starting on the current one
+    fn start_sort_picker(&mut self) {
+        let keys: Vec<SortKey> = [SortKey::Count, SortKey::Growth, SortKey::Newest, SortKey::NotInGit]
+            .into_iter()
+            .filter(|&key| match key {
+                SortKey::Growth => self.baseline.is_some(),
+                SortKey::NotInGit => self.git.is_some(),
+                _ => true,
+            })
+            .collect();
+        let selected = keys
+            .iter()
+            .position(|&key| key == self.sort)
+            .unwrap_or(0);
+        let options = keys
+            .iter()
+            .map(|key| key.label().to_string())
+            .collect();
+        self.modal = Some(Modal::choice(Prompt::Sort(keys), "Sort directories by", options, selected));
+    }
+
+    /// Open the fuzzy finder over every directory counted or listed so far
+    fn start_jump(&mut self) {
+        let mut dirs: Vec<PathBuf> = self.file_count_cache
+            .snapshot()
+            .into_iter()
+            .map(|(dir, _)| dir)
+            .collect();
+        dirs.extend(
+            self.items
+                .iter()
+                .filter(|entry| entry.is_dir && !entry.is_parent())
+                .map(|entry| entry.path.clone())
+        );
+        self.jump = Some(JumpView::new(&self.home_dir, dirs));
+    }
+
+    /// Scan the drive selected in the drive picker from its root, and close it
+    fn scan_selected_drive(&mut self) -> io::Result<()> {
+        let Some(view) = self.drives.take() else {
+            return Ok(());
+        };
+        let Some(root) = view.target() else {
+            return Ok(());
+        };
+        if self.is_read_only() {
+            return Ok(());
+        }
+        self.home_dir = root.to_path_buf();
+        self.current_dir = root.to_path_buf();
+        self.summary.action(format!("Switched to {}", root.display()));
+        let other_volume = match &self.mft {
+            Some(Ok(counts)) => mft::drive_letter(root) != Some(counts.volume),
+            _ => true,
+        };
+        if self.use_mft && other_volume {
+            self.spawn_mft();
+        }
+        self.refresh_items()
+    }
+
+    /// Go to the directory selected in the fuzzy finder and close it
+    fn jump_to_selected(&mut self) -> io::Result<()> {
+        let Some(view) = self.jump.take() else {
+            return Ok(());
+        };
+        let Some(target) = view.target() else {
+            return Ok(());
+        };
+        // Counted directories may have been deleted since
+        if self.snapshot.is_none() && !target.is_dir() {
+            return Ok(());
+        }
+        self.current_dir = target.to_path_buf();
+        self.refresh_items()
+    }
+
+    /// Count the files matching the name filter below the selected directory,
+    /// or the current one, on a background thread
+    fn start_filtered_count(&mut self) {
+        let Some(pattern) = &self.name_filter else {
+            return;
+        };
+        if self.is_read_only() {
+            return;
+        }
+        let path = match self.table_state.selected().and_then(|i| self.items.get(i)) {
+            Some(entry) if entry.is_dir && !entry.is_archive => entry.path.clone(),
+            _ => self.current_dir.clone(),
+        };
+        let text = pattern.text().to_string();
+        let pattern = pattern.clone();
+        let options = self.scan_options;
+        let sender = self.event_tx.clone();
+        let counted = path.clone();
+        let counted_pattern = text.clone();
+        self.thread_pool.execute(move || {
+            let stats = scan_dir_matching(&counted, &options, &pattern).unwrap_or_default();
+            let totals = FileTotals { files: stats.files, bytes: stats.bytes };
+            let count = FilteredCount { path: counted, pattern: counted_pattern, totals: Some(totals) };
+            sender.send(AppEvent::FilteredCount(count)).unwrap_or(());
+        });
+        self.filtered_count = Some(FilteredCount { path, pattern: text, totals: None });
+    }
+
+    /// Show a filtered recount, unless another one was started meanwhile
+    fn apply_filtered_count(&mut self, count: FilteredCount) {
+        let current = self.filtered_count
+            .as_ref()
+            .is_some_and(|running| running.path == count.path && running.pattern == count.pattern);
+        if current {
+            self.filtered_count = Some(count);
+        }
+    }
+
+    /// Answer a command from the control socket out of the caches. A directory
+    /// queried before it was counted is counted now, for the next query.
+    fn answer_control(&mut self, request: Request) -> io::Result<()> {
+        let answer = match &request.command {
+            control::Command::Query(path) => {
+                let path = path.canonicalize().unwrap_or_else(|_| path.clone());
+                let files = self.file_count_cache.peek(&path);
+                let mut counting = self.recounting.contains(&path) || self.count_progress.contains_key(&path);
+                if files.is_none() && !counting && !self.is_read_only() && path.is_dir() {
+                    self.recount(path.clone());
+                    counting = true;
+                }
+                control::count_answer(&path, files, counting)
+            }
+            control::Command::Invalidate(path) => {
+                let path = path.canonicalize().unwrap_or_else(|_| path.clone());
+                let dropped = self.invalidate(&path)?;
+                self.summary.action(format!("Invalidated {} through the control socket", path.display()));
+                control::invalidated_answer(dropped)
+            }
+            control::Command::ExportSnapshot =>
+                control::snapshot_answer(self.file_count_cache.snapshot()),
+        };
+        request.respond(answer);
+        Ok(())
+    }
+
+    /// Filter the listing again after the filters changed
+    fn refilter(&mut self) {
+        self.restore_selection = self.selected_path();
+        self.items.append(&mut self.filtered_out);
+        self.resort(true);
+    }
+
+    /// Files found so far by the running count of `path`, once it found any
+    fn counted_so_far(&self, path: &Path) -> Option<usize> {
+        let progress = self.count_progress.get(path)?;
+        Some(progress.files.load(Ordering::Relaxed)).filter(|&found| found > 0)
+    }
+
+    /// Update the files per second of the current directory's count
+    fn sample_throughput(&mut self) {
+        let now = Instant::now();
+        let files = self.counted_so_far(&self.current_dir).unwrap_or(0);
+        let throughput = &mut self.throughput;
+        if files < throughput.files {
+            // Recounted from scratch
+            *throughput = Throughput { files, ..Throughput::default() };
+            return;
+        }
+        if files > throughput.files {
+            throughput.last_progress = now;
+        }
+        let elapsed = now.duration_since(throughput.sampled_at);
+        if elapsed >= THROUGHPUT_INTERVAL {
+            throughput.per_second = Some((files - throughput.files) as f64 / elapsed.as_secs_f64());
+            throughput.files = files;
+            throughput.sampled_at = now;
+        }
+    }
+
+    /// Workers, queue and throughput of the counts, while anything is counted
+    fn status_line(&self) -> Option<String> {
+        if !self.is_counting() {
+            return None;
+        }
+        let workers = self.thread_pool.active_count() + scan::helper_threads();
+        let mut status = format!("Workers {} | Queued {}", workers, self.thread_pool.queued_count());
+        match &self.mft {
+            _ if !self.use_mft => {}
+            None => status.push_str(" | reading the MFT"),
+            Some(Ok(_)) => {}
+            Some(Err(e)) => status.push_str(&format!(" | walking, {}", e)),
+        }
+        if let Some(progress) = self.count_progress.get(&self.current_dir) {
+            let elapsed = progress.started.elapsed().as_secs();
+            if let Some(per_second) = self.throughput.per_second {
+                status.push_str(&format!(" | {:.0} files/s", per_second));
+            }
+            status.push_str(&format!(" | {}:{:02} elapsed", elapsed / 60, elapsed % 60));
+            let stalled = self.throughput.last_progress.elapsed();
+            if stalled >= STALL_WARNING {
+                status.push_str(&format!(" | no new files for {}s", stalled.as_secs()));
+            }
+        }
+        Some(status)
+    }
+
+    /// Whether a mount of this filesystem type is left out of counts (--exclude-fs)
+    fn is_excluded_fs(&self, fs_type: Option<&str>) -> bool {
+        fs_type.is_some_and(|fs_type| self.scan_options.exclude_fs.contains(FsClass::of(fs_type)))
+    }
+
+    /// Browsing a report or an archive rather than the disk: nothing can be changed or recounted
+    fn is_read_only(&self) -> bool {
+        self.snapshot.is_some() || self.archive.is_some() || self.at_roots()
+    }
+
+    /// Whether a spinner is on screen
+    fn is_counting(&self) -> bool {
+        let extensions_pending =
+            self.show_extensions &&
+            self.table_state
+                .selected()
+                .and_then(|i| self.items.get(i))
+                .is_some_and(|entry| {
+                    entry.is_dir && !entry.is_archive && !self.extension_cache.contains_key(&entry.path)
+                });
+        let duplicates_pending = self.duplicates.as_ref().is_some_and(|view| view.report.is_none());
+        let empty_dirs_pending = self.empty_dirs.as_ref().is_some_and(|view| view.report.is_none());
+        let broken_links_pending = self.broken_links.as_ref().is_some_and(|view| view.report.is_none());
+        let cleanable_pending = self.cleanable.as_ref().is_some_and(|view| view.report.is_none());
+        let filtered_count_pending = self.filtered_count.as_ref().is_some_and(|count| count.totals.is_none());
+        self.listing ||
+            (self.show_preview && self.preview_pending.is_some()) ||
+            cleanable_pending ||
+            filtered_count_pending ||
+            duplicates_pending ||
+            empty_dirs_pending ||
+            broken_links_pending ||
+            self.current_dir_count.is_none() ||
+            extensions_pending ||
+            self.items.iter().any(|i| i.is_dir && !i.is_bundle && i.file_count.is_none() && !i.left_out)
+    }
+
+    /// Turn change notifications into debounced recounts of the affected rows.
+    /// Counts stay on screen until the recount replaces them.
+    #[cfg(feature = "watch")]
+    fn process_changes(&mut self) -> io::Result<()> {
+        let now = Instant::now();
+        let changed: Vec<PathBuf> = match &self.watcher {
+            Some(watcher) => watcher.changes().collect(),
+            None => {
+                return Ok(());
+            }
+        };
+
+        for dir in changed {
+            for ancestor in dir.ancestors() {
+                if self.is_displayed(ancestor) {
+                    self.debouncer.record(ancestor.to_path_buf(), now);
+                } else {
+                    // Stale, recounted when navigated to
+                    self.file_count_cache.remove(ancestor);
+                    self.extension_cache.remove(ancestor);
+                }
+            }
+        }
+
+        for dir in self.debouncer.due(now) {
+            if !self.is_displayed(&dir) {
+                continue;
+            }
+            self.recount(dir.clone());
+            if dir == self.current_dir {
+                self.refresh_items()?; // Entries may have appeared or disappeared
+            }
+        }
+        Ok(())
+    }
+
+    /// Evict the least recently used counts once the cache is over its budget,
+    /// with what the other caches hold about those directories. Counts on
+    /// screen, of the roots and of marked entries stay.
+    fn trim_count_cache(&mut self) {
+        let evicted = self.file_count_cache.evict_over_budget(|path| {
+            path == self.current_dir ||
+                self.rows_by_path.contains_key(path) ||
+                self.roots.iter().any(|root| root == path) ||
+                self.marked.contains_key(path)
+        });
+        for path in &evicted {
+            self.size_cache.remove(path);
+            self.newest_cache.remove(path);
+            self.owner_cache.remove(path);
+            self.junk_cache.remove(path);
+            self.extension_cache.remove(path);
+            self.extension_jobs.remove(path);
+            self.hard_link_cache.remove(path);
+            self.broken_link_cache.remove(path);
+            self.skipped_cache.remove(path);
+            self.excluded_cache.remove(path);
+            self.count_time_cache.remove(path);
+        }
+    }
+
+    /// Drop the counts of the directories `stale` selects, and what the other
+    /// caches hold about them; returns how many counts were dropped. Rows keep
+    /// showing their counts, the directories are counted again when listed.
+    fn forget_counts(&mut self, stale: impl Fn(&Path) -> bool) -> usize {
+        let dropped = self.file_count_cache.retain(|p| !stale(p));
+        self.size_cache.retain(|p, _| !stale(p));
+        self.newest_cache.retain(|p, _| !stale(p));
+        self.owner_cache.retain(|p, _| !stale(p));
+        self.junk_cache.retain(|p, _| !stale(p));
+        self.extension_cache.retain(|p, _| !stale(p));
+        self.extension_jobs.retain(|p| !stale(p));
+        self.hard_link_cache.retain(|p, _| !stale(p));
+        self.broken_link_cache.retain(|p, _| !stale(p));
+        self.skipped_cache.retain(|p, _| !stale(p));
+        self.excluded_cache.retain(|p, _| !stale(p));
+        self.count_time_cache.retain(|p, _| !stale(p));
+        if let Some(view) = &mut self.cache_view {
+            view.refresh(&self.file_count_cache);
+        }
+        dropped
+    }
+
+    /// Count a directory again while its current count stays on screen, dimmed
+    fn recount(&mut self, path: PathBuf) {
+        let collect_extensions =
+            self.scan_options.collect_extensions || self.extension_cache.contains_key(&path);
+        self.recounting.insert(path.clone());
+        self.spawn_count(path, collect_extensions);
+    }
+
+    /// Forget what is cached about `path`, everything below it and the
+    /// directories above it, whose counts include its files, and recount those
+    /// on screen; returns how many counts were dropped
+    fn invalidate(&mut self, path: &Path) -> io::Result<usize> {
+        let stale = |p: &Path| p.starts_with(path) || path.starts_with(p);
+        let dropped = self.forget_counts(stale);
+        if !self.is_read_only() {
+            let displayed: Vec<PathBuf> = std::iter::once(self.current_dir.clone())
+                .chain(self.items.iter().filter(|item| item.is_dir).map(|item| item.path.clone()))
+                .filter(|p| stale(p))
+                .collect();
+            for dir in displayed {
+                self.recount(dir);
+            }
+            if path.starts_with(&self.current_dir) {
+                self.refresh_items()?; // Entries may have appeared or disappeared
+            }
+        }
+        Ok(dropped)
+    }
+
+    /// Invalidate the selected directory (or the current one) with its subtree and ancestors
+    fn invalidate_selected(&mut self) -> io::Result<()> {
+        if self.is_read_only() {
+            return Ok(());
+        }
+        let path = match self.table_state.selected().and_then(|i| self.items.get(i)) {
+            Some(entry) if entry.is_dir && !entry.is_parent() => entry.alias_of.clone().unwrap_or_else(|| entry.path.clone()),
+            _ => self.current_dir.clone(),
+        };
+        let dropped = self.invalidate(&path)?;
+        self.summary.action(format!("Invalidated {}", path.display()));
+        self.toasts.info(format!("Dropped {} cached counts in and above {}", self.numbers.count(dropped as u64), path.display()));
+        Ok(())
+    }
+
+    /// Recount the selected directory (or the current one) from scratch,
+    /// dropping everything cached below it
+    fn force_recount_selected(&mut self) {
+        if self.is_read_only() {
+            return;
+        }
+        let path = match self.table_state.selected().and_then(|i| self.items.get(i)) {
+            Some(entry) if entry.is_dir => entry.alias_of.clone().unwrap_or_else(|| entry.path.clone()),
+            _ => self.current_dir.clone(),
+        };
+        self.file_count_cache.retain(|p| p == path || !p.starts_with(&path));
+        self.extension_cache.retain(|p, _| p == &path || !p.starts_with(&path));
+        self.extension_jobs.retain(|p| !p.starts_with(&path));
+        self.summary.action(format!("Recounted {}", path.display()));
+        self.recount(path);
+    }
+
+    /// Whether the count of `path` is shown (current directory or a listed entry)
+    #[cfg(feature = "watch")]
+    fn is_displayed(&self, path: &Path) -> bool {
+        path == self.current_dir || self.items.iter().any(|i| i.is_dir && i.path == path)
+    }
+
+    /// Read the Master File Table of the current directory's volume on a
+    /// separate thread; directories are walked until it is in
+    fn spawn_mft(&mut self) {
+        self.mft = None;
+        let dir = self.current_dir.clone();
+        let sender = self.event_tx.clone();
+        std::thread::spawn(move || {
+            sender.send(AppEvent::Mft(mft::read_volume(&dir))).unwrap_or(());
+        });
+    }
+
+    fn mft_files(&self, path: &Path) -> Option<usize> {
+        match &self.mft {
+            Some(Ok(counts)) => counts.files(path),
+            _ => None,
+        }
+    }
+
+    /// Use the counts of a Master File Table that was read, also for the
+    /// directories on screen that are still being walked; returns whether any changed
+    fn apply_mft(&mut self, result: Result<MftCounts, String>) -> bool {
+        let counts = match result {
+            Ok(counts) => Arc::new(counts),
+            Err(e) => {
+                self.mft = Some(Err(e));
+                return false;
+            }
+        };
+        let mut pending: Vec<PathBuf> = self.items
+            .iter()
+            .filter(|entry| entry.is_dir && !entry.is_archive && entry.file_count.is_none())
+            .map(|entry| entry.path.clone())
+            .collect();
+        if self.current_dir_count.is_none() {
+            pending.push(self.current_dir.clone());
+        }
+        let mut updated = false;
+        for path in pending {
+            if let Some(files) = counts.files(&path) {
+                self.file_count_cache.insert(path.clone(), files);
+                updated |= self.apply_count(CountMessage { path, files, bytes: None, approximate: false });
+            }
+        }
+        self.mft = Some(Ok(counts));
+        updated
+    }
+
+    /// Query the search index for the current directory on a separate thread,
+    /// so estimates are not queued behind the walkers
+    fn spawn_index_counts(&self) {
+        let dir = self.current_dir.clone();
+        let sender = self.event_tx.clone();
+        let cache = Arc::clone(&self.index_cache);
+
+        std::thread::spawn(move || {
+            let counts = match index::index_counts(&dir) {
+                Some(counts) => counts,
+                None => {
+                    return;
+                }
+            };
+            let mut estimates = vec![(dir.clone(), counts.total, counts.total_bytes)];
+            estimates.extend(
+                counts.children.into_iter().map(|(name, files)| (dir.join(name), files, None))
+            );
+            for (path, files, bytes) in estimates {
+                cache.insert(path.clone(), files);
+                sender.send(AppEvent::Count(CountMessage { path, files, bytes, approximate: true })).unwrap_or(());
+            }
+        });
+    }
+
+    /// Make sure the extension histogram of the selected directory is available or being computed
+    fn ensure_selected_extensions(&mut self) {
+        if self.is_read_only() {
+            return;
+        }
+        let path = match self.table_state.selected().and_then(|i| self.items.get(i)) {
+            Some(entry) if entry.is_dir && !entry.is_archive => entry.path.clone(),
+            _ => {
+                return;
+            }
+        };
+        if !self.extension_cache.contains_key(&path) && self.extension_jobs.insert(path.clone()) {
+            self.spawn_count(path, true);
+        }
+    }
+
+    /// On moving to another directory, remember where the one left was and
+    /// select the directory just left when going up, or where the user was
+    /// in the new one. Row indices of another directory mean nothing here.
+    fn restore_position(&mut self) {
+        let left = std::mem::replace(&mut self.shown_dir, self.current_dir.clone());
+        if left == self.current_dir {
+            return;
+        }
+        let selected = self.table_state
+            .selected()
+            .and_then(|index| self.items.get(index))
+            .map(|entry| entry.path.clone());
+        if let Some(selected) = selected {
+            self.positions.insert(left.clone(), (selected, self.scroll_offset));
+        }
+        if self.restore_selection.is_some() {
+            return; // A tab or a saved session says what to select
+        }
+
+        let remembered = self.positions.get(&self.current_dir).cloned();
+        self.scroll_offset = remembered.as_ref().map_or(0, |(_, offset)| *offset);
+        self.table_state.select(Some(0));
+        let came_from = match self.at_roots() {
+            true => self.roots.iter().find(|root| left.starts_with(root)).cloned(),
+            false =>
+                left
+                    .ancestors()
+                    .find(|ancestor| ancestor.parent() == Some(self.current_dir.as_path()))
+                    .map(Path::to_path_buf),
+        };
+        self.restore_selection = came_from.or(remembered.map(|(selected, _)| selected));
+    }
+
+    /// Read the preview of the selected entry unless it is shown or on its way. A
+    /// directory's extension histogram is collected too, for its summary.
+    fn ensure_selected_preview(&mut self) {
+        if self.is_read_only() {
+            return;
+        }
+        let entry = match self.table_state.selected() {
+            Some(index) if !self.is_parent_entry(index) => self.items.get(index),
+            _ => None,
+        };
+        let Some((path, is_dir)) = entry.map(|entry| (entry.path.clone(), entry.is_dir && !entry.is_archive)) else {
+            return;
+        };
+        if is_dir {
+            self.ensure_selected_extensions();
+        }
+        let shown = self.preview.as_ref().is_some_and(|preview| preview.path == path);
+        if shown || self.preview_pending.as_ref() == Some(&path) {
+            return;
+        }
+        self.preview_pending = Some(path.clone());
+        spawn_preview(path, is_dir, self.event_tx.clone());
+    }
+
+    /// Show a preview that arrived, and count the subdirectories of a directory
+    /// that are not yet, to rank them by size
+    fn apply_preview(&mut self, preview: Preview) {
+        if self.preview_pending.as_ref() != Some(&preview.path) {
+            return; // Another entry was selected meanwhile
+        }
+        self.preview_pending = None;
+        if let PreviewContent::Dir(children) = &preview.content {
+            for subdir in &children.subdirs {
+                if !self.file_count_cache.contains_key(subdir) && !self.count_progress.contains_key(subdir) {
+                    self.spawn_count(subdir.clone(), false);
+                }
+            }
+        }
+        self.preview = Some(preview);
+    }
+
+    /// List several directories given on the command line side by side, each counted on its own
+    fn show_roots(&mut self, roots: Vec<PathBuf>) -> io::Result<()> {
+        self.roots = roots;
+        self.home_dir = PathBuf::new();
+        self.current_dir = PathBuf::new();
+        self.refresh_items()?;
+        self.tabs[self.active_tab] = self.tab();
+        Ok(())
+    }
+
+    /// The current directory, or what the list of roots holds
+    fn current_dir_label(&self) -> String {
+        match self.at_roots() {
+            true => format!("{} roots", self.roots.len()),
+            false => self.current_dir.display().to_string(),
+        }
+    }
+
+    /// Whether the list of roots is shown
+    fn at_roots(&self) -> bool {
+        !self.roots.is_empty() && self.current_dir == self.home_dir
+    }
+
+    /// Files in all roots, once each is counted
+    fn roots_total(&self) -> Option<usize> {
+        self.roots
+            .iter()
+            .map(|root| self.file_count_cache.peek(root))
+            .sum()
+    }
+
+    /// Refresh the item list with the roots, from the caches where they are counted
+    fn refresh_root_items(&mut self) {
+        self.listing_generation.fetch_add(1, Ordering::Relaxed); // Drop batches of the directory left
+        self.listing = false;
+        self.archive = None;
+        self.git = None;
+        self.fs_usage = None;
+        self.items.clear();
+        self.filtered_out.clear();
+        let previous_selection = self.table_state.selected().unwrap_or(0);
+        self.table_state.select(Some(previous_selection));
+
+        for root in self.roots.clone() {
+            let file_count = self.file_count_cache.peek(&root);
+            if file_count.is_none() {
+                self.spawn_count(root.clone(), self.scan_options.collect_extensions);
+            }
+            let metadata = fs::metadata(&root).ok();
+            self.items.push(DirEntry {
+                kind: EntryKind::Child,
+                name: root.display().to_string(),
+                is_dir: metadata.as_ref().is_some_and(|m| m.is_dir()),
+                is_bundle: false,
+                is_archive: false,
+                is_link: false,
+                file_count,
+                approx_count: None,
+                alias_of: None,
+                modified: metadata.as_ref().and_then(|m| m.modified().ok()),
+                newest: self.newest_cache.get(&root).map(|v| *v),
+                ownership: metadata.as_ref().and_then(Ownership::of),
+                junk: None,
+                mount: None,
+                left_out: false,
+                sizes: self.size_cache.get(&root).map(|v| *v),
+                baseline_count: None,
+                git: None,
+                path: root,
+            });
+        }
+        self.current_dir_count = self.roots_total();
+        self.current_dir_approx = None;
+        self.current_dir_approx_bytes = None;
+        self.resort(true);
+    }
+
+    /// Refresh the item list from a report whose top node is at `root`:
+    /// the snapshot rooted at `home_dir`, or a browsed archive
+    fn refresh_snapshot_items(&mut self, snapshot: &ReportNode, root: &Path) {
+        self.items.clear();
+        self.filtered_out.clear();
+
+        let include_back = self.current_dir != self.home_dir;
+        let previous_selection = self.table_state.selected().unwrap_or(0);
+        self.table_state.select(Some(previous_selection));
+
+        let find = |path: &Path| {
+            path.strip_prefix(root)
+                .ok()
+                .and_then(|relative| snapshot.find(relative))
+        };
+        let node = find(&self.current_dir);
+        self.current_dir_count = node.and_then(|n| n.files);
+
+        if include_back {
+            if let Some(parent) = self.current_dir.parent() {
+                self.items.push(DirEntry {
+                    kind: EntryKind::Parent,
+                    name: String::from(PARENT_ENTRY_NAME),
+                    path: parent.to_path_buf(),
+                    is_dir: true,
+                    is_bundle: false,
+                    is_archive: false,
+                    is_link: false,
+                    // The parent of an archive is on disk
+                    file_count: find(parent)
+                        .and_then(|n| n.files)
+                        .or_else(|| self.file_count_cache.peek(parent)),
+                    approx_count: None,
+                    alias_of: None,
+                    modified: None,
+                    newest: None,
+                    ownership: None,
+                    junk: None,
+                    mount: None,
+                    left_out: false,
+                    sizes: None,
+                    baseline_count: None,
+                    git: None,
+                });
+            }
+        }
+
+        for child in node.map(|n| n.children.as_slice()).unwrap_or(&[]) {
+            self.items.push(DirEntry {
+                kind: EntryKind::Child,
+                name: child.name.clone(),
+                path: self.current_dir.join(&child.name),
+                is_dir: child.is_dir,
+                is_bundle: false,
+                is_archive: false,
+                is_link: false,
+                file_count: child.files,
+                approx_count: None,
+                alias_of: None,
+                modified: None,
+                newest: None,
+                ownership: None,
+                junk: None,
+                mount: None,
+                left_out: false,
+                sizes: None,
+                baseline_count: None,
+                git: None,
+            });
+        }
+
+        self.fill_baselines(0);
+        self.resort(true);
+    }
+
+    /// Look up the listed directories from `first` on in the --diff baseline
+    fn fill_baselines(&mut self, first: usize) {
+        let baseline = match &self.baseline {
+            Some(baseline) => baseline,
+            None => {
+                return;
+            }
+        };
+        let root = Path::new(&baseline.name);
+        let current_dir = self.current_dir.canonicalize().unwrap_or_else(|_| self.current_dir.clone());
+        for item in self.items.iter_mut().skip(first) {
+            if !item.is_dir {
+                continue;
+            }
+            let path = if item.is_parent() {
+                current_dir.parent().map(Path::to_path_buf)
+            } else {
+                item.path.file_name().map(|name| current_dir.join(name))
+            };
+            item.baseline_count = path.and_then(|path| {
+                let relative = path.strip_prefix(root).ok()?;
+                match baseline.find(relative) {
+                    Some(node) => node.files,
+                    // Not in the baseline although its parent is: a new directory
+                    None => relative.parent().and_then(|parent| baseline.find(parent)).map(|_| 0),
+                }
+            });
+        }
+    }
+
+    /// Look up the listed entries from `first` on in the git status of the
+    /// current directory; entries git knows nothing about count zero files
+    fn fill_git(&mut self, first: usize) {
+        for item in self.items.iter_mut().skip(first) {
+            item.git = match &self.git {
+                Some(git) if !item.is_parent() =>
+                    Some(
+                        item.path
+                            .file_name()
+                            .and_then(|name| git.children.get(name))
+                            .copied()
+                            .unwrap_or_default()
+                    ),
+                _ => None,
+            };
+        }
+    }
+
+    /// Take the git status of a directory, if it is still the current one
+    fn apply_git(&mut self, status: GitStatus) {
+        if status.dir != self.current_dir || self.snapshot.is_some() || self.archive.is_some() {
+            return;
+        }
+        self.git = Some(status);
+        self.fill_git(0);
+        self.resort(true);
+    }
+
+    /// Whether the entry at `index` is the "back to parent directory" entry
+    fn is_parent_entry(&self, index: usize) -> bool {
+        self.items.get(index).is_some_and(DirEntry::is_parent)
+    }
+
+    /// Range of rows covered by the visual selection, if active
+    fn visual_range(&self) -> Option<std::ops::RangeInclusive<usize>> {
+        let anchor = self.visual_anchor?;
+        let selected = self.table_state.selected()?;
+        Some(anchor.min(selected)..=anchor.max(selected))
+    }
+
+    /// Whether a row is marked or inside the visual selection
+    fn is_marked(&self, index: usize) -> bool {
+        if self.is_parent_entry(index) {
+            return false;
+        }
+        let in_visual = self.visual_range().is_some_and(|range| range.contains(&index));
+        in_visual || self.items.get(index).is_some_and(|entry| self.marked.contains_key(&entry.path))
+    }
+
+    /// Mark the row at `index`, or unmark it if it was marked
+    fn toggle_mark(&mut self, index: usize) {
+        if self.is_parent_entry(index) {
+            return;
+        }
+        if let Some(entry) = self.items.get(index) {
+            if self.marked.remove(&entry.path).is_none() {
+                self.marked.insert(entry.path.clone(), marked_entry(entry));
+            }
+        }
+    }
+
+    /// Start a visual range selection, or mark the whole range when one is active
+    fn toggle_visual(&mut self) {
+        match self.visual_range() {
+            Some(range) => {
+                for index in range {
+                    if self.is_parent_entry(index) {
+                        continue;
+                    }
+                    if let Some(entry) = self.items.get(index) {
+                        self.marked.insert(entry.path.clone(), marked_entry(entry));
+                    }
+                }
+                self.visual_anchor = None;
+            }
+            None => {
+                self.visual_anchor = self.table_state.selected();
+            }
+        }
+    }
+
+    /// Aggregate count and size of the marked entries, for the footer
+    fn marked_summary(&self) -> Option<String> {
+        if self.marked.is_empty() {
+            return None;
+        }
+        let mut files = 0usize;
+        let mut counting = false;
+        let mut bytes = 0u64;
+        for (path, entry) in &self.marked {
+            if entry.is_dir && !entry.is_bundle {
+                match self.file_count_cache.peek(path) {
+                    Some(count) => {
+                        files += count;
+                    }
+                    None => {
+                        counting = true;
+                    }
+                }
+            } else {
+                files += 1;
+            }
+            bytes += entry.size.map_or(0, |size| size.get(self.apparent_size));
+        }
+        Some(
+            format!(
+                "Marked: {} entries, {}{} files, {} in plain files",
+                self.marked.len(),
+                self.numbers.count(files as u64),
+                if counting { "+" } else { "" },
+                format::human_bytes(bytes)
+            )
+        )
+    }
+
+    /// Preview a batch operation on the marked entries, or the selected one,
+    /// checking every entry before anything is changed
+    fn request_batch(&mut self, operation: BatchOperation) {
+        if self.is_read_only() {
+            return;
+        }
+
+        let targets: Vec<BatchTarget> = if self.marked.is_empty() {
+            let index = match self.table_state.selected() {
+                Some(index) if !self.is_parent_entry(index) => index,
+                _ => {
+                    return; // Never act on the parent directory entry
+                }
+            };
+            match self.items.get(index) {
+                Some(entry) => {
+                    let marked = marked_entry(entry);
+                    vec![BatchTarget {
+                        path: entry.path.clone(),
+                        name: entry.name.clone(),
+                        is_dir: entry.is_dir,
+                        files: entry.file_count,
+                        size: marked.size,
+                    }]
+                }
+                None => {
+                    return;
+                }
+            }
+        } else {
+            self.marked
+                .iter()
+                .map(|(path, entry)| BatchTarget {
+                    path: path.clone(),
+                    name: entry.name.clone(),
+                    is_dir: entry.is_dir,
+                    files: self.file_count_cache.peek(path),
+                    size: entry.size,
+                })
+                .collect()
+        };
+
+        let conflicts = batch_conflicts(&operation, &targets);
+        self.batch_preview = Some(BatchPreview { operation, targets, conflicts, scroll: 0 });
+    }
+
+    /// Apply a confirmed batch operation to all of its entries and update the caches.
+    /// Returns the entries that failed.
+    fn run_batch(&mut self, preview: BatchPreview) -> io::Result<Vec<(PathBuf, io::Error)>> {
+        let mut failures = Vec::new();
+        let total = preview.targets.len();
+        for target in preview.targets {
+            match &preview.operation {
+                BatchOperation::Trash =>
+                    match trash::move_to_trash(&target.path) {
+                        Ok(()) => {
+                            self.marked.remove(&target.path);
+                            self.forget_deleted(&target);
+                            let files = if target.is_dir { target.files.unwrap_or(0) } else { 1 };
+                            self.summary.deleted(files, target.size.map(|size| size.get(self.apparent_size)));
+                            self.summary.action(format!("Trashed {}", target.path.display()));
+                        }
+                        Err(e) => failures.push((target.path, e)),
+                    }
+                BatchOperation::Move(destination) => {
+                    let moved_to = destination.join(target.path.file_name().unwrap_or_default());
+                    match fs::rename(&target.path, &moved_to) {
+                        Ok(()) => {
+                            self.marked.remove(&target.path);
+                            self.forget_deleted(&target);
+                            // The destination and its ancestors gained files
+                            for ancestor in destination.ancestors() {
+                                self.file_count_cache.remove(ancestor);
+                                self.size_cache.remove(ancestor);
+                                self.newest_cache.remove(ancestor);
+                                self.extension_cache.remove(ancestor);
+                                self.junk_cache.remove(ancestor);
+                                self.extension_jobs.remove(ancestor);
+                            }
+                            self.summary.action(
+                                format!("Moved {} to {}", target.path.display(), moved_to.display())
+                            );
+                        }
+                        Err(e) => failures.push((target.path, e)),
+                    }
+                }
+                // Finishes in the background, see `apply_archive`
+                BatchOperation::Archive => {
+                    let files = if target.is_dir { target.files.unwrap_or(0) } else { 1 };
+                    spawn_archive(target.path, files, self.event_tx.clone());
+                }
+            }
+        }
+        let done = total - failures.len();
+        let entries = if done == 1 { "entry" } else { "entries" };
+        match &preview.operation {
+            _ if done == 0 => {}
+            BatchOperation::Trash => self.toasts.info(format!("Moved {} {} to the trash", done, entries)),
+            BatchOperation::Move(destination) => {
+                self.toasts.info(format!("Moved {} {} to {}", done, entries, destination.display()));
+            }
+            BatchOperation::Archive => self.toasts.info(format!("Archiving {} {}", done, entries)),
+        }
+        self.refresh_items()?;
+        Ok(failures)
+    }
+
+    /// Drop a deleted entry from the caches and subtract it from its ancestors
+    fn forget_deleted(&mut self, target: &BatchTarget) {
+        // Files removed from every ancestor's count
+        let opaque = !target.is_dir || (self.scan_options.opaque_bundles && is_bundle(&target.path));
+        let removed = if opaque { Some(1) } else { target.files };
+        let removed_bytes = match target.size {
+            Some(size) => Some(size),
+            // A bundle only contributed its own inode to the sizes above it
+            None if target.is_dir && !opaque => self.size_cache.get(&target.path).map(|v| *v),
+            None => None,
+        };
+
+        // Forget the entry and everything below it
+        self.file_count_cache.retain(|path| !path.starts_with(&target.path));
+        self.extension_cache.retain(|path, _| !path.starts_with(&target.path));
+        self.junk_cache.retain(|path, _| !path.starts_with(&target.path));
+        self.size_cache.retain(|path, _| !path.starts_with(&target.path));
+        self.newest_cache.retain(|path, _| !path.starts_with(&target.path));
+        self.touch_ancestors(&target.path);
+
+        for ancestor in target.path.ancestors().skip(1) {
+            match removed_bytes {
+                Some(removed_bytes) => {
+                    if let Some(mut sizes) = self.size_cache.get_mut(ancestor) {
+                        sizes.subtract(removed_bytes);
+                    }
+                }
+                None => {
+                    self.size_cache.remove(ancestor);
+                }
+            }
+            match removed {
+                Some(removed) => {
+                    self.file_count_cache.update(ancestor, |count| count.saturating_sub(removed));
+                }
+                // Unknown size: drop the ancestors so they get recounted
+                None => {
+                    self.file_count_cache.remove(ancestor);
+                }
+            }
+            // Histograms cannot be patched without rescanning
+            self.extension_cache.remove(ancestor);
+            self.extension_jobs.remove(ancestor);
+            self.junk_cache.remove(ancestor);
+        }
+    }
+
+    /// Move selection to the next item
+    fn next(&mut self) {
+        let last = match self.items.len().checked_sub(1) {
+            Some(last) => last,
+            None => {
+                return; // Nothing to select in an empty listing
+            }
+        };
+        let i = match self.table_state.selected() {
+            Some(i) => {
+                if i >= last { 0 } else { i + 1 }
+            }
+            None => 0,
+        };
+        self.table_state.select(Some(i));
+    }
+
+    /// Move selection to the previous item
+    fn previous(&mut self) {
+        let last = match self.items.len().checked_sub(1) {
+            Some(last) => last,
+            None => {
+                return; // Nothing to select in an empty listing
+            }
+        };
+        let i = match self.table_state.selected() {
+            Some(i) => {
+                if i == 0 || i > last { last } else { i - 1 }
+            }
+            None => last,
+        };
+        self.table_state.select(Some(i));
+    }
+
+    /// Sort directories by `key`, or back by count if they already are
+    fn toggle_sort(&mut self, key: SortKey) {
+        self.set_sort(if self.sort == key { SortKey::Count } else { key });
+    }
+
+    fn set_sort(&mut self, key: SortKey) {
+        self.sort = key;
+        self.restore_selection = self.selected_path();
+        self.resort(true);
+    }
+
+    /// Describe how the item list is ordered
+    fn sort_description(&self) -> String {
+        match self.sort {
+            SortKey::Count => String::from("Directories first, by file count (descending), then by name"),
+            SortKey::Growth =>
+                String::from("Directories first, by growth since the --diff snapshot (descending), then by name"),
+            SortKey::Newest =>
+                String::from("Directories first, by their most recent modification (newest first), then by name"),
+            SortKey::NotInGit =>
+                String::from("Directories first, by untracked and ignored files (descending), then by name"),
+        }
+    }
+
+    /// Describe the filters applied to the item list
+    fn filter_description(&self) -> String {
+        let mut filters = Vec::new();
+        match self.excluded.len() {
+            0 => {}
+            excluded if self.dim_filtered => filters.push(format!("{} excluded by the cleanup wizard (dimmed)", excluded)),
+            excluded => filters.push(format!("{} excluded by the cleanup wizard", excluded)),
+        }
+        let dimmed = if self.dim_filtered { " (dimmed)" } else { "" };
+        match self.shown_kinds {
+            ShownKinds::All => {}
+            ShownKinds::Directories => filters.push(format!("directories only{}", dimmed)),
+            ShownKinds::Files => filters.push(format!("files only{}", dimmed)),
+        }
+        if let Some(min) = self.min_count {
+            filters.push(format!("directories below {} files{}", self.numbers.count(min as u64), dimmed));
+        }
+        if let Some(pattern) = &self.name_filter {
+            filters.push(format!("files not matching {}{}", pattern.text(), dimmed));
+        }
+        if !self.scan_options.exclude_fs.is_empty() {
+            filters.push(format!("{} mounts not counted", self.scan_options.exclude_fs.describe()));
+        }
+        match filters.is_empty() {
+            true => String::from("None"),
+            false => filters.join(", "),
+        }
+    }
+
+    /// Investigation state saved with --export-state
+    #[cfg(feature = "persist")]
+    fn export_state(&self) -> AppState {
+        let mut excluded: Vec<PathBuf> = self.excluded.iter().cloned().collect();
+        excluded.sort();
+        AppState {
+            home_dir: Some(self.home_dir.clone()).filter(|home_dir| *home_dir != self.current_dir),
+            current_dir: self.current_dir.clone(),
+            selected: self.selected_path(),
+            sort_by_growth: self.sort == SortKey::Growth,
+            sort_by_newest: self.sort == SortKey::Newest,
+            dim_filtered: self.dim_filtered,
+            marked: self.marked.keys().cloned().collect(),
+            excluded,
+            pinned: self.file_count_cache.pinned(),
+            baseline: self.baseline.clone(),
+        }
+    }
+
+    /// The view of the active tab
+    fn tab(&self) -> Tab {
+        Tab {
+            current_dir: self.current_dir.clone(),
+            archive: self.archive.clone(),
+            selected: self.selected_path(),
+            sort: self.sort,
+        }
+    }
+
+    /// Open a tab on the current directory, after the active one
+    fn new_tab(&mut self) {
+        self.tabs[self.active_tab] = self.tab();
+        self.active_tab += 1;
+        self.tabs.insert(self.active_tab, self.tab());
+        if let Some(split) = self.split.as_mut().filter(|split| split.partner >= self.active_tab) {
+            split.partner += 1;
+        }
+    }
+
+    /// Close the active tab, unless it is the last one
+    fn close_tab(&mut self) -> io::Result<()> {
+        if self.tabs.len() < 2 {
+            return Ok(());
+        }
+        self.split = None;
+        self.tabs.remove(self.active_tab);
+        self.active_tab = self.active_tab.min(self.tabs.len() - 1);
+        self.show_tab(self.tabs[self.active_tab].clone())
+    }
+
+    /// Switch to the next tab (or the previous one), keeping the view of the one
+    /// left. In the dual-pane view, move the focus to the other pane instead.
+    fn switch_tab(&mut self, forward: bool) -> io::Result<()> {
+        if self.tabs.len() < 2 {
+            return Ok(());
+        }
+        self.tabs[self.active_tab] = self.tab();
+        if let Some(split) = &mut self.split {
+            let partner = std::mem::replace(&mut split.partner, self.active_tab);
+            split.items = std::mem::take(&mut self.items);
+            split.scroll_offset = std::mem::replace(&mut self.scroll_offset, split.scroll_offset);
+            self.active_tab = partner;
+            return self.show_tab(self.tabs[partner].clone());
+        }
+        let offset = if forward { 1 } else { self.tabs.len() - 1 };
+        self.active_tab = (self.active_tab + offset) % self.tabs.len();
+        self.show_tab(self.tabs[self.active_tab].clone())
+    }
+
+    /// Show a second pane on a new tab of the current directory, or go back to one pane
+    fn toggle_split(&mut self) {
+        if self.split.take().is_some() {
+            return;
+        }
+        let items = self.items.clone();
+        self.new_tab();
+        self.split = Some(SplitView {
+            partner: self.active_tab - 1,
+            items,
+            scroll_offset: self.scroll_offset,
+            compare: false,
+        });
+    }
+
+    /// What the comparison of the panes looks at: the count of a directory, the length of a file
+    fn compared_value(&self, entry: &DirEntry) -> Option<u64> {
+        if entry.is_dir {
+            let counted = entry.alias_of.as_ref().unwrap_or(&entry.path);
+            let count = self.file_count_cache.peek(counted).or(entry.file_count);
+            count.map(|count| count as u64)
+        } else {
+            entry.sizes.map(|sizes| sizes.apparent)
+        }
+    }
+
+    /// Entries of `items` by name, with what the comparison looks at. The
+    /// parent entry (first when not at the top) is left out.
+    fn compared_entries<'a>(&self, items: &'a [DirEntry]) -> HashMap<&'a str, (bool, Option<u64>)> {
+        items
+            .iter()
+            .filter(|entry| !entry.is_parent())
+            .map(|entry| (entry.name.as_str(), (entry.is_dir, self.compared_value(entry))))
+            .collect()
+    }
+
+    /// Whether an entry is missing from the other pane, or has another count or length
+    /// there. Entries still being counted on either side are not flagged yet.
+    fn differs(&self, entry: &DirEntry, other: &HashMap<&str, (bool, Option<u64>)>) -> bool {
+        if entry.is_parent() {
+            return false;
+        }
+        match other.get(entry.name.as_str()) {
+            None => true,
+            Some(&(is_dir, value)) =>
+                is_dir != entry.is_dir ||
+                    matches!((value, self.compared_value(entry)), (Some(a), Some(b)) if a != b),
+        }
+    }
+
+    fn show_tab(&mut self, tab: Tab) -> io::Result<()> {
+        self.current_dir = tab.current_dir;
+        self.archive = tab.archive;
+        self.sort = tab.sort;
+        self.table_state.select(Some(0));
+        self.restore_selection = tab.selected;
+        self.refresh_items()
+    }
+
+    /// Path of the selected entry, other than the parent entry
+    fn selected_path(&self) -> Option<PathBuf> {
+        self.table_state
+            .selected()
+            .filter(|&index| !self.is_parent_entry(index))
+            .and_then(|index| self.items.get(index))
+            .map(|entry| entry.path.clone())
+    }
+
+    /// Where the user left off, kept for the next launch with session.restore.
+    /// Marks are not carried over, so a batch operation never acts on stale ones.
+    #[cfg(feature = "persist")]
+    fn session_state(&self) -> AppState {
+        AppState { marked: Vec::new(), baseline: None, ..self.export_state() }
+    }
+
+    /// Continue from a state saved with --export-state. Marks of entries that
+    /// no longer exist are dropped; an explicit --diff wins over the saved baseline.
+    #[cfg(feature = "persist")]
+    fn import_state(&mut self, state: AppState) -> io::Result<()> {
+        if self.snapshot.is_some() || state.current_dir.is_dir() {
+            if !state.current_dir.starts_with(&self.home_dir) {
+                self.home_dir = match state.home_dir {
+                    Some(home_dir) if state.current_dir.starts_with(&home_dir) => home_dir,
+                    _ => state.current_dir.clone(),
+                };
+            }
+            self.current_dir = state.current_dir;
+            self.restore_selection = state.selected;
+        }
+        self.dim_filtered |= state.dim_filtered;
+        for path in state.marked {
+            if let Ok(metadata) = fs::symlink_metadata(&path) {
+                let is_dir = path.is_dir();
+                let entry = MarkedEntry {
+                    name: path.file_name().map(format::display_name).unwrap_or_default(),
+                    is_dir,
+                    is_bundle: is_dir && self.scan_options.opaque_bundles && is_bundle(&path),
+                    size: if is_dir { None } else { Some(ByteSizes::of(&metadata)) },
+                };
+                self.marked.insert(path, entry);
+            }
+        }
+        self.excluded.extend(state.excluded);
+        for path in state.pinned {
+            self.file_count_cache.pin(path);
+        }
+        if self.baseline.is_none() {
+            self.baseline = state.baseline;
+        }
+        if state.sort_by_growth && self.baseline.is_some() {
+            self.sort = SortKey::Growth;
+        } else if state.sort_by_newest {
+            self.sort = SortKey::Newest;
+        }
+        self.refresh_items()
+    }
+
+    /// Look for duplicate files below the selected directory, or the current one
+    fn start_duplicates(&mut self) {
+        if self.is_read_only() {
+            return;
+        }
+        let dir = match self.table_state.selected().and_then(|i| self.items.get(i)) {
+            Some(entry) if entry.is_dir && !entry.is_archive => entry.path.clone(),
+            _ => self.current_dir.clone(),
+        };
+        spawn_duplicate_scan(dir.clone(), self.thread_pool.clone(), self.event_tx.clone());
+        self.duplicates = Some(DuplicateView { dir, report: None, scroll: 0 });
+    }
+
+    /// Show a duplicate report, unless its view was closed or replaced meanwhile
+    fn apply_duplicates(&mut self, report: DuplicateReport) {
+        if let Some(view) = &mut self.duplicates {
+            if view.dir == report.dir && view.report.is_none() {
+                view.report = Some(report);
+            }
+        }
+    }
+
+    /// Look for empty directories below the selected directory, or the current one
+    fn start_empty_dirs(&mut self) {
+        if self.is_read_only() {
+            return;
+        }
+        let dir = match self.table_state.selected().and_then(|i| self.items.get(i)) {
+            Some(entry) if entry.is_dir && !entry.is_archive => entry.path.clone(),
+            _ => self.current_dir.clone(),
+        };
+        spawn_empty_dir_scan(dir.clone(), self.event_tx.clone());
+        self.empty_dirs = Some(EmptyDirView { dir, report: None, removed: 0, failures: Vec::new(), scroll: 0 });
+    }
+
+    /// Show the empty directories found, unless their view was closed or replaced meanwhile
+    fn apply_empty_dirs(&mut self, report: EmptyDirReport) {
+        if let Some(view) = &mut self.empty_dirs {
+            if view.dir == report.dir && view.report.is_none() {
+                view.report = Some(report);
+            }
+        }
+    }
+
+    /// Remove every empty directory of the view. Failed ones stay listed with their error.
+    fn remove_empty_dirs(&mut self) -> io::Result<()> {
+        let Some(view) = &mut self.empty_dirs else {
+            return Ok(());
+        };
+        let Some(report) = &mut view.report else {
+            return Ok(());
+        };
+        view.failures.clear();
+        let mut removed = Vec::new();
+        let mut kept = Vec::new();
+        for empty in report.found.drain(..) {
+            match remove_chain(&empty.path) {
+                Ok(()) => removed.push(empty),
+                Err(e) => {
+                    view.failures.push((empty.path.clone(), e));
+                    kept.push(empty);
+                }
+            }
+        }
+        report.found = kept;
+        let dirs: usize = removed.iter().map(|empty| empty.dirs).sum();
+        view.removed += dirs;
+        for empty in removed {
+            let name = empty.path.file_name().map(format::display_name).unwrap_or_default();
+            let target = BatchTarget { path: empty.path, name, is_dir: true, files: Some(0), size: None };
+            self.marked.remove(&target.path);
+            self.forget_deleted(&target);
+        }
+        if dirs > 0 {
+            self.summary.action(format!("Removed {} empty directories", dirs));
+        }
+        self.refresh_items()
+    }
+
+    /// Look for broken symlinks below the selected directory, or the current one
+    fn start_broken_links(&mut self) {
+        if self.is_read_only() {
+            return;
+        }
+        let dir = match self.table_state.selected().and_then(|i| self.items.get(i)) {
+            Some(entry) if entry.is_dir && !entry.is_archive => entry.path.clone(),
+            _ => self.current_dir.clone(),
+        };
+        spawn_broken_link_scan(dir.clone(), self.event_tx.clone());
+        self.broken_links = Some(
+            BrokenLinkView { dir, report: None, removed: 0, failures: Vec::new(), scroll: 0 }
+        );
+    }
+
+    /// Show the entries the count of the selected directory, or the current one, could not read
+    fn show_skipped(&mut self) {
+        let dir = match self.table_state.selected().and_then(|i| self.items.get(i)) {
+            Some(entry) if entry.is_dir && !entry.is_archive => entry.alias_of.clone().unwrap_or_else(|| entry.path.clone()),
+            _ => self.current_dir.clone(),
+        };
+        let skipped = match self.file_count_cache.contains_key(&dir) {
+            true => Some(self.skipped_cache.get(&dir).map_or_else(|| Arc::new(Skipped::default()), |s| Arc::clone(&s))),
+            false => None,
+        };
+        self.skipped = Some(SkippedView { dir, skipped, scroll: 0, elevated: Vec::new() });
+    }
+
+    /// Add the counts of refused directories, made as root, to every cached
+    /// count above them, which had counted them as empty
+    fn merge_elevated(&mut self, results: Vec<(PathBuf, Result<usize, String>)>) {
+        let mut outcome = Vec::new();
+        let mut added = 0;
+        let mut merged = 0;
+        for (dir, result) in results {
+            let files = match result {
+                Ok(files) => files,
+                Err(e) => {
+                    outcome.push(format!("Cannot count {} as root: {}", dir.display(), e));
+                    continue;
+                }
+            };
+            let ancestors: Vec<(PathBuf, usize)> = self.file_count_cache
+                .snapshot()
+                .into_iter()
+                .filter(|(path, _)| dir.starts_with(path) && *path != dir)
+                .map(|(path, count)| (path, count + files))
+                .collect();
+            self.file_count_cache.insert(dir.clone(), files);
+            self.skipped_cache.remove(&dir); // What root could not read is not known here
+            let message = CountMessage { path: dir.clone(), files, bytes: None, approximate: false };
+            self.apply_count(message);
+            for (path, count) in ancestors {
+                self.file_count_cache.insert(path.clone(), count);
+                self.apply_count(CountMessage { path: path.clone(), files: count, bytes: None, approximate: false });
+                // No longer missing from this count
+                if let Some(skipped) = self.skipped_cache.get(&path).map(|skipped| Arc::clone(&skipped)) {
+                    let mut skipped = (*skipped).clone();
+                    let before = skipped.entries.len();
+                    skipped.entries.retain(|entry| entry.path != dir);
+                    skipped.count -= before - skipped.entries.len();
+                    match skipped.count {
+                        0 => {
+                            self.skipped_cache.remove(&path);
+                        }
+                        _ => {
+                            self.skipped_cache.insert(path, Arc::new(skipped));
+                        }
+                    }
+                }
+            }
+            added += files;
+            merged += 1;
+        }
+        if merged > 0 {
+            let counted = format!("Counted {} more files as root in {} directories", self.numbers.count(added as u64), merged);
+            outcome.insert(0, counted);
+            self.summary.action(format!("Counted {} refused directories as root", merged));
+            self.resort(false);
+        }
+        if let Some(view) = &mut self.skipped {
+            view.skipped = self.skipped_cache
+                .get(&view.dir)
+                .map(|skipped| Arc::clone(&skipped))
+                .or(Some(Arc::new(Skipped::default())));
+            view.elevated = outcome;
+        }
+    }
+
+    /// Show the broken symlinks found, unless their view was closed or replaced meanwhile
+    fn apply_broken_links(&mut self, report: BrokenLinkReport) {
+        if let Some(view) = &mut self.broken_links {
+            if view.dir == report.dir && view.report.is_none() {
+                view.report = Some(report);
+            }
+        }
+    }
+
+    /// Remove every broken symlink of the view. Failed ones stay listed with their error.
+    fn remove_broken_links(&mut self) -> io::Result<()> {
+        let Some(view) = &mut self.broken_links else {
+            return Ok(());
+        };
+        let Some(report) = &mut view.report else {
+            return Ok(());
+        };
+        view.failures.clear();
+        let mut removed = Vec::new();
+        let mut kept = Vec::new();
+        for link in report.links.drain(..) {
+            match remove_link(&link.path) {
+                Ok(()) => removed.push(link.path),
+                Err(e) => {
+                    view.failures.push((link.path.clone(), e));
+                    kept.push(link);
+                }
+            }
+        }
+        report.links = kept;
+        view.removed += removed.len();
+        if !removed.is_empty() {
+            self.summary.action(format!("Removed {} broken symlinks", removed.len()));
+        }
+        // Links were never counted as files, only noted by the counts above them
+        for path in removed {
+            self.marked.remove(&path);
+            for ancestor in path.ancestors().skip(1) {
+                if let Some(mut links) = self.broken_link_cache.get_mut(ancestor) {
+                    *links = links.saturating_sub(1);
+                }
+            }
+            self.touch_ancestors(&path);
+        }
+        self.refresh_items()
+    }
+
+    /// Estimate what deleting the build output and caches below the current directory would reclaim
+    fn start_cleanable(&mut self) {
+        if self.is_read_only() {
+            return;
+        }
+        let dir = self.current_dir.clone();
+        spawn_cleanable_scan(dir.clone(), self.scan_options, self.event_tx.clone());
+        self.cleanable = Some(
+            CleanableView {
+                dir,
+                report: None,
+                reclaimed: FileTotals::default(),
+                failures: Vec::new(),
+                scroll: 0,
+            }
+        );
+    }
+
+    /// Show the cleanable directories found, unless their view was closed or replaced meanwhile
+    fn apply_cleanable(&mut self, report: CleanableReport) {
+        if let Some(view) = &mut self.cleanable {
+            if view.dir == report.dir && view.report.is_none() {
+                view.report = Some(report);
+            }
+        }
+    }
+
+    /// Move every directory of the cleanable view to the trash. Failed ones stay listed with their error.
+    fn trash_cleanable(&mut self) -> io::Result<()> {
+        let Some(view) = &mut self.cleanable else {
+            return Ok(());
+        };
+        let Some(report) = &mut view.report else {
+            return Ok(());
+        };
+        view.failures.clear();
+        let mut trashed = Vec::new();
+        let mut kept = Vec::new();
+        for dir in report.dirs.drain(..) {
+            match trash::move_to_trash(&dir.path) {
+                Ok(()) => {
+                    view.reclaimed.files += dir.totals.files;
+                    view.reclaimed.bytes.add(dir.totals.bytes);
+                    trashed.push(dir);
+                }
+                Err(e) => {
+                    view.failures.push((dir.path.clone(), e));
+                    kept.push(dir);
+                }
+            }
+        }
+        report.dirs = kept;
+        for dir in trashed {
+            let name = dir.path.file_name().map(format::display_name).unwrap_or_default();
+            self.forget_trashed_dir(dir.path, name, dir.totals.files);
+        }
+        self.refresh_items()
+    }
+
+    /// Start the cleanup wizard on the counted subdirectories of the current directory
+    fn start_wizard(&mut self) {
+        if self.is_read_only() {
+            return;
+        }
+        let stops = self.items
+            .iter()
+            .enumerate()
+            .filter(|(index, entry)| !self.is_parent_entry(*index) && entry.is_dir && entry.alias_of.is_none())
+            .filter(|(_, entry)| !self.excluded.contains(&entry.path))
+            .filter_map(|(_, entry)| {
+                Some(WizardStop { path: entry.path.clone(), name: entry.name.clone(), files: entry.file_count? })
+            })
+            .collect();
+        self.wizard = Some(CleanupWizard::new(stops));
+    }
+
+    /// Apply the chosen action to the wizard's current stop and move on
+    fn wizard_action(&mut self, action: StopAction) -> io::Result<()> {
+        let (path, name, files) = match self.wizard.as_ref().and_then(CleanupWizard::current) {
+            Some(stop) => (stop.path.clone(), stop.name.clone(), stop.files),
+            None => {
+                return Ok(());
+            }
+        };
+        match action {
+            StopAction::Skip => {}
+            StopAction::Archive => {
+                spawn_archive(path, files, self.event_tx.clone());
+            }
+            StopAction::Delete => {
+                if let Err(e) = trash::move_to_trash(&path) {
+                    if let Some(wizard) = &mut self.wizard {
+                        wizard.failed(&path, &e);
+                        wizard.advance(StopAction::Skip);
+                    }
+                    return Ok(());
+                }
+                self.forget_trashed_dir(path, name, files);
+                self.refresh_items()?;
+            }
+            StopAction::Exclude => {
+                self.marked.remove(&path);
+                self.summary.action(format!("Excluded {}", path.display()));
+                self.excluded.insert(path);
+                self.refresh_items()?;
+            }
+        }
+        if let Some(wizard) = &mut self.wizard {
+            wizard.advance(action);
+        }
+        Ok(())
+    }
+
+    /// Trash a directory once the wizard has archived it
+    fn apply_archive(&mut self, message: ArchiveMessage) -> io::Result<()> {
+        let trashed = match &message.result {
+            Ok(_) => trash::move_to_trash(&message.path),
+            Err(e) => Err(io::Error::new(e.kind(), e.to_string())),
+        };
+        match trashed {
+            Ok(()) => {
+                if let Ok(archive) = &message.result {
+                    self.summary.action(format!("Archived {} to {}", message.path.display(), archive.display()));
+                    if self.wizard.is_none() {
+                        self.toasts.info(format!("Archived {} to {}", message.path.display(), archive.display()));
+                    }
+                    if let Some(wizard) = &mut self.wizard {
+                        wizard.archived(&message.path, archive, message.files);
+                    }
+                }
+                let name = message.path.file_name().map(format::display_name).unwrap_or_default();
+                self.forget_trashed_dir(message.path, name, message.files);
+                // The archive itself is a new file
+                if let Ok(archive) = &message.result {
+                    let archive_size = fs::metadata(archive).map(|m| ByteSizes::of(&m)).unwrap_or_default();
+                    for ancestor in archive.ancestors().skip(1) {
+                        self.file_count_cache.update(ancestor, |count| count + 1);
+                        if let Some(mut sizes) = self.size_cache.get_mut(ancestor) {
+                            sizes.add(archive_size);
+                        }
+                    }
+                    self.touch_ancestors(archive);
+                }
+                self.refresh_items()?;
+            }
+            Err(e) => {
+                match &mut self.wizard {
+                    Some(wizard) => wizard.archive_failed(&message.path, &e),
+                    None => self.toasts.error(format!("Cannot archive {}: {}", message.path.display(), e)),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Removing or adding `path` just modified the directories above it
+    fn touch_ancestors(&self, path: &Path) {
+        let now = SystemTime::now();
+        for ancestor in path.ancestors().skip(1) {
+            if let Some(mut newest) = self.newest_cache.get_mut(ancestor) {
+                *newest = now;
+            }
+        }
+    }
+
+    /// Update the caches and the session summary after a directory was trashed
+    fn forget_trashed_dir(&mut self, path: PathBuf, name: String, files: usize) {
+        let target = BatchTarget { path, name, is_dir: true, files: Some(files), size: None };
+        self.marked.remove(&target.path);
+        self.forget_deleted(&target);
+        self.summary.deleted(files, None);
+        self.summary.action(format!("Trashed {}", target.path.display()));
+    }
+}
+
+/// Order entries: directories first by file count (descending), then by name.
+/// The "back to parent directory" entry, if present, stays on top.
+fn sort_items(items: &mut [DirEntry], sort: SortKey) {
+    let start = items.iter().take_while(|entry| entry.is_parent()).count();
+    items[start..].sort_by(|a, b| compare_entries(a, b, sort));
+}
+
+/// Sort order of two entries, see `sort_items`
+fn compare_entries(a: &DirEntry, b: &DirEntry, sort: SortKey) -> std::cmp::Ordering {
+    match (a.is_dir && !a.is_bundle, b.is_dir && !b.is_bundle) {
+        (true, true) if sort == SortKey::Newest =>
+            match (a.newest, b.newest) {
+                (Some(a_newest), Some(b_newest)) =>
+                    b_newest
+                        .cmp(&a_newest)
+                        .then(collate::compare(&a.name, &b.name)),
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                (None, None) => collate::compare(&a.name, &b.name),
+            }
+        (true, true) if sort == SortKey::NotInGit && a.git.is_some() && b.git.is_some() =>
+            match (a.git.map(|git| git.not_in_git()), b.git.map(|git| git.not_in_git())) {
+                (Some(a_files), Some(b_files)) =>
+                    b_files.cmp(&a_files).then(collate::compare(&a.name, &b.name)),
+                _ => collate::compare(&a.name, &b.name),
+            }
+        (true, true) if sort == SortKey::Growth =>
+            match (a.growth(), b.growth()) {
+                (Some(a_growth), Some(b_growth)) =>
+                    b_growth
+                        .cmp(&a_growth)
+                        .then(collate::compare(&a.name, &b.name)),
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                (None, None) => collate::compare(&a.name, &b.name),
+            }
+        (true, true) =>
+            match (a.file_count.or(a.approx_count), b.file_count.or(b.approx_count)) {
+                (Some(a_count), Some(b_count)) =>
+                    b_count
+                        .cmp(&a_count)
+                        .then(collate::compare(&a.name, &b.name)),
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                (None, None) => collate::compare(&a.name, &b.name),
+            }
+        (false, false) => collate::compare(&a.name, &b.name),
+        (true, false) => std::cmp::Ordering::Less,
+        (false, true) => std::cmp::Ordering::Greater,
+    }
+}
+
+/// Widths of the table columns, measured from their content. Optional columns
+/// are `None` when no entry has a value for them, and the name takes the rest.
+struct ColumnWidths {
+    kind: u16,
+    count: u16,
+    size: Option<u16>,
+    modified: Option<u16>,
+    newest: Option<u16>,
+    delta: Option<u16>,
+    git: Option<u16>,
+    #[cfg(unix)]
+    owner: u16,
+    #[cfg(unix)]
+    group: u16,
+    #[cfg(unix)]
+    owners: u16,
+}
+
+impl ColumnWidths {
+    /// Measure the counts and deltas of the whole listing, so the columns don't
+    /// jump while scrolling, and the modification times of the `visible` rows
+    fn measure(app: &App, visible: std::ops::Range<usize>, now: SystemTime) -> ColumnWidths {
+        let spinner = app.theme.spinner_frames
+            .iter()
+            .map(|frame| UnicodeWidthStr::width(frame.as_str()))
+            .max()
+            .unwrap_or(0);
+        let mut kind = "Type".len();
+        let mut count = "Count".len();
+        let mut any_size = false;
+        let mut any_modified = false;
+        let mut any_newest = false;
+        let mut delta = None;
+        let counting_not_in_git = app.sort == SortKey::NotInGit && app.git.is_some();
+        for entry in &app.items {
+            let label = if entry.alias_of.is_some() {
+                "Alias"
+            } else if entry.is_bundle {
+                "Pkg"
+            } else if entry.is_archive {
+                "Arch"
+            } else if entry.junk.is_some() {
+                "Junk"
+            } else if entry.mount.is_some() {
+                "Mount"
+            } else if entry.is_dir {
+                "Dir"
+            } else {
+                "File"
+            };
+            kind = kind.max(label.len());
+            count = count.max(match (entry.file_count, entry.approx_count) {
+                _ if !entry.is_dir => 1, // "-"
+                _ if counting_not_in_git =>
+                    match entry.git {
+                        Some(git) => app.numbers.width(git.not_in_git() as u64).max("Not in git ↓".chars().count()),
+                        None => 1,
+                    }
+                (Some(files), _) if entry.is_bundle => app.numbers.width(files as u64) + 2, // "(n)"
+                (Some(files), _) => app.numbers.width(files as u64),
+                (None, Some(estimate)) if !entry.is_bundle => app.numbers.width(estimate as u64) + 1, // "~n"
+                (None, None) if entry.left_out => 1, // "-"
+                (None, None) if !entry.is_bundle =>
+                    match app.counted_so_far(entry.alias_of.as_ref().unwrap_or(&entry.path)) {
+                        Some(found) => app.numbers.width(found as u64) + 1, // "n+"
+                        None => spinner,
+                    }
+                _ => spinner,
+            });
+            any_size |= entry.sizes.is_some();
+            any_modified |= entry.modified.is_some();
+            any_newest |= entry.newest.is_some();
+            if let (true, Some(growth)) = (app.baseline.is_some(), entry.growth()) {
+                let width = 1 + app.numbers.width(growth.unsigned_abs()); // With the sign
+                delta = Some(width.max(delta.unwrap_or("Delta ↓".chars().count())));
+            }
+        }
+        let size = any_size.then(|| {
+            app.items[visible.clone()]
+                .iter()
+                .map(|entry| size_text(app, entry).len())
+                .fold("Apparent".len(), usize::max)
+        });
+        let modified = any_modified.then(|| {
+            app.items[visible.clone()]
+                .iter()
+                .map(|entry| UnicodeWidthStr::width(time_text(app, entry.modified, now).as_str()))
+                .fold("Modified".len(), usize::max)
+        });
+        let newest = any_newest.then(|| {
+            app.items[visible.clone()]
+                .iter()
+                .filter(|entry| entry.is_dir)
+                .map(|entry| UnicodeWidthStr::width(time_text(app, entry.newest, now).as_str()))
+                .fold("Newest ↓".chars().count(), usize::max)
+        });
+        let git = app.items
+            .iter()
+            .filter_map(|entry| entry.git.and_then(|git| git.state()))
+            .map(|state| state.label().len() as u16)
+            .max()
+            .map(|width| width.max("Git".len() as u16));
+        #[cfg(unix)]
+        let widest = |header: &str, text: fn(&App, &DirEntry) -> String| {
+            app.items[visible.clone()]
+                .iter()
+                .map(|entry| UnicodeWidthStr::width(text(app, entry).as_str()))
+                .fold(header.len(), usize::max) as u16
+        };
+        ColumnWidths {
+            kind: kind as u16,
+            count: count as u16,
+            size: size.map(|width| width as u16),
+            modified: modified.map(|width| width as u16),
+            newest: newest.map(|width| width as u16),
+            delta: delta.map(|width| width as u16),
+            git,
+            #[cfg(unix)]
+            owner: widest("Owner", owner_text),
+            #[cfg(unix)]
+            group: widest("Group", group_text),
+            #[cfg(unix)]
+            owners: widest("Files owned by", owners_text),
+        }
+    }
+
+    /// Width fitting the content of a column, None when it has nothing to show
+    fn fitting(&self, column: Column) -> Option<u16> {
+        match column {
+            Column::Type => Some(self.kind),
+            Column::Name => Some(0), // What the others leave
+            Column::Count => Some(self.count),
+            #[cfg(feature = "charts")]
+            Column::Bar => Some(BAR_WIDTH),
+            Column::Percent => Some("100.0%".len() as u16),
+            Column::Size => self.size,
+            Column::Modified => self.modified,
+            Column::Newest => self.newest,
+            Column::CountTime => Some("12m34s".len() as u16),
+            #[cfg(unix)]
+            Column::Owner => Some(self.owner),
+            #[cfg(unix)]
+            Column::Group => Some(self.group),
+            #[cfg(unix)]
+            Column::Mode => Some("drwxr-xr-x".len() as u16),
+            #[cfg(unix)]
+            Column::Owners => Some(self.owners),
+            Column::Delta => self.delta,
+            Column::Git => self.git,
+        }
+    }
+
+    /// The visible columns of `layout` that have something to show, in order,
+    /// with their configured width or the one fitting their content. Columns
+    /// at the end that leave the name too little of `area` are dropped, as is
+    /// the type column in the compact layout.
+    fn shown(&self, layout: &ColumnLayout, area: Rect, narrow: bool) -> Vec<(Column, u16)> {
+        let mut shown: Vec<(Column, u16)> = layout
+            .visible()
+            .filter(|&column| !(narrow && column == Column::Type))
+            .filter_map(|column| {
+                let fitting = self.fitting(column)?;
+                Some((column, layout.widths.get(&column).copied().unwrap_or(fitting)))
+            })
+            .collect();
+        let others = |shown: &[(Column, u16)]| {
+            shown
+                .iter()
+                .filter(|(column, _)| *column != Column::Name)
+                .map(|(_, width)| width + 1) // And the spacing
+                .sum::<u16>()
+        };
+        while others(&shown) + 1 + 3 + MIN_NAME_WIDTH > area.width {
+            match shown.iter().rposition(|(column, _)| *column != Column::Name) {
+                Some(last) => shown.remove(last),
+                None => break,
+            };
+        }
+        shown
+    }
+
+    /// Column constraints for a table `area`; a name without a configured width
+    /// gets what the others leave
+    fn constraints(shown: &[(Column, u16)], area: Rect) -> Vec<Constraint> {
+        let used = shown.iter().map(|(_, width)| width).sum::<u16>() + (shown.len() as u16) + 1 + 3; // Spacing, borders, ">> "
+        let rest = area.width.saturating_sub(used);
+        shown
+            .iter()
+            .map(|&(column, width)| {
+                match column {
+                    Column::Name if width == 0 => Constraint::Length(rest),
+                    _ => Constraint::Length(width),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Directories a count left out, the first few by path
+fn excluded_text(excluded: &[PathBuf]) -> String {
+    const SHOWN: usize = 4;
+    let mut text = excluded
+        .iter()
+        .take(SHOWN)
+        .map(|path| path.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    if excluded.len() > SHOWN {
+        text.push_str(&format!(" and {} more", excluded.len() - SHOWN));
+    }
+    text
+}
+
+/// Summary of the files in junk directories below the current directory, by category,
+/// e.g. `Junk: 12,345 files (62%), 80.2 MiB: Node.js packages 10,000, Python caches 2,345`
+fn junk_summary(app: &App) -> Option<String> {
+    let total = app.current_dir_count?;
+    let categories = app.junk_cache.get(&app.current_dir)?;
+    let files: usize = categories.iter().map(|(_, totals)| totals.files).sum();
+    if files == 0 || total == 0 {
+        return None;
+    }
+    let mut bytes = ByteSizes::default();
+    for (_, totals) in categories.iter() {
+        bytes.add(totals.bytes);
+    }
+    let breakdown: Vec<String> = categories
+        .iter()
+        .map(|(category, totals)| format!("{} {}", category, app.numbers.count(totals.files as u64)))
+        .collect();
+    Some(
+        format!(
+            "Junk: {} files ({:.0}%), {}: {}",
+            app.numbers.count(files as u64),
+            ((files as f64) * 100.0) / (total as f64),
+            format::human_bytes(bytes.get(app.apparent_size)),
+            breakdown.join(", ")
+        )
+    )
+}
+
+/// Files below the current directory by git status, e.g.
+/// `Git: 1,234 tracked, 56 untracked, 7,890 ignored files`
+fn git_summary(app: &App) -> Option<String> {
+    let total = app.git.as_ref()?.total();
+    let counting = if app.sort == SortKey::NotInGit { " (counting untracked and ignored, G for all)" } else { "" };
+    Some(
+        format!(
+            "Git: {} tracked, {} untracked, {} ignored files{}",
+            app.numbers.count(total.tracked as u64),
+            app.numbers.count(total.untracked as u64),
+            app.numbers.count(total.ignored as u64),
+            counting
+        )
+    )
+}
+
+/// Git state of an entry with its style, inside a work tree
+fn git_cell(app: &App, entry: &DirEntry) -> Cell<'static> {
+    match entry.git.and_then(|git| git.state()) {
+        Some(state @ GitState::Ignored) => Cell::from(state.label()).style(app.theme.filtered),
+        Some(state @ GitState::Untracked) => Cell::from(state.label()).style(app.theme.differs),
+        Some(state) => Cell::from(state.label()),
+        None => Cell::from(""),
+    }
+}
+
+/// Share of the current directory's total counted in a subdirectory, once both are known
+fn share_of_total(app: &App, entry: &DirEntry) -> Option<f64> {
+    if !entry.is_dir || entry.is_parent() {
+        return None;
+    }
+    let total = app.current_dir_count.filter(|&total| total > 0)?;
+    Some(((entry.file_count? as f64) / (total as f64)).min(1.0))
+}
+
+/// Default width of the bar column
+#[cfg(feature = "charts")]
+const BAR_WIDTH: u16 = 12;
+
+/// Bar of `width` cells for `count` out of `largest`, in eighths of a cell
+#[cfg(feature = "charts")]
+fn bar_text(count: usize, largest: usize, width: u16, ascii: bool) -> String {
+    const PARTIAL: [&str; 8] = ["", "▏", "▎", "▍", "▌", "▋", "▊", "▉"];
+    if largest == 0 {
+        return String::new();
+    }
+    if ascii {
+        let cells = ((count as f64) / (largest as f64) * f64::from(width)).round() as usize;
+        return "#".repeat(cells);
+    }
+    let eighths = ((count as f64) / (largest as f64) * f64::from(width) * 8.0).round() as usize;
+    format!("{}{}", "█".repeat(eighths / 8), PARTIAL[eighths % 8])
+}
+
+/// A share as a percentage, e.g. `42.5%`; tiny shares read `<0.1%` rather than `0.0%`
+fn percent_text(share: f64) -> String {
+    match share {
+        0.0 => String::from("0%"),
+        _ if share < 0.0005 => String::from("<0.1%"),
+        _ => format!("{:.1}%", share * 100.0),
+    }
+}
+
+/// Text of the size cell of an entry, in the selected size mode
+fn size_text(app: &App, entry: &DirEntry) -> String {
+    match entry.sizes {
+        Some(sizes) if !entry.is_bundle => format::human_bytes(sizes.get(app.apparent_size)),
+        _ => String::new(),
+    }
+}
+
+/// Name of the user owning an entry
+#[cfg(unix)]
+fn owner_text(app: &App, entry: &DirEntry) -> String {
+    entry.ownership.map(|ownership| app.names.user(ownership.uid)).unwrap_or_default()
+}
+
+/// Name of the group owning an entry
+#[cfg(unix)]
+fn group_text(app: &App, entry: &DirEntry) -> String {
+    entry.ownership.map(|ownership| app.names.group(ownership.gid)).unwrap_or_default()
+}
+
+/// Who owns most of the files below a counted directory, e.g. `www-data 87%`
+#[cfg(unix)]
+fn owners_text(app: &App, entry: &DirEntry) -> String {
+    if !entry.is_dir || entry.is_parent() {
+        return String::new();
+    }
+    match app.owner_cache.get(entry.alias_of.as_ref().unwrap_or(&entry.path)).map(|v| *v) {
+        Some((uid, share)) => format!("{} {:.0}%", app.names.user(uid), share * 100.0),
+        None => String::new(),
+    }
+}
+
+/// How long the last count of a directory took
+fn count_time_text(app: &App, entry: &DirEntry) -> String {
+    if !entry.is_dir || entry.is_parent() {
+        return String::new();
+    }
+    match app.count_time_cache.get(entry.alias_of.as_ref().unwrap_or(&entry.path)) {
+        Some(count_time) => format::elapsed(*count_time),
+        None => String::new(),
+    }
+}
+
+/// Text of a modification time cell
+fn time_text(app: &App, time: Option<SystemTime>, now: SystemTime) -> String {
+    match time {
+        Some(time) if app.absolute_times => format::absolute_time(time),
+        Some(time) => format::relative_time(time, now),
+        None => String::from("-"),
+    }
+}
+
+/// First row to show so that the selection stays inside a viewport of `height` rows
+fn scroll_offset(offset: usize, selected: Option<usize>, height: usize, len: usize) -> usize {
+    let height = height.max(1);
+    // Don't leave blank rows at the bottom after the list shrank
+    let mut offset = offset.min(len.saturating_sub(height));
+    if let Some(selected) = selected.filter(|&selected| selected < len) {
+        if selected < offset {
+            offset = selected;
+        } else if selected >= offset + height {
+            offset = selected + 1 - height;
+        }
+    }
+    offset
+}
+
+/// Calculate the wrapped height of text given a maximum width
+fn calculate_wrapped_height(text: &str, max_width: u16) -> u16 {
+    let mut height = 0u16;
+    for line in text.lines() {
+        let line_width = UnicodeWidthStr::width(line) as u16;
+        let line_height = if line_width == 0 { 1 } else { (line_width - 1) / max_width.max(1) + 1 };
+        height += line_height;
+    }
+    height
+}
+
+/// Command that opens a path with the system default application
+fn open_command(path: &Path) -> Command {
+    if cfg!(target_os = "macos") {
+        let mut command = Command::new("open");
+        command.arg(path);
+        command
+    } else if cfg!(windows) {
+        // The empty argument is the window title expected by `start`
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", ""]).arg(path);
+        command
+    } else {
+        let mut command = Command::new("xdg-open");
+        command.arg(path);
+        command
+    }
+}
+
+/// Number of extensions listed before the rest is summed up as "other"
+const TOP_EXTENSIONS: usize = 10;
+
+/// Subdirectories and extensions listed in a directory's preview
+const TOP_SUBDIRS: usize = 5;
+
+/// Build the lines of the file type and extension breakdown panel for the selected entry
+fn extension_lines(app: &App) -> Vec<Spans<'static>> {
+    let entry = match app.table_state.selected().and_then(|i| app.items.get(i)) {
+        Some(entry) => entry,
+        None => {
+            return Vec::new();
+        }
+    };
+    if !entry.is_dir {
+        return vec![Spans::from("Not a directory")];
+    }
+    if app.snapshot.is_some() {
+        return vec![Spans::from("Not available for reports")];
+    }
+    if entry.is_archive || app.archive.is_some() {
+        return vec![Spans::from("Not available for archives")];
+    }
+
+    let breakdown = match app.extension_cache.get(&entry.path) {
+        Some(breakdown) => breakdown,
+        None => {
+            let spinner_frame = app.theme.spinner(app.spinner_index);
+            return vec![Spans::from(format!("Collecting{}", spinner_frame))];
+        }
+    };
+    let histogram = &breakdown.extensions;
+    if histogram.is_empty() {
+        return vec![Spans::from("No files")];
+    }
+
+    // Composition by file type, with each type's share of the files and its size
+    let total: usize = breakdown.file_types
+        .iter()
+        .map(|(_, totals)| totals.files)
+        .sum();
+    let mut lines: Vec<Spans<'static>> = breakdown.file_types
+        .iter()
+        .map(|(category, totals)| {
+            Spans::from(
+                format!(
+                    "{:<9} {:>8} {:>4.0}% {:>10}",
+                    category,
+                    app.numbers.count(totals.files as u64),
+                    ((totals.files as f64) * 100.0) / (total.max(1) as f64),
+                    format::human_bytes(totals.bytes.get(app.apparent_size))
+                )
+            )
+        })
+        .collect();
+    if !lines.is_empty() {
+        lines.push(Spans::from(""));
+    }
+
+    let width = histogram
+        .iter()
+        .take(TOP_EXTENSIONS)
+        .map(|(ext, _)| UnicodeWidthStr::width(ext.as_str()))
+        .max()
+        .unwrap_or(0)
+        .max(5);
+    lines.extend(
+        histogram
+            .iter()
+            .take(TOP_EXTENSIONS)
+            .map(|(ext, count)| Spans::from(format!("{:<width$} {:>8}", ext, app.numbers.count(*count as u64), width = width)))
+    );
+    let other: usize = histogram
+        .iter()
+        .skip(TOP_EXTENSIONS)
+        .map(|(_, count)| count)
+        .sum();
+    if other > 0 {
+        lines.push(Spans::from(format!("{:<width$} {:>8}", "other", app.numbers.count(other as u64), width = width)));
+    }
+    lines
+}
+
+/// Build the lines of the preview pane for the selected entry
+fn preview_lines(app: &App) -> Vec<Spans<'static>> {
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let entry = match app.table_state.selected() {
+        Some(index) if !app.is_parent_entry(index) => app.items.get(index),
+        _ => None,
+    };
+    let Some(entry) = entry else {
+        return Vec::new();
+    };
+    if app.snapshot.is_some() {
+        return vec![Spans::from("Not available for reports")];
+    }
+    if app.archive.is_some() {
+        return vec![Spans::from("Not available for archives")];
+    }
+    let Some(preview) = app.preview.as_ref().filter(|preview| preview.path == entry.path) else {
+        return vec![Spans::from(format!("Reading{}", app.theme.spinner(app.spinner_index)))];
+    };
+
+    let mut lines = vec![Spans::from(Span::styled(entry.name.clone(), bold))];
+    match &preview.content {
+        PreviewContent::Error(e) => {
+            lines.push(Spans::from(Span::styled(format!("Cannot read: {}", e), app.theme.differs)));
+        }
+        PreviewContent::Text(text) => {
+            lines.push(Spans::from(""));
+            lines.extend(text.iter().map(|line| Spans::from(line.clone())));
+        }
+        PreviewContent::Binary(info) => {
+            lines.push(Spans::from(""));
+            lines.push(Spans::from(format!("Binary file, {}", info.category.to_lowercase())));
+            lines.push(Spans::from(format!("Size: {} ({} bytes)", format::human_bytes(info.size), info.size)));
+            lines.push(Spans::from(format!("Modified: {}", time_text(app, info.modified, SystemTime::now()))));
+            if let Some(mode) = info.mode {
+                lines.push(Spans::from(format!("Permissions: {:04o}", mode)));
+            }
+        }
+        PreviewContent::Dir(children) => {
+            lines.push(
+                Spans::from(
+                    format!(
+                        "Directly inside: {} files, {} directories{}",
+                        app.numbers.count(children.files as u64),
+                        app.numbers.count(children.subdirs.len() as u64),
+                        match children.others {
+                            0 => String::new(),
+                            others => format!(", {} other", app.numbers.count(others as u64)),
+                        }
+                    )
+                )
+            );
+
+            // Largest subdirectories among those counted so far
+            let mut counted: Vec<(usize, &Path)> = children.subdirs
+                .iter()
+                .filter_map(|subdir| Some((app.file_count_cache.peek(subdir)?, subdir.as_path())))
+                .collect();
+            if !children.subdirs.is_empty() {
+                counted.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+                lines.push(Spans::from(""));
+                let mut heading = vec![Span::styled("Largest subdirectories", bold)];
+                if counted.len() < children.subdirs.len() {
+                    heading.push(
+                        Span::raw(
+                            format!(
+                                " ({} of {} counted{})",
+                                counted.len(),
+                                children.subdirs.len(),
+                                app.theme.spinner(app.spinner_index)
+                            )
+                        )
+                    );
+                }
+                lines.push(Spans::from(heading));
+                for (files, subdir) in counted.iter().take(TOP_SUBDIRS) {
+                    let name = format::display_name(subdir.file_name().unwrap_or_default());
+                    lines.push(Spans::from(format!("{:>10}  {}/", app.numbers.count(*files as u64), name)));
+                }
+            }
+
+            // Extension histogram, from the breakdown panel's data
+            lines.push(Spans::from(""));
+            lines.push(Spans::from(Span::styled("File types", bold)));
+            match app.extension_cache.get(&entry.path) {
+                None => lines.push(Spans::from(format!("Collecting{}", app.theme.spinner(app.spinner_index)))),
+                Some(breakdown) if breakdown.extensions.is_empty() => lines.push(Spans::from("No files")),
+                Some(breakdown) => {
+                    for (extension, count) in breakdown.extensions.iter().take(TOP_SUBDIRS) {
+                        lines.push(Spans::from(format!("{:>10}  {}", app.numbers.count(*count as u64), extension)));
+                    }
+                }
+            }
+        }
+    }
+    lines
+}
+
+/// Problems that would make a batch operation fail halfway
+fn batch_conflicts(operation: &BatchOperation, targets: &[BatchTarget]) -> Vec<String> {
+    let mut conflicts = Vec::new();
+    let mut names = HashSet::new();
+    for target in targets {
+        if fs::symlink_metadata(&target.path).is_err() {
+            conflicts.push(format!("{} no longer exists", target.path.display()));
+            continue;
+        }
+        if let BatchOperation::Move(destination) = operation {
+            let name = target.path.file_name().unwrap_or_default();
+            if target.path.parent() == Some(destination.as_path()) {
+                conflicts.push(format!("{} is already in this directory", target.path.display()));
+            } else if destination.starts_with(&target.path) {
+                conflicts.push(format!("{} cannot be moved into itself", target.path.display()));
+            } else if fs::symlink_metadata(destination.join(name)).is_ok() || !names.insert(name) {
+                conflicts.push(format!("{} already exists in {}", target.name, destination.display()));
+            }
+        }
+    }
+    conflicts
+}
+
+/// Build the lines of the batch preview dialog: every entry with its cached
+/// count or size, the total, and any conflicts
+fn batch_preview_lines(app: &App, preview: &BatchPreview) -> Vec<Spans<'static>> {
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let mut lines = Vec::new();
+
+    let title = match &preview.operation {
+        BatchOperation::Trash => String::from("Move to trash:"),
+        BatchOperation::Move(destination) => format!("Move into {}:", destination.display()),
+        BatchOperation::Archive => String::from("Archive as .tar.gz, then move to trash:"),
+    };
+    lines.push(Spans::from(Span::styled(title, bold)));
+
+    let mut files = 0usize;
+    let mut counting = false;
+    let mut bytes = 0u64;
+    for target in &preview.targets {
+        let detail = if target.is_dir {
+            match target.files {
+                Some(count) => format!("{} files", app.numbers.count(count as u64)),
+                None => String::from("count not known yet"),
+            }
+        } else {
+            target.size.map(|size| format::human_bytes(size.get(app.apparent_size))).unwrap_or_default()
+        };
+        lines.push(Spans::from(format!("  {}  {}", target.path.display(), detail)));
+        if target.is_dir {
+            files += target.files.unwrap_or(0);
+            counting |= target.files.is_none();
+        } else {
+            files += 1;
+        }
+        bytes += target.size.map_or(0, |size| size.get(app.apparent_size));
+    }
+    lines.push(
+        Spans::from(
+            vec![
+                Span::styled("Total: ", bold),
+                Span::raw(
+                    format!(
+                        "{} entries, {}{} files, {} in plain files",
+                        preview.targets.len(),
+                        app.numbers.count(files as u64),
+                        if counting { "+" } else { "" },
+                        format::human_bytes(bytes)
+                    )
+                )
+            ]
+        )
+    );
+
+    for conflict in &preview.conflicts {
+        lines.push(Spans::from(Span::styled(format!("! {}", conflict), bold)));
+    }
+
+    lines.push(Spans::from(""));
+    let mut keys = Vec::new();
+    if preview.conflicts.is_empty() {
+        keys.push(Span::styled("y", app.theme.key));
+        keys.push(Span::raw(" - Confirm all   "));
+    }
+    keys.push(Span::styled("n/Esc", app.theme.key));
+    keys.push(Span::raw(" - Cancel   "));
+    keys.push(Span::styled("↑/↓", app.theme.key));
+    keys.push(Span::raw(" - Scroll"));
+    lines.push(Spans::from(keys));
+    lines
+}
+
+/// Entries of an archive. An unreadable archive counts as one file, like in a
+/// scan, and lists only itself.
+fn read_archive_or_self(path: &Path) -> ReportNode {
+    archive_tree(path).unwrap_or_else(|_| ReportNode {
+        name: path.display().to_string(),
+        is_dir: true,
+        files: Some(1),
+        children: vec![ReportNode {
+            name: path.file_name().map(format::display_name).unwrap_or_default(),
+            is_dir: false,
+            files: None,
+            children: Vec::new(),
+            count_time: None,
+        }],
+        count_time: None,
+    })
+}
+
+/// Snapshot of an entry's details for the marked set
+fn marked_entry(entry: &DirEntry) -> MarkedEntry {
+    let size = if entry.is_dir {
+        None
+    } else {
+        fs::symlink_metadata(&entry.path)
+            .ok()
+            .map(|m| ByteSizes::of(&m))
+    };
+    MarkedEntry { name: entry.name.clone(), is_dir: entry.is_dir, is_bundle: entry.is_bundle, size }
+}
+
+/// Compute a rectangle of the given size centered within `area`
+fn centered_rect(width: u16, height: u16, area: Rect) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    }
+}
+
+/// Build the lines shown in the help popup, wrapped to `width` columns with
+/// the rest of a description indented under its start
+fn help_lines(app: &App, width: usize) -> Vec<Spans<'static>> {
+    let key_style = app.theme.key;
+    let title_style = Style::default().add_modifier(Modifier::BOLD);
+    // A label, then its text wrapped beside it
+    let labelled = |label: Span<'static>, text: &str, lines: &mut Vec<Spans<'static>>| {
+        let indent = label.width();
+        let mut label = Some(label);
+        for row in format::wrap(text, width.saturating_sub(indent)) {
+            let first = label.take().unwrap_or_else(|| Span::raw(" ".repeat(indent)));
+            lines.push(Spans::from(vec![first, Span::raw(row)]));
+        }
+    };
+
+    let mut lines = vec![Spans::from(Span::styled("Key bindings", title_style))];
+    for (key, description) in KEY_BINDINGS {
+        labelled(Span::styled(format!("{:>12}  ", key), key_style), description, &mut lines);
+    }
+    lines.push(Spans::from(""));
+    labelled(Span::styled("Sort: ", title_style), &app.sort_description(), &mut lines);
+    labelled(Span::styled("Filters: ", title_style), &app.filter_description(), &mut lines);
+    lines
+}
+
+/// Browse `start_dir` (or the snapshot) interactively until the user quits
+pub fn run(
+    start_dir: PathBuf,
+    host_paths: Option<HostPathTranslator>,
+    snapshot: Option<ReportNode>,
+    options: &Options
+) -> Result<(), Box<dyn std::error::Error>> {
+    // Load the config file and theme
+    let config = config::ConfigFile::load(options.config_file.as_deref())?;
+    let mut theme = Theme::from_config(&config)?;
+    if options.no_color || env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+        theme = theme.without_colors(options.no_color);
+    }
+
+    // Initialize the App
+    let mut scan_options = ScanOptions::default();
+    if let Some(collect_extensions) = config.get_bool("scan.extensions") {
+        scan_options.collect_extensions = collect_extensions;
+    }
+    if let Some(opaque_bundles) = config.get_bool("scan.opaque_bundles") {
+        scan_options.opaque_bundles = opaque_bundles;
+    }
+    if let Some(dedupe_hard_links) = config.get_bool("scan.dedupe_hard_links") {
+        scan_options.dedupe_hard_links = dedupe_hard_links;
+    }
+    #[cfg(feature = "archive")]
+    if let Some(count_archives) = config.get_bool("scan.archives") {
+        scan_options.count_archives = count_archives;
+    }
+    if let Some(sniff_types) = config.get_bool("scan.sniff_types") {
+        scan_options.sniff_types = sniff_types;
+    }
+    if let Some(classes) = config.get_str_list("scan.exclude_fs") {
+        scan_options.exclude_fs = FsClasses::parse(&classes.join(","))?;
+    }
+    if let Some(classes) = options.exclude_fs {
+        scan_options.exclude_fs = classes;
+    }
+    if let Some(dirs) = config.get_str_list("scan.system_dirs") {
+        scan::set_system_dirs(dirs.into_iter().map(PathBuf::from).collect());
+    }
+    scan_options.collect_owners = cfg!(unix);
+    scan_options.collect_junk = true;
+    let use_index = options.index || config.get_bool("index.enabled").unwrap_or(false);
+    let watch = options.watch || config.get_bool("watch.enabled").unwrap_or(false);
+    let mut roots = vec![start_dir.clone()];
+    roots.extend(options.more_dirs.iter().cloned());
+    let cache_budget = CacheBudget::from_config(&config)?;
+    let mut app = App::new(start_dir, host_paths, theme, snapshot, scan_options, use_index, cache_budget)?;
+    if roots.len() > 1 {
+        // Absolute, so each root keeps its place whatever the working directory
+        let mut absolute: Vec<PathBuf> = Vec::new();
+        for root in roots {
+            let root = root.canonicalize().unwrap_or(root);
+            if !absolute.contains(&root) {
+                absolute.push(root);
+            }
+        }
+        app.show_roots(absolute)?;
+    }
+    app.absolute_times = config.get_bool("display.absolute_times").unwrap_or(false);
+    app.apparent_size = config.get_bool("display.apparent_size").unwrap_or(false);
+    app.min_count = match (options.min_count, config.get_int("display.min_count")) {
+        (Some(min), _) => Some(min),
+        (None, Some(min)) => Some(usize::try_from(min).map_err(|_| "display.min_count must not be negative")?),
+        (None, None) => None,
+    };
+    app.numbers = NumberFormat::from_env(config.get_bool("display.compact_counts").unwrap_or(false));
+    app.dim_filtered = config.get_bool("display.dim_filtered").unwrap_or(false);
+    app.icons = config.get_bool("display.icons").unwrap_or(false) && !app.theme.ascii;
+    if let Some(locale) = config.get_str("display.collation") {
+        collate::set_locale(locale);
+    }
+    app.columns = ColumnLayout::from_config(&config)?;
+    #[cfg(unix)]
+    {
+        app.names = Names::load();
+    }
+    if let Some(path) = &options.diff {
+        let text = fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+        let baseline = ReportNode::parse(&text).map_err(|e| format!("Cannot load {}: {}", path.display(), e))?;
+        app.baseline = Some(baseline);
+        app.fill_baselines(0);
+    }
+    #[cfg(feature = "persist")]
+    if let Some(path) = &options.import_state {
+        app.import_state(AppState::load(path)?)?;
+    }
+    // Continue where the last session left off, unless told where to start
+    #[cfg(feature = "persist")]
+    let session_file = (config.get_bool("session.restore").unwrap_or(false) && app.snapshot.is_none())
+        .then(|| crate::dirs::state_dir(options.state_dir.as_deref()))
+        .flatten()
+        .map(|dir| dir.join(SESSION_FILE));
+    #[cfg(feature = "persist")]
+    if options.start_dir.is_none() && options.import_state.is_none() {
+        if let Some(state) = session_file.as_deref().and_then(|path| AppState::load(path).ok()) {
+            app.import_state(state)?;
+        }
+    }
+    #[cfg(not(feature = "persist"))]
+    if options.import_state.is_some() || options.export_state.is_some() {
+        return Err(crate::missing_feature("State export/import", "persist").into());
+    }
+    if watch && app.snapshot.is_none() {
+        #[cfg(feature = "watch")]
+        {
+            let watcher = Watcher::spawn();
+            watcher.watch(&app.current_dir);
+            app.watcher = Some(watcher);
+        }
+        #[cfg(not(feature = "watch"))]
+        return Err(crate::missing_feature("Watch mode", "watch").into());
+    }
+
+    // Count from the Master File Table once it is read, walking meanwhile
+    app.use_mft = (options.mft || config.get_bool("scan.mft").unwrap_or(false)) && app.snapshot.is_none();
+    if app.use_mft {
+        app.spawn_mft();
+    }
+
+    // Without a directory to start from, Windows users pick a drive first
+    if cfg!(windows) && options.start_dir.is_none() && app.snapshot.is_none() {
+        app.drives = Some(DriveView::new(&app.current_dir));
+    }
+
+    // Let other tools query and invalidate the counts of this instance
+    let _control_socket = match &options.control_socket {
+        Some(path) => Some(control::listen(path, app.event_tx.clone(), AppEvent::Control)?),
+        None => None,
+    };
+
+    // Set up the terminal, making sure it is restored however the program ends
+    screen::install_panic_hook();
+    screen::install_signal_handlers()?;
+    screen::enter()?;
+    let _screen_guard = screen::Guard;
+    let backend = CrosstermBackend::new(io::stdout());
+    let mut terminal = Terminal::new(backend)?;
+
+    // Initialize table_area
+    let mut table_area = Rect::default();
+    // Where the Name column starts and how wide it is, and the rows whose name it cut
+    let mut name_columns = (0, 0);
+    let mut truncated_rows = HashSet::new();
+
+    // Input and animation frames arrive on the same channel as count results
+    let input = InputReader::spawn(app.event_tx.clone());
+    let tick_interval = config
+        .get_int("display.tick_interval_ms")
+        .map_or(TICK_INTERVAL, |ms| Duration::from_millis(ms.clamp(MIN_TICK_MS, MAX_TICK_MS) as u64));
+    let ticker = Ticker::spawn(app.event_tx.clone(), tick_interval);
+
+    // Main loop
+    let mut redraw_ui = true;
+    let mut counts_updated = false; // Re-sorted on a later tick
+    let mut last_resort = Instant::now();
+    let mut last_key: Option<Instant> = None; // Rows do not move while keys are being pressed
+    // Run until the user quits; an error ends it early, but still saves the state below
+    let ended = (|| -> Result<(), Box<dyn std::error::Error>> {
+        loop {
+            if redraw_ui {
+                // Draw the UI
+                terminal.draw(|f| {
+                    let size = f.size();
+
+                    // Calculate block width (subtract borders)
+                    let block_width = size.width.saturating_sub(2);
+                    let narrow = size.width < NARROW_WIDTH;
+                    // In the compact layout the totals go below the path rather than after it
+                    let label = match narrow {
+                        true => format!("{}\n", app.current_dir_label()),
+                        false => format!("{} ", app.current_dir_label()),
+                    };
+
+                    // Get current directory path string
+                    let mut current_dir_text = if let Some(count) = app.current_dir_count {
+                        let mut link_notes = match app.hard_link_cache.get(&app.current_dir).map(|v| *v) {
+                            Some(links) if links > 0 => format!(", {} hard links counted once", links),
+                            _ => String::new(),
+                        };
+                        if let Some(links) = app.broken_link_cache.get(&app.current_dir).map(|v| *v).filter(|&links| links > 0) {
+                            link_notes.push_str(&format!(", {} broken symlinks (L lists them)", links));
+                        }
+                        if let Some(skipped) = app.skipped_cache.get(&app.current_dir) {
+                            link_notes.push_str(&format!(", {} entries unreadable (E lists them)", skipped.count));
+                        }
+                        if let Some(excluded) = app.excluded_cache.get(&app.current_dir) {
+                            link_notes.push_str(&format!(", not counted: {}", excluded_text(&excluded)));
+                        }
+                        match app.size_cache.get(&app.current_dir) {
+                            Some(sizes) =>
+                                format!(
+                                    "{}(Total files: {}, {} {}{})",
+                                    label,
+                                    app.numbers.count(count as u64),
+                                    format::human_bytes(sizes.get(app.apparent_size)),
+                                    if app.apparent_size { "apparent size" } else { "on disk" },
+                                    link_notes
+                                ),
+                            None =>
+                                format!(
+                                    "{}(Total files: {}{})",
+                                    label,
+                                    app.numbers.count(count as u64),
+                                    link_notes
+                                ),
+                        }
+                    } else if let Some(estimate) = app.current_dir_approx {
+                        let spinner_frame = app.theme.spinner(app.spinner_index);
+                        let size = match app.current_dir_approx_bytes {
+                            Some(bytes) => format!(", ~{}", format::human_bytes(bytes)),
+                            None => String::new(),
+                        };
+                        format!(
+                            "{}(Total files: ~{}{} index-based, counting{})",
+                            label,
+                            app.numbers.count(estimate as u64),
+                            size,
+                            spinner_frame
+                        )
+                    } else {
+                        let spinner_frame = app.theme.spinner(app.spinner_index);
+                        match app.counted_so_far(&app.current_dir) {
+                            Some(found) =>
+                                format!(
+                                    "{}(Counting files: {} so far{})",
+                                    label,
+                                    app.numbers.count(found as u64),
+                                    spinner_frame
+                                ),
+                            None => format!("{}(Counting files{})", label, spinner_frame),
+                        }
+                    };
+
+                    // Space and inodes left on the filesystem, the budget the count eats into
+                    if let Some(usage) = &app.fs_usage {
+                        current_dir_text.push('\n');
+                        current_dir_text.push_str(&usage.describe(&app.numbers));
+                    }
+
+                    // Whose files these are, with the ownership columns
+                    #[cfg(unix)]
+                    if app.columns.visible().any(|column| column == Column::Owners) {
+                        if let Some((uid, share)) = app.owner_cache.get(&app.current_dir).map(|v| *v) {
+                            current_dir_text.push_str(
+                                &format!("\nFiles owned by {}: {:.0}%", app.names.user(uid), share * 100.0)
+                            );
+                        }
+                    }
+
+                    // How long the count took, with the count time column
+                    if app.columns.visible().any(|column| column == Column::CountTime) {
+                        if let Some(count_time) = app.count_time_cache.get(&app.current_dir).map(|v| *v) {
+                            current_dir_text.push_str(&format!("\nCounted in {}", format::elapsed(count_time)));
+                        }
+                    }
+
+                    // How much of the count is build output and caches that can be regenerated
+                    if let Some(text) = junk_summary(&app) {
+                        current_dir_text.push('\n');
+                        current_dir_text.push_str(&text);
+                    }
+
+                    // Files matching the name filter, counted with `F`
+                    if let Some(count) = &app.filtered_count {
+                        let below = count.path.strip_prefix(&app.current_dir).unwrap_or(&count.path);
+                        let below = match below.as_os_str().is_empty() {
+                            true => String::from("here"),
+                            false => format!("in {}", below.display()),
+                        };
+                        current_dir_text.push_str(
+                            &(match count.totals {
+                                Some(totals) =>
+                                    format!(
+                                        "\nMatching {} {}: {} files, {}",
+                                        count.pattern,
+                                        below,
+                                        app.numbers.count(totals.files as u64),
+                                        format::human_bytes(totals.bytes.get(app.apparent_size))
+                                    ),
+                                None =>
+                                    format!(
+                                        "\nCounting files matching {} {}{}",
+                                        count.pattern,
+                                        below,
+                                        app.theme.spinner(app.spinner_index)
+                                    ),
+                            })
+                        );
+                    }
+
+                    // How much of it git does not track, inside a work tree
+                    if let Some(text) = git_summary(&app) {
+                        current_dir_text.push('\n');
+                        current_dir_text.push_str(&text);
+                    }
+
+                    // Append the host-side path when running in a container
+                    if let Some(translator) = &app.host_paths {
+                        if let Some(host_path) = translator.translate(&app.current_dir) {
+                            current_dir_text.push_str(&format!("\nHost path: {}", host_path.display()));
+                        }
+                    }
+
+                    // Calculate the height after wrapping
+                    let num_lines = calculate_wrapped_height(&current_dir_text, block_width);
+
+                    // Set block height including borders; the compact layout keeps most of the screen for the list
+                    let mut current_dir_height = num_lines + 2; // +2 for borders
+                    if narrow {
+                        current_dir_height = current_dir_height.min((size.height / 3).max(3));
+                    }
+
+                    // Status line below the key bindings while counting
+                    let status_line = app.status_line();
+                    let footer_height = if status_line.is_some() { 4 } else { 3 };
+
+                    // Set up the layout
+                    let chunks = Layout::default()
+                        .direction(Direction::Vertical)
+                        .constraints(
+                            [
+                                Constraint::Length(current_dir_height), // Current directory
+                                Constraint::Min(1), // File list
+                                Constraint::Length(footer_height), // Footer
+                            ].as_ref()
+                        )
+                        .split(size);
+
+                    // Display the "Current Directory" block, with the tabs once there are several
+                    let mut title = vec![Span::styled("Current Directory", Style::default().add_modifier(Modifier::BOLD))];
+                    if app.tabs.len() > 1 {
+                        for (index, tab) in app.tabs.iter().enumerate() {
+                            let dir = if index == app.active_tab { &app.current_dir } else { &tab.current_dir };
+                            let name = dir.file_name().map(format::display_name).unwrap_or_else(|| dir.display().to_string());
+                            let style = if index == app.active_tab {
+                                app.theme.focused.add_modifier(Modifier::REVERSED)
+                            } else {
+                                app.theme.unfocused
+                            };
+                            title.push(Span::raw(" "));
+                            title.push(Span::styled(format!(" {}:{} ", index + 1, name), style));
+                        }
+                    }
+                    let title_block = Block::default().borders(Borders::ALL).title(Spans::from(title));
+
+                    // Paragraph containing the current directory, with wrapping enabled
+                    let current_dir_paragraph = Paragraph::new(current_dir_text)
+                        .block(title_block)
+                        .wrap(Wrap { trim: false });
+
+                    f.render_widget(current_dir_paragraph, chunks[0]);
+
+                    // Split off the preview pane or the extension breakdown panel when enabled
+                    let list_area = if app.show_preview {
+                        let columns = Layout::default()
+                            .direction(Direction::Horizontal)
+                            .constraints([Constraint::Percentage(55), Constraint::Percentage(45)].as_ref())
+                            .split(chunks[1]);
+                        let preview_paragraph = Paragraph::new(preview_lines(&app)).block(
+                            Block::default().borders(Borders::ALL).border_style(app.theme.unfocused).title("Preview")
+                        );
+                        f.render_widget(preview_paragraph, columns[1]);
+                        columns[0]
+                    } else if app.show_extensions {
+                        let lines = extension_lines(&app);
+                        // As wide as its widest line, within a quarter to half of the screen
+                        let panel_width = (lines.iter().map(Spans::width).max().unwrap_or(0) as u16)
+                            .saturating_add(2) // Borders
+                            .clamp(chunks[1].width / 4, chunks[1].width / 2);
+                        let columns = Layout::default()
+                            .direction(Direction::Horizontal)
+                            .constraints([Constraint::Min(0), Constraint::Length(panel_width)].as_ref())
+                            .split(chunks[1]);
+                        let extension_paragraph = Paragraph::new(lines).block(
+                            Block::default().borders(Borders::ALL).border_style(app.theme.unfocused).title("File types")
+                        );
+                        f.render_widget(extension_paragraph, columns[1]);
+                        columns[0]
+                    } else {
+                        chunks[1]
+                    };
+
+                    // Dual-pane view: the other pane takes the left or right half, in tab order
+                    let (list_area, other_area) = match &app.split {
+                        Some(split) => {
+                            let halves = Layout::default()
+                                .direction(Direction::Horizontal)
+                                .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+                                .split(list_area);
+                            if split.partner < app.active_tab {
+                                (halves[1], Some(halves[0]))
+                            } else {
+                                (halves[0], Some(halves[1]))
+                            }
+                        }
+                        None => (list_area, None),
+                    };
+
+                    // Only the rows inside the viewport are built
+                    let viewport_rows = list_area.height.saturating_sub(3) as usize; // Borders and header
+                    app.scroll_offset = scroll_offset(
+                        app.scroll_offset,
+                        app.table_state.selected(),
+                        viewport_rows,
+                        app.items.len()
+                    );
+                    let visible = app.scroll_offset..(app.scroll_offset + viewport_rows).min(app.items.len());
+                    let mut state = TableState::default();
+                    state.select(
+                        app.table_state
+                            .selected()
+                            .filter(|selected| visible.contains(selected))
+                            .map(|selected| selected - visible.start)
+                    );
+
+                    let now = SystemTime::now();
+                    let widths = ColumnWidths::measure(&app, visible.clone(), now);
+
+                    // Prepare table data
+                    let shown = widths.shown(&app.columns, list_area, narrow);
+                    let header_cells = shown.iter().map(|(column, _)| {
+                        let title = match column {
+                            Column::Type => "Type",
+                            Column::Name => "Name",
+                            Column::Count if app.sort == SortKey::NotInGit && app.git.is_some() => "Not in git ↓",
+                            Column::Count => "Count",
+                            #[cfg(feature = "charts")]
+                            Column::Bar => "",
+                            Column::Percent => "Share",
+                            Column::Size if app.apparent_size => "Apparent",
+                            Column::Size => "On disk",
+                            Column::Modified => "Modified",
+                            Column::Delta if app.sort == SortKey::Growth => "Delta ↓",
+                            Column::Newest if app.sort == SortKey::Newest => "Newest ↓",
+                            Column::Newest => "Newest",
+                            Column::CountTime => "Took",
+                            #[cfg(unix)]
+                            Column::Owner => "Owner",
+                            #[cfg(unix)]
+                            Column::Group => "Group",
+                            #[cfg(unix)]
+                            Column::Mode => "Mode",
+                            #[cfg(unix)]
+                            Column::Owners => "Files owned by",
+                            Column::Delta => "Delta",
+                            Column::Git => "Git",
+                        };
+                        Cell::from(title).style(app.theme.header)
+                    });
+                    let header = Row::new(header_cells)
+                        .style(app.theme.header_row)
+                        .height(1);
+
+                    let spinner_frame = app.theme.spinner(app.spinner_index);
+
+                    // Entries of the other pane and of this one, by name, when comparing them
+                    let compared = app.split
+                        .as_ref()
+                        .filter(|split| split.compare)
+                        .map(|split| (app.compared_entries(&split.items), app.compared_entries(&app.items)));
+
+                    // Bars are scaled to the largest count of the listing
+                    #[cfg(feature = "charts")]
+                    let largest = app.items
+                        .iter()
+                        .filter(|entry| entry.is_dir && !entry.is_parent())
+                        .filter_map(|entry| entry.file_count)
+                        .max()
+                        .unwrap_or(0);
+
+                    let column_widths = ColumnWidths::constraints(&shown, list_area);
+                    let name_width = shown
+                        .iter()
+                        .zip(&column_widths)
+                        .find(|((column, _), _)| *column == Column::Name)
+                        .map_or(0, |(_, constraint)| {
+                            match constraint {
+                                Constraint::Length(width) => *width as usize,
+                                _ => 0,
+                            }
+                        });
+                    truncated_rows.clear();
+
+                    let rows = app.items[visible.clone()].iter().zip(visible.clone()).map(|(entry, index)| {
+                        let stale = app.recounting.contains(entry.alias_of.as_ref().unwrap_or(&entry.path));
+                        let mut row_style = Style::default();
+                        if app.theme.zebra_rows && !index.is_multiple_of(2) {
+                            row_style = row_style.patch(app.theme.zebra);
+                        }
+                        if let Some(share) = share_of_total(&app, entry) {
+                            row_style = row_style.patch(app.theme.heat(share));
+                        }
+                        if app.excluded.contains(&entry.path) || app.is_filtered_out(entry) {
+                            row_style = row_style.patch(app.theme.filtered);
+                        }
+                        if stale {
+                            row_style = row_style.patch(app.theme.stale);
+                        }
+                        if compared.as_ref().is_some_and(|(other, _)| app.differs(entry, other)) {
+                            row_style = row_style.patch(app.theme.differs);
+                        }
+                        let cells: Vec<Cell> = shown
+                            .iter()
+                            .map(|&(column, _width)| {
+                                match column {
+                                    Column::Type =>
+                                        if entry.alias_of.is_some() {
+                                            Cell::from("Alias").style(app.theme.dir)
+                                        } else if entry.is_bundle {
+                                            Cell::from("Pkg").style(app.theme.file)
+                                        } else if entry.is_archive {
+                                            Cell::from("Arch").style(app.theme.dir)
+                                        } else if entry.junk.is_some() {
+                                            Cell::from("Junk").style(app.theme.junk)
+                                        } else if entry.mount.is_some() {
+                                            Cell::from("Mount").style(app.theme.dir)
+                                        } else if entry.is_dir {
+                                            Cell::from("Dir").style(app.theme.dir)
+                                        } else {
+                                            Cell::from("File").style(app.theme.file)
+                                        },
+                                    Column::Name => {
+                                        let is_parent = entry.is_parent();
+                                        let mut spans = Vec::new();
+                                        if app.icons {
+                                            let kind = IconKind {
+                                                is_dir: entry.is_dir,
+                                                is_parent,
+                                                is_link: entry.is_link,
+                                                is_archive: entry.is_archive,
+                                                is_mount: entry.mount.is_some(),
+                                            };
+                                            let style = if entry.is_dir { app.theme.dir } else { app.theme.file };
+                                            spans.push(Span::styled(format!("{} ", icons::icon(&entry.path, kind)), style));
+                                        }
+                                        if is_parent {
+                                            spans.push(Span::styled(entry.name.clone(), app.theme.parent));
+                                        } else {
+                                            let mut name = entry.name.clone();
+                                            if let Some(primary) = entry.alias_of.as_ref().and_then(|p| p.file_name()) {
+                                                name = format!("{} = {}", name, format::display_name(primary));
+                                            }
+                                            // Cut in the middle, leaving room for the icon, the mark and the mount badge
+                                            let marked = app.is_marked(index);
+                                            let badge = entry.mount.as_ref().map_or(0, |fs_type| fs_type.width() + 3);
+                                            let prefix = if app.icons { 2 } else { 0 } + if marked { 2 } else { 0 };
+                                            let room = name_width.saturating_sub(badge + prefix);
+                                            if app.name_scroll > 0 {
+                                                // Cut at the right, where scrolling further shows the rest
+                                                if name.width() > room + app.name_scroll {
+                                                    truncated_rows.insert(index);
+                                                }
+                                                name = format::skip_columns(&name, app.name_scroll);
+                                            } else if name.width() > room {
+                                                name = format::truncate_middle(&name, room);
+                                                truncated_rows.insert(index);
+                                            }
+                                            spans.push(
+                                                if marked {
+                                                    Span::styled(format!("* {}", name), app.theme.marked)
+                                                } else if entry.junk.is_some() {
+                                                    Span::styled(name, app.theme.junk)
+                                                } else {
+                                                    Span::raw(name)
+                                                }
+                                            );
+                                            if let Some(fs_type) = &entry.mount {
+                                                spans.push(Span::styled(format!(" [{}]", fs_type), app.theme.mount));
+                                            }
+                                        }
+                                        Cell::from(Spans::from(spans))
+                                    }
+                                    Column::Count =>
+                                        if app.sort == SortKey::NotInGit && app.git.is_some() {
+                                            match entry.git.filter(|_| entry.is_dir) {
+                                                Some(git) => Cell::from(app.numbers.count(git.not_in_git() as u64)),
+                                                None => Cell::from("-"),
+                                            }
+                                        } else if entry.is_bundle {
+                                            // Internal count of an opaque bundle
+                                            match entry.file_count {
+                                                Some(count) => Cell::from(format!("({})", app.numbers.count(count as u64))),
+                                                None => Cell::from(spinner_frame),
+                                            }
+                                        } else if entry.is_dir {
+                                            match (entry.file_count, entry.approx_count) {
+                                                (Some(count), _) => Cell::from(app.numbers.count(count as u64)),
+                                                (None, Some(estimate)) =>
+                                                    Cell::from(format!("~{}", app.numbers.count(estimate as u64))).style(app.theme.approximate),
+                                                (None, None) if entry.left_out =>
+                                                    Cell::from("-").style(app.theme.filtered),
+                                                // Running number while counting, so long counts show their scale
+                                                (None, None) =>
+                                                    match app.counted_so_far(entry.alias_of.as_ref().unwrap_or(&entry.path)) {
+                                                        Some(found) =>
+                                                            Cell::from(format!("{}+", app.numbers.count(found as u64))).style(app.theme.approximate),
+                                                        None => Cell::from(spinner_frame),
+                                                    }
+                                            }
+                                        } else {
+                                            Cell::from("-")
+                                        },
+                                    #[cfg(feature = "charts")]
+                                    Column::Bar =>
+                                        match entry.file_count.filter(|_| entry.is_dir && !entry.is_parent()) {
+                                            Some(count) => Cell::from(bar_text(count, largest, _width, app.theme.ascii)),
+                                            None => Cell::from(""),
+                                        },
+                                    Column::Percent =>
+                                        match share_of_total(&app, entry) {
+                                            Some(share) => Cell::from(percent_text(share)),
+                                            None => Cell::from(""),
+                                        },
+                                    Column::Size => Cell::from(size_text(&app, entry)),
+                                    Column::Modified => Cell::from(time_text(&app, entry.modified, now)),
+                                    Column::Newest if entry.is_dir => Cell::from(time_text(&app, entry.newest, now)),
+                                    Column::Newest => Cell::from(""),
+                                    Column::CountTime => Cell::from(count_time_text(&app, entry)),
+                                    #[cfg(unix)]
+                                    Column::Owner => Cell::from(owner_text(&app, entry)),
+                                    #[cfg(unix)]
+                                    Column::Group => Cell::from(group_text(&app, entry)),
+                                    #[cfg(unix)]
+                                    Column::Mode => Cell::from(entry.ownership.map(|o| o.mode_text()).unwrap_or_default()),
+                                    #[cfg(unix)]
+                                    Column::Owners => Cell::from(owners_text(&app, entry)),
+                                    Column::Delta =>
+                                        match entry.growth() {
+                                            Some(growth) => Cell::from(app.numbers.signed(growth)),
+                                            None => Cell::from(""),
+                                        }
+                                    Column::Git => git_cell(&app, entry),
+                                }
+                            })
+                            .collect();
+                        Row::new(cells).style(row_style).height(1)
+                    });
+
+                    let mut title = match &compared {
+                        Some((other, _)) => {
+                            let differing = app.items.iter().filter(|entry| app.differs(entry, other)).count();
+                            format!("File Counter ({} differ)", differing)
+                        }
+                        None => String::from("File Counter"),
+                    };
+                    let mut filters = Vec::new();
+                    match app.shown_kinds {
+                        ShownKinds::All => {}
+                        ShownKinds::Directories => filters.push(String::from("directories only")),
+                        ShownKinds::Files => filters.push(String::from("files only")),
+                    }
+                    if let Some(min) = app.min_count {
+                        filters.push(format!("≥ {} files", app.numbers.count(min as u64)));
+                    }
+                    if let Some(pattern) = &app.name_filter {
+                        filters.push(format!("files matching {}", pattern.text()));
+                    }
+                    if !app.filtered_out.is_empty() {
+                        filters.push(format!("{} hidden", app.filtered_out.len()));
+                    }
+                    if !filters.is_empty() {
+                        title.push_str(&format!(" ({})", filters.join(", ")));
+                    }
+                    if app.name_scroll > 0 {
+                        title.push_str(&format!(" [names scrolled by {}, ← to go back]", app.name_scroll));
+                    }
+                    let t = Table::new(rows)
+                        .header(header)
+                        .block(Block::default().borders(Borders::ALL).border_style(app.theme.focused).title(title))
+                        .highlight_style(app.theme.selection)
+                        .highlight_symbol(">> ")
+                        .widths(&column_widths);
+
+                    f.render_stateful_widget(t, list_area, &mut state);
+
+                    // Save the table area for mouse event handling
+                    table_area = list_area;
+                    let name_offset = shown
+                        .iter()
+                        .take_while(|(column, _)| *column != Column::Name)
+                        .zip(&column_widths)
+                        .map(|(_, constraint)| {
+                            match constraint {
+                                Constraint::Length(width) => *width + 1, // And the spacing
+                                _ => 1,
+                            }
+                        })
+                        .sum::<u16>();
+                    name_columns = (list_area.x + 1 + 3 + name_offset, name_width as u16); // After the border and ">> "
+
+                    // The other pane, as it was when it lost the focus, with up-to-date counts
+                    if let (Some(split), Some(area)) = (&app.split, other_area) {
+                        let rows = split.items
+                            .iter()
+                            .skip(split.scroll_offset)
+                            .take(area.height.saturating_sub(3) as usize)
+                            .map(|entry| {
+                                let (kind, kind_style) = if entry.is_dir {
+                                    ("Dir", app.theme.dir)
+                                } else {
+                                    ("File", app.theme.file)
+                                };
+                                let count = if !entry.is_dir {
+                                    String::from("-")
+                                } else if let Some(count) = app.compared_value(entry) {
+                                    app.numbers.count(count)
+                                } else if let Some(found) = app.counted_so_far(entry.alias_of.as_ref().unwrap_or(&entry.path)) {
+                                    format!("{}+", app.numbers.count(found as u64))
+                                } else {
+                                    spinner_frame.to_string()
+                                };
+                                let mut style = Style::default();
+                                if entry.is_parent() {
+                                    style = app.theme.parent;
+                                }
+                                if compared.as_ref().is_some_and(|(_, active)| app.differs(entry, active)) {
+                                    style = style.patch(app.theme.differs);
+                                }
+                                Row::new(vec![Cell::from(kind).style(kind_style), Cell::from(entry.name.clone()), Cell::from(count)])
+                                    .style(style)
+                                    .height(1)
+                            });
+                        let dir = &app.tabs[split.partner].current_dir;
+                        let title = match &compared {
+                            Some((_, active)) => {
+                                let differing = split.items.iter().filter(|entry| app.differs(entry, active)).count();
+                                format!("{} ({} differ)", dir.display(), differing)
+                            }
+                            None => dir.display().to_string(),
+                        };
+                        let header = Row::new(["Type", "Name", "Count"].map(|h| Cell::from(h).style(app.theme.header)))
+                            .style(app.theme.header_row)
+                            .height(1);
+                        // Type and count columns, the name takes the rest (borders and column gaps aside)
+                        let name_width = area.width.saturating_sub(5 + 10 + 4);
+                        let column_widths = [Constraint::Length(5), Constraint::Length(name_width), Constraint::Length(10)];
+                        let other_table = Table::new(rows)
+                            .header(header)
+                            .block(Block::default().borders(Borders::ALL).border_style(app.theme.unfocused).title(title))
+                            .widths(&column_widths);
+                        f.render_widget(other_table, area);
+                    }
+
+                    // Footer: display key bindings, only how to quit and get help in the compact layout
+                    let mut footer_text = vec![
+                        Spans::from(
+                            match narrow {
+                                true =>
+                                    vec![
+                                        Span::styled("q - Quit", app.theme.key),
+                                        Span::raw(" | "),
+                                        Span::styled("? - Help", app.theme.key)
+                                    ],
+                                false =>
+                                    vec![
+                                        Span::styled(
+                                            "q - Quit",
+                                            app.theme.key
+                                        ),
+                                        Span::raw(" | "),
+                                        Span::styled(
+                                            "↑/↓/k/j - Move",
+                                            app.theme.key
+                                        ),
+                                        Span::raw(" | "),
+                                        Span::styled(
+                                            "Enter - Open",
+                                            app.theme.key
+                                        ),
+                                        Span::raw(" | "),
+                                        Span::styled(
+                                            "? - Help",
+                                            app.theme.key
+                                        )
+                                    ],
+                            }
+                        )
+                    ];
+                    if let Some(status) = status_line {
+                        footer_text.push(Spans::from(Span::styled(status, app.theme.approximate)));
+                    }
+                    let mut footer_block = Block::default().borders(Borders::ALL);
+                    if let Some(summary) = app.marked_summary() {
+                        footer_block = footer_block.title(Span::styled(summary, app.theme.marked));
+                    } else if app.visual_anchor.is_some() {
+                        footer_block = footer_block.title(Span::styled("-- VISUAL --", app.theme.marked));
+                    } else if app.frozen {
+                        footer_block = footer_block.title(Span::styled("-- ORDER FROZEN (z re-sorts) --", app.theme.marked));
+                    }
+                    let footer_paragraph = Paragraph::new(footer_text)
+                        .block(footer_block)
+                        .wrap(Wrap { trim: true });
+
+                    f.render_widget(footer_paragraph, chunks[2]);
+
+                    // Batch operation preview
+                    if let Some(preview) = &app.batch_preview {
+                        let lines = batch_preview_lines(&app, preview);
+                        let popup_height = ((lines.len() as u16) + 2).min(size.height.saturating_sub(2)); // +2 for borders
+                        let popup_area = centered_rect(size.width.saturating_sub(4).max(60), popup_height, size);
+                        let dialog = Paragraph::new(lines)
+                            .block(Block::default().borders(Borders::ALL).title("Preview"))
+                            .wrap(Wrap { trim: false })
+                            .scroll((preview.scroll, 0));
+
+                        f.render_widget(Clear, popup_area);
+                        f.render_widget(dialog, popup_area);
+                    }
+
+                    // Cleanup wizard dialog
+                    if let Some(wizard) = &app.wizard {
+                        let lines = wizard.lines(&app.theme);
+                        let popup_height = (lines.len() as u16) + 2; // +2 for borders
+                        let popup_area = centered_rect(70, popup_height, size);
+                        let dialog = Paragraph::new(lines)
+                            .block(Block::default().borders(Borders::ALL).title("Cleanup wizard"))
+                            .wrap(Wrap { trim: false });
+
+                        f.render_widget(Clear, popup_area);
+                        f.render_widget(dialog, popup_area);
+                    }
+
+                    // Duplicate files view
+                    if let Some(view) = &app.duplicates {
+                        let lines = view.lines(&app.theme, app.theme.spinner(app.spinner_index));
+                        let popup_height = ((lines.len() as u16) + 2).min(size.height.saturating_sub(2)); // +2 for borders
+                        let popup_area = centered_rect(size.width.saturating_sub(4).max(60), popup_height, size);
+                        let dialog = Paragraph::new(lines)
+                            .block(Block::default().borders(Borders::ALL).title("Duplicates"))
+                            .wrap(Wrap { trim: false })
+                            .scroll((view.scroll, 0));
+
+                        f.render_widget(Clear, popup_area);
+                        f.render_widget(dialog, popup_area);
+                    }
+
+                    // Empty directories view
+                    if let Some(view) = &app.empty_dirs {
+                        let lines = view.lines(&app.theme, app.theme.spinner(app.spinner_index), app.is_read_only());
+                        let popup_height = ((lines.len() as u16) + 2).min(size.height.saturating_sub(2)); // +2 for borders
+                        let popup_area = centered_rect(size.width.saturating_sub(4).max(60), popup_height, size);
+                        let dialog = Paragraph::new(lines)
+                            .block(Block::default().borders(Borders::ALL).title("Empty directories"))
+                            .wrap(Wrap { trim: false })
+                            .scroll((view.scroll, 0));
+
+                        f.render_widget(Clear, popup_area);
+                        f.render_widget(dialog, popup_area);
+                    }
+
+                    // Broken symlinks view
+                    if let Some(view) = &app.broken_links {
+                        let lines = view.lines(&app.theme, app.theme.spinner(app.spinner_index), app.is_read_only());
+                        let popup_height = ((lines.len() as u16) + 2).min(size.height.saturating_sub(2)); // +2 for borders
+                        let popup_area = centered_rect(size.width.saturating_sub(4).max(60), popup_height, size);
+                        let dialog = Paragraph::new(lines)
+                            .block(Block::default().borders(Borders::ALL).title("Broken symlinks"))
+                            .wrap(Wrap { trim: false })
+                            .scroll((view.scroll, 0));
+
+                        f.render_widget(Clear, popup_area);
+                        f.render_widget(dialog, popup_area);
+                    }
+
+                    // Skipped entries view
+                    if let Some(view) = &app.skipped {
+                        let lines = view.lines(&app.theme, cfg!(unix) && !app.is_read_only());
+                        let popup_height = ((lines.len() as u16) + 2).min(size.height.saturating_sub(2)); // +2 for borders
+                        let popup_area = centered_rect(size.width.saturating_sub(4).max(60), popup_height, size);
+                        let dialog = Paragraph::new(lines)
+                            .block(Block::default().borders(Borders::ALL).title("Unreadable entries"))
+                            .wrap(Wrap { trim: false })
+                            .scroll((view.scroll, 0));
+
+                        f.render_widget(Clear, popup_area);
+                        f.render_widget(dialog, popup_area);
+                    }
+
+                    // Cleanable space view
+                    if let Some(view) = &app.cleanable {
+                        let lines = view.lines(
+                            &app.theme,
+                            &app.numbers,
+                            app.apparent_size,
+                            app.theme.spinner(app.spinner_index),
+                            app.is_read_only()
+                        );
+                        let popup_height = ((lines.len() as u16) + 2).min(size.height.saturating_sub(2)); // +2 for borders
+                        let popup_area = centered_rect(size.width.saturating_sub(4).max(60), popup_height, size);
+                        let dialog = Paragraph::new(lines)
+                            .block(Block::default().borders(Borders::ALL).title("Cleanable space"))
+                            .wrap(Wrap { trim: false })
+                            .scroll((view.scroll, 0));
+
+                        f.render_widget(Clear, popup_area);
+                        f.render_widget(dialog, popup_area);
+                    }
+
+                    // Drive picker
+                    if let Some(view) = &app.drives {
+                        let lines = view.lines(&app.theme);
+                        let popup_height = ((lines.len() as u16) + 2).min(size.height.saturating_sub(2)); // +2 for borders
+                        let popup_area = centered_rect(size.width.saturating_sub(4).max(60), popup_height, size);
+                        let dialog = Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title("Drives"));
+
+                        f.render_widget(Clear, popup_area);
+                        f.render_widget(dialog, popup_area);
+                    }
+
+                    // Cache statistics
+                    if let Some(view) = &app.cache_view {
+                        let popup_height = size.height.saturating_sub(4).min((view.oldest.len() as u16) + 10); // +2 for borders, 8 for the statistics and keys
+                        let popup_area = centered_rect(size.width.saturating_sub(4).max(60), popup_height, size);
+                        let lines = view.lines(
+                            &app.theme,
+                            &app.numbers,
+                            &app.file_count_cache,
+                            !app.is_read_only(),
+                            popup_height.saturating_sub(2) as usize
+                        );
+                        let dialog = Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title("Count cache"));
+
+                        f.render_widget(Clear, popup_area);
+                        f.render_widget(dialog, popup_area);
+                    }
+
+                    // Fuzzy finder
+                    if let Some(view) = &app.jump {
+                        let popup_height = size.height.saturating_sub(4).min((view.matches.len() as u16) + 4); // +2 for borders, 2 for the query
+                        let popup_area = centered_rect(size.width.saturating_sub(4).max(60), popup_height, size);
+                        let lines = view.lines(
+                            &app.theme,
+                            &app.numbers,
+                            &app.file_count_cache,
+                            popup_height.saturating_sub(2) as usize
+                        );
+                        let dialog = Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title("Jump"));
+
+                        f.render_widget(Clear, popup_area);
+                        f.render_widget(dialog, popup_area);
+                    }
+
+                    // Column picker
+                    if app.column_picker {
+                        let lines = app.columns.picker_lines(&app.theme);
+                        let popup_height = (lines.len() as u16) + 2; // +2 for borders
+                        let popup_area = centered_rect(40, popup_height, size);
+                        let dialog = Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title("Columns"));
+
+                        f.render_widget(Clear, popup_area);
+                        f.render_widget(dialog, popup_area);
+                    }
+
+                    // Full path of an entry whose name may have been cut
+                    if let Some((path, _)) = &app.full_path {
+                        let text = path.display().to_string();
+                        let width = ((text.width() as u16) + 2).min(size.width.saturating_sub(4)).max(20);
+                        let lines = (text.width() as u16).div_ceil(width.saturating_sub(2).max(1)).max(1);
+                        let popup_area = centered_rect(width, lines + 2, size);
+                        let popup = Paragraph::new(text)
+                            .block(Block::default().borders(Borders::ALL).title("Full path"))
+                            .wrap(Wrap { trim: false });
+
+                        f.render_widget(Clear, popup_area);
+                        f.render_widget(popup, popup_area);
+                    }
+
+                    // Help popup drawn on top of everything else
+                    if app.show_help {
+                        let popup_width = HELP_WIDTH.min(size.width);
+                        let lines = help_lines(&app, popup_width.saturating_sub(2) as usize);
+                        let popup_height = ((lines.len() as u16) + 2).min(size.height.saturating_sub(2)); // +2 for borders
+                        let popup_area = centered_rect(popup_width, popup_height, size);
+                        app.help_page = popup_height.saturating_sub(2);
+                        app.help_scroll = app.help_scroll.min((lines.len() as u16).saturating_sub(app.help_page));
+                        let title = match (lines.len() as u16) > app.help_page {
+                            true => "Help (↑/↓, PgUp/PgDn to scroll, Esc to close)",
+                            false => "Help (Esc to close)",
+                        };
+                        let help_paragraph = Paragraph::new(lines)
+                            .block(Block::default().borders(Borders::ALL).title(title))
+                            .scroll((app.help_scroll, 0));
+
+                        f.render_widget(Clear, popup_area);
+                        f.render_widget(help_paragraph, popup_area);
+                    }
+
+                    // Modal prompt, over the popup it may have been opened from
+                    if let Some(modal) = &app.modal {
+                        let lines = modal.lines(&app.theme);
+                        let width = lines
+                            .iter()
+                            .map(|line| line.width())
+                            .chain([modal.title.chars().count(), 40])
+                            .max()
+                            .unwrap_or(0);
+                        let popup_area = centered_rect((width as u16) + 2, (lines.len() as u16) + 2, size);
+                        let dialog = Paragraph::new(lines)
+                            .block(Block::default().borders(Borders::ALL).title(modal.title.clone()))
+                            .wrap(Wrap { trim: false });
+
+                        f.render_widget(Clear, popup_area);
+                        f.render_widget(dialog, popup_area);
+                    }
+
+                    // Toasts above the footer, over any popup
+                    if !app.toasts.is_empty() {
+                        let toast_area = app.toasts.area(Rect { height: chunks[2].y, ..size });
+                        f.render_widget(Clear, toast_area);
+                        f.render_widget(
+                            Paragraph::new(app.toasts.lines(&app.theme)).block(Block::default().borders(Borders::ALL)),
+                            toast_area
+                        );
+                    }
+                })?;
+                redraw_ui = false;
+            }
+
+            // After drawing, handle any pending actions
+            if let Some(action) = app.action_pending.take() {
+                match action {
+                    Action::EnterDirectory(index) => {
+                        if let Some(selected_entry) = app.items.get(index) {
+                            if selected_entry.is_dir && !selected_entry.is_bundle {
+                                app.current_dir = selected_entry.path.clone();
+                                app.refresh_items()?;
+                                redraw_ui = true;
+                            }
+                        }
+                    }
+                }
+            }
+
+            // Tick only while something moves or is about to; idle, the loop sleeps until the next event
+            let busy =
+                app.is_counting() ||
+                counts_updated ||
+                !app.toasts.is_empty() ||
+                last_key.is_some_and(|at| at.elapsed() < RESORT_IDLE);
+            #[cfg(feature = "watch")]
+            let busy = busy || app.watcher.is_some(); // Changes are picked up on ticks
+            ticker.set_active(busy);
+
+            // Wait for the next input, tick or count result
+            let event = app.event_rx.recv()?;
+            if let AppEvent::Input(Ok(Event::Key(_))) = &event {
+                last_key = Some(Instant::now());
+            }
+            match event {
+                AppEvent::Tick => {
+                    if app.toasts.expire() {
+                        redraw_ui = true;
+                    }
+
+                    // Re-sort the counts that arrived in one go, once the keys pause
+                    let idle = last_key.is_none_or(|at| at.elapsed() >= RESORT_IDLE);
+                    if counts_updated && idle && last_resort.elapsed() >= RESORT_INTERVAL {
+                        app.resort(false);
+                        counts_updated = false;
+                        last_resort = Instant::now();
+                        redraw_ui = true;
+                    }
+
+                    #[cfg(feature = "watch")]
+                    app.process_changes()?;
+
+                    if app.show_extensions {
+                        app.ensure_selected_extensions();
+                    }
+                    if app.show_preview {
+                        app.ensure_selected_preview();
+                    }
+
+                    // Animate the spinner while anything is being counted
+                    if app.is_counting() {
+                        app.sample_throughput();
+                        app.spinner_index = app.spinner_index.wrapping_add(1);
+                        redraw_ui = true;
+                    }
+                }
+                AppEvent::Count(message) => {
+                    counts_updated |= app.apply_count(message);
+                    app.trim_count_cache();
+                }
+                AppEvent::Mft(result) => {
+                    counts_updated |= app.apply_mft(result);
+                    redraw_ui = true;
+                }
+                AppEvent::Listing(batch) => {
+                    app.apply_listing(batch);
+                    redraw_ui = true;
+                }
+                AppEvent::Archived(message) => {
+                    app.apply_archive(message)?;
+                    redraw_ui = true;
+                }
+                AppEvent::Duplicates(report) => {
+                    app.apply_duplicates(report);
+                    redraw_ui = true;
+                }
+                AppEvent::EmptyDirs(report) => {
+                    app.apply_empty_dirs(report);
+                    redraw_ui = true;
+                }
+                AppEvent::BrokenLinks(report) => {
+                    app.apply_broken_links(report);
+                    redraw_ui = true;
+                }
+                AppEvent::Cleanable(report) => {
+                    app.apply_cleanable(report);
+                    redraw_ui = true;
+                }
+                AppEvent::Git(status) => {
+                    app.apply_git(status);
+                    redraw_ui = true;
+                }
+                AppEvent::Preview(preview) => {
+                    app.apply_preview(preview);
+                    redraw_ui = true;
+                }
+                AppEvent::FilteredCount(count) => {
+                    app.apply_filtered_count(count);
+                    redraw_ui = true;
+                }
+                AppEvent::Control(request) => {
+                    app.answer_control(request)?;
+                    redraw_ui = true;
+                }
+                AppEvent::Input(event) =>
+                    match event {
+                        Ok(evt) =>
+                            match evt {
+                                // Raw mode swallows SIGINT, so treat Ctrl-C as quit everywhere
+                                Event::Key(key) if
+                                    key.code == KeyCode::Char('c') &&
+                                    key.modifiers.contains(KeyModifiers::CONTROL)
+                                => {
+                                    break;
+                                }
+                                // Lay everything out for the new size right away, on a cleared
+                                // screen so that nothing of the old layout is left behind
+                                Event::Resize(_, _) => {
+                                    terminal.autoresize()?;
+                                    terminal.clear()?;
+                                    redraw_ui = true;
+                                }
+                                // Handle keyboard events
+                                // A modal takes every key, over any view below it
+                                Event::Key(key) if app.modal.is_some() => {
+                                    if let Some(modal) = &mut app.modal {
+                                        match modal.handle_key(key) {
+                                            Outcome::Open => {}
+                                            Outcome::Cancelled => {
+                                                app.modal = None;
+                                            }
+                                            Outcome::Answered(answer) => {
+                                                if let Some(modal) = app.modal.take() {
+                                                    app.answer_modal(modal, answer)?;
+                                                }
+                                            }
+                                        }
+                                    }
+                                    redraw_ui = true;
+                                }
+                                Event::Key(key) if app.drives.is_some() =>
+                                    match key.code {
+                                        KeyCode::Enter => {
+                                            app.scan_selected_drive()?;
+                                            redraw_ui = true;
+                                        }
+                                        KeyCode::Down | KeyCode::Char('j') => {
+                                            if let Some(view) = &mut app.drives {
+                                                view.move_selection(true);
+                                            }
+                                            redraw_ui = true;
+                                        }
+                                        KeyCode::Up | KeyCode::Char('k') => {
+                                            if let Some(view) = &mut app.drives {
+                                                view.move_selection(false);
+                                            }
+                                            redraw_ui = true;
+                                        }
+                                        KeyCode::Char('q') | KeyCode::Esc => {
+                                            app.drives = None;
+                                            redraw_ui = true;
+                                        }
+                                        _ => {}
+                                    }
+                                Event::Key(key) if app.cache_view.is_some() =>
+                                    match key.code {
+                                        KeyCode::Down | KeyCode::Char('j') => {
+                                            if let Some(view) = &mut app.cache_view {
+                                                view.move_selection(true);
+                                            }
+                                            redraw_ui = true;
+                                        }
+                                        KeyCode::Up | KeyCode::Char('k') => {
+                                            if let Some(view) = &mut app.cache_view {
+                                                view.move_selection(false);
+                                            }
+                                            redraw_ui = true;
+                                        }
+                                        // Keep the selected count whatever the budget
+                                        KeyCode::Char('p') => {
+                                            if let Some(view) = &mut app.cache_view {
+                                                if let Some(path) = view.target().map(Path::to_path_buf) {
+                                                    let pinned = app.file_count_cache.toggle_pin(&path);
+                                                    view.refresh(&app.file_count_cache);
+                                                    let verb = if pinned { "Pinned" } else { "Unpinned" };
+                                                    app.toasts.info(format!("{} {}", verb, path.display()));
+                                                }
+                                            }
+                                            redraw_ui = true;
+                                        }
+                                        // Forget the selected count and those below it
+                                        KeyCode::Char('d') if !app.is_read_only() => {
+                                            if let Some(path) = app.cache_view.as_ref().and_then(|view| view.target()).map(Path::to_path_buf) {
+                                                let dropped = app.forget_counts(|p| p.starts_with(&path));
+                                                app.summary.action(format!("Cleared the cached counts below {}", path.display()));
+                                                app.toasts.info(format!("Dropped {} cached counts", app.numbers.count(dropped as u64)));
+                                            }
+                                            redraw_ui = true;
+                                        }
+                                        KeyCode::Char('D') if !app.is_read_only() => {
+                                            let question = String::from("Forget every cached count? Directories are counted again as they are listed.");
+                                            app.modal = Some(Modal::confirm(Prompt::ClearCache, "Count cache", question));
+                                            redraw_ui = true;
+                                        }
+                                        KeyCode::Char('q') | KeyCode::Esc => {
+                                            app.cache_view = None;
+                                            redraw_ui = true;
+                                        }
+                                        _ => {}
+                                    }
+                                Event::Key(key) if app.jump.is_some() =>
+                                    match key.code {
+                                        KeyCode::Enter => {
+                                            app.jump_to_selected()?;
+                                            redraw_ui = true;
+                                        }
+                                        KeyCode::Esc => {
+                                            app.jump = None;
+                                            redraw_ui = true;
+                                        }
+                                        KeyCode::Down | KeyCode::Up => {
+                                            if let Some(view) = &mut app.jump {
+                                                view.move_selection(key.code == KeyCode::Down);
+                                            }
+                                            redraw_ui = true;
+                                        }
+                                        KeyCode::Char(c @ ('n' | 'p')) if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                                            if let Some(view) = &mut app.jump {
+                                                view.move_selection(c == 'n');
+                                            }
+                                            redraw_ui = true;
+                                        }
+                                        KeyCode::Backspace => {
+                                            if let Some(view) = &mut app.jump {
+                                                view.query.pop();
+                                                view.update();
+                                            }
+                                            redraw_ui = true;
+                                        }
+                                        KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                                            if let Some(view) = &mut app.jump {
+                                                view.query.push(c);
+                                                view.update();
+                                            }
+                                            redraw_ui = true;
+                                        }
+                                        _ => {}
+                                    }
+                                Event::Key(key) if app.batch_preview.is_some() =>
+                                    match key.code {
+                                        KeyCode::Char('y') | KeyCode::Char('Y') if
+                                            app.batch_preview.as_ref().is_some_and(|p| p.conflicts.is_empty())
+                                        => {
+                                            if let Some(preview) = app.batch_preview.take() {
+                                                for (path, e) in app.run_batch(preview)? {
+                                                    app.toasts.error(format!("Cannot process {}: {}", path.display(), e));
+                                                }
+                                            }
+                                            redraw_ui = true;
+                                        }
+                                        KeyCode::Down | KeyCode::Char('j') => {
+                                            if let Some(preview) = &mut app.batch_preview {
+                                                preview.scroll = preview.scroll.saturating_add(1);
+                                            }
+                                            redraw_ui = true;
+                                        }
+                                        KeyCode::Up | KeyCode::Char('k') => {
+                                            if let Some(preview) = &mut app.batch_preview {
+                                                preview.scroll = preview.scroll.saturating_sub(1);
+                                            }
+                                            redraw_ui = true;
+                                        }
+                                        KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                                            app.batch_preview = None;
+                                            redraw_ui = true;
+                                        }
+                                        _ => {}
+                                    }
+                                Event::Key(key) if app.duplicates.is_some() =>
+                                    match key.code {
+                                        KeyCode::Down | KeyCode::Char('j') => {
+                                            if let Some(view) = &mut app.duplicates {
+                                                view.scroll = view.scroll.saturating_add(1);
+                                            }
+                                            redraw_ui = true;
+                                        }
+                                        KeyCode::Up | KeyCode::Char('k') => {
+                                            if let Some(view) = &mut app.duplicates {
+                                                view.scroll = view.scroll.saturating_sub(1);
+                                            }
+                                            redraw_ui = true;
+                                        }
+                                        KeyCode::Char('q') | KeyCode::Esc => {
+                                            app.duplicates = None;
+                                            redraw_ui = true;
+                                        }
+                                        _ => {}
+                                    }
+                                Event::Key(key) if app.empty_dirs.is_some() =>
+                                    match key.code {
+                                        KeyCode::Down | KeyCode::Char('j') => {
+                                            if let Some(view) = &mut app.empty_dirs {
+                                                view.scroll = view.scroll.saturating_add(1);
+                                            }
+                                            redraw_ui = true;
+                                        }
+                                        KeyCode::Up | KeyCode::Char('k') => {
+                                            if let Some(view) = &mut app.empty_dirs {
+                                                view.scroll = view.scroll.saturating_sub(1);
+                                            }
+                                            redraw_ui = true;
+                                        }
+                                        // Ask before removing everything found
+                                        KeyCode::Char('d') if !app.is_read_only() => {
+                                            let found = app.empty_dirs
+                                                .as_ref()
+                                                .and_then(|view| view.report.as_ref())
+                                                .map_or(0, |report| report.total_dirs());
+                                            if found > 0 {
+                                                let question = format!("Remove all {} empty directories?", found);
+                                                app.modal = Some(
+                                                    Modal::confirm(Prompt::RemoveEmptyDirs, "Empty directories", question)
+                                                );
+                                            }
+                                            redraw_ui = true;
+                                        }
+                                        KeyCode::Char('q') | KeyCode::Esc => {
+                                            app.empty_dirs = None;
+                                            redraw_ui = true;
+                                        }
+                                        _ => {}
+                                    }
+                                Event::Key(key) if app.broken_links.is_some() =>
+                                    match key.code {
+                                        KeyCode::Down | KeyCode::Char('j') => {
+                                            if let Some(view) = &mut app.broken_links {
+                                                view.scroll = view.scroll.saturating_add(1);
+                                            }
+                                            redraw_ui = true;
+                                        }
+                                        KeyCode::Up | KeyCode::Char('k') => {
+                                            if let Some(view) = &mut app.broken_links {
+                                                view.scroll = view.scroll.saturating_sub(1);
+                                            }
+                                            redraw_ui = true;
+                                        }
+                                        // Ask before removing everything found
+                                        KeyCode::Char('d') if !app.is_read_only() => {
+                                            let found = app.broken_links
+                                                .as_ref()
+                                                .and_then(|view| view.report.as_ref())
+                                                .map_or(0, |report| report.links.len());
+                                            if found > 0 {
+                                                let question = format!("Remove all {} broken symlinks?", found);
+                                                app.modal = Some(
+                                                    Modal::confirm(Prompt::RemoveBrokenLinks, "Broken symlinks", question)
+                                                );
+                                            }
+                                            redraw_ui = true;
+                                        }
+                                        KeyCode::Char('q') | KeyCode::Esc => {
+                                            app.broken_links = None;
+                                            redraw_ui = true;
+                                        }
+                                        _ => {}
+                                    }
+                                Event::Key(key) if app.skipped.is_some() =>
+                                    match key.code {
+                                        KeyCode::Down | KeyCode::Char('j') => {
+                                            if let Some(view) = &mut app.skipped {
+                                                view.scroll = view.scroll.saturating_add(1);
+                                            }
+                                            redraw_ui = true;
+                                        }
+                                        KeyCode::Up | KeyCode::Char('k') => {
+                                            if let Some(view) = &mut app.skipped {
+                                                view.scroll = view.scroll.saturating_sub(1);
+                                            }
+                                            redraw_ui = true;
+                                        }
+                                        // Count the refused directories as root, sudo asking for a password on the terminal
+                                        KeyCode::Char('s') if cfg!(unix) && !app.is_read_only() => {
+                                            let dirs = app.skipped.as_ref().map(SkippedView::denied_dirs).unwrap_or_default();
+                                            if !dirs.is_empty() {
+                                                input.pause();
+                                                screen::restore()?;
+                                                println!("Counting {} directories as root with sudo...", dirs.len());
+                                                let results = count_elevated(&dirs);
+                                                screen::enter()?;
+                                                input.resume();
+                                                terminal.clear()?;
+                                                app.merge_elevated(results);
+                                            }
+                                            redraw_ui = true;
+                                        }
+                                        KeyCode::Char('q') | KeyCode::Esc => {
+                                            app.skipped = None;
+                                            redraw_ui = true;
+                                        }
+                                        _ => {}
+                                    }
+                                Event::Key(key) if app.cleanable.is_some() =>
+                                    match key.code {
+                                        KeyCode::Down | KeyCode::Char('j') => {
+                                            if let Some(view) = &mut app.cleanable {
+                                                view.scroll = view.scroll.saturating_add(1);
+                                            }
+                                            redraw_ui = true;
+                                        }
+                                        KeyCode::Up | KeyCode::Char('k') => {
+                                            if let Some(view) = &mut app.cleanable {
+                                                view.scroll = view.scroll.saturating_sub(1);
+                                            }
+                                            redraw_ui = true;
+                                        }
+                                        // Ask before trashing everything found
+                                        KeyCode::Char('d') if !app.is_read_only() => {
+                                            let found = app.cleanable
+                                                .as_ref()
+                                                .and_then(|view| view.report.as_ref())
+                                                .map_or(0, |report| report.dirs.len());
+                                            if found > 0 {
+                                                let question = format!("Move all {} directories to the trash?", found);
+                                                app.modal = Some(
+                                                    Modal::confirm(Prompt::TrashCleanable, "Build output and caches", question)
+                                                );
+                                            }
+                                            redraw_ui = true;
+                                        }
+                                        KeyCode::Char('q') | KeyCode::Esc => {
+                                            app.cleanable = None;
+                                            redraw_ui = true;
+                                        }
+                                        _ => {}
+                                    }
+                                Event::Key(key) if app.wizard.is_some() =>
+                                    match key.code {
+                                        KeyCode::Char('s') | KeyCode::Char(' ') => {
+                                            app.wizard_action(StopAction::Skip)?;
+                                            redraw_ui = true;
+                                        }
+                                        KeyCode::Char('a') => {
+                                            app.wizard_action(StopAction::Archive)?;
+                                            redraw_ui = true;
+                                        }
+                                        KeyCode::Char('d') => {
+                                            app.wizard_action(StopAction::Delete)?;
+                                            redraw_ui = true;
+                                        }
+                                        KeyCode::Char('e') => {
+                                            app.wizard_action(StopAction::Exclude)?;
+                                            redraw_ui = true;
+                                        }
+                                        // Jump to the summary, or close it
+                                        KeyCode::Char('q') | KeyCode::Esc => {
+                                            match &mut app.wizard {
+                                                Some(wizard) if !wizard.is_finished() => wizard.finish(),
+                                                _ => {
+                                                    app.wizard = None;
+                                                }
+                                            }
+                                            redraw_ui = true;
+                                        }
+                                        _ => {}
+                                    }
+                                Event::Key(key) if app.column_picker =>
+                                    match key.code {
+                                        KeyCode::Char('c') | KeyCode::Esc => {
+                                            app.column_picker = false;
+                                            redraw_ui = true;
+                                        }
+                                        KeyCode::Char(key) => {
+                                            app.columns.toggle(key);
+                                            redraw_ui = true;
+                                        }
+                                        _ => {}
+                                    }
+                                Event::Key(key) if app.show_help =>
+                                    match key.code {
+                                        KeyCode::Char('q') => {
+                                            break;
+                                        }
+                                        // Close the help popup
+                                        KeyCode::Esc | KeyCode::Char('?') => {
+                                            app.show_help = false;
+                                            redraw_ui = true;
+                                        }
+                                        // Scroll it; drawing stops at the last row
+                                        KeyCode::Down | KeyCode::Char('j') => {
+                                            app.help_scroll = app.help_scroll.saturating_add(1);
+                                            redraw_ui = true;
+                                        }
+                                        KeyCode::Up | KeyCode::Char('k') => {
+                                            app.help_scroll = app.help_scroll.saturating_sub(1);
+                                            redraw_ui = true;
+                                        }
+                                        KeyCode::PageDown => {
+                                            app.help_scroll = app.help_scroll.saturating_add(app.help_page.max(1));
+                                            redraw_ui = true;
+                                        }
+                                        KeyCode::PageUp => {
+                                            app.help_scroll = app.help_scroll.saturating_sub(app.help_page.max(1));
+                                            redraw_ui = true;
+                                        }
+                                        _ => {}
+                                    }
+                                // Any key closes the full path popup
+                                Event::Key(_) if app.full_path.is_some() => {
+                                    app.full_path = None;
+                                    redraw_ui = true;
+                                }
+                                Event::Key(key) =>
+                                    match key.code {
+                                        // Quit the program
+                                        KeyCode::Char('q') => {
+                                            break;
+                                        }
+                                        // Scroll every name sideways, to read long ones whole
+                                        KeyCode::Right => {
+                                            let longest = app.items.iter().map(|entry| entry.name.width()).max().unwrap_or(0);
+                                            let limit = longest.saturating_sub(name_columns.1 as usize);
+                                            app.name_scroll = (app.name_scroll + NAME_SCROLL_STEP).min(limit);
+                                            redraw_ui = true;
+                                        }
+                                        KeyCode::Left => {
+                                            app.name_scroll = app.name_scroll.saturating_sub(NAME_SCROLL_STEP);
+                                            redraw_ui = true;
+                                        }
+                                        // Full path of the selected entry, whose name the column may cut
+                                        KeyCode::Char('i') => {
+                                            let selected = app.table_state.selected().and_then(|index| app.items.get(index));
+                                            app.full_path = selected.map(|entry| (entry.path.clone(), false));
+                                            redraw_ui = true;
+                                        }
+                                        // Jump to any directory found so far
+                                        KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                                            app.start_jump();
+                                            redraw_ui = true;
+                                        }
+                                        // Go to a directory by its path
+                                        KeyCode::Char(':') if app.snapshot.is_none() && app.archive.is_none() => {
+                                            app.modal = Some(Modal::input(Prompt::GoToPath, "Go to directory", "", ""));
+                                            redraw_ui = true;
+                                        }
+                                        // Open a tab on the current directory, or close the active one
+                                        KeyCode::Char('t') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                                            app.new_tab();
+                                            redraw_ui = true;
+                                        }
+                                        KeyCode::Char('w') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                                            app.close_tab()?;
+                                            redraw_ui = true;
+                                        }
+                                        // Switch to the next or previous tab
+                                        KeyCode::Tab => {
+                                            app.switch_tab(true)?;
+                                            redraw_ui = true;
+                                        }
+                                        KeyCode::BackTab => {
+                                            app.switch_tab(false)?;
+                                            redraw_ui = true;
+                                        }
+                                        // Dual-pane view, and highlighting what differs between the panes
+                                        KeyCode::Char('|') => {
+                                            app.toggle_split();
+                                            redraw_ui = true;
+                                        }
+                                        KeyCode::Char('=') => {
+                                            if let Some(split) = &mut app.split {
+                                                split.compare = !split.compare;
+                                            }
+                                            redraw_ui = true;
+                                        }
+                                        // Move up
+                                        KeyCode::Up | KeyCode::Char('k') => {
+                                            app.previous();
+                                            redraw_ui = true;
+                                        }
+                                        // Move down
+                                        KeyCode::Down | KeyCode::Char('j') => {
+                                            app.next();
+                                            redraw_ui = true;
+                                        }
+                                        // Enter directory
+                                        KeyCode::Enter => {
+                                            if let Some(selected) = app.table_state.selected() {
+                                                app.action_pending = Some(Action::EnterDirectory(selected));
+                                            }
+                                        }
+                                        // Go to home directory
+                                        KeyCode::Char('h') => {
+                                            app.current_dir = app.home_dir.clone();
+                                            app.refresh_items()?;
+                                            redraw_ui = true;
+                                        }
+                                        // Open the selected entry with the default application
+                                        KeyCode::Char('o') => {
+                                            let selected = app.table_state
+                                                .selected()
+                                                .and_then(|i| app.items.get(i))
+                                                .map(|entry| entry.path.clone());
+                                            if let (Some(path), false) = (selected, app.is_read_only()) {
+                                                // Hand the terminal over while the opener runs
+                                                input.pause();
+                                                screen::restore()?;
+                                                let result = open_command(&path).status();
+                                                screen::enter()?;
+                                                input.resume();
+                                                terminal.clear()?;
+                                                redraw_ui = true;
+
+                                                app.summary.action(format!("Opened {}", path.display()));
+                                                match result {
+                                                    Ok(status) if !status.success() => {
+                                                        app.toasts.error(format!("Opening {} failed: {}", path.display(), status));
+                                                    }
+                                                    Err(e) => {
+                                                        app.toasts.error(format!("Cannot open {}: {}", path.display(), e));
+                                                    }
+                                                    Ok(_) => {}
+                                                }
+                                            }
+                                        }
+                                        // Mark or unmark the selected entry and move on
+                                        KeyCode::Char(' ') => {
+                                            if let Some(selected) = app.table_state.selected() {
+                                                app.toggle_mark(selected);
+                                                app.next();
+                                            }
+                                            redraw_ui = true;
+                                        }
+                                        // Visual range selection
+                                        KeyCode::Char('v') => {
+                                            app.toggle_visual();
+                                            redraw_ui = true;
+                                        }
+                                        // Cancel the visual selection, or clear all marks
+                                        KeyCode::Esc => {
+                                            if app.visual_anchor.is_some() {
+                                                app.visual_anchor = None;
+                                            } else {
+                                                app.marked.clear();
+                                            }
+                                            redraw_ui = true;
+                                        }
+                                        // Move the marked or selected entries to the trash
+                                        KeyCode::Char('d') => {
+                                            app.request_batch(BatchOperation::Trash);
+                                            redraw_ui = true;
+                                        }
+                                        // Move the marked entries into the current directory
+                                        KeyCode::Char('M') if !app.marked.is_empty() => {
+                                            app.request_batch(BatchOperation::Move(app.current_dir.clone()));
+                                            redraw_ui = true;
+                                        }
+                                        // Archive the marked or selected entries
+                                        KeyCode::Char('A') => {
+                                            app.request_batch(BatchOperation::Archive);
+                                            redraw_ui = true;
+                                        }
+                                        // Walk through the largest subdirectories one at a time
+                                        KeyCode::Char('W') => {
+                                            app.start_wizard();
+                                            redraw_ui = true;
+                                        }
+                                        // Look for redundant copies below the selected directory
+                                        KeyCode::Char('D') => {
+                                            app.start_duplicates();
+                                            redraw_ui = true;
+                                        }
+                                        // Look for empty directory litter below the selected directory
+                                        KeyCode::Char('e') => {
+                                            app.start_empty_dirs();
+                                            redraw_ui = true;
+                                        }
+                                        // List the symlinks the counts skip because their target is gone
+                                        KeyCode::Char('L') => {
+                                            app.start_broken_links();
+                                            redraw_ui = true;
+                                        }
+                                        // Pick a drive or mounted filesystem to scan instead
+                                        KeyCode::Char('V') if !app.is_read_only() => {
+                                            app.drives = Some(DriveView::new(&app.current_dir));
+                                            redraw_ui = true;
+                                        }
+                                        // What the count cache holds and how often it answers
+                                        KeyCode::Char('C') => {
+                                            app.cache_view = Some(CacheView::new(&app.file_count_cache));
+                                            redraw_ui = true;
+                                        }
+                                        // Which entries the count could not read, so whether to trust it
+                                        KeyCode::Char('E') => {
+                                            app.show_skipped();
+                                            redraw_ui = true;
+                                        }
+                                        // What removing the build output and caches would reclaim
+                                        KeyCode::Char('J') => {
+                                            app.start_cleanable();
+                                            redraw_ui = true;
+                                        }
+                                        // Toggle whether bundles are opaque items
+                                        KeyCode::Char('b') => {
+                                            app.scan_options.opaque_bundles = !app.scan_options.opaque_bundles;
+                                            // Counts depend on the bundle mode
+                                            app.file_count_cache.clear();
+                                            app.extension_cache.clear();
+                                            app.extension_jobs.clear();
+                                            app.refresh_items()?;
+                                            redraw_ui = true;
+                                        }
+                                        // Re-read the listing of the current directory
+                                        KeyCode::Char('r') => {
+                                            app.refresh_items()?;
+                                            redraw_ui = true;
+                                        }
+                                        // Sort the counts that arrived right away and redraw everything
+                                        KeyCode::Char('l') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                                            if counts_updated {
+                                                app.resort(false);
+                                                counts_updated = false;
+                                                last_resort = Instant::now();
+                                            }
+                                            terminal.clear()?;
+                                            redraw_ui = true;
+                                        }
+                                        // Freeze the order while counts arrive, or sort again
+                                        KeyCode::Char('z') => {
+                                            app.frozen = !app.frozen;
+                                            if !app.frozen {
+                                                app.resort(true);
+                                            }
+                                            redraw_ui = true;
+                                        }
+                                        // Recount the selected directory, ignoring the cache
+                                        KeyCode::Char('R') => {
+                                            app.force_recount_selected();
+                                            redraw_ui = true;
+                                        }
+                                        // Forget the selected subtree and the directories above it, and count them again
+                                        KeyCode::Char('I') => {
+                                            app.invalidate_selected()?;
+                                            redraw_ui = true;
+                                        }
+                                        // Toggle the extension breakdown panel
+                                        KeyCode::Char('x') => {
+                                            app.show_extensions = !app.show_extensions;
+                                            redraw_ui = true;
+                                        }
+                                        // Toggle the preview pane
+                                        KeyCode::Char('p') => {
+                                            app.show_preview = !app.show_preview;
+                                            if app.show_preview {
+                                                app.ensure_selected_preview();
+                                            }
+                                            redraw_ui = true;
+                                        }
+                                        // Sort by growth since the baseline, or back by count
+                                        KeyCode::Char('g') if app.baseline.is_some() => {
+                                            app.toggle_sort(SortKey::Growth);
+                                            redraw_ui = true;
+                                        }
+                                        // Sort by the most recent modification below each directory, or back by count
+                                        KeyCode::Char('m') => {
+                                            app.toggle_sort(SortKey::Newest);
+                                            redraw_ui = true;
+                                        }
+                                        // Count only untracked and ignored files, or back to all of them
+                                        KeyCode::Char('G') if app.git.is_some() => {
+                                            app.toggle_sort(SortKey::NotInGit);
+                                            redraw_ui = true;
+                                        }
+                                        // Pick the sort key from a list
+                                        KeyCode::Char('S') => {
+                                            app.start_sort_picker();
+                                            redraw_ui = true;
+                                        }
+                                        // Toggle relative/ISO-8601 modification times
+                                        KeyCode::Char('t') => {
+                                            app.absolute_times = !app.absolute_times;
+                                            redraw_ui = true;
+                                        }
+                                        // Hide directories below ten times the minimum count, or a tenth of it
+                                        KeyCode::Char('>') => {
+                                            let min = app.min_count.map_or(MIN_COUNT_STEP, |min| min.saturating_mul(MIN_COUNT_STEP));
+                                            app.set_min_count(Some(min));
+                                            redraw_ui = true;
+                                        }
+                                        KeyCode::Char('<') if app.min_count.is_some() => {
+                                            let min = app.min_count.map(|min| min / MIN_COUNT_STEP).filter(|&min| min > 1);
+                                            app.set_min_count(min);
+                                            redraw_ui = true;
+                                        }
+                                        // Show only directories, only files, or both
+                                        KeyCode::Char('f') => {
+                                            app.cycle_shown_kinds();
+                                            redraw_ui = true;
+                                        }
+                                        // Edit the name filter, starting from the current one
+                                        KeyCode::Char('/') => {
+                                            let text = app.name_filter.as_ref().map_or("", |pattern| pattern.text());
+                                            app.modal = Some(
+                                                Modal::input(Prompt::NameFilter, "Filter files by name", text, FILTER_HINT)
+                                            );
+                                            redraw_ui = true;
+                                        }
+                                        // Count only the files matching the name filter
+                                        KeyCode::Char('F') => {
+                                            app.start_filtered_count();
+                                            redraw_ui = true;
+                                        }
+                                        // Toggle grouped/compact counts
+                                        KeyCode::Char(',') => {
+                                            app.numbers.compact = !app.numbers.compact;
+                                            redraw_ui = true;
+                                        }
+                                        // Toggle size on disk/apparent size, like `du --apparent-size`
+                                        KeyCode::Char('a') => {
+                                            app.apparent_size = !app.apparent_size;
+                                            redraw_ui = true;
+                                        }
+                                        // Show or hide columns
+                                        KeyCode::Char('c') => {
+                                            app.column_picker = true;
+                                            redraw_ui = true;
+                                        }
+                                        // Show the help popup
+                                        KeyCode::Char('?') => {
+                                            app.show_help = true;
+                                            app.help_scroll = 0;
+                                            redraw_ui = true;
+                                        }
+                                        _ => {}
+                                    }
+                                // Handle mouse events
+                                Event::Mouse(mouse_event) if
+                                    !app.show_help &&
+                                    app.modal.is_none() &&
+                                    !app.column_picker &&
+                                    app.batch_preview.is_none() &&
+                                    app.wizard.is_none() &&
+                                    app.duplicates.is_none() &&
+                                    app.empty_dirs.is_none() &&
+                                    app.broken_links.is_none() &&
+                                    app.skipped.is_none() &&
+                                    app.cleanable.is_none() &&
+                                    app.jump.is_none() &&
+                                    app.cache_view.is_none() &&
+                                    app.drives.is_none()
+                                => {
+                                    // Hovering a cut name shows it whole, until the mouse leaves it
+                                    if let MouseEventKind::Moved = mouse_event.kind {
+                                        let (name_x, name_width) = name_columns;
+                                        let hovered = Some(mouse_event.row)
+                                            .filter(|&row| row >= table_area.top() + 2 && row < table_area.bottom().saturating_sub(1))
+                                            .filter(|_| mouse_event.column >= name_x && mouse_event.column < name_x + name_width)
+                                            .map(|row| app.scroll_offset + ((row - table_area.top() - 2) as usize))
+                                            .filter(|index| truncated_rows.contains(index))
+                                            .and_then(|index| app.items.get(index));
+                                        match hovered {
+                                            Some(entry) if app.full_path.as_ref().is_none_or(|(path, _)| *path != entry.path) => {
+                                                app.full_path = Some((entry.path.clone(), true));
+                                                redraw_ui = true;
+                                            }
+                                            Some(_) => {}
+                                            None if app.full_path.as_ref().is_some_and(|(_, hovering)| *hovering) => {
+                                                app.full_path = None;
+                                                redraw_ui = true;
+                                            }
+                                            None => {}
+                                        }
+                                    }
+                                    if let MouseEventKind::Down(MouseButton::Left) = mouse_event.kind {
+                                        let mouse_row = mouse_event.row;
+                                        let mouse_col = mouse_event.column;
+                                        // Check if the click is within the table area
+                                        if
+                                            mouse_row >= table_area.top() + 2 &&
+                                            // +1 for top border, +1 for header
+                                            mouse_row < table_area.bottom().saturating_sub(1) &&
+                                            // -1 for bottom border
+                                            mouse_col > table_area.left() &&
+                                            // +1 for left border
+                                            mouse_col < table_area.right().saturating_sub(1)
+                                            // -1 for right border
+                                        {
+                                            // Calculate the index of the clicked item
+                                            let relative_row = mouse_row - table_area.top() - 2;
+                                            // -2 for top border and header
+                                            let index = app.scroll_offset + (relative_row as usize);
+                                            if index < app.items.len() {
+                                                app.table_state.select(Some(index));
+                                                // Set pending action
+                                                app.action_pending = Some(Action::EnterDirectory(index));
+                                                redraw_ui = true;
+                                            }
+                                        }
+                                    }
+                                }
+                                _ => {}
+                            }
+                        Err(e) => {
+                            app.toasts.error(format!("Error reading event: {}", e));
+                            redraw_ui = true;
+                        }
+                    }
+            }
+        }
+        Ok(())
+    })();
+
+    // Restore terminal; counting threads are detached and end with the process
+    let restored = screen::restore();
+
+    let print_summary = options.summary || config.get_bool("display.summary").unwrap_or(false);
+    if print_summary && ended.is_ok() {
+        app.summary.cache_used(app.file_count_cache.stats());
+        println!("{}", app.summary.render(&app.numbers));
+    }
+    // After an error the state is still saved, and failing to is only a warning
+    #[cfg(feature = "persist")]
+    if let Some(path) = &options.export_state {
+        if let Err(e) = app.export_state().save(path) {
+            if ended.is_ok() {
+                return Err(e.into());
+            }
+            eprintln!("Warning: {}", e);
+        }
+    }
+    #[cfg(feature = "persist")]
+    if let Some(path) = &session_file {
+        let saved = match path.parent() {
+            Some(dir) => fs::create_dir_all(dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e)),
+            None => Ok(()),
+        };
+        if let Err(e) = saved.and_then(|()| app.session_state().save(path)) {
+            eprintln!("Warning: {}", e);
+        }
+    }
+
+    ended?;
+    Ok(restored?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dir(name: &str, files: usize, children: Vec<ReportNode>) -> ReportNode {
+        ReportNode { name: String::from(name), is_dir: true, files: Some(files), children, count_time: None }
+    }
+
+    #[test]
+    fn selection_follows_the_first_entry_when_resorted() {
+        let snapshot = dir("snap", 8, vec![dir("logs", 8, vec![dir("a", 5, Vec::new()), dir("b", 3, Vec::new())])]);
+        let home = PathBuf::from("/snap");
+        let mut app = App::new(
+            home.clone(),
+            None,
+            Theme::default(),
+            Some(snapshot),
+            ScanOptions::default(),
+            false,
+            CacheBudget { max_entries: None, max_bytes: None }
+        ).unwrap();
+        app.current_dir = home.join("logs");
+        app.refresh_items().unwrap();
+        let names = |app: &App| app.items.iter().map(|item| item.name.clone()).collect::<Vec<_>>();
+        assert_eq!(names(&app), [PARENT_ENTRY_NAME, "a", "b"]);
+
+        // The first entry below the parent row is selected when b overtakes it
+        app.table_state.select(Some(1));
+        app.items[2].file_count = Some(10);
+        app.resort(true);
+        assert_eq!(names(&app), [PARENT_ENTRY_NAME, "b", "a"]);
+        assert_eq!(app.table_state.selected(), Some(2));
+
+        // The parent row stays selected
+        app.table_state.select(Some(0));
+        app.items[2].file_count = Some(20);
+        app.resort(true);
+        assert_eq!(app.table_state.selected(), Some(0));
+    }
+}
@@ -0,0 +1,212 @@
+use crate::{ cli::UpdateOptions, dirs, json::{ self, Value } };
+use sha2::{ Digest, Sha256 };
+use std::{ env, fs, path::{ Path, PathBuf }, process::{ Command, Stdio }, time::Duration };
+
+/// Latest release of the project, as served by the GitHub API
+const RELEASE_ENDPOINT: &str = "https://api.github.com/repos/huangkaibo/file-counter/releases/latest";
+/// How long a release lookup is reused, to stay clear of the API rate limit
+const RELEASE_CACHE_TTL: Duration = Duration::from_secs(3600);
+/// Release asset listing `<sha256>  <asset name>` for every binary
+const CHECKSUMS_ASSET: &str = "SHA256SUMS";
+
+/// Name of the release asset built for this platform, e.g. `file_counter-linux-x86_64`
+fn asset_name() -> String {
+    let suffix = if cfg!(windows) { ".exe" } else { "" };
+    format!("file_counter-{}-{}{}", env::consts::OS, env::consts::ARCH, suffix)
+}
+
+/// Download a URL with curl, falling back to wget
+fn download(url: &str) -> Result<Vec<u8>, String> {
+    let attempts: [(&str, &[&str]); 2] = [
+        ("curl", &["-fsSL", "-H", "User-Agent: file_counter"]),
+        ("wget", &["-q", "-O", "-", "--header", "User-Agent: file_counter"]),
+    ];
+    let mut last_error = String::from("Neither curl nor wget is available");
+    for (tool, args) in attempts {
+        match Command::new(tool).args(args).arg(url).stdin(Stdio::null()).stderr(Stdio::piped()).output() {
+            Ok(output) if output.status.success() => {
+                return Ok(output.stdout);
+            }
+            Ok(output) => {
+                last_error = format!(
+                    "{} failed to download {}: {}",
+                    tool,
+                    url,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                );
+            }
+            Err(_) => {
+                continue; // Tool not installed, try the next one
+            }
+        }
+    }
+    Err(last_error)
+}
+
+/// Parse `v1.2.3` / `1.2.3` into comparable numbers
+fn parse_version(version: &str) -> Vec<u64> {
+    version
+        .trim_start_matches('v')
+        .split('.')
+        .map(|part| {
+            part.chars()
+                .take_while(|c| c.is_ascii_digit())
+                .collect::<String>()
+                .parse()
+                .unwrap_or(0)
+        })
+        .collect()
+}
+
+/// Expected checksum of `asset` from a `SHA256SUMS` file
+fn expected_checksum(checksums: &str, asset: &str) -> Option<String> {
+    checksums.lines().find_map(|line| {
+        let mut fields = line.split_whitespace();
+        let digest = fields.next()?;
+        let name = fields.next()?.trim_start_matches('*');
+        (name == asset).then(|| digest.to_lowercase())
+    })
+}
+
+/// Lowercase hex SHA-256 of `data`, the way `SHA256SUMS` lists it
+fn hex_digest(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Download URL of a release asset by name
+fn asset_url<'a>(release: &'a Value, name: &str) -> Option<&'a str> {
+    release
+        .get("assets")?
+        .as_array()?
+        .iter()
+        .find(|asset| asset.get("name").and_then(Value::as_str) == Some(name))?
+        .get("browser_download_url")?
+        .as_str()
+}
+
+/// Check the release endpoint and replace the running executable with the
+/// latest release for this platform, after verifying its SHA-256 checksum.
+/// `SHA256SUMS` comes from the same release as the binary, so the check
+/// detects a corrupted or truncated download, not tampering: whoever can
+/// replace the binary can replace its checksum as well.
+pub fn self_update(options: &UpdateOptions) -> Result<(), String> {
+    let current = env!("CARGO_PKG_VERSION");
+    let response = latest_release(dirs::cache_dir(options.cache_dir.as_deref()))?;
+    let release = json::parse(&String::from_utf8_lossy(&response))?;
+    let tag = release
+        .get("tag_name")
+        .and_then(Value::as_str)
+        .ok_or("The release endpoint returned no tag_name")?;
+
+    if parse_version(tag) <= parse_version(current) {
+        println!("file_counter {} is up to date", current);
+        return Ok(());
+    }
+    if options.check_only {
+        println!("Update available: {} -> {}", current, tag);
+        return Ok(());
+    }
+
+    let asset = asset_name();
+    let binary_url = asset_url(&release, &asset).ok_or_else(|| format!("Release {} has no {} binary", tag, asset))?;
+    let checksums_url = asset_url(&release, CHECKSUMS_ASSET).ok_or_else(||
+        format!("Release {} has no {} file, refusing to install an unverified binary", tag, CHECKSUMS_ASSET)
+    )?;
+
+    let checksums = download(checksums_url)?;
+    let expected = expected_checksum(&String::from_utf8_lossy(&checksums), &asset).ok_or_else(||
+        format!("{} does not list {}", CHECKSUMS_ASSET, asset)
+    )?;
+    println!("Downloading {} {}...", asset, tag);
+    let binary = download(binary_url)?;
+    let actual = hex_digest(&binary);
+    if actual != expected {
+        return Err(format!("Checksum mismatch for {}: expected {}, got {}", asset, expected, actual));
+    }
+
+    let exe = env::current_exe().map_err(|e| format!("Cannot locate the running executable: {}", e))?;
+    replace_executable(&exe, &binary).map_err(|e| format!("Cannot replace {}: {}", exe.display(), e))?;
+    println!("Updated file_counter {} -> {}", current, tag);
+    Ok(())
+}
+
+/// Latest release metadata, from the cache directory if fetched within the last hour
+fn latest_release(cache_dir: Option<PathBuf>) -> Result<Vec<u8>, String> {
+    let cache_file = cache_dir.map(|dir| dir.join("latest-release.json"));
+    if let Some(cache_file) = &cache_file {
+        let fresh = fs::metadata(cache_file)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age < RELEASE_CACHE_TTL);
+        if let (true, Ok(cached)) = (fresh, fs::read(cache_file)) {
+            return Ok(cached);
+        }
+    }
+    let response = download(RELEASE_ENDPOINT)?;
+    if let Some(cache_file) = &cache_file {
+        // Caching is best effort
+        if let Some(dir) = cache_file.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        let _ = fs::write(cache_file, &response);
+    }
+    Ok(response)
+}
+
+/// Write the new binary next to the old one and swap it in with a rename,
+/// so an interrupted update never leaves a truncated executable behind
+fn replace_executable(exe: &Path, binary: &[u8]) -> std::io::Result<()> {
+    let staged = sibling(exe, "new");
+    fs::write(&staged, binary)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(exe).map(|m| m.permissions().mode()).unwrap_or(0o755);
+        fs::set_permissions(&staged, fs::Permissions::from_mode(mode))?;
+    }
+    // Windows cannot overwrite a running executable, but it can rename it
+    #[cfg(windows)]
+    let old = {
+        let old = sibling(exe, "old");
+        let _ = fs::remove_file(&old);
+        fs::rename(exe, &old)?;
+        old
+    };
+    fs::rename(&staged, exe).inspect_err(|_| {
+        let _ = fs::remove_file(&staged);
+        // Put the running executable back rather than leave none behind
+        #[cfg(windows)]
+        let _ = fs::rename(&old, exe);
+    })
+}
+
+/// `exe` with an extra extension, in the same directory
+fn sibling(exe: &Path, extension: &str) -> PathBuf {
+    let mut name = exe.as_os_str().to_os_string();
+    name.push(".");
+    name.push(extension);
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Self-update checksums match the SHA-256 test vectors of FIPS 180-2
+    #[test]
+    fn hashes_update_downloads() {
+        assert_eq!(hex_digest(b"abc"), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        assert_eq!(
+            hex_digest(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+        );
+        assert_eq!(
+            hex_digest(&vec![b'a'; 1_000_000]),
+            "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0"
+        );
+    }
+}
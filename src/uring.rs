@@ -0,0 +1,193 @@
+use crate::{ scan::ByteSizes, vfs::EntryMetadata };
+use io_uring::{ opcode, types, IoUring };
+use std::{
+    cell::RefCell,
+    ffi::CString,
+    io,
+    mem,
+    os::unix::ffi::OsStrExt,
+    path::Path,
+    sync::atomic::{ AtomicBool, Ordering },
+    time::{ Duration, UNIX_EPOCH },
+};
+
+/// Submission queue entries per ring: lookups of a large directory go in
+/// batches of this many, all in flight at once
+const RING_ENTRIES: u32 = 256;
+
+/// Set once a ring could not be set up (no io_uring, or a seccomp filter
+/// refusing it), so other threads do not try again
+static UNAVAILABLE: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    static RING: RefCell<Option<IoUring>> = const { RefCell::new(None) };
+}
+
+/// Metadata of `paths`, following symlinks, looked up through a ring of this
+/// thread; None when io_uring cannot be used, and `metadata` should be called
+pub fn statx_batch(paths: &[&Path]) -> Option<Vec<io::Result<EntryMetadata>>> {
+    if UNAVAILABLE.load(Ordering::Relaxed) {
+        return None;
+    }
+    RING.with(|ring| {
+        let mut ring = ring.borrow_mut();
+        if ring.is_none() {
+            match new_ring() {
+                Ok(created) => {
+                    *ring = Some(created);
+                }
+                Err(_) => {
+                    UNAVAILABLE.store(true, Ordering::Relaxed);
+                    return None;
+                }
+            }
+        }
+        let results = statx(ring.as_mut()?, paths);
+        if results.is_none() {
+            *ring = None; // Requests may still be in flight; their buffers were leaked
+        }
+        results
+    })
+}
+
+fn new_ring() -> io::Result<IoUring> {
+    let mut ring = IoUring::new(RING_ENTRIES)?;
+    // Kernels before 5.6 set up rings but cannot statx through them
+    match statx(&mut ring, &[Path::new("/")]).and_then(|mut results| results.pop()) {
+        Some(Ok(_)) => Ok(ring),
+        Some(Err(e)) => Err(e),
+        None => Err(io::Error::from(io::ErrorKind::Unsupported)),
+    }
+}
+
+/// Look up every path; None if the kernel refused the batch, in which case
+/// the ring must not be used again
+fn statx(ring: &mut IoUring, paths: &[&Path]) -> Option<Vec<io::Result<EntryMetadata>>> {
+    let mut results = Vec::with_capacity(paths.len());
+    for chunk in paths.chunks(ring.params().sq_entries() as usize) {
+        let names: Vec<CString> = chunk
+            .iter()
+            .map(|path| CString::new(path.as_os_str().as_bytes()).unwrap_or_default())
+            .collect();
+        // SAFETY: statx is plain data, for which zero is a valid value
+        let mut buffers: Vec<libc::statx> = (0..chunk.len()).map(|_| unsafe { mem::zeroed() }).collect();
+        match submit_and_wait(ring, &names, &mut buffers) {
+            Ok(codes) => {
+                results.extend(
+                    codes
+                        .into_iter()
+                        .zip(&buffers)
+                        .map(|(code, buffer)| {
+                            match code {
+                                0 => Ok(entry_metadata(buffer)),
+                                _ => Err(io::Error::from_raw_os_error(-code)),
+                            }
+                        })
+                );
+            }
+            Err(_) => {
+                // The kernel may still write to these
+                mem::forget(names);
+                mem::forget(buffers);
+                return None;
+            }
+        }
+    }
+    Some(results)
+}
+
+/// Queue a statx of each name into its buffer, then wait for all of them;
+/// returns each one's result: 0 or a negated errno
+fn submit_and_wait(ring: &mut IoUring, names: &[CString], buffers: &mut [libc::statx]) -> io::Result<Vec<i32>> {
+    {
+        let mut queue = ring.submission();
+        for (index, (name, buffer)) in names.iter().zip(buffers.iter_mut()).enumerate() {
+            let entry = opcode::Statx
+                ::new(types::Fd(libc::AT_FDCWD), name.as_ptr(), (buffer as *mut libc::statx).cast())
+                .mask(libc::STATX_BASIC_STATS) // No flags: follow symlinks, like `fs::metadata`
+                .build()
+                .user_data(index as u64);
+            // SAFETY: the name and the buffer outlive the request, or are leaked if it fails
+            unsafe { queue.push(&entry) }.map_err(|_| io::Error::from(io::ErrorKind::WouldBlock))?;
+        }
+    }
+
+    let mut codes = vec![0; names.len()];
+    let mut completed = 0;
+    while completed < names.len() {
+        if let Err(e) = ring.submit_and_wait(names.len() - completed) {
+            match e.raw_os_error() {
+                Some(libc::EINTR | libc::EAGAIN | libc::EBUSY) => {}
+                _ => {
+                    return Err(e);
+                }
+            }
+        }
+        for completion in ring.completion() {
+            if let Some(code) = codes.get_mut(completion.user_data() as usize) {
+                *code = completion.result();
+                completed += 1;
+            }
+        }
+    }
+    Ok(codes)
+}
+
+fn entry_metadata(buffer: &libc::statx) -> EntryMetadata {
+    let kind = u32::from(buffer.stx_mode) & libc::S_IFMT;
+    let modified = &buffer.stx_mtime;
+    let modified = match u64::try_from(modified.tv_sec) {
+        Ok(seconds) => UNIX_EPOCH.checked_add(Duration::new(seconds, modified.tv_nsec)),
+        Err(_) =>
+            UNIX_EPOCH.checked_sub(Duration::from_secs(modified.tv_sec.unsigned_abs()))
+                .and_then(|time| time.checked_add(Duration::from_nanos(u64::from(modified.tv_nsec)))),
+    };
+    EntryMetadata {
+        is_file: kind == libc::S_IFREG,
+        is_dir: kind == libc::S_IFDIR,
+        sizes: ByteSizes { apparent: buffer.stx_size, disk: buffer.stx_blocks.saturating_mul(512) },
+        identity: Some((libc::makedev(buffer.stx_dev_major, buffer.stx_dev_minor), buffer.stx_ino)),
+        links: u64::from(buffer.stx_nlink),
+        modified,
+        owner: Some(buffer.stx_uid),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::{ FileSystem, LocalFs };
+    use std::{ fs, path::PathBuf };
+
+    /// Lookups through io_uring agree with `metadata`, missing paths included;
+    /// passes without checking where the kernel or a seccomp filter refuses rings
+    #[test]
+    fn statx_through_io_uring() {
+        let dir = std::env::temp_dir().join(format!("file_counter-uring-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("file"), b"0123456789").unwrap();
+        fs::create_dir(dir.join("sub")).unwrap();
+        std::os::unix::fs::symlink(dir.join("file"), dir.join("link")).unwrap();
+        let paths: Vec<PathBuf> = ["file", "sub", "link", "missing"].iter().map(|name| dir.join(name)).collect();
+        let paths: Vec<&Path> = paths.iter().map(PathBuf::as_path).collect();
+
+        if let Some(batch) = statx_batch(&paths) {
+            for (path, ring) in paths.iter().zip(batch) {
+                match (ring, LocalFs.metadata(path)) {
+                    (Ok(ring), Ok(std)) => {
+                        assert_eq!(
+                            (ring.is_file, ring.is_dir, ring.sizes, ring.identity, ring.links, ring.modified, ring.owner),
+                            (std.is_file, std.is_dir, std.sizes, std.identity, std.links, std.modified, std.owner),
+                            "{}",
+                            path.display()
+                        );
+                    }
+                    (Err(ring), Err(std)) => assert_eq!(ring.kind(), std.kind(), "{}", path.display()),
+                    (ring, std) => panic!("{}: {:?} through the ring, {:?} from metadata", path.display(), ring.is_ok(), std.is_ok()),
+                }
+            }
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
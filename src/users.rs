@@ -0,0 +1,89 @@
+use crate::{
+    cli::UsersOptions,
+    numbers::NumberFormat,
+    owners::Names,
+    scan::{ scan_dir, FileTotals, ScanOptions },
+};
+use std::{ fs, path::Path };
+
+/// One row of the per-user report
+struct UserRow {
+    uid: u32,
+    name: String,
+    totals: FileTotals,
+}
+
+/// Count the tree below `dir` by owner and print a table of the users, most
+/// files first, or write it as CSV
+pub fn run(dir: &Path, options: &UsersOptions) -> Result<(), String> {
+    if !cfg!(unix) {
+        return Err(String::from("File owners are only known on Unix"));
+    }
+    let root = dir.canonicalize().map_err(|e| format!("Cannot open {}: {}", dir.display(), e))?;
+    let scan_options = ScanOptions { collect_owners: true, ..ScanOptions::default() };
+    let stats = scan_dir(&root, &scan_options).map_err(|e| format!("Cannot count {}: {}", root.display(), e))?;
+    let names = Names::load();
+    let mut rows: Vec<UserRow> = stats.owners
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(uid, totals)| UserRow { uid, name: names.user(uid), totals })
+        .collect();
+    rows.sort_by(|a, b| b.totals.files.cmp(&a.totals.files).then_with(|| a.name.cmp(&b.name)));
+
+    if let Some(path) = &options.csv {
+        let text = csv(&rows);
+        if path.as_os_str() == "-" {
+            print!("{}", text);
+        } else {
+            fs::write(path, text).map_err(|e| format!("Cannot write {}: {}", path.display(), e))?;
+        }
+        return Ok(());
+    }
+
+    let numbers = NumberFormat::from_env(false);
+    println!("Files by owner below {}", root.display());
+    println!();
+    println!("{:<20}{:>14}{:>8}{:>20}{:>20}", "User", "Files", "Share", "On disk", "Apparent");
+    for row in &rows {
+        let share = if stats.files > 0 { (row.totals.files as f64) * 100.0 / (stats.files as f64) } else { 0.0 };
+        println!(
+            "{:<20}{:>14}{:>7.1}%{:>20}{:>20}",
+            row.name,
+            numbers.count(row.totals.files as u64),
+            share,
+            numbers.count(row.totals.bytes.disk),
+            numbers.count(row.totals.bytes.apparent)
+        );
+    }
+    println!();
+    let users = if rows.len() == 1 { "user" } else { "users" };
+    println!("{} files owned by {} {}", numbers.count(stats.files as u64), rows.len(), users);
+    Ok(())
+}
+
+/// The rows as CSV with a header line, sizes in bytes
+fn csv(rows: &[UserRow]) -> String {
+    let mut text = String::from("user,uid,files,disk_bytes,apparent_bytes\n");
+    for row in rows {
+        text.push_str(
+            &format!(
+                "{},{},{},{},{}\n",
+                csv_field(&row.name),
+                row.uid,
+                row.totals.files,
+                row.totals.bytes.disk,
+                row.totals.bytes.apparent
+            )
+        );
+    }
+    text
+}
+
+/// Quote a field containing a separator, quote or line break (RFC 4180)
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
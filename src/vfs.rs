@@ -0,0 +1,320 @@
+use crate::scan::ByteSizes;
+use std::{ borrow::Cow, fs, io, path::{ Path, PathBuf }, time::SystemTime };
+
+/// What a scan needs to know about an entry, after following symlinks
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EntryMetadata {
+    pub is_file: bool,
+    pub is_dir: bool,
+    pub sizes: ByteSizes,
+    pub identity: Option<(u64, u64)>, // Device and inode, where the backend has them
+    pub links: u64, // Hard links to the same data; 1 where unknown
+    pub modified: Option<SystemTime>, // Where the backend records it
+    pub owner: Option<u32>, // User id, on Unix
+}
+
+impl EntryMetadata {
+    /// Identity of a file reachable through several hard links
+    pub fn hard_link_identity(&self) -> Option<(u64, u64)> {
+        self.identity.filter(|_| self.links > 1)
+    }
+}
+
+/// Type of an entry as its directory lists it, without reading its metadata
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EntryKind {
+    File,
+    Dir,
+    Symlink,
+    Other, // Fifo, socket, device
+}
+
+/// Where a scan reads directories from. The local disk is one backend; others
+/// (archives, remote stores, in-memory trees for tests) only need these calls.
+pub trait FileSystem: Sync {
+    /// Paths of the entries of a directory, in no particular order. Unreadable
+    /// entries are left out.
+    fn read_dir(&self, dir: &Path) -> io::Result<Vec<PathBuf>>;
+
+    /// Like `read_dir`, with the type of each entry where the listing gives it
+    /// for free. Backends that do not know it keep the default, and every
+    /// entry's metadata is read instead.
+    fn read_dir_typed(&self, dir: &Path) -> io::Result<Vec<(PathBuf, Option<EntryKind>)>> {
+        Ok(
+            self
+                .read_dir(dir)?
+                .into_iter()
+                .map(|path| (path, None))
+                .collect()
+        )
+    }
+
+    /// Metadata of an entry, following symlinks
+    fn metadata(&self, path: &Path) -> io::Result<EntryMetadata>;
+
+    /// Metadata of several entries, in order. Backends that can have the
+    /// lookups in flight together override this.
+    fn metadata_batch(&self, paths: &[&Path]) -> Vec<io::Result<EntryMetadata>> {
+        paths
+            .iter()
+            .map(|path| self.metadata(path))
+            .collect()
+    }
+
+    /// The path with every symlink resolved
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf>;
+
+    /// Whether the entry itself is a symlink, without following it. Backends
+    /// without symlinks keep the default. On Windows, junctions and mount
+    /// points are links too, so they are followed like symlinks and a loop
+    /// back to an ancestor is caught by its resolved path.
+    fn is_symlink(&self, _path: &Path) -> bool {
+        false
+    }
+}
+
+/// The local filesystem through `std::fs`
+pub struct LocalFs;
+
+impl FileSystem for LocalFs {
+    /// Entries are joined to `dir` as given, so a `\\?\` prefix added to
+    /// read a long path does not show up in theirs
+    fn read_dir(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        Ok(
+            fs::read_dir(long_path(dir))?
+                .flatten()
+                .map(|entry| dir.join(entry.file_name()))
+                .collect()
+        )
+    }
+
+    /// Raw `getdents64` on Linux, reading large batches of entries per call;
+    /// the entry types other systems put in their listings elsewhere
+    #[cfg(target_os = "linux")]
+    fn read_dir_typed(&self, dir: &Path) -> io::Result<Vec<(PathBuf, Option<EntryKind>)>> {
+        getdents(dir)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn read_dir_typed(&self, dir: &Path) -> io::Result<Vec<(PathBuf, Option<EntryKind>)>> {
+        Ok(
+            fs::read_dir(long_path(dir))?
+                .flatten()
+                .map(|entry| {
+                    let kind = entry.file_type().ok().map(|file_type| {
+                        if file_type.is_symlink() {
+                            EntryKind::Symlink
+                        } else if file_type.is_dir() {
+                            EntryKind::Dir
+                        } else if file_type.is_file() {
+                            EntryKind::File
+                        } else {
+                            EntryKind::Other
+                        }
+                    });
+                    (dir.join(entry.file_name()), kind)
+                })
+                .collect()
+        )
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<EntryMetadata> {
+        let metadata = fs::metadata(long_path(path))?;
+        let (identity, links) = identity_and_links(&metadata);
+        Ok(EntryMetadata {
+            is_file: metadata.is_file(),
+            is_dir: metadata.is_dir(),
+            sizes: ByteSizes::of(&metadata),
+            identity,
+            links,
+            modified: metadata.modified().ok(),
+            owner: owner(&metadata),
+        })
+    }
+
+    /// One io_uring submission per batch with the experimental `io_uring`
+    /// feature, so latency-bound storage answers the lookups concurrently
+    #[cfg(all(feature = "io_uring", target_os = "linux", target_env = "gnu"))]
+    fn metadata_batch(&self, paths: &[&Path]) -> Vec<io::Result<EntryMetadata>> {
+        crate::uring::statx_batch(paths).unwrap_or_else(|| {
+            paths
+                .iter()
+                .map(|path| self.metadata(path))
+                .collect()
+        })
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        long_path(path).canonicalize()
+    }
+
+    fn is_symlink(&self, path: &Path) -> bool {
+        long_path(path).is_symlink()
+    }
+}
+
+/// Bytes of directory entries read per `getdents64` call; `readdir` asks for 32 KiB
+#[cfg(target_os = "linux")]
+const GETDENTS_BUFFER: usize = 256 * 1024;
+
+/// The entries of `dir` with their `d_type`, which most Linux filesystems
+/// fill in; where one reports `DT_UNKNOWN` the type is left to `metadata`
+#[cfg(target_os = "linux")]
+fn getdents(dir: &Path) -> io::Result<Vec<(PathBuf, Option<EntryKind>)>> {
+    use std::{ ffi::CString, os::{ fd::{ FromRawFd, OwnedFd }, unix::ffi::OsStrExt } };
+
+    let c_dir = CString::new(dir.as_os_str().as_bytes()).map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+    // SAFETY: `c_dir` is a NUL-terminated path
+    let fd = unsafe { libc::open(c_dir.as_ptr(), libc::O_RDONLY | libc::O_DIRECTORY | libc::O_CLOEXEC) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: `fd` was just opened and is owned by nothing else
+    let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+    thread_local! {
+        // Reused across directories, as most hold a few entries
+        static BUFFER: std::cell::RefCell<Vec<u8>> = std::cell::RefCell::new(vec![0u8; GETDENTS_BUFFER]);
+    }
+    BUFFER.with(|buffer| read_dents(dir, &fd, &mut buffer.borrow_mut()))
+}
+
+#[cfg(target_os = "linux")]
+fn read_dents(dir: &Path, fd: &std::os::fd::OwnedFd, buffer: &mut [u8]) -> io::Result<Vec<(PathBuf, Option<EntryKind>)>> {
+    use std::{ ffi::OsStr, os::{ fd::AsRawFd, unix::ffi::OsStrExt } };
+
+    let mut entries = Vec::new();
+    loop {
+        // SAFETY: the kernel writes at most `buffer.len()` bytes into `buffer`
+        let read = unsafe {
+            libc::syscall(libc::SYS_getdents64, fd.as_raw_fd(), buffer.as_mut_ptr(), buffer.len())
+        };
+        if read < 0 {
+            let e = io::Error::last_os_error();
+            if e.kind() == io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(e);
+        }
+        if read == 0 {
+            return Ok(entries);
+        }
+        // struct linux_dirent64 { u64 d_ino; i64 d_off; u16 d_reclen; u8 d_type; char d_name[]; }
+        let mut records = &buffer[..read as usize];
+        while records.len() > 19 {
+            let length = u16::from_ne_bytes([records[16], records[17]]) as usize;
+            if length <= 19 || length > records.len() {
+                break;
+            }
+            let (record, rest) = records.split_at(length);
+            records = rest;
+            let name = &record[19..];
+            let name = &name[..name.iter().position(|&byte| byte == 0).unwrap_or(name.len())];
+            if name == b"." || name == b".." {
+                continue;
+            }
+            let kind = match record[18] {
+                libc::DT_REG => Some(EntryKind::File),
+                libc::DT_DIR => Some(EntryKind::Dir),
+                libc::DT_LNK => Some(EntryKind::Symlink),
+                libc::DT_UNKNOWN => None,
+                _ => Some(EntryKind::Other),
+            };
+            entries.push((dir.join(OsStr::from_bytes(name)), kind));
+        }
+    }
+}
+
+/// Paths this long need the `\\?\` prefix on Windows, or the calls fail as
+/// if the entry did not exist (`MAX_PATH` is 260, minus room for a file name)
+#[cfg(windows)]
+const MAX_PLAIN_PATH: usize = 248;
+
+/// The path in a form Windows accepts beyond `MAX_PATH`: absolute, with `.`
+/// and `..` resolved (the prefix turns off that parsing) and prefixed with
+/// `\\?\`, or `\\?\UNC\` for a share. Short paths, and paths already in a
+/// device or verbatim form, are left alone.
+#[cfg(windows)]
+pub fn long_path(path: &Path) -> Cow<'_, Path> {
+    use std::{ ffi::OsString, os::windows::ffi::OsStrExt, path::{ Component, Prefix } };
+
+    // MAX_PATH counts UTF-16 units
+    if path.as_os_str().encode_wide().count() < MAX_PLAIN_PATH {
+        return Cow::Borrowed(path);
+    }
+    if let Some(Component::Prefix(prefix)) = path.components().next() {
+        if prefix.kind().is_verbatim() || matches!(prefix.kind(), Prefix::DeviceNS(_)) {
+            return Cow::Borrowed(path);
+        }
+    }
+    let Ok(absolute) = std::path::absolute(path) else {
+        return Cow::Borrowed(path);
+    };
+    let mut components = absolute.components();
+    let Some(Component::Prefix(prefix)) = components.next() else {
+        return Cow::Borrowed(path);
+    };
+    let mut prefixed = OsString::new();
+    match prefix.kind() {
+        Prefix::UNC(server, share) => {
+            prefixed.push(r"\\?\UNC\");
+            prefixed.push(server);
+            prefixed.push(r"\");
+            prefixed.push(share);
+            prefixed.push(components.as_path()); // From the root on
+        }
+        Prefix::Disk(_) => {
+            prefixed.push(r"\\?\");
+            prefixed.push(&absolute);
+        }
+        _ => {
+            return Cow::Borrowed(path);
+        }
+    }
+    Cow::Owned(PathBuf::from(prefixed))
+}
+
+/// Other systems have no such limit
+#[cfg(not(windows))]
+pub fn long_path(path: &Path) -> Cow<'_, Path> {
+    Cow::Borrowed(path)
+}
+
+#[cfg(unix)]
+fn identity_and_links(metadata: &fs::Metadata) -> (Option<(u64, u64)>, u64) {
+    use std::os::unix::fs::MetadataExt;
+    (Some((metadata.dev(), metadata.ino())), metadata.nlink())
+}
+
+#[cfg(not(unix))]
+fn identity_and_links(_metadata: &fs::Metadata) -> (Option<(u64, u64)>, u64) {
+    (None, 1)
+}
+
+#[cfg(unix)]
+fn owner(metadata: &fs::Metadata) -> Option<u32> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.uid())
+}
+
+#[cfg(not(unix))]
+fn owner(_metadata: &fs::Metadata) -> Option<u32> {
+    None
+}
+
+#[cfg(all(test, windows))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefixes_long_paths() {
+        let deep = "d\\".repeat(150);
+        let disk = PathBuf::from(format!(r"C:\{}", deep));
+        assert_eq!(long_path(&disk).as_os_str(), format!(r"\\?\C:\{}", deep).as_str());
+        let share = PathBuf::from(format!(r"\\server\share\{}", deep));
+        assert_eq!(long_path(&share).as_os_str(), format!(r"\\?\UNC\server\share\{}", deep).as_str());
+        // Already verbatim, or short
+        assert_eq!(long_path(&long_path(&share)), long_path(&share));
+        assert_eq!(long_path(Path::new(r"C:\short")), Path::new(r"C:\short"));
+    }
+}
@@ -0,0 +1,337 @@
+use std::{
+    collections::HashMap,
+    path::{ Path, PathBuf },
+    sync::mpsc::{ self, Receiver, Sender },
+    thread,
+    time::{ Duration, Instant },
+};
+
+/// Wait this long after the last change before recounting, so bursts settle
+const QUIET_PERIOD: Duration = Duration::from_millis(500);
+/// Never hold a change back longer than this, even if events keep coming
+const MAX_DELAY: Duration = Duration::from_secs(5);
+/// Minimum time between two recounts of the same directory
+const MIN_RECOUNT_INTERVAL: Duration = Duration::from_secs(3);
+
+/// Watches a directory tree on a background thread and reports the directories
+/// whose entries changed (created, deleted or renamed)
+pub struct Watcher {
+    roots: Sender<PathBuf>,
+    changes: Receiver<PathBuf>,
+}
+
+impl Watcher {
+    pub fn spawn() -> Watcher {
+        let (roots, root_rx) = mpsc::channel();
+        let (change_tx, changes) = mpsc::channel();
+        thread::spawn(move || platform::run(root_rx, change_tx));
+        Watcher { roots, changes }
+    }
+
+    /// Watch the tree below `root` instead of the previous one
+    pub fn watch(&self, root: &Path) {
+        self.roots.send(root.to_path_buf()).unwrap_or(());
+    }
+
+    /// Directories changed since the last call
+    pub fn changes(&self) -> impl Iterator<Item = PathBuf> + '_ {
+        self.changes.try_iter()
+    }
+}
+
+/// A change waiting for its directory to settle
+struct Pending {
+    first: Instant,
+    last: Instant,
+}
+
+/// Coalesces change notifications per directory and bounds how often each
+/// directory is recounted, so high-churn trees (log rotation, spools) don't
+/// turn into a constant rescan loop
+#[derive(Default)]
+pub struct Debouncer {
+    pending: HashMap<PathBuf, Pending>,
+    last_recount: HashMap<PathBuf, Instant>,
+}
+
+impl Debouncer {
+    pub fn record(&mut self, dir: PathBuf, now: Instant) {
+        self.pending
+            .entry(dir)
+            .and_modify(|p| {
+                p.last = now;
+            })
+            .or_insert(Pending { first: now, last: now });
+    }
+
+    /// Directories that should be recounted now
+    pub fn due(&mut self, now: Instant) -> Vec<PathBuf> {
+        self.last_recount.retain(|_, at| now.duration_since(*at) < MIN_RECOUNT_INTERVAL);
+
+        let mut due = Vec::new();
+        for (dir, pending) in &self.pending {
+            let settled =
+                now.duration_since(pending.last) >= QUIET_PERIOD ||
+                now.duration_since(pending.first) >= MAX_DELAY;
+            if settled && !self.last_recount.contains_key(dir) {
+                due.push(dir.clone());
+            }
+        }
+        for dir in &due {
+            self.pending.remove(dir);
+            self.last_recount.insert(dir.clone(), now);
+        }
+        due
+    }
+}
+
+/// inotify-based watcher. Watches follow directories across renames, so
+/// rotated directories keep being reported under their new name.
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::{
+        collections::HashMap,
+        ffi::{ CString, OsStr },
+        fs,
+        io,
+        os::unix::ffi::OsStrExt,
+        path::{ Path, PathBuf },
+        sync::mpsc::{ Receiver, Sender },
+    };
+
+    /// How long a read waits before checking for a new root
+    const POLL_TIMEOUT_MS: i32 = 250;
+
+    const WATCH_MASK: u32 =
+        libc::IN_CREATE |
+        libc::IN_DELETE |
+        libc::IN_MOVED_FROM |
+        libc::IN_MOVED_TO |
+        libc::IN_DELETE_SELF |
+        libc::IN_ONLYDIR;
+
+    /// An inotify instance and the directory of each watch descriptor
+    struct Inotify {
+        fd: i32,
+        dirs: HashMap<i32, PathBuf>,
+    }
+
+    impl Inotify {
+        fn new() -> io::Result<Inotify> {
+            let fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(Inotify { fd, dirs: HashMap::new() })
+        }
+
+        /// Watch `root` and every directory below it. Symlinks are not followed.
+        fn add_tree(&mut self, root: &Path) {
+            let mut dirs_to_visit = vec![root.to_path_buf()];
+            while let Some(dir) = dirs_to_visit.pop() {
+                let Ok(path) = CString::new(dir.as_os_str().as_bytes()) else {
+                    continue;
+                };
+                let wd = unsafe { libc::inotify_add_watch(self.fd, path.as_ptr(), WATCH_MASK) };
+                if wd < 0 {
+                    // Out of watches (fs.inotify.max_user_watches): keep what we have
+                    if io::Error::last_os_error().raw_os_error() == Some(libc::ENOSPC) {
+                        return;
+                    }
+                    continue;
+                }
+                self.dirs.insert(wd, dir.clone());
+
+                let Ok(entries) = fs::read_dir(&dir) else {
+                    continue;
+                };
+                for entry in entries.flatten() {
+                    if entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
+                        dirs_to_visit.push(entry.path());
+                    }
+                }
+            }
+        }
+
+        /// Stop watching `root` and everything below it
+        fn remove_tree(&mut self, root: &Path) {
+            let fd = self.fd;
+            self.dirs.retain(|&wd, dir| {
+                let keep = !dir.starts_with(root);
+                if !keep {
+                    unsafe {
+                        libc::inotify_rm_watch(fd, wd);
+                    }
+                }
+                keep
+            });
+        }
+
+        /// Wait for events and report the changed directories.
+        /// Returns false when the instance is no longer usable.
+        fn read_events(&mut self, changes: &Sender<PathBuf>) -> bool {
+            let mut pollfd = libc::pollfd { fd: self.fd, events: libc::POLLIN, revents: 0 };
+            if unsafe { libc::poll(&mut pollfd, 1, POLL_TIMEOUT_MS) } <= 0 {
+                return true;
+            }
+
+            let mut buffer = [0u8; 64 * 1024];
+            let len = unsafe { libc::read(self.fd, buffer.as_mut_ptr().cast(), buffer.len()) };
+            if len < 0 {
+                return io::Error::last_os_error().kind() == io::ErrorKind::WouldBlock;
+            }
+
+            let header = std::mem::size_of::<libc::inotify_event>();
+            let mut offset = 0;
+            while offset + header <= len as usize {
+                let event: libc::inotify_event = unsafe {
+                    std::ptr::read_unaligned(buffer[offset..].as_ptr().cast())
+                };
+                let name_bytes = &buffer[offset + header..offset + header + event.len as usize];
+                let name_end = name_bytes.iter().position(|&b| b == 0).unwrap_or(name_bytes.len());
+                let name = OsStr::from_bytes(&name_bytes[..name_end]);
+                offset += header + event.len as usize;
+
+                if event.mask & libc::IN_Q_OVERFLOW != 0 {
+                    // Events were lost: report every watched directory
+                    for dir in self.dirs.values() {
+                        changes.send(dir.clone()).unwrap_or(());
+                    }
+                    continue;
+                }
+                if event.mask & libc::IN_IGNORED != 0 {
+                    self.dirs.remove(&event.wd);
+                    continue;
+                }
+                let Some(dir) = self.dirs.get(&event.wd).cloned() else {
+                    continue;
+                };
+                if event.mask & libc::IN_DELETE_SELF != 0 {
+                    continue; // Reported by the parent directory
+                }
+
+                if event.mask & libc::IN_ISDIR != 0 && !name.is_empty() {
+                    let child = dir.join(name);
+                    if event.mask & (libc::IN_MOVED_FROM | libc::IN_DELETE) != 0 {
+                        self.remove_tree(&child);
+                    }
+                    if event.mask & (libc::IN_CREATE | libc::IN_MOVED_TO) != 0 {
+                        self.add_tree(&child);
+                    }
+                }
+                if changes.send(dir).is_err() {
+                    return false;
+                }
+            }
+            true
+        }
+    }
+
+    impl Drop for Inotify {
+        fn drop(&mut self) {
+            unsafe {
+                libc::close(self.fd);
+            }
+        }
+    }
+
+    pub fn run(roots: Receiver<PathBuf>, changes: Sender<PathBuf>) {
+        let mut current: Option<(PathBuf, Inotify)> = None;
+        loop {
+            // Block until there is something to watch, then pick up the latest requested root
+            let next_root = match current {
+                Some(_) => roots.try_iter().last(),
+                None => {
+                    match roots.recv() {
+                        Ok(root) => Some(root),
+                        Err(_) => {
+                            return;
+                        }
+                    }
+                }
+            };
+            if let Some(root) = next_root {
+                if current.as_ref().map(|(r, _)| r) != Some(&root) {
+                    current = Inotify::new()
+                        .ok()
+                        .map(|mut inotify| {
+                            inotify.add_tree(&root);
+                            (root, inotify)
+                        });
+                }
+            }
+
+            if let Some((_, inotify)) = &mut current {
+                if !inotify.read_events(&changes) {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Portable fallback: poll the modification time of the root and its direct
+/// subdirectories. Changes deeper in the tree are not noticed.
+#[cfg(not(target_os = "linux"))]
+mod platform {
+    use std::{
+        collections::HashMap,
+        fs,
+        path::PathBuf,
+        sync::mpsc::{ Receiver, RecvTimeoutError, Sender },
+        time::{ Duration, SystemTime },
+    };
+
+    const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+    fn modification_times(root: &PathBuf) -> HashMap<PathBuf, SystemTime> {
+        let mut times = HashMap::new();
+        let mut dirs = vec![root.clone()];
+        if let Ok(entries) = fs::read_dir(root) {
+            for entry in entries.flatten() {
+                if entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
+                    dirs.push(entry.path());
+                }
+            }
+        }
+        for dir in dirs {
+            if let Ok(modified) = fs::metadata(&dir).and_then(|m| m.modified()) {
+                times.insert(dir, modified);
+            }
+        }
+        times
+    }
+
+    pub fn run(roots: Receiver<PathBuf>, changes: Sender<PathBuf>) {
+        let mut root = match roots.recv() {
+            Ok(root) => root,
+            Err(_) => {
+                return;
+            }
+        };
+        let mut times = modification_times(&root);
+        loop {
+            match roots.recv_timeout(POLL_INTERVAL) {
+                Ok(new_root) => {
+                    if new_root != root {
+                        root = new_root;
+                        times = modification_times(&root);
+                    }
+                    continue;
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => {
+                    return;
+                }
+            }
+
+            let new_times = modification_times(&root);
+            for (dir, modified) in &new_times {
+                if times.get(dir) != Some(modified) && changes.send(dir.clone()).is_err() {
+                    return;
+                }
+            }
+            times = new_times;
+        }
+    }
+}
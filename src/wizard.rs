@@ -0,0 +1,172 @@
+use crate::{ events::AppEvent, theme::Theme };
+use ratatui::{ style::{ Modifier, Style }, text::{ Span, Spans } };
+use std::{ io, path::{ Path, PathBuf }, process::Command, sync::mpsc::Sender, thread };
+
+/// A directory offered by the cleanup wizard
+pub struct WizardStop {
+    pub path: PathBuf,
+    pub name: String,
+    pub files: usize,
+}
+
+/// What the user chose for one stop
+pub enum StopAction {
+    Skip,
+    Archive,
+    Delete,
+    Exclude,
+}
+
+/// Guided walk through the largest subtrees of a directory, largest first
+pub struct CleanupWizard {
+    pub stops: Vec<WizardStop>,
+    pub position: usize, // Past the last stop once finished: the summary is shown
+    reclaimed_files: usize, // Files removed by deleting or archiving
+    archives_pending: usize,
+    outcomes: Vec<String>, // One line per action taken
+}
+
+/// Outcome of archiving a directory on a background thread
+pub struct ArchiveMessage {
+    pub path: PathBuf,
+    pub files: usize,
+    pub result: io::Result<PathBuf>, // The archive written next to the directory
+}
+
+impl CleanupWizard {
+    pub fn new(mut stops: Vec<WizardStop>) -> CleanupWizard {
+        stops.sort_by(|a, b| b.files.cmp(&a.files).then(a.name.cmp(&b.name)));
+        CleanupWizard { stops, position: 0, reclaimed_files: 0, archives_pending: 0, outcomes: Vec::new() }
+    }
+
+    pub fn current(&self) -> Option<&WizardStop> {
+        self.stops.get(self.position)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.position >= self.stops.len()
+    }
+
+    /// Record the action taken for the current stop and move to the next one
+    pub fn advance(&mut self, action: StopAction) {
+        if let Some(stop) = self.stops.get(self.position) {
+            match action {
+                StopAction::Skip => {}
+                StopAction::Archive => {
+                    self.archives_pending += 1;
+                }
+                StopAction::Delete => {
+                    self.reclaimed_files = self.reclaimed_files.saturating_add(stop.files);
+                    self.outcomes.push(format!("Trashed {} ({} files)", stop.name, stop.files));
+                }
+                StopAction::Exclude => {
+                    self.outcomes.push(format!("Excluded {}", stop.name));
+                }
+            }
+        }
+        self.position = self.position.saturating_add(1);
+    }
+
+    /// Record a failed action, shown in the summary
+    pub fn failed(&mut self, path: &Path, error: &io::Error) {
+        self.outcomes.push(format!("Failed on {}: {}", path.display(), error));
+    }
+
+    /// Record an archived directory whose original was moved to the trash
+    pub fn archived(&mut self, dir: &Path, archive: &Path, files: usize) {
+        self.archives_pending = self.archives_pending.saturating_sub(1);
+        // The archive itself is one file
+        self.reclaimed_files = self.reclaimed_files.saturating_add(files.saturating_sub(1));
+        self.outcomes.push(format!("Archived {} to {}", dir.display(), archive.display()));
+    }
+
+    /// Record a failed archive (or a failure to trash the archived original)
+    pub fn archive_failed(&mut self, dir: &Path, error: &io::Error) {
+        self.archives_pending = self.archives_pending.saturating_sub(1);
+        self.failed(dir, error);
+    }
+
+    /// Move through all remaining stops straight to the summary
+    pub fn finish(&mut self) {
+        self.position = self.stops.len();
+    }
+
+    /// Lines of the wizard dialog: the current stop and its actions, or the summary
+    pub fn lines(&self, theme: &Theme) -> Vec<Spans<'static>> {
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        let key = |label: &str, description: &str| {
+            vec![Span::styled(label.to_string(), theme.key), Span::raw(format!(" - {}   ", description))]
+        };
+        let mut lines = Vec::new();
+
+        match self.current() {
+            Some(stop) => {
+                lines.push(Spans::from(format!("Stop {} of {}, largest first", self.position + 1, self.stops.len())));
+                lines.push(Spans::from(vec![Span::styled(stop.name.clone(), bold)]));
+                lines.push(Spans::from(stop.path.display().to_string()));
+                lines.push(Spans::from(format!("Contains {} files", stop.files)));
+                lines.push(Spans::from(""));
+                let mut actions = key("s", "Skip");
+                actions.extend(key("a", "Archive (.tar.gz)"));
+                lines.push(Spans::from(actions));
+                let mut actions = key("d", "Move to trash");
+                actions.extend(key("e", "Exclude from listing"));
+                lines.push(Spans::from(actions));
+                lines.push(Spans::from(key("q/Esc", "Finish")));
+            }
+            None => {
+                lines.push(Spans::from(vec![Span::styled(String::from("Cleanup summary"), bold)]));
+                if self.stops.is_empty() {
+                    lines.push(Spans::from("No counted directories to review yet"));
+                }
+                for outcome in &self.outcomes {
+                    lines.push(Spans::from(format!("  {}", outcome)));
+                }
+                if self.archives_pending > 0 {
+                    lines.push(Spans::from(format!("  Archiving {} more...", self.archives_pending)));
+                }
+                lines.push(Spans::from(format!("Reclaimed: {} files", self.reclaimed_files)));
+                lines.push(Spans::from(""));
+                lines.push(Spans::from(key("Esc", "Close")));
+            }
+        }
+        lines
+    }
+}
+
+/// Pack `dir` into a `.tar.gz` next to it on a background thread
+pub fn spawn_archive(dir: PathBuf, files: usize, sender: Sender<AppEvent>) {
+    thread::spawn(move || {
+        let result = archive(&dir);
+        sender.send(AppEvent::Archived(ArchiveMessage { path: dir, files, result })).unwrap_or(());
+    });
+}
+
+fn archive(dir: &Path) -> io::Result<PathBuf> {
+    let parent = dir.parent().ok_or_else(|| io::Error::other("cannot archive a root directory"))?;
+    let name = dir.file_name().ok_or_else(|| io::Error::other("cannot archive a root directory"))?;
+
+    // First free name among `name.tar.gz`, `name.1.tar.gz`, ...
+    let mut destination = None;
+    for n in 0..=u32::MAX {
+        let mut candidate = name.to_os_string();
+        if n > 0 {
+            candidate.push(format!(".{}", n));
+        }
+        candidate.push(".tar.gz");
+        let candidate = parent.join(candidate);
+        if !candidate.exists() {
+            destination = Some(candidate);
+            break;
+        }
+    }
+    let destination = destination.ok_or_else(|| io::Error::other("no free archive name"))?;
+
+    let output = Command::new("tar").arg("-czf").arg(&destination).arg("-C").arg(parent).arg(name).output()?;
+    if output.status.success() {
+        Ok(destination)
+    } else {
+        let _ = std::fs::remove_file(&destination);
+        Err(io::Error::other(String::from_utf8_lossy(&output.stderr).trim().to_string()))
+    }
+}